@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+
+use gluon::{new_vm, vm::api::FunctionRef, ThreadExt};
+
+// Benchmarks workloads which allocate many short lived values
+fn short_lived_records(b: &mut Bencher) {
+    let vm = new_vm();
+    let text = r#"
+    let loop n acc =
+        if n #Int== 0 then
+            acc.x
+        else
+            let p = { x = acc.x #Int+ 1, y = n }
+            loop (n #Int- 1) { x = p.x, y = p.y }
+    \n -> loop n { x = 0, y = 0 }
+    "#;
+    vm.load_script("test", text).unwrap();
+    let mut test: FunctionRef<fn(i32) -> i32> = vm.get_global("test").unwrap();
+    b.iter(|| {
+        let result = test.call(10_000).unwrap();
+        black_box(result)
+    })
+}
+
+fn short_lived_closures(b: &mut Bencher) {
+    let vm = new_vm();
+    let text = r#"
+    let loop n acc =
+        if n #Int== 0 then
+            acc
+        else
+            let add x = x #Int+ n
+            loop (n #Int- 1) (add acc)
+    \n -> loop n 0
+    "#;
+    vm.load_script("test", text).unwrap();
+    let mut test: FunctionRef<fn(i32) -> i32> = vm.get_global("test").unwrap();
+    b.iter(|| {
+        let result = test.call(10_000).unwrap();
+        black_box(result)
+    })
+}
+
+// Keeps a large structure alive while allocating garbage so that a collector which traverses
+// every reachable value on each collection has to do a lot of extra work
+fn garbage_with_large_live_set(b: &mut Bencher) {
+    let vm = new_vm();
+    let text = r#"
+    let { List } = import! std.list
+    let build n acc = if n #Int== 0 then acc else build (n #Int- 1) (Cons n acc)
+    let live = build 10000 Nil
+    let head l =
+        match l with
+        | Cons x _ -> x
+        | Nil -> 0
+    let loop n acc =
+        if n #Int== 0 then
+            acc
+        else
+            match Cons n (Cons acc Nil) with
+            | Cons x _ -> loop (n #Int- 1) x
+            | Nil -> acc
+    // `live` stays reachable through this closure
+    \n -> loop n (head live)
+    "#;
+    vm.load_script("test", text).unwrap();
+    let mut test: FunctionRef<fn(i32) -> i32> = vm.get_global("test").unwrap();
+    b.iter(|| {
+        let result = test.call(10_000).unwrap();
+        black_box(result)
+    })
+}
+
+fn gc_benchmark(c: &mut Criterion) {
+    c.bench_function("short lived records", short_lived_records);
+    c.bench_function("short lived closures", short_lived_closures);
+    c.bench_function("garbage with large live set", garbage_with_large_live_set);
+}
+
+criterion_group!(gc, gc_benchmark);
+criterion_main!(gc);
@@ -0,0 +1,192 @@
+#ifndef GLUON_H
+#define GLUON_H
+
+/* Generated by cbindgen from src/lib.rs. Do not edit this file manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Returned by all functions which can fail. A message describing the error can be retrieved with
+ * `glu_last_error`.
+ */
+typedef enum {
+  GluError_Ok,
+  GluError_Unknown,
+  /**
+   * A string passed to the API was not valid UTF-8
+   */
+  GluError_Utf8,
+  /**
+   * There is no value at the given index
+   */
+  GluError_Index,
+  /**
+   * The value does not have the type the function expected
+   */
+  GluError_Type,
+  /**
+   * Compiling or running gluon code failed
+   */
+  GluError_Vm,
+} GluError;
+
+/**
+ * Enum signaling a successful or unsuccess ful call to an extern function.
+ * If an error occured the error message is expected to be on the top of the stack.
+ */
+typedef enum {
+  GluStatus_Ok,
+  GluStatus_Yield,
+  GluStatus_Error,
+} GluStatus;
+
+/**
+ * Representation of the virtual machine
+ */
+typedef struct GluThread GluThread;
+
+typedef uint32_t GluVmIndex;
+
+typedef GluStatus (*GluFunction)(const GluThread*);
+
+typedef int64_t GluVmInt;
+
+typedef uint32_t GluVmTag;
+
+/**
+ * Retrieves the message of the last error that was returned on the calling OS thread. The message
+ * is valid until another error is returned on the same thread.
+ */
+void glu_last_error(const uint8_t **out, uintptr_t *out_len);
+
+const GluThread *glu_new_vm(void);
+
+void glu_free_vm(const GluThread *vm);
+
+GluError glu_run_expr(const GluThread *vm,
+                      const uint8_t *module,
+                      uintptr_t module_len,
+                      const uint8_t *expr,
+                      uintptr_t expr_len);
+
+GluError glu_load_script(const GluThread *vm,
+                         const uint8_t *module,
+                         uintptr_t module_len,
+                         const uint8_t *expr,
+                         uintptr_t expr_len);
+
+/**
+ * Loads the file at `filename` as a module, using the same name as `import!` would
+ */
+GluError glu_load_file(const GluThread *vm, const uint8_t *filename, uintptr_t len);
+
+/**
+ * Registers `function` as a module called `name` which gluon code can load with `import!`.
+ * `typ` is the gluon type of the function and `args` must be the number of arguments it takes.
+ */
+GluError glu_register_function(const GluThread *vm,
+                               const uint8_t *name,
+                               uintptr_t name_len,
+                               const uint8_t *typ,
+                               uintptr_t typ_len,
+                               GluFunction function,
+                               GluVmIndex args);
+
+/**
+ * Pushes the global value at `name` (such as `my_module.my_function`) onto the stack
+ */
+GluError glu_push_global(const GluThread *vm, const uint8_t *name, uintptr_t len);
+
+/**
+ * Calls the function at `stack_len - args - 1` with the `args` values above it. The arguments
+ * and the function are replaced by the result.
+ */
+GluError glu_call_function(const GluThread *thread, GluVmIndex args);
+
+uintptr_t glu_len(const GluThread *vm);
+
+void glu_pop(const GluThread *vm, uintptr_t n);
+
+void glu_push_int(const GluThread *vm, GluVmInt int_);
+
+void glu_push_byte(const GluThread *vm, uint8_t b);
+
+void glu_push_float(const GluThread *vm, double float_);
+
+void glu_push_bool(const GluThread *vm, int8_t b);
+
+GluError glu_push_function(const GluThread *vm,
+                           const uint8_t *name,
+                           uintptr_t len,
+                           GluFunction function,
+                           GluVmIndex args);
+
+/**
+ * Push a string to the stack. The string must be valid utf-8 or an error will be returned
+ */
+GluError glu_push_string(const GluThread *vm, const uint8_t *s, uintptr_t len);
+
+/**
+ * Push a string to the stack. If the string is not utf-8 this function will trigger undefined
+ * behaviour.
+ */
+GluError glu_push_string_unchecked(const GluThread *vm, const uint8_t *s, uintptr_t len);
+
+#if !defined(GLUON_WASM32)
+void glu_push_light_userdata(const GluThread *vm, void *data);
+#endif
+
+GluError glu_get_byte(const GluThread *vm, GluVmIndex index, uint8_t *out);
+
+GluError glu_get_int(const GluThread *vm, GluVmIndex index, GluVmInt *out);
+
+GluError glu_get_float(const GluThread *vm, GluVmIndex index, double *out);
+
+GluError glu_get_bool(const GluThread *vm, GluVmIndex index, int8_t *out);
+
+/**
+ * The returned string is garbage collected and may not be valid after the string is removed from
+ * its slot in the stack
+ */
+GluError glu_get_string(const GluThread *vm,
+                        GluVmIndex index,
+                        const uint8_t **out,
+                        uintptr_t *out_len);
+
+#if !defined(GLUON_WASM32)
+GluError glu_get_light_userdata(const GluThread *vm, GluVmIndex index, void **out);
+#endif
+
+/**
+ * Retrieves the tag of the variant at `index`. Tags are numbered from 0 in the order that the
+ * constructors are declared in.
+ */
+GluError glu_get_tag(const GluThread *vm, GluVmIndex index, GluVmTag *out);
+
+/**
+ * Pushes the field `name` of the record at `index` onto the stack
+ */
+GluError glu_push_field(const GluThread *vm,
+                        GluVmIndex index,
+                        const uint8_t *name,
+                        uintptr_t len);
+
+/**
+ * Pushes the argument at `arg` of the variant at `index` onto the stack
+ */
+GluError glu_push_variant_arg(const GluThread *vm, GluVmIndex index, uintptr_t arg);
+
+/**
+ * Retrieves the number of elements in the array at `index`
+ */
+GluError glu_array_len(const GluThread *vm, GluVmIndex index, uintptr_t *out);
+
+/**
+ * Pushes the element at `element` of the array at `index` onto the stack
+ */
+GluError glu_push_array_element(const GluThread *vm, GluVmIndex index, uintptr_t element);
+
+#endif /* GLUON_H */
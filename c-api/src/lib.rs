@@ -1,34 +1,83 @@
-//! A (WIP) C API allowing use of gluon in other langauges than Rust.
+//! A C API allowing use of gluon in other langauges than Rust.
+//!
+//! The C header for this API is checked in as `include/gluon.h`. Run `scripts/generate_c_header.sh`
+//! to regenerate it after changing the API.
 #![doc(html_root_url = "https://docs.rs/gluon_c-api/0.13.1")] // # GLUON
 
-use std::{slice, str};
+use std::{cell::RefCell, fmt, result::Result as StdResult, slice, str};
 
 use futures::{executor::block_on, future};
 
 use gluon::{
+    base::types::{ArcType, NullInterner, TypeExt},
+    import::add_extern_module,
     vm::{
-        api::{CPrimitive, Getable, Hole, OpaqueValue, Pushable},
+        api::{CPrimitive, Hole, OpaqueValue, Pushable, ValueRef},
         stack,
-        thread::{RootedThread, Status, Thread, ThreadInternal},
-        types::{VmIndex, VmInt},
+        thread::{RootedThread, RootedValue, Status, Thread, ThreadInternal},
+        types::{VmIndex, VmInt, VmTag},
+        ExternModule,
     },
     ThreadExt,
 };
 
 pub type Function = extern "C" fn(&Thread) -> Status;
 
-// TODO What should the c api return as errors
-// TODO How should error messages be returned
+/// Returned by all functions which can fail. A message describing the error can be retrieved with
+/// `glu_last_error`.
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub enum Error {
     Ok,
     Unknown,
+    /// A string passed to the API was not valid UTF-8
+    Utf8,
+    /// There is no value at the given index
+    Index,
+    /// The value does not have the type the function expected
+    Type,
+    /// Compiling or running gluon code failed
+    Vm,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = RefCell::new(String::new());
+}
+
+fn set_error(error: Error, message: impl fmt::Display) -> Error {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message.to_string());
+    error
+}
+
+fn vm_error(err: impl fmt::Display) -> Error {
+    set_error(Error::Vm, err)
+}
+
+fn try_c(f: impl FnOnce() -> StdResult<(), Error>) -> Error {
+    match f() {
+        Ok(()) => Error::Ok,
+        Err(err) => err,
+    }
+}
+
+unsafe fn to_str<'a>(s: &'a u8, len: usize) -> StdResult<&'a str, Error> {
+    str::from_utf8(slice::from_raw_parts(s, len)).map_err(|err| set_error(Error::Utf8, err))
+}
+
+/// Retrieves the message of the last error that was returned on the calling OS thread. The message
+/// is valid until another error is returned on the same thread.
+#[no_mangle]
+pub extern "C" fn glu_last_error(out: &mut *const u8, out_len: &mut usize) {
+    LAST_ERROR.with(|last_error| {
+        let last_error = last_error.borrow();
+        *out = last_error.as_ptr();
+        *out_len = last_error.len();
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn glu_new_vm() -> *const Thread {
-    let vm = RootedThread::new();
+    let vm = gluon::new_vm();
     vm.into_raw()
 }
 
@@ -45,19 +94,13 @@ pub unsafe extern "C" fn glu_run_expr(
     expr: &u8,
     expr_len: usize,
 ) -> Error {
-    let module = match str::from_utf8(slice::from_raw_parts(module, module_len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    let expr = match str::from_utf8(slice::from_raw_parts(expr, expr_len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    let result = vm.run_expr::<OpaqueValue<&Thread, Hole>>(module, expr);
-    match result {
-        Ok(_) => Error::Ok,
-        Err(_) => Error::Unknown,
-    }
+    try_c(|| {
+        let module = to_str(module, module_len)?;
+        let expr = to_str(expr, expr_len)?;
+        vm.run_expr::<OpaqueValue<&Thread, Hole>>(module, expr)
+            .map_err(vm_error)?;
+        Ok(())
+    })
 }
 
 #[no_mangle]
@@ -68,21 +111,75 @@ pub unsafe extern "C" fn glu_load_script(
     expr: &u8,
     expr_len: usize,
 ) -> Error {
-    let module = match str::from_utf8(slice::from_raw_parts(module, module_len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    let expr = match str::from_utf8(slice::from_raw_parts(expr, expr_len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    let result = vm.load_script(module, expr);
-    match result {
-        Ok(_) => Error::Ok,
-        Err(_) => Error::Unknown,
-    }
+    try_c(|| {
+        let module = to_str(module, module_len)?;
+        let expr = to_str(expr, expr_len)?;
+        vm.load_script(module, expr).map_err(vm_error)
+    })
+}
+
+/// Loads the file at `filename` as a module, using the same name as `import!` would
+#[no_mangle]
+pub unsafe extern "C" fn glu_load_file(vm: &Thread, filename: &u8, len: usize) -> Error {
+    try_c(|| {
+        let filename = to_str(filename, len)?;
+        vm.load_file(filename).map_err(vm_error)
+    })
 }
 
+/// Registers `function` as a module called `name` which gluon code can load with `import!`.
+/// `typ` is the gluon type of the function and `args` must be the number of arguments it takes.
+#[no_mangle]
+pub unsafe extern "C" fn glu_register_function(
+    vm: &Thread,
+    name: &u8,
+    name_len: usize,
+    typ: &u8,
+    typ_len: usize,
+    function: Function,
+    args: VmIndex,
+) -> Error {
+    try_c(|| {
+        let name = to_str(name, name_len)?;
+        let typ = parse_type(vm, to_str(typ, typ_len)?)?;
+        let id = name.to_string();
+        add_extern_module(vm, name, move |thread| {
+            Ok(ExternModule {
+                metadata: Default::default(),
+                value: CPrimitive::new(function, args, &id).marshal(thread)?,
+                typ: typ.clone(),
+            })
+        });
+        Ok(())
+    })
+}
+
+fn parse_type(vm: &Thread, typ: &str) -> StdResult<ArcType, Error> {
+    // Types can't be typechecked on their own so the type is declared as an alias and extracted
+    // from the type of the record exporting it
+    let expr = format!("type Type = {}\n{{ Type }}", typ);
+    let (_, record) = vm.typecheck_str("<type>", &expr, None).map_err(vm_error)?;
+    let field = record
+        .type_field_iter()
+        .next()
+        .ok_or_else(|| set_error(Error::Type, format!("Invalid type `{}`", typ)))?;
+    Ok(field.typ.typ(&mut NullInterner).into_owned())
+}
+
+/// Pushes the global value at `name` (such as `my_module.my_function`) onto the stack
+#[no_mangle]
+pub unsafe extern "C" fn glu_push_global(vm: &Thread, name: &u8, len: usize) -> Error {
+    try_c(|| {
+        let name = to_str(name, len)?;
+        let value = vm
+            .get_global::<OpaqueValue<RootedThread, Hole>>(name)
+            .map_err(vm_error)?;
+        push(vm, value)
+    })
+}
+
+/// Calls the function at `stack_len - args - 1` with the `args` values above it. The arguments
+/// and the function are replaced by the result.
 #[no_mangle]
 pub extern "C" fn glu_call_function(thread: &Thread, args: VmIndex) -> Error {
     match block_on(future::poll_fn(|cx| {
@@ -90,7 +187,7 @@ pub extern "C" fn glu_call_function(thread: &Thread, args: VmIndex) -> Error {
         thread.call_function(cx, context, args)
     })) {
         Ok(_) => Error::Ok,
-        Err(_) => Error::Unknown,
+        Err(err) => vm_error(err),
     }
 }
 
@@ -138,27 +235,19 @@ pub unsafe extern "C" fn glu_push_function(
     function: Function,
     args: VmIndex,
 ) -> Error {
-    let s = match str::from_utf8(slice::from_raw_parts(name, len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    match Thread::push(vm, CPrimitive::new(function, args, s)) {
-        Ok(()) => Error::Ok,
-        Err(_) => Error::Unknown,
-    }
+    try_c(|| {
+        let s = to_str(name, len)?;
+        push(vm, CPrimitive::new(function, args, s))
+    })
 }
 
 /// Push a string to the stack. The string must be valid utf-8 or an error will be returned
 #[no_mangle]
 pub unsafe extern "C" fn glu_push_string(vm: &Thread, s: &u8, len: usize) -> Error {
-    let s = match str::from_utf8(slice::from_raw_parts(s, len)) {
-        Ok(s) => s,
-        Err(_) => return Error::Unknown,
-    };
-    match s.push(&mut vm.current_context()) {
-        Ok(()) => Error::Ok,
-        Err(_) => Error::Unknown,
-    }
+    try_c(|| {
+        let s = to_str(s, len)?;
+        push(vm, s)
+    })
 }
 
 /// Push a string to the stack. If the string is not utf-8 this function will trigger undefined
@@ -166,10 +255,7 @@ pub unsafe extern "C" fn glu_push_string(vm: &Thread, s: &u8, len: usize) -> Err
 #[no_mangle]
 pub unsafe extern "C" fn glu_push_string_unchecked(vm: &Thread, s: &u8, len: usize) -> Error {
     let s = str::from_utf8_unchecked(slice::from_raw_parts(s, len));
-    match s.push(&mut vm.current_context()) {
-        Ok(()) => Error::Ok,
-        Err(_) => Error::Unknown,
-    }
+    try_c(|| push(vm, s))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -180,27 +266,34 @@ pub extern "C" fn glu_push_light_userdata(vm: &Thread, data: *mut libc::c_void)
 
 #[no_mangle]
 pub extern "C" fn glu_get_byte(vm: &Thread, index: VmIndex, out: &mut u8) -> Error {
-    get_value(vm, index, out)
+    get_value(vm, index, "a Byte", out, |value| match value {
+        ValueRef::Byte(b) => Some(b),
+        _ => None,
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn glu_get_int(vm: &Thread, index: VmIndex, out: &mut VmInt) -> Error {
-    get_value(vm, index, out)
+    get_value(vm, index, "an Int", out, |value| match value {
+        ValueRef::Int(i) => Some(i),
+        _ => None,
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn glu_get_float(vm: &Thread, index: VmIndex, out: &mut f64) -> Error {
-    get_value(vm, index, out)
+    get_value(vm, index, "a Float", out, |value| match value {
+        ValueRef::Float(f) => Some(f),
+        _ => None,
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn glu_get_bool(vm: &Thread, index: VmIndex, out: &mut i8) -> Error {
-    let mut b = false;
-    let err = get_value(vm, index, &mut b);
-    if err == Error::Ok {
-        *out = b as i8;
-    }
-    err
+    get_value(vm, index, "a Bool", out, |value| match value {
+        ValueRef::Data(data) => Some((data.tag() == 1) as i8),
+        _ => None,
+    })
 }
 
 /// The returned string is garbage collected and may not be valid after the string is removed from
@@ -214,16 +307,14 @@ pub unsafe extern "C" fn glu_get_string(
 ) -> Error {
     let mut context = vm.context();
     let stack = context.stack_frame::<stack::State>();
-    match stack
-        .get_variant(index)
-        .map(|value| <&str>::from_value(vm, value))
-    {
-        Some(value) => {
+    match stack.get_variant(index).map(|value| value.as_ref()) {
+        Some(ValueRef::String(value)) => {
             *out = &*value.as_ptr();
             *out_len = value.len();
             Error::Ok
         }
-        None => Error::Unknown,
+        Some(_) => type_error(index, "a String"),
+        None => index_error(index),
     }
 }
 
@@ -234,29 +325,162 @@ pub extern "C" fn glu_get_light_userdata(
     index: VmIndex,
     out: &mut *mut libc::c_void,
 ) -> Error {
-    let mut userdata = 0usize;
-    let err = get_value(vm, index, &mut userdata);
-    if err == Error::Ok {
-        *out = userdata as *mut libc::c_void;
-    }
-    err
+    // Light userdata is pushed as an `Int`
+    get_value(vm, index, "light userdata", out, |value| match value {
+        ValueRef::Int(i) => Some(i as usize as *mut libc::c_void),
+        _ => None,
+    })
+}
+
+/// Retrieves the tag of the variant at `index`. Tags are numbered from 0 in the order that the
+/// constructors are declared in.
+#[no_mangle]
+pub extern "C" fn glu_get_tag(vm: &Thread, index: VmIndex, out: &mut VmTag) -> Error {
+    try_c(|| {
+        let value = get_rooted(vm, index)?;
+        match value.get_variant().as_ref() {
+            ValueRef::Data(data) => {
+                *out = data.tag();
+                Ok(())
+            }
+            _ => Err(type_error(index, "a record or variant")),
+        }
+    })
+}
+
+/// Pushes the field `name` of the record at `index` onto the stack
+#[no_mangle]
+pub unsafe extern "C" fn glu_push_field(
+    vm: &Thread,
+    index: VmIndex,
+    name: &u8,
+    len: usize,
+) -> Error {
+    try_c(|| {
+        let name = to_str(name, len)?;
+        let value = get_rooted(vm, index)?;
+        let field = match value.get_variant().as_ref() {
+            ValueRef::Data(data) => data
+                .lookup_field(vm, name)
+                .map(|field| vm.root_value::<RootedThread>(field)),
+            _ => return Err(type_error(index, "a record")),
+        };
+        let field = field.ok_or_else(|| {
+            set_error(
+                Error::Index,
+                format!("The record at index {} has no field `{}`", index, name),
+            )
+        })?;
+        push(vm, field)
+    })
+}
+
+/// Pushes the argument at `arg` of the variant at `index` onto the stack
+#[no_mangle]
+pub extern "C" fn glu_push_variant_arg(vm: &Thread, index: VmIndex, arg: usize) -> Error {
+    try_c(|| {
+        let value = get_rooted(vm, index)?;
+        let arg = match value.get_variant().as_ref() {
+            ValueRef::Data(data) => data
+                .get_variant(arg)
+                .map(|arg| vm.root_value::<RootedThread>(arg)),
+            _ => return Err(type_error(index, "a variant")),
+        };
+        let arg = arg.ok_or_else(|| {
+            set_error(
+                Error::Index,
+                format!("The variant at index {} has too few arguments", index),
+            )
+        })?;
+        push(vm, arg)
+    })
+}
+
+/// Retrieves the number of elements in the array at `index`
+#[no_mangle]
+pub extern "C" fn glu_array_len(vm: &Thread, index: VmIndex, out: &mut usize) -> Error {
+    try_c(|| {
+        let value = get_rooted(vm, index)?;
+        match value.get_variant().as_ref() {
+            ValueRef::Array(array) => {
+                *out = array.as_ref().len();
+                Ok(())
+            }
+            _ => Err(type_error(index, "an array")),
+        }
+    })
+}
+
+/// Pushes the element at `element` of the array at `index` onto the stack
+#[no_mangle]
+pub extern "C" fn glu_push_array_element(vm: &Thread, index: VmIndex, element: usize) -> Error {
+    try_c(|| {
+        let value = get_rooted(vm, index)?;
+        let element = match value.get_variant().as_ref() {
+            ValueRef::Array(array) => array
+                .as_ref()
+                .get(element)
+                .map(|element| vm.root_value::<RootedThread>(element)),
+            _ => return Err(type_error(index, "an array")),
+        };
+        let element = element.ok_or_else(|| {
+            set_error(
+                Error::Index,
+                format!("The array at index {} is too short", index),
+            )
+        })?;
+        push(vm, element)
+    })
+}
+
+fn index_error(index: VmIndex) -> Error {
+    set_error(
+        Error::Index,
+        format!("There is no value at index {}", index),
+    )
+}
+
+fn type_error(index: VmIndex, expected: &str) -> Error {
+    set_error(
+        Error::Type,
+        format!("The value at index {} is not {}", index, expected),
+    )
 }
 
-fn get_value<T>(vm: &Thread, index: VmIndex, out: &mut T) -> Error
+fn push<'vm, T>(vm: &'vm Thread, value: T) -> StdResult<(), Error>
 where
-    T: for<'vm, 'value> Getable<'vm, 'value>,
+    T: Pushable<'vm>,
 {
+    vm.push(value).map_err(vm_error)
+}
+
+fn get_rooted(vm: &Thread, index: VmIndex) -> StdResult<RootedValue<RootedThread>, Error> {
     let mut context = vm.context();
     let stack = context.stack_frame::<stack::State>();
-    match stack
-        .get_variant(index)
-        .map(|value| T::from_value(vm, value))
-    {
-        Some(value) => {
+    match stack.get_variant(index) {
+        Some(value) => Ok(vm.root_value(value)),
+        None => Err(index_error(index)),
+    }
+}
+
+/// Reads the value at `index` with `f`, which returns `None` if the value is not of the
+/// `expected` type
+fn get_value<T>(
+    vm: &Thread,
+    index: VmIndex,
+    expected: &str,
+    out: &mut T,
+    f: impl FnOnce(ValueRef) -> Option<T>,
+) -> Error {
+    let mut context = vm.context();
+    let stack = context.stack_frame::<stack::State>();
+    match stack.get_variant(index).map(|value| f(value.as_ref())) {
+        Some(Some(value)) => {
             *out = value;
             Error::Ok
         }
-        None => Error::Unknown,
+        Some(None) => type_error(index, expected),
+        None => index_error(index),
     }
 }
 
@@ -355,4 +579,93 @@ mod tests {
             glu_free_vm(vm);
         }
     }
+
+    #[test]
+    fn errors_have_messages() {
+        unsafe {
+            let vm = &*glu_new_vm();
+
+            let name = "missing.global";
+            assert_eq!(
+                glu_push_global(vm, &name.as_bytes()[0], name.len()),
+                Error::Vm
+            );
+            let mut message_ptr = ptr::null();
+            let mut message_len = 0;
+            glu_last_error(&mut message_ptr, &mut message_len);
+            let message = str::from_utf8(slice::from_raw_parts(message_ptr, message_len)).unwrap();
+            assert!(message.contains("missing"), "{}", message);
+
+            let mut int = 0;
+            assert_eq!(glu_get_int(vm, 0, &mut int), Error::Index);
+
+            glu_push_int(vm, 1);
+            let mut string_ptr = ptr::null();
+            let mut string_len = 0;
+            assert_eq!(
+                glu_get_string(vm, 0, &mut string_ptr, &mut string_len),
+                Error::Type
+            );
+            glu_last_error(&mut message_ptr, &mut message_len);
+            let message = str::from_utf8(slice::from_raw_parts(message_ptr, message_len)).unwrap();
+            assert!(message.contains("not a String"), "{}", message);
+
+            let mut float = 0.0;
+            assert_eq!(glu_get_float(vm, 0, &mut float), Error::Type);
+            let mut b = 0;
+            assert_eq!(glu_get_bool(vm, 0, &mut b), Error::Type);
+
+            glu_free_vm(vm);
+        }
+    }
+
+    #[test]
+    fn read_records_variants_and_arrays() {
+        unsafe {
+            let vm = &*glu_new_vm();
+
+            let module = "test";
+            let script = r#"
+                type Shape = | Circle Float | Rect Float Float
+                { shapes = [Circle 1.0, Rect 2.0 3.0] }
+            "#;
+            assert_eq!(
+                glu_load_script(
+                    vm,
+                    &module.as_bytes()[0],
+                    module.len(),
+                    &script.as_bytes()[0],
+                    script.len()
+                ),
+                Error::Ok
+            );
+            assert_eq!(
+                glu_push_global(vm, &module.as_bytes()[0], module.len()),
+                Error::Ok
+            );
+            let field = "shapes";
+            assert_eq!(
+                glu_push_field(vm, 0, &field.as_bytes()[0], field.len()),
+                Error::Ok
+            );
+
+            let mut len = 0;
+            assert_eq!(glu_array_len(vm, 1, &mut len), Error::Ok);
+            assert_eq!(len, 2);
+
+            assert_eq!(glu_push_array_element(vm, 1, 1), Error::Ok);
+            let mut tag = 0;
+            assert_eq!(glu_get_tag(vm, 2, &mut tag), Error::Ok);
+            assert_eq!(tag, 1);
+
+            assert_eq!(glu_push_variant_arg(vm, 2, 1), Error::Ok);
+            let mut height = 0.0;
+            assert_eq!(glu_get_float(vm, 3, &mut height), Error::Ok);
+            assert_eq!(height, 3.0);
+
+            assert_eq!(glu_push_array_element(vm, 1, 2), Error::Index);
+
+            glu_free_vm(vm);
+        }
+    }
 }
@@ -0,0 +1,39 @@
+//! Compiles `tests/main.c` against the generated header and the `cdylib` and runs it
+#![cfg(unix)]
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn c_api() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // Test executables are placed in `target/<profile>/deps`, next to the `cdylib`
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    let output = lib_dir.join("gluon_c_api_test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg(manifest_dir.join("tests").join("main.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-lgluon_c_api")
+        .arg("-o")
+        .arg(&output)
+        .status()
+        .expect("Unable to run the C compiler");
+    assert!(status.success(), "Unable to compile tests/main.c");
+
+    let output = Command::new(&output)
+        .env("LD_LIBRARY_PATH", lib_dir)
+        .env("DYLD_LIBRARY_PATH", lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,135 @@
+// Exercises the C API through the generated header. Run by `tests/c.rs`.
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "gluon.h"
+
+#define STR(s) (const uint8_t *)(s), strlen(s)
+
+static GluStatus add_one(const GluThread *vm) {
+    GluVmInt x;
+    if (glu_get_int(vm, 0, &x) != GluError_Ok) {
+        glu_push_string(vm, STR("Expected an Int"));
+        return GluStatus_Error;
+    }
+    glu_push_int(vm, x + 1);
+    return GluStatus_Ok;
+}
+
+static void expect_ok(GluError err) {
+    if (err != GluError_Ok) {
+        const uint8_t *message;
+        uintptr_t len;
+        glu_last_error(&message, &len);
+        fprintf(stderr, "%.*s\n", (int)len, (const char *)message);
+    }
+    assert(err == GluError_Ok);
+}
+
+static void call_global(void) {
+    const GluThread *vm = glu_new_vm();
+
+    expect_ok(glu_load_script(vm, STR("test"), STR("let add x y : Int -> Int -> Int = x + y\n"
+                                                   "{ add }")));
+    expect_ok(glu_push_global(vm, STR("test.add")));
+    glu_push_int(vm, 1);
+    glu_push_int(vm, 2);
+    expect_ok(glu_call_function(vm, 2));
+
+    GluVmInt result;
+    expect_ok(glu_get_int(vm, 0, &result));
+    assert(result == 3);
+
+    glu_free_vm(vm);
+}
+
+static void records_variants_and_arrays(void) {
+    const GluThread *vm = glu_new_vm();
+
+    expect_ok(glu_load_script(vm, STR("shapes"),
+                              STR("type Shape = | Circle Float | Rect Float Float\n"
+                                  "{ name = \"shapes\", shapes = [Circle 1.0, Rect 2.0 3.0] }")));
+    expect_ok(glu_push_global(vm, STR("shapes")));
+
+    expect_ok(glu_push_field(vm, 0, STR("name")));
+    const uint8_t *name;
+    uintptr_t name_len;
+    expect_ok(glu_get_string(vm, 1, &name, &name_len));
+    assert(name_len == 6 && memcmp(name, "shapes", 6) == 0);
+
+    expect_ok(glu_push_field(vm, 0, STR("shapes")));
+    uintptr_t len;
+    expect_ok(glu_array_len(vm, 2, &len));
+    assert(len == 2);
+
+    double total = 0.0;
+    for (uintptr_t i = 0; i < len; i++) {
+        expect_ok(glu_push_array_element(vm, 2, i));
+        GluVmTag tag;
+        expect_ok(glu_get_tag(vm, 3, &tag));
+        uintptr_t args = tag == 0 ? 1 : 2;
+        for (uintptr_t arg = 0; arg < args; arg++) {
+            double value;
+            expect_ok(glu_push_variant_arg(vm, 3, arg));
+            expect_ok(glu_get_float(vm, 4, &value));
+            total += value;
+            glu_pop(vm, 1);
+        }
+        glu_pop(vm, 1);
+    }
+    assert(total == 6.0);
+
+    assert(glu_push_field(vm, 0, STR("missing")) == GluError_Index);
+
+    glu_free_vm(vm);
+}
+
+static void callbacks(void) {
+    const GluThread *vm = glu_new_vm();
+
+    expect_ok(glu_register_function(vm, STR("add_one"), STR("Int -> Int"), add_one, 1));
+    expect_ok(glu_load_script(vm, STR("test"), STR("let add_one = import! add_one\n"
+                                                   "{ result = add_one 41 }")));
+    expect_ok(glu_push_global(vm, STR("test.result")));
+
+    GluVmInt result;
+    expect_ok(glu_get_int(vm, 0, &result));
+    assert(result == 42);
+
+    glu_free_vm(vm);
+}
+
+static void errors(void) {
+    const GluThread *vm = glu_new_vm();
+
+    assert(glu_load_script(vm, STR("test"), STR("1 + \"\"")) == GluError_Vm);
+    const uint8_t *message;
+    uintptr_t len;
+    glu_last_error(&message, &len);
+    assert(len > 0);
+
+    GluVmInt result;
+    assert(glu_get_int(vm, 0, &result) == GluError_Index);
+
+    glu_push_int(vm, 1);
+    const uint8_t *string;
+    uintptr_t string_len;
+    assert(glu_get_string(vm, 0, &string, &string_len) == GluError_Type);
+    glu_last_error(&message, &len);
+    assert(len > 0);
+
+    double float_value;
+    assert(glu_get_float(vm, 0, &float_value) == GluError_Type);
+
+    glu_free_vm(vm);
+}
+
+int main(void) {
+    call_global();
+    records_variants_and_arrays();
+    callbacks();
+    errors();
+    printf("ok\n");
+    return 0;
+}
@@ -44,6 +44,7 @@ use self::{
 pub use self::error::{Help, HelpError, SpannedTypeError, TypeError};
 
 mod error;
+mod exhaustive;
 mod generalize;
 mod mod_type;
 
@@ -131,6 +132,9 @@ pub struct Typecheck<'a, 'ast> {
     pub(crate) subs: Substitution<RcType>,
     named_variables: FnvMap<Symbol, RcType>,
     pub(crate) errors: Errors<SpannedTypeError<Symbol, RcType<Symbol>>>,
+    /// Problems which do not prevent the expression from being compiled, such as an unreachable
+    /// alternative in a `match` expression
+    warnings: Error,
     /// Type variables `let test: a -> b` (`a` and `b`)
     kind_cache: KindCache,
 
@@ -173,6 +177,7 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
             symbols: symbols,
             named_variables: FnvMap::default(),
             errors: Errors::new(),
+            warnings: Errors::new(),
             kind_cache: interner.kind_cache.clone(),
             implicit_resolver: crate::implicits::ImplicitResolver::new(environment, metadata),
             unbound_variables: ScopedMap::new(),
@@ -195,6 +200,19 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
         self.subs.error()
     }
 
+    fn warning(&mut self, span: Span<BytePos>, warning: TypeError<Symbol, ArcType>) {
+        debug!("Warning: {}", warning);
+        self.warnings.push(Spanned {
+            span,
+            value: warning.into(),
+        });
+    }
+
+    /// Removes and returns the warnings found while typechecking
+    pub fn take_warnings(&mut self) -> Error {
+        mem::replace(&mut self.warnings, Errors::new())
+    }
+
     fn bool(&mut self) -> RcType {
         let typ = self.environment.get_bool().clone();
         self.translate_arc_type(&typ)
@@ -397,6 +415,8 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 | DuplicateField(_)
                 | UndefinedRecord { .. }
                 | EmptyCase
+                | NonExhaustivePattern { .. }
+                | UnreachablePattern
                 | KindError(_)
                 | RecursionCheck(_)
                 | Message(_) => (),
@@ -808,8 +828,9 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 *typ = self.subs.bind_arc(&new_type);
                 Ok((new_type, Vec::new()))
             }
-            Expr::Match(ref mut expr, ref mut alts) => {
-                let mut scrutinee_type = self.infer_expr(&mut **expr);
+            Expr::Match(ref mut scrutinee, ref mut alts) => {
+                let errors_before = self.errors.len();
+                let mut scrutinee_type = self.infer_expr(&mut **scrutinee);
                 let modifier = scrutinee_type.modifier;
                 let expected_type = expected_type.take().map(|t| t.to_owned());
 
@@ -854,9 +875,6 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
 
                     // The variant we matched on will not appear in any followup bindings so remove
                     // this variant from the type we are matching on
-                    //
-                    // TODO Make this more general so it can error when not matching on all the
-                    // variants
                     {
                         *unaliased_scrutinee_type = self.subs.zonk(&unaliased_scrutinee_type);
                         let replaced = match (&alt.pattern.value, &**unaliased_scrutinee_type) {
@@ -887,6 +905,24 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
 
                     expr_type = Some(alt_type);
                 }
+
+                // Only check the patterns if they typechecked successfully as the check relies
+                // on the types of the constructors
+                if !alts.is_empty() && self.errors.len() == errors_before {
+                    let coverage = exhaustive::check_alternatives(self, alts);
+                    for span in coverage.unreachable {
+                        self.warning(span, TypeError::UnreachablePattern);
+                    }
+                    if !coverage.missing.is_empty() {
+                        self.warning(
+                            expr.span,
+                            TypeError::NonExhaustivePattern {
+                                missing: coverage.missing,
+                            },
+                        );
+                    }
+                }
+
                 expr_type
                     .ok_or(TypeError::EmptyCase)
                     .map(|typ| (typ, Vec::new()))
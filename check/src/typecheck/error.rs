@@ -52,6 +52,14 @@ pub enum TypeError<I, T> {
     },
     /// Found a case expression without any alternatives
     EmptyCase,
+    /// The alternatives of a `match` expression does not cover every possible value. Contains
+    /// examples of patterns which are not covered
+    NonExhaustivePattern {
+        missing: Vec<String>,
+    },
+    /// An alternative in a `match` expression can never be matched as the alternatives before it
+    /// already matches every value it could match
+    UnreachablePattern,
     Message(String),
     UnableToResolveImplicit(implicits::Error<T>),
     TypeConstructorReturnsWrongType {
@@ -210,6 +218,23 @@ where
                 Ok(())
             }
             EmptyCase => write!(f, "`case` expression with no alternatives"),
+            NonExhaustivePattern { missing } => {
+                writeln!(
+                    f,
+                    "Non-exhaustive pattern match. The following patterns are not covered:"
+                )?;
+                for (i, pattern) in missing.iter().enumerate() {
+                    if i != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "    `{}`", pattern)?;
+                }
+                Ok(())
+            }
+            UnreachablePattern => write!(
+                f,
+                "Unreachable pattern. The previous alternatives already matches every value this pattern could match"
+            ),
             Message(msg) => write!(f, "{}", msg),
             UnableToResolveImplicit(err) => write!(f, "{}", err),
             TypeConstructorReturnsWrongType { expected, actual } => write!(
@@ -234,6 +259,9 @@ where
         use self::TypeError::*;
         match *self {
             UnableToResolveImplicit(ref err) => err.as_diagnostic(),
+            NonExhaustivePattern { .. } | UnreachablePattern => {
+                Diagnostic::new_warning(self.to_string())
+            }
             _ => Diagnostic::new_error(self.to_string()),
         }
     }
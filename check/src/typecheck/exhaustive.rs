@@ -0,0 +1,444 @@
+//! Checks that the alternatives of a `match` expression covers every value of the matched type
+//! and that each alternative can actually be reached.
+//!
+//! The check is an implementation of the usefulness algorithm described in
+//! "Warnings for pattern matching" by Luc Maranget. Patterns are first lowered into a simpler
+//! representation (`Pat`) which only distinguishes between wildcards, variant constructors,
+//! records (tuples are treated as records with `_0`, `_1`, ... fields) and literals.
+use std::fmt;
+
+use crate::base::{
+    ast::{self, Alternative, Literal, Pattern, SpannedPattern},
+    fnv::FnvMap,
+    pos::{BytePos, Span},
+    symbol::Symbol,
+    types::{arg_iter, Type, TypeExt},
+};
+
+use crate::typ::RcType;
+
+use super::{ctor_return_type, Typecheck};
+
+/// The maximum number of uncovered patterns that are reported for a single `match` expression
+const MAX_MISSING_PATTERNS: usize = 3;
+
+/// The result of checking the alternatives of a `match` expression
+pub(crate) struct Coverage {
+    /// The spans of the patterns which can never be matched as earlier alternatives already
+    /// matches every value they could match
+    pub unreachable: Vec<Span<BytePos>>,
+    /// Example patterns which are not matched by any alternative
+    pub missing: Vec<String>,
+}
+
+pub(crate) fn check_alternatives(tc: &Typecheck, alts: &[Alternative<Symbol>]) -> Coverage {
+    let mut checker = Checker {
+        tc,
+        variants: FnvMap::default(),
+    };
+
+    let rows: Vec<_> = alts
+        .iter()
+        .map(|alt| vec![checker.lower(&alt.pattern)])
+        .collect();
+
+    let unreachable = alts
+        .iter()
+        .zip(&rows)
+        .enumerate()
+        .filter(|&(i, (_, row))| !checker.is_useful(&rows[..i], row))
+        .map(|(_, (alt, _))| alt.pattern.span)
+        .collect();
+
+    let missing = checker
+        .missing_patterns(&rows, 1)
+        .into_iter()
+        .map(|mut witness| witness.pop().expect("Witness").to_string())
+        .collect();
+
+    Coverage {
+        unreachable,
+        missing,
+    }
+}
+
+/// Simplified pattern used during the check. Also used to construct the examples of patterns
+/// that are not covered.
+#[derive(Clone, Debug)]
+enum Pat {
+    Wild,
+    Variant(Symbol, Vec<Pat>),
+    Record(Vec<(String, Pat)>),
+    Literal(Literal),
+}
+
+impl Pat {
+    fn is_wild(&self) -> bool {
+        match self {
+            Pat::Wild => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pat::Wild => write!(f, "_"),
+            Pat::Variant(name, args) => {
+                write!(f, "{}", name.declared_name())?;
+                for arg in args {
+                    match arg {
+                        Pat::Variant(_, inner) if !inner.is_empty() => write!(f, " ({})", arg)?,
+                        _ => write!(f, " {}", arg)?,
+                    }
+                }
+                Ok(())
+            }
+            Pat::Record(fields) => {
+                let is_tuple = fields.len() != 1
+                    && fields
+                        .iter()
+                        .enumerate()
+                        .all(|(i, (name, _))| *name == format!("_{}", i));
+                if is_tuple {
+                    write!(f, "(")?;
+                    for (i, (_, pat)) in fields.iter().enumerate() {
+                        if i != 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}", pat)?;
+                    }
+                    write!(f, ")")
+                } else if fields.iter().all(|(_, pat)| pat.is_wild()) {
+                    write!(f, "_")
+                } else {
+                    write!(f, "{{ ")?;
+                    let fields = fields.iter().filter(|(_, pat)| !pat.is_wild());
+                    for (i, (name, pat)) in fields.enumerate() {
+                        if i != 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{} = {}", name, pat)?;
+                    }
+                    write!(f, " }}")
+                }
+            }
+            Pat::Literal(literal) => match literal {
+                Literal::Byte(b) => write!(f, "{}b", b),
+                Literal::Int(i) => write!(f, "{}", i),
+                Literal::Float(x) => write!(f, "{}", x),
+                Literal::String(s) => write!(f, "{:?}", s),
+                Literal::Char(c) => write!(f, "{:?}", c),
+            },
+        }
+    }
+}
+
+/// The constructor at the head of a pattern
+#[derive(Clone, Debug, PartialEq)]
+enum Ctor {
+    Variant(Symbol, usize),
+    Record(Vec<String>),
+    Literal(Literal),
+}
+
+impl Ctor {
+    /// Compares variants by name only since the constructors in the patterns may have been bound
+    /// to other symbols than the ones in the type
+    fn name_eq(&self, other: &Ctor) -> bool {
+        match (self, other) {
+            (Ctor::Variant(l, _), Ctor::Variant(r, _)) => l.name_eq(r),
+            _ => self == other,
+        }
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Variant(_, arity) => *arity,
+            Ctor::Record(fields) => fields.len(),
+            Ctor::Literal(_) => 0,
+        }
+    }
+
+    /// Builds a pattern from this constructor using the first `arity` patterns in `args`
+    fn apply(&self, args: &mut Vec<Pat>) -> Pat {
+        let rest = args.split_off(self.arity());
+        let args = std::mem::replace(args, rest);
+        match self {
+            Ctor::Variant(name, _) => Pat::Variant(name.clone(), args),
+            Ctor::Record(fields) => Pat::Record(fields.iter().cloned().zip(args).collect()),
+            Ctor::Literal(literal) => Pat::Literal(literal.clone()),
+        }
+    }
+}
+
+enum Signature {
+    /// Every constructor of the matched type appears in the column
+    Complete(Vec<Ctor>),
+    /// The column is missing constructors of the matched type. Contains the missing
+    /// constructors if they are known
+    Incomplete(Vec<Ctor>),
+}
+
+type Row = Vec<Pat>;
+
+struct Checker<'t, 'a, 'ast> {
+    tc: &'t Typecheck<'a, 'ast>,
+    /// Maps each variant constructor to all the constructors (and their arity) of the variant
+    /// type it belongs to. `None` if the constructors could not be determined in which case the
+    /// constructors are assumed to be exhaustively matched by the constructors that appear in the
+    /// patterns (GADTs, polymorphic variants, etc)
+    variants: FnvMap<Symbol, Option<Vec<(Symbol, usize)>>>,
+}
+
+impl Checker<'_, '_, '_> {
+    fn lower(&mut self, pattern: &SpannedPattern<Symbol>) -> Pat {
+        match &pattern.value {
+            Pattern::As(_, pattern) => self.lower(pattern),
+            Pattern::Ident(_) | Pattern::Error => Pat::Wild,
+            Pattern::Constructor(id, args) => {
+                if !self.variants.contains_key(&id.name) {
+                    let ctors = self.variant_constructors(&id.typ);
+                    self.variants.insert(id.name.clone(), ctors);
+                }
+                Pat::Variant(
+                    id.name.clone(),
+                    args.iter().map(|arg| self.lower(arg)).collect(),
+                )
+            }
+            Pattern::Record { fields, .. } => Pat::Record(
+                ast::pattern_values(fields)
+                    .map(|(name, value)| {
+                        let pattern = match value {
+                            Some(pattern) => self.lower(pattern),
+                            None => Pat::Wild,
+                        };
+                        (name.value.declared_name().to_string(), pattern)
+                    })
+                    .collect(),
+            ),
+            Pattern::Tuple { elems, .. } => Pat::Record(
+                elems
+                    .iter()
+                    .enumerate()
+                    .map(|(i, elem)| (format!("_{}", i), self.lower(elem)))
+                    .collect(),
+            ),
+            Pattern::Literal(literal) => Pat::Literal(literal.clone()),
+        }
+    }
+
+    /// Returns all constructors of the variant type that a constructor with type `ctor_type`
+    /// constructs
+    fn variant_constructors(&self, ctor_type: &RcType) -> Option<Vec<(Symbol, usize)>> {
+        let variant_type = self.tc.remove_aliases(ctor_return_type(ctor_type).clone());
+        let row = match &**variant_type.remove_forall() {
+            Type::Variant(row) => row.clone(),
+            _ => return None,
+        };
+
+        let mut iter = row.row_iter();
+        let ctors = iter
+            .by_ref()
+            .map(|field| {
+                let mut args = arg_iter(field.typ.remove_forall());
+                let arity = args.by_ref().count();
+                // GADT constructors specify their return type in which case not every
+                // constructor may be possible to match against
+                match **args.typ {
+                    Type::Opaque => Some((field.name.clone(), arity)),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        // Polymorphic variants may contain any number of additional constructors
+        match **iter.current_type() {
+            Type::EmptyRow => Some(ctors),
+            _ => None,
+        }
+    }
+
+    fn head_ctor(&self, pat: &Pat, column: &[&Pat]) -> Option<Ctor> {
+        Some(match pat {
+            Pat::Wild => return None,
+            Pat::Variant(name, args) => Ctor::Variant(name.clone(), args.len()),
+            Pat::Record(_) => Ctor::Record(record_fields(column.iter().cloned().chain(Some(pat)))),
+            Pat::Literal(literal) => Ctor::Literal(literal.clone()),
+        })
+    }
+
+    /// Determines which constructors appear in `column`. If `unknown_is_complete` is set then
+    /// variants whose constructors could not be determined are assumed to be completely covered.
+    fn signature(&self, column: &[&Pat], unknown_is_complete: bool) -> Signature {
+        let mut ctors: Vec<Ctor> = Vec::new();
+        for pat in column {
+            if let Some(ctor) = self.head_ctor(pat, column) {
+                if !ctors.iter().any(|c| c.name_eq(&ctor)) {
+                    ctors.push(ctor);
+                }
+            }
+        }
+
+        match ctors.first() {
+            None | Some(Ctor::Literal(_)) => Signature::Incomplete(Vec::new()),
+            Some(Ctor::Record(_)) => Signature::Complete(ctors),
+            Some(Ctor::Variant(name, _)) => match self.variants.get(name) {
+                Some(Some(all_ctors)) => {
+                    let missing: Vec<_> = all_ctors
+                        .iter()
+                        .filter(|(name, _)| {
+                            // The constructors in the patterns may have been bound to other
+                            // symbols than the ones in the type so only compare their names
+                            !ctors.iter().any(|ctor| match ctor {
+                                Ctor::Variant(ctor_name, _) => ctor_name.name_eq(name),
+                                _ => false,
+                            })
+                        })
+                        .map(|(name, arity)| Ctor::Variant(name.clone(), *arity))
+                        .collect();
+                    if missing.is_empty() {
+                        Signature::Complete(ctors)
+                    } else {
+                        Signature::Incomplete(missing)
+                    }
+                }
+                _ if unknown_is_complete => Signature::Complete(ctors),
+                _ => Signature::Incomplete(Vec::new()),
+            },
+        }
+    }
+
+    /// Returns true if there exists a value which is matched by `row` but not by any of the rows
+    /// in `matrix`
+    fn is_useful(&self, matrix: &[Row], row: &[Pat]) -> bool {
+        let (head, _) = match row.split_first() {
+            Some(x) => x,
+            None => return matrix.is_empty(),
+        };
+        let column: Vec<_> = matrix.iter().map(|row| &row[0]).collect();
+
+        match self.head_ctor(head, &column) {
+            Some(ctor) => self.is_useful(
+                &specialize_matrix(matrix, &ctor),
+                &specialize_row(row, &ctor).expect("Specialized row"),
+            ),
+            None => match self.signature(&column, false) {
+                Signature::Complete(ctors) => ctors.iter().any(|ctor| {
+                    self.is_useful(
+                        &specialize_matrix(matrix, ctor),
+                        &specialize_row(row, ctor).expect("Specialized row"),
+                    )
+                }),
+                Signature::Incomplete(_) => self.is_useful(&default_matrix(matrix), &row[1..]),
+            },
+        }
+    }
+
+    /// Returns rows of `arity` patterns which are not matched by any row in `matrix`
+    fn missing_patterns(&self, matrix: &[Row], arity: usize) -> Vec<Row> {
+        if arity == 0 {
+            return if matrix.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        }
+        let column: Vec<_> = matrix.iter().map(|row| &row[0]).collect();
+
+        let mut missing = Vec::new();
+        match self.signature(&column, true) {
+            Signature::Complete(ctors) => {
+                for ctor in ctors {
+                    let sub_matrix = specialize_matrix(matrix, &ctor);
+                    for mut row in self.missing_patterns(&sub_matrix, ctor.arity() + arity - 1) {
+                        let pattern = ctor.apply(&mut row);
+                        row.insert(0, pattern);
+                        missing.push(row);
+                        if missing.len() >= MAX_MISSING_PATTERNS {
+                            return missing;
+                        }
+                    }
+                }
+            }
+            Signature::Incomplete(missing_ctors) => {
+                let heads: Vec<_> = if missing_ctors.is_empty() {
+                    vec![Pat::Wild]
+                } else {
+                    missing_ctors
+                        .iter()
+                        .map(|ctor| ctor.apply(&mut vec![Pat::Wild; ctor.arity()]))
+                        .collect()
+                };
+                for row in self.missing_patterns(&default_matrix(matrix), arity - 1) {
+                    for head in &heads {
+                        let mut row = row.clone();
+                        row.insert(0, head.clone());
+                        missing.push(row);
+                        if missing.len() >= MAX_MISSING_PATTERNS {
+                            return missing;
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+}
+
+/// Collects the union of all fields mentioned by the record patterns in `column`
+fn record_fields<'p>(column: impl IntoIterator<Item = &'p Pat>) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    for pat in column {
+        if let Pat::Record(record_fields) = pat {
+            for (name, _) in record_fields {
+                if !fields.contains(name) {
+                    fields.push(name.clone());
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// Returns the arguments of the head pattern (followed by the rest of the row) if it matches
+/// `ctor`
+fn specialize_row(row: &[Pat], ctor: &Ctor) -> Option<Row> {
+    let (head, tail) = row.split_first().expect("Non-empty row");
+    let mut new_row = match (head, ctor) {
+        (Pat::Wild, _) => vec![Pat::Wild; ctor.arity()],
+        (Pat::Variant(name, args), Ctor::Variant(ctor_name, _)) if name.name_eq(ctor_name) => {
+            args.clone()
+        }
+        (Pat::Record(fields), Ctor::Record(names)) => names
+            .iter()
+            .map(|name| {
+                fields
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .map_or(Pat::Wild, |(_, pat)| pat.clone())
+            })
+            .collect(),
+        (Pat::Literal(l), Ctor::Literal(r)) if l == r => Vec::new(),
+        _ => return None,
+    };
+    new_row.extend(tail.iter().cloned());
+    Some(new_row)
+}
+
+fn specialize_matrix(matrix: &[Row], ctor: &Ctor) -> Vec<Row> {
+    matrix
+        .iter()
+        .filter_map(|row| specialize_row(row, ctor))
+        .collect()
+}
+
+/// Returns the rows which matches any constructor in the first column
+fn default_matrix(matrix: &[Row]) -> Vec<Row> {
+    matrix
+        .iter()
+        .filter(|row| row[0].is_wild())
+        .map(|row| row[1..].to_owned())
+        .collect()
+}
@@ -0,0 +1,141 @@
+extern crate gluon_base as base;
+extern crate gluon_check as check;
+extern crate gluon_parser as parser;
+
+#[macro_use]
+mod support;
+
+use crate::check::typecheck::TypeError;
+
+test_check_warnings! {
+    exhaustive_variant,
+    r#"
+    type Test = | A | B Int
+    match A with
+    | A -> 0
+    | B x -> x
+    "#,
+    "Int"
+}
+
+test_check_warnings! {
+    exhaustive_nested_tuple,
+    r#"
+    type Option a = | None | Some a
+    match (Some 1, None) with
+    | (Some x, _) -> x
+    | (None, Some y) -> y
+    | (None, None) -> 0
+    "#,
+    "Int"
+}
+
+test_check_warnings! {
+    exhaustive_record,
+    r#"
+    type Test = | A | B
+    match { x = A, y = 1 } with
+    | { x = A } -> 0
+    | { x = B, y } -> y
+    "#,
+    "Int"
+}
+
+test_check_warnings! {
+    exhaustive_literal_with_wildcard,
+    r#"
+    match 1 with
+    | 1 -> "one"
+    | _ -> "many"
+    "#,
+    "String"
+}
+
+test_check_warnings! {
+    non_exhaustive_variant,
+    r#"
+    type AB = | A | B
+    match A with
+    | B -> 1
+    "#,
+    "Int",
+    NonExhaustivePattern { .. }
+}
+
+test_check_warnings! {
+    non_exhaustive_literal,
+    r#"
+    match 1 with
+    | 1 -> "one"
+    | 2 -> "two"
+    "#,
+    "String",
+    NonExhaustivePattern { .. }
+}
+
+test_check_warnings! {
+    unreachable_wildcard,
+    r#"
+    type AB = | A | B
+    match A with
+    | A -> 1
+    | B -> 2
+    | _ -> 3
+    "#,
+    "Int",
+    UnreachablePattern
+}
+
+test_check_warnings! {
+    unreachable_duplicate_alternative,
+    r#"
+    type Option a = | None | Some a
+    match Some 1 with
+    | Some x -> x
+    | None -> 0
+    | Some 2 -> 2
+    "#,
+    "Int",
+    UnreachablePattern
+}
+
+#[test]
+fn non_exhaustive_reports_uncovered_patterns() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+type Option a = | None | Some a
+type Test = | A | B | C
+match (Some A, 1) with
+| (None, _) -> 0
+| (Some A, _) -> 1
+"#;
+    let (_, result, warnings) = support::typecheck_expr_with_warnings(text, None);
+
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+    let missing = match &warnings[0].value.error {
+        TypeError::NonExhaustivePattern { missing } => missing.clone(),
+        err => panic!("Unexpected warning: {}", err),
+    };
+    assert_eq!(missing, ["(Some B, _)", "(Some C, _)"]);
+}
+
+#[test]
+fn gadt_constructors_are_not_required() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+type Test a =
+    | Int : Int -> Test Int
+    | Float : Float -> Test Float
+
+let f x : Test Int -> Int =
+    match x with
+    | Int i -> i
+()
+"#;
+    let (_, result, warnings) = support::typecheck_expr_with_warnings(text, None);
+
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+    assert!(!warnings.has_errors(), "{}", warnings);
+}
@@ -196,11 +196,28 @@ pub fn typecheck_expr_expected(
     text: &str,
     expected: Option<&ArcType>,
 ) -> (RootExpr<Symbol>, Result<ArcType, Error>) {
+    let (expr, result, _) = typecheck_expr_with_warnings(text, expected);
+    (expr, result)
+}
+
+/// Typechecks `text`, also returning the warnings that were found
+pub fn typecheck_expr_with_warnings(
+    text: &str,
+    expected: Option<&ArcType>,
+) -> (
+    RootExpr<Symbol>,
+    Result<ArcType, Error>,
+    Errors<typecheck::SpannedTypeError<Symbol>>,
+) {
     let mut expr = match parse_new(text) {
         Ok(expr) => expr,
         Err((expr, err)) => {
             let err = in_file_error(text, err);
-            return (expr.unwrap_or_else(|| panic!("{}", err)), Err(err.into()));
+            return (
+                expr.unwrap_or_else(|| panic!("{}", err)),
+                Err(err.into()),
+                Errors::new(),
+            );
         }
     };
 
@@ -209,7 +226,7 @@ pub fn typecheck_expr_expected(
     let mut interner = interner.borrow_mut();
 
     let source = codespan::FileMap::new("test".into(), text.to_string());
-    let result = {
+    let (result, warnings) = {
         let (arena, expr) = expr.arena_expr();
         let arena = arena.borrow();
 
@@ -231,10 +248,15 @@ pub fn typecheck_expr_expected(
             arena,
         );
 
-        tc.typecheck_expr_expected(expr, expected)
+        let result = tc.typecheck_expr_expected(expr, expected);
+        (result, tc.take_warnings())
     };
 
-    (expr, result.map_err(|err| in_file_error(text, err).into()))
+    (
+        expr,
+        result.map_err(|err| in_file_error(text, err).into()),
+        warnings,
+    )
 }
 
 pub fn typecheck_expr(text: &str) -> (RootExpr<Symbol>, Result<ArcType, Error>) {
@@ -451,6 +473,36 @@ macro_rules! test_check_err {
     };
 }
 
+macro_rules! test_check_warnings {
+    ($name:ident, $source:expr, $typ:expr $(, $id: pat)*) => {
+        #[test]
+        fn $name() {
+            #[allow(unused_imports)]
+            use crate::check::typecheck::TypeError::*;
+
+            let _ = env_logger::try_init();
+            let text = $source;
+            let (_, result, warnings) = support::typecheck_expr_with_warnings(text, None);
+            assert_req!(result.map(|x| x.to_string()), Ok($typ.to_string()));
+
+            #[allow(unused_mut)]
+            let mut iter = (&warnings).into_iter();
+            $(
+            match iter.next() {
+                Some(&crate::base::pos::Spanned { value: crate::base::error::Help { error: $id, .. }, .. }) => (),
+                _ => assert!(
+                    false,
+                    "Found warnings:\n{}\nbut expected {}",
+                    crate::support::in_file_error(text, warnings.clone()),
+                    stringify!($id)
+                ),
+            }
+            )*
+            assert!(iter.count() == 0, "Found more warnings than expected\n{}", warnings);
+        }
+    };
+}
+
 macro_rules! assert_err {
     ($e: expr, $($id: pat),+) => {
         assert_err!([""] $e, $($id),+)
@@ -261,6 +261,24 @@ impl<'a, 'ast, F> FindVisitor<'a, 'ast, F> {
     }
 }
 
+/// Returns the expressions in the holes of an expanded interpolated string,
+/// `concat ["abc", display hole, ..]`
+fn interpolation_holes<'a, 'ast>(
+    replacement: &'a SpannedExpr<'ast, Symbol>,
+) -> impl Iterator<Item = &'a SpannedExpr<'ast, Symbol>> {
+    let parts: &'a [SpannedExpr<'ast, Symbol>] = match replacement.value {
+        Expr::App { ref args, .. } => match args.first().map(|arg| &arg.value) {
+            Some(Expr::Array(array)) => &array.exprs[..],
+            _ => &[],
+        },
+        _ => &[],
+    };
+    parts.iter().filter_map(|part| match part.value {
+        Expr::App { ref args, .. } => args.first(),
+        _ => None,
+    })
+}
+
 struct VisitUnExpanded<'a: 'e, 'e, 'ast, F: 'e>(&'e mut FindVisitor<'a, 'ast, F>);
 
 impl<'a, 'e, 'ast, F> Visitor<'a, 'ast> for VisitUnExpanded<'a, 'e, 'ast, F>
@@ -677,8 +695,27 @@ where
                 }
             }
             Expr::MacroExpansion {
-                ref replacement, ..
-            } => self.visit_expr(replacement),
+                ref original,
+                ref replacement,
+            } => match original.value {
+                // Interpolated strings are expanded into calls to `std.interpolate` which are
+                // given the spans of the literal parts so only the holes are searched
+                Expr::Literal(ast::Literal::String(_)) => {
+                    match interpolation_holes(replacement)
+                        .find(|hole| hole.span.containment(self.pos) == Ordering::Equal)
+                    {
+                        Some(hole) => self.visit_expr(hole),
+                        None => {
+                            self.found = if current.span.containment(self.pos) == Ordering::Equal {
+                                MatchState::Found(Match::Expr(original))
+                            } else {
+                                MatchState::Empty
+                            };
+                        }
+                    }
+                }
+                _ => self.visit_expr(replacement),
+            },
             Expr::Annotated(..) => unimplemented!(), // FIXME
             Expr::Error(..) => (),
         }
@@ -218,6 +218,36 @@ a
     assert_eq!(result, expected);
 }
 
+#[test]
+fn suggest_inside_interpolated_string_hole() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+let abc = 1
+let abd = 2
+let xyz = 3
+"value: ${ab}"
+"#;
+    let result = suggest_loc(text, 4, 12);
+    let expected = Ok(vec!["abc".into(), "abd".into()]);
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn suggest_field_inside_interpolated_string_hole() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+let record = { abc = 1, abd = 2, xyz = 3 }
+"value: ${record.ab} and more"
+"#;
+    let result = suggest_loc(text, 2, 19);
+    let expected = Ok(vec!["abc".into(), "abd".into()]);
+
+    assert_eq!(result, expected);
+}
+
 #[test]
 fn suggest_as_pattern() {
     let _ = env_logger::try_init();
@@ -185,6 +185,15 @@ r##"abc
     assert_eq!(&format_expr(expr).unwrap(), expr);
 }
 
+#[test]
+fn interpolated_string_literal() {
+    let expr = r#"
+let x = 1
+"a ${x} b ${ { y = x }.y }"
+"#;
+    assert_eq!(&format_expr(expr).unwrap(), expr);
+}
+
 #[test]
 fn implicit_arg() {
     let expr = r#"
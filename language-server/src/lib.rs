@@ -0,0 +1,678 @@
+//! A language server for gluon.
+//!
+//! The server speaks the [language server protocol][lsp] over any reader/writer pair (usually
+//! stdin/stdout) and answers requests by typechecking the open documents through the same
+//! incremental compiler database which `gluon` itself uses, so only the modules affected by an
+//! edit are checked again.
+//!
+//! [lsp]:https://microsoft.github.io/language-server-protocol/
+#![doc(html_root_url = "https://docs.rs/gluon_language_server/0.13.1")] // # GLUON
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+extern crate gluon_completion as completion;
+
+pub mod position;
+pub mod protocol;
+pub mod rpc;
+
+use std::{
+    env, fmt,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use {
+    serde::{de::DeserializeOwned, Serialize},
+    serde_json::{json, Value},
+};
+
+use gluon::{
+    base::{
+        ast::OwnedExpr,
+        error::InFile,
+        filename_to_module,
+        fnv::FnvMap,
+        pos::{ByteOffset, BytePos, Span},
+        symbol::Symbol,
+        types::{ArcType, TypeExt},
+    },
+    compiler_pipeline::TypecheckValue,
+    either::Either,
+    query::{Compilation, CompilationBase},
+    Error, RootedThread, ThreadExt,
+};
+
+use gluon_format::Formatter;
+
+use crate::{
+    protocol::*,
+    rpc::{
+        Message, ResponseError, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND,
+        PARSE_ERROR,
+    },
+};
+
+struct Document {
+    module: String,
+    text: String,
+}
+
+/// A typechecked document together with the span its source occupies in the code map
+struct Checked {
+    value: TypecheckValue<Arc<OwnedExpr<Symbol>>>,
+    file_span: Span<BytePos>,
+}
+
+impl Checked {
+    fn byte_pos(&self, text: &str, position: Position) -> BytePos {
+        self.file_span.start() + ByteOffset::from(position::byte_offset(text, position) as i64)
+    }
+
+    fn range(&self, text: &str, span: Span<BytePos>) -> Range {
+        let start = self.file_span.start().to_usize();
+        position::range(
+            text,
+            span.start().to_usize().saturating_sub(start),
+            span.end().to_usize().saturating_sub(start),
+        )
+    }
+}
+
+pub struct Server {
+    thread: RootedThread,
+    documents: FnvMap<String, Document>,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Server {
+    pub fn new(thread: RootedThread) -> Server {
+        Server {
+            thread,
+            documents: FnvMap::default(),
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    /// Returns true once the client has sent the `exit` notification
+    pub fn exited(&self) -> bool {
+        self.exit
+    }
+
+    /// Handles a single message from the client, returning the responses and notifications which
+    /// should be sent back
+    pub fn handle(&mut self, message: Value) -> Vec<Message> {
+        let message = match serde_json::from_value(message) {
+            Ok(message) => message,
+            Err(err) => {
+                return vec![Message::failure(
+                    Value::Null,
+                    INVALID_REQUEST,
+                    err.to_string(),
+                )]
+            }
+        };
+        match message {
+            Message::Request(request) => {
+                debug!("Request `{}`", request.method);
+                let response = if self.shutdown {
+                    Err(error(INVALID_REQUEST, "The server has been shut down"))
+                } else {
+                    self.request(&request.method, request.params)
+                };
+                vec![match response {
+                    Ok(result) => Message::success(request.id, result),
+                    Err(err) => Message::failure(request.id, err.code, err.message),
+                }]
+            }
+            Message::Notification(notification) => {
+                debug!("Notification `{}`", notification.method);
+                match self.notification(&notification.method, notification.params) {
+                    Ok(messages) => messages,
+                    Err(err) => {
+                        error!("{}: {}", notification.method, err.message);
+                        Vec::new()
+                    }
+                }
+            }
+            // The server never sends any requests so there are no responses to handle
+            Message::Response(_) => Vec::new(),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Incremental
+                    "textDocumentSync": 2,
+                    "hoverProvider": true,
+                    "completionProvider": {
+                        "resolveProvider": false,
+                        "triggerCharacters": ["."],
+                    },
+                    "signatureHelpProvider": {
+                        "triggerCharacters": [" ", "("],
+                    },
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": {
+                    "name": "gluon_language_server",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => to_value(self.hover(parse_params(params)?)?),
+            "textDocument/completion" => to_value(self.completion(parse_params(params)?)?),
+            "textDocument/signatureHelp" => to_value(self.signature_help(parse_params(params)?)?),
+            "textDocument/definition" => to_value(self.definition(parse_params(params)?)?),
+            "textDocument/references" => to_value(self.references(parse_params(params)?)?),
+            "textDocument/documentSymbol" => {
+                to_value(self.document_symbols(parse_params(params)?)?)
+            }
+            "textDocument/formatting" => to_value(self.formatting(parse_params(params)?)?),
+            _ => Err(error(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) -> Result<Vec<Message>, ResponseError> {
+        match method {
+            "exit" => {
+                self.exit = true;
+                Ok(Vec::new())
+            }
+            "textDocument/didOpen" => {
+                let params: DidOpenTextDocumentParams = parse_params(params)?;
+                let item = params.text_document;
+                self.documents.insert(
+                    item.uri.clone(),
+                    Document {
+                        module: uri_to_module(&item.uri),
+                        text: item.text,
+                    },
+                );
+                Ok(vec![self.publish_diagnostics(&item.uri)?])
+            }
+            "textDocument/didChange" => {
+                let params: DidChangeTextDocumentParams = parse_params(params)?;
+                let uri = params.text_document.uri;
+                {
+                    let document = self.documents.get_mut(&uri).ok_or_else(|| unknown(&uri))?;
+                    for change in params.content_changes {
+                        position::apply_change(&mut document.text, change.range, &change.text);
+                    }
+                }
+                Ok(vec![self.publish_diagnostics(&uri)?])
+            }
+            "textDocument/didClose" => {
+                let params: DocumentParams = parse_params(params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                Ok(vec![publish(uri, Vec::new())])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn document(&self, uri: &str) -> Result<&Document, ResponseError> {
+        self.documents.get(uri).ok_or_else(|| unknown(uri))
+    }
+
+    /// Typechecks `document`, returning the (possibly partially) typechecked expression and the
+    /// errors that were found
+    fn check(&self, document: &Document) -> (Option<Checked>, Option<Error>) {
+        self.thread
+            .get_database_mut()
+            .add_module(document.module.clone(), &document.text);
+
+        let mut db = self.thread.get_database();
+        let (value, err) =
+            match futures::executor::block_on(db.typechecked_module(document.module.clone(), None))
+            {
+                Ok(value) => (Some(value), None),
+                Err((value, err)) => (value, Some(err)),
+            };
+        let checked = db.get_filemap(&document.module).and_then(|file_map| {
+            value.map(|value| Checked {
+                value,
+                file_span: file_map.span(),
+            })
+        });
+        (checked, err)
+    }
+
+    fn checked(&self, uri: &str) -> Result<Option<(&Document, Checked)>, ResponseError> {
+        let document = self.document(uri)?;
+        Ok(self.check(document).0.map(|checked| (document, checked)))
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Result<Message, ResponseError> {
+        let document = self.document(uri)?;
+        let (checked, err) = self.check(document);
+
+        let mut diagnostics = Vec::new();
+        if let Some(err) = err {
+            let file_span = checked.map(|checked| checked.file_span).or_else(|| {
+                self.thread
+                    .get_database()
+                    .get_filemap(&document.module)
+                    .map(|file_map| file_map.span())
+            });
+            error_diagnostics(&document.text, file_span, &err, &mut diagnostics);
+        }
+        Ok(publish(uri.to_string(), diagnostics))
+    }
+
+    fn hover(&self, params: TextDocumentPositionParams) -> Result<Option<Hover>, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = checked.byte_pos(&document.text, params.position);
+
+        let env = self.thread.get_env();
+        let typ = match completion::find(&env, checked.file_span, expr, pos) {
+            Ok(typ) => typ,
+            Err(()) => return Ok(None),
+        };
+
+        let mut contents = format!("```gluon\n{}\n```", typ);
+        let comment =
+            completion::get_metadata(&checked.value.metadata_map, checked.file_span, expr, pos)
+                .and_then(|metadata| metadata.comment.as_ref());
+        if let Some(comment) = comment {
+            contents.push_str("\n\n");
+            contents.push_str(&comment.content);
+        }
+
+        let range = completion::completion(completion::SpanAt, checked.file_span, expr, pos)
+            .ok()
+            .map(|span| checked.range(&document.text, span));
+        Ok(Some(Hover {
+            contents: MarkupContent::markdown(contents),
+            range,
+        }))
+    }
+
+    fn completion(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Vec<CompletionItem>, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = checked.byte_pos(&document.text, params.position);
+
+        let env = self.thread.get_env();
+        let suggestions = completion::suggest(&env, checked.file_span, expr, pos);
+        Ok(suggestions
+            .into_iter()
+            .map(|suggestion| {
+                let (kind, detail) = match &suggestion.typ {
+                    Either::Left(kind) => (completion_item_kind::CLASS, kind.to_string()),
+                    Either::Right(typ) => (
+                        if is_function(typ) {
+                            completion_item_kind::FUNCTION
+                        } else {
+                            completion_item_kind::VARIABLE
+                        },
+                        typ.to_string(),
+                    ),
+                };
+                let documentation = completion::suggest_metadata(
+                    &checked.value.metadata_map,
+                    &env,
+                    checked.file_span,
+                    expr,
+                    pos,
+                    &suggestion.name,
+                )
+                .and_then(|metadata| metadata.comment.as_ref())
+                .map(|comment| MarkupContent::markdown(comment.content.clone()));
+                CompletionItem {
+                    label: suggestion.name,
+                    kind: Some(kind),
+                    detail: Some(detail),
+                    documentation,
+                }
+            })
+            .collect())
+    }
+
+    fn signature_help(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<SignatureHelp>, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = checked.byte_pos(&document.text, params.position);
+
+        let env = self.thread.get_env();
+        Ok(
+            completion::signature_help(&env, checked.file_span, expr, pos).map(|help| {
+                let parameters = help
+                    .typ
+                    .remove_forall_and_implicit_args()
+                    .arg_iter()
+                    .map(|arg| ParameterInformation {
+                        label: arg.to_string(),
+                    })
+                    .collect();
+                SignatureHelp {
+                    signatures: vec![SignatureInformation {
+                        label: format!("{} : {}", help.name, help.typ),
+                        documentation: None,
+                        parameters,
+                    }],
+                    active_signature: Some(0),
+                    active_parameter: help.index,
+                }
+            }),
+        )
+    }
+
+    /// Returns the spans of all uses of the symbol at `position`, ordered by where they appear in
+    /// the document. The first span is the binding of the symbol if it is bound in this document.
+    fn symbol_spans(
+        &self,
+        params: &TextDocumentPositionParams,
+    ) -> Result<Option<(&Document, Checked, Vec<Span<BytePos>>)>, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = checked.byte_pos(&document.text, params.position);
+
+        let mut spans = match completion::find_all_symbols(checked.file_span, expr, pos) {
+            Ok((_, spans)) => spans,
+            Err(()) => return Ok(None),
+        };
+        spans.retain(|span| checked.file_span.contains(*span));
+        spans.sort_by_key(|span| span.start());
+        Ok(Some((document, checked, spans)))
+    }
+
+    fn definition(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<Location>, ResponseError> {
+        let uri = params.text_document.uri.clone();
+        Ok(self
+            .symbol_spans(&params)?
+            .and_then(|(document, checked, spans)| {
+                spans.first().map(|span| Location {
+                    uri,
+                    range: checked.range(&document.text, *span),
+                })
+            }))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Result<Vec<Location>, ResponseError> {
+        let include_declaration = params.context.include_declaration;
+        let params = TextDocumentPositionParams {
+            text_document: params.text_document,
+            position: params.position,
+        };
+        let uri = &params.text_document.uri;
+        Ok(match self.symbol_spans(&params)? {
+            Some((document, checked, spans)) => spans
+                .iter()
+                .skip(if include_declaration { 0 } else { 1 })
+                .map(|span| Location {
+                    uri: uri.clone(),
+                    range: checked.range(&document.text, *span),
+                })
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn document_symbols(
+        &self,
+        params: DocumentParams,
+    ) -> Result<Vec<DocumentSymbol>, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+        let expr = checked.value.expr.expr();
+        let symbols = completion::all_symbols(checked.file_span, expr);
+        Ok(document_symbols(document, &checked, &symbols))
+    }
+
+    fn formatting(&self, params: DocumentParams) -> Result<Vec<TextEdit>, ResponseError> {
+        let document = self.document(&params.text_document.uri)?;
+        let formatted = self
+            .thread
+            .format_expr(&mut Formatter::default(), &document.module, &document.text)
+            .map_err(|err| error(INTERNAL_ERROR, err.to_string()))?;
+        if formatted == document.text {
+            return Ok(Vec::new());
+        }
+        Ok(vec![TextEdit {
+            range: position::range(&document.text, 0, document.text.len()),
+            new_text: formatted,
+        }])
+    }
+}
+
+fn document_symbols(
+    document: &Document,
+    checked: &Checked,
+    symbols: &[completion::SpCompletionSymbol],
+) -> Vec<DocumentSymbol> {
+    symbols
+        .iter()
+        .map(|symbol| {
+            let selection_range = checked.range(&document.text, symbol.span);
+            let (kind, detail, range) = match &symbol.value.content {
+                completion::CompletionSymbolContent::Value { typ, expr } => (
+                    if is_function(typ) {
+                        symbol_kind::FUNCTION
+                    } else {
+                        symbol_kind::VARIABLE
+                    },
+                    typ.to_string(),
+                    checked.range(
+                        &document.text,
+                        Span::new(symbol.span.start(), expr.span.end().max(symbol.span.end())),
+                    ),
+                ),
+                completion::CompletionSymbolContent::Type { .. } => {
+                    (symbol_kind::CLASS, String::new(), selection_range)
+                }
+            };
+            DocumentSymbol {
+                name: symbol.value.name.declared_name().to_string(),
+                detail: if detail.is_empty() {
+                    None
+                } else {
+                    Some(detail)
+                },
+                kind,
+                range,
+                selection_range,
+                children: document_symbols(document, checked, &symbol.value.children),
+            }
+        })
+        .collect()
+}
+
+fn error_diagnostics(
+    text: &str,
+    file_span: Option<Span<BytePos>>,
+    err: &Error,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match err {
+        Error::Multiple(errors) => {
+            for err in errors {
+                error_diagnostics(text, file_span, err, diagnostics);
+            }
+        }
+        Error::Parse(err) => in_file_diagnostics(text, file_span, err, diagnostics),
+        Error::Typecheck(err) => in_file_diagnostics(text, file_span, err, diagnostics),
+        Error::Macro(err) => in_file_diagnostics(text, file_span, err, diagnostics),
+        err => diagnostics.push(diagnostic(Range::default(), err.to_string())),
+    }
+}
+
+fn in_file_diagnostics<E>(
+    text: &str,
+    file_span: Option<Span<BytePos>>,
+    err: &InFile<E>,
+    diagnostics: &mut Vec<Diagnostic>,
+) where
+    E: fmt::Display,
+{
+    for spanned in err.errors() {
+        let diagnostic = match file_span {
+            Some(file_span) if file_span.contains(spanned.span) => {
+                let start = file_span.start().to_usize();
+                diagnostic(
+                    position::range(
+                        text,
+                        spanned.span.start().to_usize() - start,
+                        spanned.span.end().to_usize() - start,
+                    ),
+                    spanned.value.to_string(),
+                )
+            }
+            // Errors in imported modules can't be placed in this document so report them at
+            // the start instead
+            _ => diagnostic(
+                Range::default(),
+                format!("{}: {}", err.source_name(), spanned.value),
+            ),
+        };
+        diagnostics.push(diagnostic);
+    }
+}
+
+fn diagnostic(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(diagnostic_severity::ERROR),
+        source: Some("gluon".into()),
+        message,
+    }
+}
+
+fn publish(uri: String, diagnostics: Vec<Diagnostic>) -> Message {
+    Message::notification(
+        "textDocument/publishDiagnostics",
+        json!(PublishDiagnosticsParams { uri, diagnostics }),
+    )
+}
+
+fn is_function(typ: &ArcType) -> bool {
+    typ.remove_forall_and_implicit_args()
+        .as_function()
+        .is_some()
+}
+
+fn error(code: i64, message: impl Into<String>) -> ResponseError {
+    ResponseError {
+        code,
+        message: message.into(),
+    }
+}
+
+fn unknown(uri: &str) -> ResponseError {
+    error(INVALID_PARAMS, format!("Unknown document `{}`", uri))
+}
+
+fn parse_params<P>(params: Value) -> Result<P, ResponseError>
+where
+    P: DeserializeOwned,
+{
+    serde_json::from_value(params).map_err(|err| error(INVALID_PARAMS, err.to_string()))
+}
+
+fn to_value<T>(value: T) -> Result<Value, ResponseError>
+where
+    T: Serialize,
+{
+    serde_json::to_value(value).map_err(|err| error(INTERNAL_ERROR, err.to_string()))
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.trim_start_matches("file://");
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = iter.clone().take(2).collect::<Vec<_>>();
+            if let Some(decoded) = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(decoded);
+                iter.nth(1);
+                continue;
+            }
+        }
+        bytes.push(b);
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Returns the name of the module which the document at `uri` defines. Paths inside the current
+/// directory are made relative so that the module matches the name used to import it.
+pub fn uri_to_module(uri: &str) -> String {
+    let path = uri_to_path(uri);
+    let path = env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(|path| path.to_path_buf()))
+        .unwrap_or(path);
+    filename_to_module(&path.to_string_lossy())
+}
+
+/// Runs a language server which reads messages from `input` and writes its responses to
+/// `output` until the client sends the `exit` notification or closes `input`
+pub fn run(
+    thread: RootedThread,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut server = Server::new(thread);
+    while let Some(message) = rpc::read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                let message = Message::failure(Value::Null, PARSE_ERROR, err.to_string());
+                rpc::write_message(&mut output, &message)?;
+                continue;
+            }
+        };
+        for message in server.handle(message) {
+            rpc::write_message(&mut output, &message)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(())
+}
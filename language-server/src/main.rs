@@ -0,0 +1,17 @@
+extern crate env_logger;
+
+extern crate gluon;
+extern crate gluon_language_server;
+
+use std::io;
+
+fn main() {
+    env_logger::init();
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(err) = gluon_language_server::run(gluon::new_vm(), stdin.lock(), stdout.lock()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,63 @@
+//! Conversions between byte offsets into a document and the line/UTF-16 column positions used
+//! by the language server protocol.
+
+use crate::protocol::{Position, Range};
+
+/// Returns the byte offset of `position` in `text`. Positions past the end of a line or past
+/// the end of the document are clamped to the end of the line or document respectively.
+pub fn byte_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or_else(|| line.len())];
+
+    let mut character = 0;
+    for (i, c) in line.char_indices() {
+        if character >= position.character {
+            return line_start + i;
+        }
+        character += c.len_utf16() as u64;
+    }
+    line_start + line.trim_end_matches('\r').len()
+}
+
+/// Returns the position of the byte offset `offset` in `text`
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u64,
+        character: before[line_start..]
+            .chars()
+            .map(|c| c.len_utf16() as u64)
+            .sum(),
+    }
+}
+
+pub fn range(text: &str, start: usize, end: usize) -> Range {
+    Range {
+        start: position(text, start),
+        end: position(text, end),
+    }
+}
+
+/// Applies an edit replacing `range` (or the entire document if `None`) with `new_text`
+pub fn apply_change(text: &mut String, range: Option<Range>, new_text: &str) {
+    match range {
+        Some(range) => {
+            let start = byte_offset(text, range.start);
+            let end = byte_offset(text, range.end).max(start);
+            text.replace_range(start..end, new_text);
+        }
+        None => {
+            text.clear();
+            text.push_str(new_text);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! JSON-RPC messages and the `Content-Length` framing used by the language server protocol.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    Request(Request),
+    Notification(Notification),
+    Response(Response),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    #[serde(rename = "result")]
+    Success(Value),
+    #[serde(rename = "error")]
+    Failure(ResponseError),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Message {
+    pub fn notification(method: &str, params: Value) -> Message {
+        Message::Notification(Notification {
+            jsonrpc: "2.0".into(),
+            method: method.into(),
+            params,
+        })
+    }
+
+    pub fn success(id: Value, result: Value) -> Message {
+        Message::Response(Response {
+            jsonrpc: "2.0".into(),
+            id,
+            outcome: Outcome::Success(result),
+        })
+    }
+
+    pub fn failure(id: Value, code: i64, message: impl Into<String>) -> Message {
+        Message::Response(Response {
+            jsonrpc: "2.0".into(),
+            id,
+            outcome: Outcome::Failure(ResponseError {
+                code,
+                message: message.into(),
+            }),
+        })
+    }
+}
+
+/// Reads the next message from `reader`. Returns `None` once the input has been closed.
+///
+/// Only I/O and framing errors are returned as `Err`. A correctly framed body which is not valid
+/// JSON is returned as `Some(Err(..))` so that the caller can report it and keep reading.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<serde_json::Result<Value>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name.trim(), value.trim()),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Malformed header `{}`", header),
+                ))
+            }
+        };
+        if name.eq_ignore_ascii_case("Content-Length") {
+            content_length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?,
+            );
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
@@ -0,0 +1,284 @@
+use gluon_language_server::{
+    position,
+    protocol::{Position, Range},
+    rpc::{self, Message, Outcome},
+    Server,
+};
+
+use gluon::VmBuilder;
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///test.glu";
+
+fn new_server(text: &str) -> (Server, Vec<Message>) {
+    let thread = VmBuilder::new()
+        .import_paths(Some(vec![".".into(), "..".into()]))
+        .build();
+    let mut server = Server::new(thread);
+    server.handle(json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {},
+    }));
+    let messages = server.handle(json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+            "textDocument": {
+                "uri": URI,
+                "languageId": "gluon",
+                "version": 1,
+                "text": text,
+            },
+        },
+    }));
+    (server, messages)
+}
+
+fn request(server: &mut Server, method: &str, params: Value) -> Value {
+    let mut messages = server.handle(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    }));
+    assert_eq!(messages.len(), 1);
+    match messages.pop().unwrap() {
+        Message::Response(rpc::Response {
+            outcome: Outcome::Success(result),
+            ..
+        }) => result,
+        message => panic!("Unexpected message: {:?}", message),
+    }
+}
+
+fn position_params(line: u64, character: u64) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn diagnostics(messages: &[Message]) -> Vec<Value> {
+    match messages {
+        [Message::Notification(notification)] => {
+            assert_eq!(notification.method, "textDocument/publishDiagnostics");
+            notification.params["diagnostics"]
+                .as_array()
+                .unwrap()
+                .clone()
+        }
+        _ => panic!("Expected diagnostics, got: {:?}", messages),
+    }
+}
+
+#[test]
+fn no_diagnostics_for_valid_document() {
+    let (_, messages) = new_server("let x = 1\nx");
+    assert_eq!(diagnostics(&messages), Vec::<Value>::new());
+}
+
+#[test]
+fn diagnostics_for_type_error() {
+    let (_, messages) = new_server("let x = 1\nx #Int+ \"\"");
+    let diagnostics = diagnostics(&messages);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+}
+
+#[test]
+fn diagnostics_are_updated_on_change() {
+    let (mut server, _) = new_server("let x = 1\nx");
+    let messages = server.handle(json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{
+                "range": {
+                    "start": { "line": 1, "character": 0 },
+                    "end": { "line": 1, "character": 1 },
+                },
+                "text": "y",
+            }],
+        },
+    }));
+    assert_eq!(diagnostics(&messages).len(), 1);
+}
+
+#[test]
+fn hover() {
+    let (mut server, _) = new_server("/// A number\nlet x = 1\nx");
+    let result = request(&mut server, "textDocument/hover", position_params(2, 0));
+    let contents = result["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("Int"), "{}", contents);
+    assert!(contents.contains("A number"), "{}", contents);
+}
+
+#[test]
+fn completion() {
+    let (mut server, _) = new_server("let abc = 1\nlet abd = \"\"\nab");
+    let result = request(
+        &mut server,
+        "textDocument/completion",
+        position_params(2, 2),
+    );
+    let mut labels: Vec<_> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap().to_string())
+        .collect();
+    labels.sort();
+    assert_eq!(labels, ["abc", "abd"]);
+}
+
+#[test]
+fn definition_and_references() {
+    let (mut server, _) = new_server("let x = 1\nx #Int+ x");
+    let result = request(
+        &mut server,
+        "textDocument/definition",
+        position_params(1, 0),
+    );
+    assert_eq!(
+        result["range"],
+        json!({
+            "start": { "line": 0, "character": 4 },
+            "end": { "line": 0, "character": 5 },
+        })
+    );
+
+    let mut params = position_params(1, 0);
+    params["context"] = json!({ "includeDeclaration": false });
+    let result = request(&mut server, "textDocument/references", params);
+    assert_eq!(result.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn document_symbols() {
+    let (mut server, _) = new_server("let f x = x\nlet y = 1\n{ f, y }");
+    let result = request(
+        &mut server,
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names: Vec<_> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["f", "y"]);
+}
+
+#[test]
+fn formatting() {
+    let (mut server, _) = new_server("let   x = 1\nx\n");
+    let result = request(
+        &mut server,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": URI }, "options": {} }),
+    );
+    assert_eq!(result[0]["newText"], "let x = 1\nx\n");
+}
+
+#[test]
+fn unknown_method() {
+    let (mut server, _) = new_server("1");
+    let messages = server.handle(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "textDocument/unknown",
+    }));
+    match &messages[..] {
+        [Message::Response(rpc::Response {
+            outcome: Outcome::Failure(err),
+            ..
+        })] => assert_eq!(err.code, rpc::METHOD_NOT_FOUND),
+        _ => panic!("Unexpected messages: {:?}", messages),
+    }
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    let text = "let å = 1\nlet 𝄞x = 2";
+    let offset = text.find('x').unwrap();
+    let pos = Position {
+        line: 1,
+        character: 6,
+    };
+    assert_eq!(position::position(text, offset), pos);
+    assert_eq!(position::byte_offset(text, pos), offset);
+
+    let mut text = text.to_string();
+    position::apply_change(
+        &mut text,
+        Some(Range {
+            start: Position {
+                line: 0,
+                character: 4,
+            },
+            end: Position {
+                line: 0,
+                character: 5,
+            },
+        }),
+        "a",
+    );
+    assert_eq!(text, "let a = 1\nlet 𝄞x = 2");
+}
+
+#[test]
+fn read_and_write_messages() {
+    let message = Message::success(json!(1), json!(null));
+    let mut buffer = Vec::new();
+    rpc::write_message(&mut buffer, &message).unwrap();
+    let read = rpc::read_message(&mut &buffer[..])
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(serde_json::from_value::<Message>(read).unwrap(), message);
+}
+
+#[test]
+fn invalid_json_is_reported_and_the_server_keeps_running() {
+    let body = "{ not json";
+    let mut input = format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes();
+    rpc::write_message(
+        &mut input,
+        &Message::Request(rpc::Request {
+            jsonrpc: "2.0".into(),
+            id: json!(1),
+            method: "shutdown".into(),
+            params: Value::Null,
+        }),
+    )
+    .unwrap();
+
+    let mut output = Vec::new();
+    gluon_language_server::run(VmBuilder::new().build(), &input[..], &mut output).unwrap();
+
+    let mut output = &output[..];
+    let mut messages = Vec::new();
+    while let Some(message) = rpc::read_message(&mut output).unwrap() {
+        messages.push(serde_json::from_value::<Message>(message.unwrap()).unwrap());
+    }
+    match &messages[..] {
+        [Message::Response(rpc::Response {
+            id: Value::Null,
+            outcome: Outcome::Failure(err),
+            ..
+        }), Message::Response(rpc::Response {
+            id,
+            outcome: Outcome::Success(_),
+            ..
+        })] => {
+            assert_eq!(err.code, rpc::PARSE_ERROR);
+            assert_eq!(*id, json!(1));
+        }
+        _ => panic!("Unexpected messages: {:?}", messages),
+    }
+}
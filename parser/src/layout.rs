@@ -311,6 +311,8 @@ where
                 | (&Token::RBrace, _)
                 | (&Token::RBracket, _)
                 | (&Token::RParen, _)
+                | (&Token::InterpolationMiddle(_), _)
+                | (&Token::InterpolationEnd(_), _)
                 | (&Token::Comma, _) => {
                     self.indent_levels.pop();
 
@@ -333,7 +335,15 @@ where
                             Context::Brace
                             | Context::Bracket
                             | Context::Paren
-                            | Context::Attribute => return Ok(token),
+                            | Context::Attribute => {
+                                // The part of an interpolated string between two holes also
+                                // opens the next hole
+                                if let Token::InterpolationMiddle(_) = token.value {
+                                    self.indent_levels
+                                        .push(Offside::new(token.span.start(), Context::Paren))?;
+                                }
+                                return Ok(token);
+                            }
                             Context::Block { .. } if token.value == Token::CloseBlock => {
                                 if let Some(offside) = self.indent_levels.last_mut() {
                                     // The enclosing block should not emit a block separator for the next
@@ -518,6 +528,8 @@ where
                 Token::LBrace => Some(Context::Brace),
                 Token::LBracket => Some(Context::Bracket),
                 Token::LParen => Some(Context::Paren),
+                // The holes of an interpolated string are delimited like parenthesized expressions
+                Token::InterpolationStart(_) => Some(Context::Paren),
                 Token::AttributeOpen => Some(Context::Attribute),
                 _ => None,
             };
@@ -606,6 +618,8 @@ fn token_closes_context(token: &Token, context: Context) -> bool {
         | (&Token::RBrace, Context::Brace)
         | (&Token::RBracket, Context::Bracket)
         | (&Token::RParen, Context::Paren)
+        | (&Token::InterpolationMiddle(_), Context::Paren)
+        | (&Token::InterpolationEnd(_), Context::Paren)
         | (&Token::CloseBlock, Context::Block { .. })
         | (&Token::In, Context::Rec)
         | (&Token::In, Context::Let)
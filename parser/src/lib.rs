@@ -108,6 +108,121 @@ fn shrink_hidden_spans<Id>(mut expr: SpannedExpr<Id>) -> SpannedExpr<Id> {
     expr
 }
 
+/// A literal part of an interpolated string and the expression in the hole following it, if any.
+/// The span of the literal part includes the delimiters around it (`"`, `${` and `}`).
+type InterpolationPart<'ast, Id> = (Spanned<String, BytePos>, Option<SpannedExpr<'ast, Id>>);
+
+/// Returns the expression `(import! std.interpolate).<name>`
+fn interpolate_function<'ast, Id>(
+    arena: ast::ArenaRef<'_, 'ast, Id>,
+    type_cache: &TypeCache<Id, ArcType<Id>>,
+    env: &mut dyn IdentEnv<Ident = Id>,
+    span: Span<BytePos>,
+    name: &str,
+) -> SpannedExpr<'ast, Id>
+where
+    Id: Clone,
+{
+    let module = pos::spanned(
+        span,
+        Expr::Projection(
+            arena.alloc(pos::spanned(
+                span,
+                Expr::Ident(new_ident(type_cache, env.from_str("std"))),
+            )),
+            env.from_str("interpolate"),
+            type_cache.hole(),
+        ),
+    );
+    let import = pos::spanned(
+        span,
+        Expr::App {
+            func: arena.alloc(pos::spanned(
+                span,
+                Expr::Ident(new_ident(type_cache, env.from_str("import!"))),
+            )),
+            implicit_args: &mut [],
+            args: arena.alloc_extend(Some(module)),
+        },
+    );
+    pos::spanned(
+        span,
+        Expr::Projection(arena.alloc(import), env.from_str(name), type_cache.hole()),
+    )
+}
+
+/// Desugars an interpolated string such as `"total: ${x}"` into
+/// `(import! std.interpolate).concat ["total: ", (import! std.interpolate).display x]`.
+///
+/// Unlike `do` expressions which use the `flat_map` which is in scope, the functions are reached
+/// through `import!` so the desugaring works regardless of what is in scope. `display` takes the
+/// `Show` instance of the hole as an implicit argument so it is found through implicit resolution,
+/// except that strings are inserted without the quotes that `Show String` adds. The result is
+/// wrapped in a `MacroExpansion` so that the original string is kept for the formatter.
+fn interpolate<'ast, Id>(
+    arena: ast::ArenaRef<'_, 'ast, Id>,
+    type_cache: &TypeCache<Id, ArcType<Id>>,
+    env: &mut dyn IdentEnv<Ident = Id>,
+    span: Span<BytePos>,
+    parts: Vec<InterpolationPart<'ast, Id>>,
+) -> Expr<'ast, Id>
+where
+    Id: Clone,
+{
+    // The added functions are given the spans of the literal parts so that the spans of the holes
+    // only contain the expressions that were written
+    let concat_span = parts
+        .first()
+        .expect("An interpolated string has at least one hole")
+        .0
+        .span;
+    let concat = interpolate_function(arena, type_cache, env, concat_span, "concat");
+
+    let mut literal = String::new();
+    let mut exprs = Vec::new();
+    for (part, hole) in parts {
+        literal.push_str(&part.value);
+        let part_span = part.span;
+        if !part.value.is_empty() {
+            exprs.push(part.map(|s| Expr::Literal(ast::Literal::String(s))));
+        }
+        if let Some(hole) = hole {
+            let display = interpolate_function(arena, type_cache, env, part_span, "display");
+            exprs.push(pos::spanned(
+                hole.span,
+                Expr::App {
+                    func: arena.alloc(display),
+                    implicit_args: &mut [],
+                    args: arena.alloc_extend(Some(hole)),
+                },
+            ));
+        }
+    }
+
+    let replacement = pos::spanned(
+        span,
+        Expr::App {
+            func: arena.alloc(concat),
+            implicit_args: &mut [],
+            args: arena.alloc_extend(Some(pos::spanned(
+                span,
+                Expr::Array(ast::Array {
+                    typ: type_cache.hole(),
+                    exprs: arena.alloc_extend(exprs),
+                }),
+            ))),
+        },
+    );
+
+    Expr::MacroExpansion {
+        original: arena.alloc(pos::spanned(
+            span,
+            Expr::Literal(ast::Literal::String(literal)),
+        )),
+        replacement: arena.alloc(replacement),
+    }
+}
+
 fn transform_errors<'a, Iter>(
     source_span: Span<BytePos>,
     errors: Iter,
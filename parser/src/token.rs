@@ -22,6 +22,12 @@ pub enum Token<'input> {
     Operator(&'input str),
 
     StringLiteral(StringLiteral<'input>),
+    /// The start of an interpolated string up to the first hole, `"abc ${`
+    InterpolationStart(StringLiteral<'input>),
+    /// The part of an interpolated string between two holes, `} abc ${`
+    InterpolationMiddle(StringLiteral<'input>),
+    /// The end of an interpolated string after the last hole, `} abc"`
+    InterpolationEnd(StringLiteral<'input>),
     CharLiteral(char),
     IntLiteral(i64),
     ByteLiteral(u8),
@@ -77,6 +83,9 @@ impl<'input> fmt::Display for Token<'input> {
             Identifier(_) => "Identifier",
             Operator(_) => "Operator",
             StringLiteral(_) => "StringLiteral",
+            InterpolationStart(_) => "InterpolationStart",
+            InterpolationMiddle(_) => "InterpolationMiddle",
+            InterpolationEnd(_) => "InterpolationEnd",
             CharLiteral(_) => "CharLiteral",
             IntLiteral(_) => "IntLiteral",
             ByteLiteral(_) => "ByteLiteral",
@@ -150,6 +159,7 @@ fn unescape_string_literal(mut s: &str) -> String {
             b'"' => '"',
             b'\\' => '\\',
             b'/' => '/',
+            b'$' => '$',
             b'n' => '\n',
             b'r' => '\r',
             b't' => '\t',
@@ -284,6 +294,8 @@ pub struct Tokenizer<'input> {
     input: &'input str,
     chars: CharLocations<'input>,
     start_index: BytePos,
+    /// The number of unclosed `{` in each hole of the interpolated strings being tokenized
+    interpolations: Vec<u32>,
 }
 
 impl<'input> Tokenizer<'input> {
@@ -297,6 +309,7 @@ impl<'input> Tokenizer<'input> {
             input: input.src(),
             chars,
             start_index: input.start_index(),
+            interpolations: Vec::new(),
         }
     }
 
@@ -438,6 +451,7 @@ impl<'input> Tokenizer<'input> {
             Some((_, b'"')) => Ok(b'"'),
             Some((_, b'\\')) => Ok(b'\\'),
             Some((_, b'/')) => Ok(b'/'),
+            Some((_, b'$')) => Ok(b'$'),
             Some((_, b'n')) => Ok(b'\n'),
             Some((_, b'r')) => Ok(b'\r'),
             Some((_, b't')) => Ok(b'\t'),
@@ -450,24 +464,46 @@ impl<'input> Tokenizer<'input> {
         }
     }
 
-    fn string_literal(&mut self, start: Location) -> Result<SpannedToken<'input>, SpError> {
+    /// Tokenizes a string literal starting after the opening `"`, or the rest of an interpolated
+    /// string after the `}` which closes a hole if `continued` is true.
+    fn string_literal(
+        &mut self,
+        start: Location,
+        continued: bool,
+    ) -> Result<SpannedToken<'input>, SpError> {
         let content_start = self.next_loc();
         loop {
             let scan_start = self.next_loc();
-            self.take_until(scan_start, |b| b == b'"' || b == b'\\');
+            self.take_until(scan_start, |b| b == b'"' || b == b'\\' || b == b'$');
             match self.bump() {
                 Some((_, b'\\')) => {
                     self.escape_code()?;
                 }
+                Some((content_end, b'$')) if self.test_lookahead(|ch| ch == b'{') => {
+                    self.bump();
+                    self.interpolations.push(0);
+
+                    let content = StringLiteral::Escaped(self.slice(content_start, content_end));
+                    let token = if continued {
+                        Token::InterpolationMiddle(content)
+                    } else {
+                        Token::InterpolationStart(content)
+                    };
+                    return Ok(pos::spanned2(start, self.next_loc(), token));
+                }
+                Some((_, b'$')) => (),
                 Some((_, b'"')) => {
                     let end = self.next_loc();
 
                     let mut content_end = end;
                     content_end.absolute.0 -= 1;
 
-                    let token = Token::StringLiteral(StringLiteral::Escaped(
-                        self.slice(content_start, content_end),
-                    ));
+                    let content = StringLiteral::Escaped(self.slice(content_start, content_end));
+                    let token = if continued {
+                        Token::InterpolationEnd(content)
+                    } else {
+                        Token::StringLiteral(content)
+                    };
                     return Ok(pos::spanned2(start, end, token));
                 }
                 _ => break,
@@ -669,10 +705,27 @@ impl<'input> Iterator for Tokenizer<'input> {
             return match ch {
                 b',' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Comma))),
                 b'\\' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Lambda))),
-                b'{' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBrace))),
+                b'{' => {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBrace)))
+                }
                 b'[' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBracket))),
                 b'(' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LParen))),
-                b'}' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBrace))),
+                b'}' => match self.interpolations.last_mut() {
+                    // Closes the hole of an interpolated string
+                    Some(0) => {
+                        self.interpolations.pop();
+                        Some(self.string_literal(start, true))
+                    }
+                    depth => {
+                        if let Some(depth) = depth {
+                            *depth -= 1;
+                        }
+                        Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBrace)))
+                    }
+                },
                 b']' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBracket))),
                 b')' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RParen))),
                 b'?' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Question))),
@@ -680,7 +733,7 @@ impl<'input> Iterator for Tokenizer<'input> {
                 b'r' if self.test_lookahead(|ch| ch == b'"' || ch == b'#') => {
                     Some(self.raw_string_literal(start))
                 }
-                b'"' => Some(self.string_literal(start)),
+                b'"' => Some(self.string_literal(start, false)),
                 b'\'' => Some(self.char_literal(start)),
 
                 b'/' if self.test_lookahead(|ch| ch == b'/') => match self.line_comment(start) {
@@ -921,6 +974,36 @@ mod test {
         assert_eq!(StringLiteral::Escaped(r#"\"\""#).unescape(), r#""""#);
     }
 
+    #[test]
+    fn interpolated_string_literals() {
+        test(
+            r#""a ${x} b ${ {y} } c" "\${x}""#,
+            vec![
+                (
+                    r#"~~~~~                        "#,
+                    InterpolationStart(StringLiteral::Escaped("a ")),
+                ),
+                (r#"     ~                       "#, Identifier("x")),
+                (
+                    r#"      ~~~~~~                 "#,
+                    InterpolationMiddle(StringLiteral::Escaped(" b ")),
+                ),
+                (r#"             ~               "#, LBrace),
+                (r#"              ~              "#, Identifier("y")),
+                (r#"               ~             "#, RBrace),
+                (
+                    r#"                 ~~~~        "#,
+                    InterpolationEnd(StringLiteral::Escaped(" c")),
+                ),
+                (
+                    r#"                      ~~~~~~~"#,
+                    Token::StringLiteral(StringLiteral::Escaped("\\${x}")),
+                ),
+            ],
+        );
+        assert_eq!(StringLiteral::Escaped("\\${x}").unescape(), "${x}");
+    }
+
     #[test]
     fn raw_string_literals() {
         test(
@@ -383,6 +383,83 @@ fn span_string_literal() {
     assert_eq!(e.expr().span, Span::new(BytePos::from(1), BytePos::from(7)));
 }
 
+#[test]
+fn interpolated_string() {
+    let _ = ::env_logger::try_init();
+
+    let e = parse_clear_span!(r#" "a ${x} b ${f 1}" "#);
+    mk_ast_arena!(arena);
+    let arena = (*arena).borrow();
+    let string = |s: &str| no_loc(Expr::Literal(Literal::String(s.into())));
+    let interpolate = |name| {
+        let module = app(
+            arena,
+            id("import!"),
+            vec![field_access(arena, id("std"), "interpolate")],
+        );
+        field_access(arena, module, name)
+    };
+    let display = |e| app(arena, interpolate("display"), vec![e]);
+    match &e.expr().value {
+        Expr::MacroExpansion {
+            original,
+            replacement,
+        } => {
+            assert_eq!(
+                original.value,
+                Expr::Literal(Literal::String("a  b ".into()))
+            );
+            assert_eq!(
+                **replacement,
+                app(
+                    arena,
+                    interpolate("concat"),
+                    vec![array(
+                        arena,
+                        vec![
+                            string("a "),
+                            display(id("x")),
+                            string(" b "),
+                            display(app(arena, id("f"), vec![int(1)])),
+                        ]
+                    )]
+                )
+            );
+        }
+        expr => panic!("Expected an interpolated string, found {:?}", expr),
+    }
+}
+
+#[test]
+fn span_interpolated_string_hole() {
+    let _ = ::env_logger::try_init();
+
+    let e = parse_zero_index!(r#" "a ${ x } b" "#);
+    assert_eq!(
+        e.expr().span,
+        Span::new(BytePos::from(1), BytePos::from(13))
+    );
+    match &e.expr().value {
+        Expr::MacroExpansion { replacement, .. } => match &replacement.value {
+            Expr::App { args, .. } => match &args[0].value {
+                Expr::Array(array) => match &array.exprs[1].value {
+                    Expr::App { args, .. } => {
+                        assert_eq!(args[0].span, Span::new(BytePos::from(7), BytePos::from(8)));
+                        assert_eq!(
+                            array.exprs[1].span,
+                            Span::new(BytePos::from(7), BytePos::from(8))
+                        );
+                    }
+                    expr => panic!("{:?}", expr),
+                },
+                expr => panic!("{:?}", expr),
+            },
+            expr => panic!("{:?}", expr),
+        },
+        expr => panic!("{:?}", expr),
+    }
+}
+
 #[test]
 fn span_app() {
     let _ = ::env_logger::try_init();
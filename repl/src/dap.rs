@@ -0,0 +1,388 @@
+//! Debug Adapter Protocol frontend for `gluon::debugger`
+//!
+//! Messages are read from and written to the streams given to `run` using the same
+//! `Content-Length` framing as the language server protocol. Since the protocol uses stdout the
+//! debugged program should avoid writing to stdout itself.
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use gluon::{
+    debugger::{Debugger, Frontend, Resume, Stop, StopReason, Variable},
+    Error, Result,
+};
+
+use crate::base::filename_to_module;
+
+/// The only thread that is reported to the client
+const THREAD_ID: i64 = 1;
+
+/// What the client asked for with a request
+enum Action {
+    None,
+    Launch(Option<String>),
+    ConfigurationDone,
+    Resume(Resume),
+    Disconnect,
+}
+
+struct Dap<R, W> {
+    input: R,
+    output: W,
+    seq: i64,
+    stop: Option<Stop>,
+}
+
+pub async fn run<R, W>(
+    debugger: &Debugger,
+    program: Option<String>,
+    input: R,
+    output: W,
+) -> Result<()>
+where
+    R: BufRead + Send,
+    W: Write + Send,
+{
+    let mut dap = Dap {
+        input,
+        output,
+        seq: 0,
+        stop: None,
+    };
+
+    let mut program = program;
+    let mut launched = false;
+    let mut configured = false;
+    while !(launched && configured) {
+        match dap.next_action(debugger).await? {
+            Action::Launch(launch_program) => {
+                launched = true;
+                program = launch_program.or(program);
+            }
+            Action::ConfigurationDone => configured = true,
+            Action::Disconnect => return Ok(()),
+            Action::Resume(_) | Action::None => (),
+        }
+    }
+
+    let result = match program {
+        Some(program) => dap.run_program(debugger, &program).await,
+        None => Err(Error::from("No program to debug was specified".to_string())),
+    };
+    let exit_code = match result {
+        Ok(()) => 0,
+        Err(err) => {
+            dap.event(
+                "output",
+                json!({ "category": "stderr", "output": format!("{}\n", err) }),
+            )?;
+            1
+        }
+    };
+    dap.event("exited", json!({ "exitCode": exit_code }))?;
+    dap.event("terminated", json!({}))?;
+
+    // Answer requests until the client disconnects
+    loop {
+        match dap.next_action(debugger).await {
+            Ok(Action::Disconnect) | Err(_) => return Ok(()),
+            Ok(_) => (),
+        }
+    }
+}
+
+impl<R, W> Dap<R, W>
+where
+    R: BufRead + Send,
+    W: Write + Send,
+{
+    async fn run_program(&mut self, debugger: &Debugger, program: &str) -> Result<()> {
+        let source = fs::read_to_string(program)?;
+        let module = filename_to_module(program);
+        let (value, typ) = debugger.run_expr(self, &module, &source).await?;
+        let output = format!(
+            "{} : {}\n",
+            gluon::debugger::show_value(value.vm(), &typ, value.get_variant()),
+            typ
+        );
+        self.event("output", json!({ "category": "console", "output": output }))?;
+        Ok(())
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let content = serde_json::to_string(&message)?;
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.output.flush()
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn respond(
+        &mut self,
+        request: &Value,
+        result: std::result::Result<Value, String>,
+    ) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+
+    fn read(&mut self) -> io::Result<Option<Value>> {
+        let mut content_length = None;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let mut parts = header.splitn(2, ':');
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                if name.trim().eq_ignore_ascii_case("Content-Length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let content_length = content_length.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+        })?;
+        let mut content = vec![0; content_length];
+        self.input.read_exact(&mut content)?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads and answers requests until one of them requires an action from the caller
+    async fn next_action(&mut self, debugger: &Debugger) -> Result<Action> {
+        loop {
+            let request = match self.read()? {
+                Some(request) => request,
+                None => return Ok(Action::Disconnect),
+            };
+            if request["type"] != "request" {
+                continue;
+            }
+            let (result, action) = self.handle(debugger, &request).await;
+            self.respond(&request, result)?;
+            if request["command"] == "initialize" {
+                // Lets the client know that breakpoints can be sent
+                self.event("initialized", json!({}))?;
+            }
+            match action {
+                Action::None => (),
+                action => return Ok(action),
+            }
+        }
+    }
+
+    async fn handle(
+        &mut self,
+        debugger: &Debugger,
+        request: &Value,
+    ) -> (std::result::Result<Value, String>, Action) {
+        let arguments = &request["arguments"];
+        let result = match request["command"].as_str().unwrap_or("") {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsConditionalBreakpoints": true,
+                "supportsEvaluateForHovers": true,
+            })),
+            "launch" => {
+                let program = arguments["program"].as_str().map(|s| s.to_string());
+                return (Ok(Value::Null), Action::Launch(program));
+            }
+            "configurationDone" => return (Ok(Value::Null), Action::ConfigurationDone),
+            "disconnect" | "terminate" => return (Ok(Value::Null), Action::Disconnect),
+            "setBreakpoints" => Ok(set_breakpoints(debugger, arguments)),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(self.scopes(arguments)),
+            "variables" => Ok(self.variables(arguments)),
+            "evaluate" => self.evaluate(debugger, arguments).await,
+            "continue" => return self.resume(Resume::Continue),
+            "next" => return self.resume(Resume::StepOver),
+            "stepIn" => return self.resume(Resume::StepIn),
+            "stepOut" => return self.resume(Resume::StepOut),
+            command => Err(format!("Unsupported request `{}`", command)),
+        };
+        (result, Action::None)
+    }
+
+    fn resume(&mut self, resume: Resume) -> (std::result::Result<Value, String>, Action) {
+        if self.stop.is_none() {
+            return (Err("The program is not stopped".into()), Action::None);
+        }
+        (
+            Ok(json!({ "allThreadsContinued": true })),
+            Action::Resume(resume),
+        )
+    }
+
+    fn stack_trace(&self) -> Value {
+        let frames = self.stop.iter().flat_map(|stop| stop.frames.iter());
+        let frames: Vec<_> = frames
+            .enumerate()
+            .map(|(i, frame)| {
+                json!({
+                    "id": i,
+                    "name": frame.function_name.as_deref().unwrap_or("<unknown>"),
+                    "source": {
+                        "name": frame.source_name,
+                        "path": module_path(&frame.source_name),
+                    },
+                    "line": frame.line.unwrap_or(0),
+                    "column": 1,
+                })
+            })
+            .collect();
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    /// Each frame gets two variable references, `2 * frame + 1` for locals and `2 * frame + 2`
+    /// for upvariables
+    fn scopes(&self, arguments: &Value) -> Value {
+        let frame = arguments["frameId"].as_u64().unwrap_or(0);
+        json!({
+            "scopes": [
+                { "name": "Locals", "variablesReference": 2 * frame + 1, "expensive": false },
+                { "name": "Upvars", "variablesReference": 2 * frame + 2, "expensive": false },
+            ]
+        })
+    }
+
+    fn variables(&self, arguments: &Value) -> Value {
+        let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+        let variables: Vec<_> = self
+            .stop
+            .as_ref()
+            .and_then(|stop| stop.frames.get(reference.saturating_sub(1) / 2))
+            .map(|frame| {
+                let variables: &[Variable] = if reference % 2 == 1 {
+                    &frame.locals
+                } else {
+                    &frame.upvars
+                };
+                variables
+                    .iter()
+                    .map(|variable| {
+                        json!({
+                            "name": variable.name,
+                            "value": variable.show(),
+                            "type": variable.typ.to_string(),
+                            "variablesReference": 0,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        json!({ "variables": variables })
+    }
+
+    async fn evaluate(
+        &self,
+        debugger: &Debugger,
+        arguments: &Value,
+    ) -> std::result::Result<Value, String> {
+        let stop = self.stop.as_ref().ok_or("The program is not stopped")?;
+        let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+        let frame = stop.frames.get(frame).ok_or("Invalid frame")?;
+        let expr = arguments["expression"].as_str().unwrap_or("");
+        let value = debugger
+            .evaluate_to_string(frame, expr)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(json!({ "result": value, "variablesReference": 0 }))
+    }
+}
+
+#[async_trait::async_trait]
+impl<R, W> Frontend for Dap<R, W>
+where
+    R: BufRead + Send,
+    W: Write + Send,
+{
+    async fn stopped(&mut self, debugger: &Debugger, stop: &Stop) -> Result<Resume> {
+        let (reason, description, breakpoints) = match &stop.reason {
+            StopReason::Breakpoint(ids) => ("breakpoint", None, ids.clone()),
+            StopReason::Step => ("step", None, Vec::new()),
+            StopReason::ConditionError {
+                breakpoint,
+                message,
+            } => ("exception", Some(message.clone()), vec![*breakpoint]),
+        };
+        self.stop = Some(stop.clone());
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+                "hitBreakpointIds": breakpoints,
+            }),
+        )?;
+
+        let action = self.next_action(debugger).await;
+        self.stop = None;
+        match action? {
+            Action::Resume(resume) => Ok(resume),
+            Action::Disconnect => Err(Error::from("Debugging aborted".to_string())),
+            _ => Ok(Resume::Continue),
+        }
+    }
+}
+
+fn set_breakpoints(debugger: &Debugger, arguments: &Value) -> Value {
+    let path = arguments["source"]["path"]
+        .as_str()
+        .or_else(|| arguments["source"]["name"].as_str())
+        .unwrap_or("");
+    debugger.clear_breakpoints(path);
+
+    let breakpoints: Vec<_> = arguments["breakpoints"]
+        .as_array()
+        .map(|breakpoints| &breakpoints[..])
+        .unwrap_or(&[])
+        .iter()
+        .map(|breakpoint| {
+            let line = breakpoint["line"].as_u64().unwrap_or(0) as usize;
+            let condition = breakpoint["condition"]
+                .as_str()
+                .filter(|condition| !condition.trim().is_empty())
+                .map(|condition| condition.to_string());
+            let id = debugger.add_breakpoint(path, line, condition);
+            json!({ "id": id, "verified": true, "line": line })
+        })
+        .collect();
+    json!({ "breakpoints": breakpoints })
+}
+
+/// Guesses the path of the file which defines `module`
+fn module_path(module: &str) -> String {
+    format!("{}.glu", module.trim_start_matches('@').replace('.', "/"))
+}
@@ -0,0 +1,303 @@
+//! Command line frontend for `gluon::debugger`
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use structopt::StructOpt;
+
+use gluon::{
+    debugger::{Debugger, Frame, Frontend, Resume, Stop, StopReason},
+    Error, Result, RootedThread,
+};
+
+use crate::base::filename_to_module;
+
+#[derive(StructOpt)]
+#[structopt(about = "Runs a gluon program under the debugger")]
+pub struct DebugOpt {
+    #[structopt(
+        long = "break",
+        short = "b",
+        help = "Adds a breakpoint before starting, written as `FILE:LINE`"
+    )]
+    breakpoints: Vec<String>,
+
+    #[structopt(
+        long = "dap",
+        help = "Communicates with a client through the Debug Adapter Protocol on stdin/stdout"
+    )]
+    dap: bool,
+
+    #[structopt(name = "FILE", help = "The gluon program to debug")]
+    input: Option<String>,
+}
+
+const HELP: &str = r#"Commands:
+    run | r                       Starts the program
+    break | b FILE:LINE [if EXPR] Adds a breakpoint, stopping only if EXPR is `True`
+    delete | d ID                 Removes a breakpoint
+    breakpoints                   Lists all breakpoints
+    continue | c                  Continues until the next breakpoint
+    step | s                      Steps to the next line, entering function calls
+    next | n                      Steps to the next line, stepping over function calls
+    finish | f                    Continues until the current function returns
+    backtrace | bt                Prints the stack
+    frame N                       Selects the frame to inspect
+    locals | l                    Prints the variables of the selected frame
+    print | p EXPR                Evaluates EXPR in the selected frame
+    quit | q                      Aborts the program
+    help | h                      Prints this message"#;
+
+pub async fn run(thread: RootedThread, opt: &DebugOpt) -> Result<()> {
+    let debugger = Debugger::new(thread);
+    for breakpoint in &opt.breakpoints {
+        let (file, line, condition) = parse_breakpoint(breakpoint)?;
+        debugger.add_breakpoint(file, line, condition);
+    }
+
+    if opt.dap {
+        return crate::dap::run(
+            &debugger,
+            opt.input.clone(),
+            io::BufReader::new(io::stdin()),
+            io::stdout(),
+        )
+        .await;
+    }
+
+    let file = opt
+        .input
+        .as_ref()
+        .ok_or_else(|| Error::from("Expected a file to debug".to_string()))?;
+    let source = fs::read_to_string(file)?;
+    let module = filename_to_module(file);
+
+    let mut cli = Cli {
+        module: module.clone(),
+        source: source.clone(),
+        frame: 0,
+    };
+    if !cli.prepare(&debugger)? {
+        return Ok(());
+    }
+    let (value, typ) = debugger.run_expr(&mut cli, &module, &source).await?;
+    println!(
+        "Program finished: {} : {}",
+        gluon::debugger::show_value(value.vm(), &typ, value.get_variant()),
+        typ
+    );
+    Ok(())
+}
+
+/// Parses `FILE:LINE [if EXPR]`
+fn parse_breakpoint(s: &str) -> Result<(&str, usize, Option<String>)> {
+    let (location, condition) = match s.find(" if ") {
+        Some(i) => (&s[..i], Some(s[i + 4..].trim().to_string())),
+        None => (s, None),
+    };
+    let location = location.trim();
+    let invalid = || Error::from(format!("Expected `FILE:LINE`, found `{}`", location));
+    let i = location.rfind(':').ok_or_else(invalid)?;
+    let line = location[i + 1..].parse().map_err(|_| invalid())?;
+    Ok((&location[..i], line, condition))
+}
+
+struct Cli {
+    module: String,
+    source: String,
+    /// The frame which is inspected by `locals` and `print`
+    frame: usize,
+}
+
+enum Command<'a> {
+    Resume(Resume),
+    Run,
+    Quit,
+    Other(&'a str, &'a str),
+}
+
+fn parse_command(line: &str) -> Command {
+    let line = line.trim();
+    let (command, args) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim()),
+        None => (line, ""),
+    };
+    match command {
+        "run" | "r" => Command::Run,
+        "continue" | "c" => Command::Resume(Resume::Continue),
+        "step" | "s" => Command::Resume(Resume::StepIn),
+        "next" | "n" => Command::Resume(Resume::StepOver),
+        "finish" | "f" => Command::Resume(Resume::StepOut),
+        "quit" | "q" => Command::Quit,
+        _ => Command::Other(command, args),
+    }
+}
+
+fn read_line(prompt: &str) -> Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+impl Cli {
+    /// Reads commands until the program should be started. Returns `false` if the user quit.
+    fn prepare(&mut self, debugger: &Debugger) -> Result<bool> {
+        println!(
+            "Debugging `{}`. Type `help` for a list of commands",
+            self.module
+        );
+        loop {
+            let line = match read_line("(debug) ")? {
+                Some(line) => line,
+                None => return Ok(false),
+            };
+            match parse_command(&line) {
+                Command::Run | Command::Resume(_) => return Ok(true),
+                Command::Quit => return Ok(false),
+                Command::Other(command, args) => {
+                    if !self.breakpoint_command(debugger, command, args) {
+                        println!("The program is not running");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles the commands which do not need a stopped program. Returns `false` if `command`
+    /// were not one of them.
+    fn breakpoint_command(&self, debugger: &Debugger, command: &str, args: &str) -> bool {
+        match command {
+            "break" | "b" => match parse_breakpoint(args) {
+                Ok((file, line, condition)) => {
+                    let file = if file.is_empty() { &self.module } else { file };
+                    let id = debugger.add_breakpoint(file, line, condition);
+                    println!("Breakpoint {} at {}:{}", id, file, line);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "delete" | "d" => match args.parse() {
+                Ok(id) if debugger.remove_breakpoint(id) => (),
+                _ => println!("No breakpoint `{}`", args),
+            },
+            "breakpoints" => {
+                for breakpoint in debugger.breakpoints() {
+                    print!(
+                        "{}: {}:{}",
+                        breakpoint.id, breakpoint.module, breakpoint.line
+                    );
+                    match breakpoint.condition {
+                        Some(condition) => println!(" if {}", condition),
+                        None => println!(),
+                    }
+                }
+            }
+            "help" | "h" | "" => println!("{}", HELP),
+            _ => return false,
+        }
+        true
+    }
+
+    fn print_location(&self, frame: &Frame) {
+        let line = match frame.line {
+            Some(line) => line,
+            None => {
+                println!("{}", frame.source_name);
+                return;
+            }
+        };
+        print!("{}:{}", frame.source_name, line);
+        if let Some(function_name) = &frame.function_name {
+            print!(" in {}", function_name);
+        }
+        println!();
+        if frame.source_name.trim_start_matches('@') == self.module {
+            if let Some(text) = self.source.lines().nth(line - 1) {
+                println!("{:>5} | {}", line, text);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Frontend for Cli {
+    async fn stopped(&mut self, debugger: &Debugger, stop: &Stop) -> Result<Resume> {
+        match &stop.reason {
+            StopReason::Breakpoint(ids) => {
+                let ids: Vec<_> = ids.iter().map(|id| id.to_string()).collect();
+                print!("Breakpoint {} at ", ids.join(", "));
+            }
+            StopReason::Step => (),
+            StopReason::ConditionError {
+                breakpoint,
+                message,
+            } => {
+                println!(
+                    "Unable to evaluate the condition of breakpoint {}:\n{}",
+                    breakpoint, message
+                );
+            }
+        }
+        self.frame = 0;
+        if let Some(frame) = stop.frames.first() {
+            self.print_location(frame);
+        }
+
+        loop {
+            let line = match read_line("(debug) ")? {
+                Some(line) => line,
+                None => return Err(Error::from("Debugging aborted".to_string())),
+            };
+            let (command, args) = match parse_command(&line) {
+                Command::Resume(resume) => return Ok(resume),
+                Command::Run => {
+                    println!("The program is already running");
+                    continue;
+                }
+                Command::Quit => return Err(Error::from("Debugging aborted".to_string())),
+                Command::Other(command, args) => (command, args),
+            };
+            match command {
+                "backtrace" | "bt" => {
+                    for (i, frame) in stop.frames.iter().enumerate() {
+                        print!("#{} ", i);
+                        self.print_location(frame);
+                    }
+                }
+                "frame" => match args.parse::<usize>() {
+                    Ok(i) if i < stop.frames.len() => {
+                        self.frame = i;
+                        self.print_location(&stop.frames[i]);
+                    }
+                    _ => println!("No frame `{}`", args),
+                },
+                "locals" | "l" => {
+                    for variable in stop.frames[self.frame].variables() {
+                        println!("{} : {} = {}", variable.name, variable.typ, variable.show());
+                    }
+                }
+                "print" | "p" => {
+                    match debugger
+                        .evaluate_to_string(&stop.frames[self.frame], args)
+                        .await
+                    {
+                        Ok(value) => println!("{}", value),
+                        Err(err) => println!("{}", err),
+                    }
+                }
+                _ => {
+                    if !self.breakpoint_command(debugger, command, args) {
+                        println!(
+                            "Unknown command `{}`. Type `help` for a list of commands",
+                            command
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
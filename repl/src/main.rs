@@ -28,11 +28,15 @@ use gluon::{base, parser, vm};
 use crate::base::filename_to_module;
 
 use gluon::{
-    new_vm_async, vm::thread::ThreadInternal, vm::Error as VMError, Error, Result, Thread,
-    ThreadExt,
+    new_vm_async, profiler::Profile, vm::thread::ThreadInternal, vm::Error as VMError, Error,
+    Result, Thread, ThreadExt,
 };
 
+mod dap;
+mod debugger;
 mod repl;
+mod serve;
+mod test_runner;
 
 const APP_INFO: app_dirs::AppInfo = app_dirs::AppInfo {
     name: "gluon-repl",
@@ -79,6 +83,23 @@ impl ::std::str::FromStr for Color {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProfileFormat {
+    Folded,
+    Json,
+}
+
+impl ::std::str::FromStr for ProfileFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "folded" => ProfileFormat::Folded,
+            "json" => ProfileFormat::Json,
+            _ => return Err("Expected one of 'folded', 'json'"),
+        })
+    }
+}
+
 #[derive(StructOpt)]
 #[structopt(about = "Formats gluon source code")]
 pub struct FmtOpt {
@@ -92,6 +113,18 @@ pub enum SubOpt {
     Fmt(FmtOpt),
     #[structopt(name = "doc", about = "Documents gluon source code")]
     Doc(::gluon_doc::Opt),
+    #[structopt(name = "debug", about = "Runs a gluon program under the debugger")]
+    Debug(debugger::DebugOpt),
+    #[structopt(
+        name = "serve",
+        about = "Serves JSON-RPC requests, one per line, on stdin and stdout"
+    )]
+    Serve(serve::ServeOpt),
+    #[structopt(
+        name = "test",
+        about = "Runs the `std.test` test modules found in the given paths"
+    )]
+    Test(test_runner::TestOpt),
 }
 
 const LONG_VERSION: &str = concat!(clap::crate_version!(), "\n", "commit: ", env!("GIT_HASH"));
@@ -130,6 +163,20 @@ pub struct Opt {
     )]
     no_std: bool,
 
+    #[structopt(
+        long = "profile",
+        parse(from_os_str),
+        help = "Profiles the executed files and writes the result to the given file"
+    )]
+    profile: Option<PathBuf>,
+
+    #[structopt(
+        long = "profile-format",
+        default_value = "folded",
+        help = "Format of the profile: folded (for flamegraphs), json"
+    )]
+    profile_format: ProfileFormat,
+
     #[structopt(name = "FILE", help = "Executes each file as a gluon program")]
     input: Vec<String>,
 
@@ -148,6 +195,16 @@ where
     Ok(())
 }
 
+fn write_profile(profile: &Profile, path: &Path, format: ProfileFormat) -> Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    match format {
+        ProfileFormat::Folded => profile.write_folded(&mut file)?,
+        ProfileFormat::Json => profile.write_json(&mut file)?,
+    }
+    file.flush()?;
+    Ok(())
+}
+
 #[cfg(feature = "env_logger")]
 fn init_env_logger() {
     let _ = ::env_logger::try_init();
@@ -251,6 +308,16 @@ async fn run(opt: &Opt, color: Color, vm: &Thread) -> std::result::Result<(), gl
             gluon_doc::generate_for_path(&thread, input, output)
                 .map_err(|err| format!("{}\n{}", err, err.backtrace()))?;
         }
+        Some(SubOpt::Debug(ref debug_opt)) => {
+            debugger::run(vm.root_thread(), debug_opt).await?;
+        }
+        Some(SubOpt::Serve(ref serve_opt)) => {
+            let stdin = io::BufReader::new(io::stdin());
+            serve::run(&vm.root_thread(), serve_opt, stdin, io::stdout()).await?;
+        }
+        Some(SubOpt::Test(ref test_opt)) => {
+            test_runner::run(vm, test_opt, io::stdout()).await?;
+        }
         None => {
             if opt.interactive {
                 let prompt = opt.prompt.clone();
@@ -258,7 +325,15 @@ async fn run(opt: &Opt, color: Color, vm: &Thread) -> std::result::Result<(), gl
                 let use_std_lib = !opt.no_std;
                 repl::run(color, &prompt, debug_level, use_std_lib).await?;
             } else if !opt.input.is_empty() {
-                run_files(&vm, &opt.input).await?;
+                match opt.profile {
+                    Some(ref profile_path) => {
+                        let profiler = vm.start_profiling();
+                        let result = run_files(&vm, &opt.input).await;
+                        write_profile(&profiler.finish(), profile_path, opt.profile_format)?;
+                        result?;
+                    }
+                    None => run_files(&vm, &opt.input).await?,
+                }
             } else {
                 writeln!(io::stderr(), "{}", Opt::clap().get_matches().usage())
                     .expect("Error writing help to stderr");
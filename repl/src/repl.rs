@@ -117,6 +117,65 @@ fn switch_debug_level(args: WithVM<&str>) -> IO<Result<String, String>> {
     IO::Value(Ok(vm.global_env().get_debug_level().to_string()))
 }
 
+fn heap_snapshot(args: WithVM<&str>) -> IO<Result<String, String>> {
+    use std::fmt::Write;
+    let vm = args.vm;
+    let snapshot = vm.heap_snapshot();
+    match args.value.trim() {
+        "" => (),
+        "json" => {
+            return IO::Value(
+                serde_json::to_string_pretty(&snapshot).map_err(|err| err.to_string()),
+            )
+        }
+        format => {
+            return IO::Value(Err(format!(
+                "Unknown heap snapshot format `{}`, expected nothing or `json`",
+                format
+            )))
+        }
+    }
+
+    let mut buffer = String::new();
+    writeln!(
+        buffer,
+        "{} values using {} bytes",
+        snapshot.count, snapshot.bytes
+    )
+    .unwrap();
+    for typ in &snapshot.types {
+        write!(buffer, "\n{:>8} {:>10}  {}", typ.count, typ.bytes, typ.name).unwrap();
+        match &typ.retention_path {
+            Some(path) => write!(buffer, "\n{:20}retained by {}", "", path.join(" -> ")).unwrap(),
+            None => write!(buffer, "\n{:20}unreachable", "").unwrap(),
+        }
+    }
+
+    fn write_threads(buffer: &mut String, threads: &[vm::gc::snapshot::ThreadHeap], depth: usize) {
+        for thread in threads {
+            if thread.busy {
+                write!(buffer, "\n{:indent$}thread: busy", "", indent = 2 * depth).unwrap();
+            } else {
+                write!(
+                    buffer,
+                    "\n{:indent$}thread: {} values using {} bytes",
+                    "",
+                    thread.count,
+                    thread.bytes,
+                    indent = 2 * depth
+                )
+                .unwrap();
+            }
+            write_threads(buffer, &thread.threads, depth + 1);
+        }
+    }
+    if !snapshot.threads.is_empty() {
+        buffer.push_str("\n");
+        write_threads(&mut buffer, &snapshot.threads, 0);
+    }
+    IO::Value(Ok(buffer))
+}
+
 fn complete(thread: &Thread, name: &str, fileinput: &str, pos: usize) -> GluonResult<Vec<String>> {
     use gluon::compiler_pipeline::*;
 
@@ -598,6 +657,7 @@ fn load_repl(vm: &Thread) -> vm::Result<vm::ExternModule> {
             find_kind => primitive!(1, find_kind),
             parse_color => primitive!(1, "parse_color", |s: &str| s.parse::<Color>()),
             switch_debug_level => primitive!(1, switch_debug_level),
+            heap_snapshot => primitive!(1, heap_snapshot),
             eval_line => primitive!(2, async fn eval_line),
             finish_or_interrupt => primitive!(2, async fn finish_or_interrupt),
         ),
@@ -753,6 +813,32 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn heap_snapshot() {
+        let _ = env_logger::try_init();
+        let vm = new_vm().await;
+        compile_repl(&vm)
+            .await
+            .unwrap_or_else(|err| panic!("{}", err));
+        let mut heap_snapshot: FunctionRef<QueryFn> =
+            vm.get_global("repl.prim.heap_snapshot").unwrap();
+        match heap_snapshot.call_async("").await {
+            Ok(IO::Value(Ok(_))) => (),
+            x => assert!(false, "{:?}", x),
+        }
+        match heap_snapshot.call_async("json").await {
+            Ok(IO::Value(Ok(json))) => {
+                let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+                assert!(json["count"].as_u64().unwrap() > 0, "{}", json);
+            }
+            x => assert!(false, "{:?}", x),
+        }
+        match heap_snapshot.call_async("xml").await {
+            Ok(IO::Value(Err(_))) => (),
+            x => assert!(false, "{:?}", x),
+        }
+    }
+
     #[tokio::test]
     async fn complete_repl_empty() {
         let _ = env_logger::try_init();
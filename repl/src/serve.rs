@@ -0,0 +1,547 @@
+//! JSON-RPC frontend which lets another process drive a gluon vm
+//!
+//! Each line read from the input is a JSON-RPC 2.0 request and every response is written as a
+//! single line to the output. Arguments and return values are marshalled using the type of the
+//! called function, see `gluon::vm::api::json::Typed` for the representation.
+use std::{
+    io::{self, BufRead, Write},
+    result::Result as StdResult,
+    sync::{Arc, Mutex},
+};
+
+use futures::{channel::mpsc, prelude::*};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use gluon::{
+    check::check_signature,
+    vm::{
+        api::{
+            generic::A,
+            json::{to_typed_json, Typed},
+            Getable, Hole, OpaqueValue, OwnedFunction, VmType, IO,
+        },
+        thread::{RootedValue, ThreadInternal},
+    },
+    Error, Result, RootedThread, ThreadExt,
+};
+
+use crate::base::{
+    filename_to_module, resolve,
+    types::{ArcType, ArgType, NullInterner, Type, TypeExt},
+};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Used for all errors reported by gluon itself (type errors, panics, interrupts, ...)
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(StructOpt)]
+pub struct ServeOpt {
+    #[structopt(name = "FILE", help = "Loads each file before serving any requests")]
+    input: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoadParams {
+    Source { name: String, source: String },
+    File { file: String },
+}
+
+#[derive(Deserialize)]
+struct CallParams {
+    name: String,
+    #[serde(default)]
+    args: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct TypeParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct InterruptParams {
+    /// The id of the `call` request to interrupt
+    id: Value,
+}
+
+/// What the reader thread passes on to the server
+enum Message {
+    Request(Request),
+    /// `interrupt` requests are acted on as soon as they are read since the server is busy
+    /// running the call that should be interrupted
+    Interrupted {
+        id: Option<Value>,
+        result: StdResult<Value, RpcError>,
+    },
+    Invalid(String),
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        RpcError {
+            code: SERVER_ERROR,
+            message: err.to_string(),
+        }
+    }
+}
+
+struct Server {
+    thread: RootedThread,
+    /// The id of the current `call` request and the thread executing it, if any
+    running: Arc<Mutex<Option<(Option<Value>, RootedThread)>>>,
+}
+
+pub async fn run<R, W>(thread: &RootedThread, opt: &ServeOpt, input: R, mut output: W) -> Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    for file in &opt.input {
+        thread.load_file_async(file).await?;
+    }
+
+    let server = Server {
+        thread: thread.clone(),
+        running: Arc::new(Mutex::new(None)),
+    };
+
+    let (sender, mut messages) = mpsc::unbounded();
+    let running = server.running.clone();
+    std::thread::spawn(move || {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    error!("Unable to read request: {}", err);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message = match serde_json::from_str::<Request>(&line) {
+                Ok(request) if request.method == "interrupt" => Message::Interrupted {
+                    id: request.id,
+                    result: params(request.params).map(|InterruptParams { id }| {
+                        let running = running.lock().unwrap();
+                        let thread = running
+                            .as_ref()
+                            .filter(|(running_id, _)| running_id.as_ref() == Some(&id))
+                            .map(|(_, thread)| thread);
+                        if let Some(thread) = thread {
+                            thread.interrupt();
+                        }
+                        Value::Bool(thread.is_some())
+                    }),
+                },
+                Ok(request) => Message::Request(request),
+                Err(err) => Message::Invalid(err.to_string()),
+            };
+            if sender.unbounded_send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(message) = messages.next().await {
+        let (id, result) = match message {
+            Message::Request(request) => {
+                let id = request.id.clone();
+                (id, server.handle(request).await)
+            }
+            Message::Interrupted { id, result } => (id, result),
+            Message::Invalid(message) => (
+                Some(Value::Null),
+                Err(RpcError {
+                    code: PARSE_ERROR,
+                    message,
+                }),
+            ),
+        };
+        // Requests without an id are notifications which do not get a response
+        let id = match id {
+            Some(id) => id,
+            None => continue,
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        };
+        serde_json::to_writer(&mut output, &response).map_err(io::Error::from)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn params<T>(params: Value) -> StdResult<T, RpcError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(params).map_err(|err| RpcError {
+        code: INVALID_PARAMS,
+        message: err.to_string(),
+    })
+}
+
+impl Server {
+    async fn handle(&self, request: Request) -> StdResult<Value, RpcError> {
+        match &request.method[..] {
+            "load" => Ok(self.load(params(request.params)?).await?),
+            "call" => Ok(self.call(request.id, params(request.params)?).await?),
+            "type" => {
+                let TypeParams { name } = params(request.params)?;
+                let typ = self.thread.get_global_type(&name).map_err(Error::from)?;
+                Ok(Value::String(typ.to_string()))
+            }
+            method => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method `{}`", method),
+            }),
+        }
+    }
+
+    async fn load(&self, params: LoadParams) -> Result<Value> {
+        let module = match params {
+            LoadParams::Source { name, source } => {
+                self.thread.load_script_async(&name, &source).await?;
+                filename_to_module(&name)
+            }
+            LoadParams::File { file } => {
+                self.thread.load_file_async(&file).await?;
+                filename_to_module(&file)
+            }
+        };
+        let typ = self.thread.get_global_type(&module)?;
+        Ok(json!({ "module": module, "type": typ.to_string() }))
+    }
+
+    async fn call(&self, id: Option<Value>, params: CallParams) -> Result<Value> {
+        let typ = self.thread.get_global_type(&params.name)?;
+        let value: OpaqueValue<RootedThread, Hole> = self.thread.get_global(&params.name)?;
+
+        // Each call runs on its own thread as an interrupted thread can't be used again
+        let thread = self.thread.new_thread()?;
+        *self.running.lock().unwrap() = Some((id, thread.clone()));
+        let result = call(&thread, &params.name, value.into_inner(), typ, &params.args).await;
+        *self.running.lock().unwrap() = None;
+        result
+    }
+}
+
+async fn call(
+    thread: &RootedThread,
+    name: &str,
+    mut value: RootedValue<RootedThread>,
+    mut typ: ArcType,
+    args: &[Value],
+) -> Result<Value> {
+    for arg in args {
+        let (arg_type, return_type) = match typ.remove_forall().as_function_with_type() {
+            Some((ArgType::Explicit, arg_type, return_type)) => {
+                (arg_type.clone(), return_type.clone())
+            }
+            _ => {
+                return Err(Error::from(format!(
+                    "`{}` can't be called with {} arguments",
+                    name,
+                    args.len()
+                )))
+            }
+        };
+        value = apply(thread, &value, &arg_type, arg).await?;
+        typ = return_type;
+    }
+
+    if check_signature(&thread.get_env(), &typ, &IO::<A>::make_forall_type(thread)) {
+        value = thread.execute_io_top(value.get_variant()).await?;
+        let actual = resolve::remove_aliases(&thread.get_env(), &mut NullInterner, typ);
+        typ = match *actual {
+            Type::App(_, ref args) => args[0].clone(),
+            _ => ice!("ICE: Expected IO type found: `{}`", actual),
+        };
+    }
+
+    Ok(to_typed_json(thread, value.get_variant(), &typ)?)
+}
+
+async fn apply<'a>(
+    thread: &RootedThread,
+    function: &RootedValue<RootedThread>,
+    arg_type: &'a ArcType,
+    arg: &'a Value,
+) -> Result<RootedValue<RootedThread>> {
+    let mut function: OwnedFunction<fn(Typed<'a>) -> OpaqueValue<RootedThread, Hole>> =
+        OwnedFunction::from_value(thread, function.get_variant());
+    let result = function.call_async(Typed::new(arg_type, arg)).await?;
+    Ok(result.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io::Read, sync::mpsc as std_mpsc, time::Duration};
+
+    use gluon::import::Import;
+
+    async fn new_vm() -> RootedThread {
+        if std::env::var("GLUON_PATH").is_err() {
+            std::env::set_var("GLUON_PATH", "..");
+        }
+        let vm = gluon::new_vm_async().await;
+        let import = vm.get_macros().get("import");
+        import
+            .as_ref()
+            .and_then(|import| import.downcast_ref::<Import>())
+            .expect("Import macro")
+            .add_path("..");
+        vm
+    }
+
+    async fn serve(requests: &[Value]) -> Vec<Value> {
+        let vm = new_vm().await;
+        let input = requests
+            .iter()
+            .map(|request| format!("{}\n", request))
+            .collect::<String>();
+        let mut output = Vec::new();
+        run(
+            &vm,
+            &ServeOpt { input: Vec::new() },
+            io::Cursor::new(input.into_bytes()),
+            &mut output,
+        )
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    const SOURCE: &str = r#"
+type Shape = | Circle Float | Rect Float Float
+let area shape : Shape -> Float =
+    match shape with
+    | Circle r -> 3.0 * r * r
+    | Rect w h -> w * h
+let add x y : Int -> Int -> Int = x + y
+let swap p : { x : Int, y : String } -> { x : String, y : Int } = { x = p.y, y = p.x }
+let is_positive x : Int -> Bool = x > 0
+let shapes x : Float -> Array Shape = [Circle x, Rect x 2.0]
+{ Shape, area, add, swap, is_positive, shapes }
+"#;
+
+    #[tokio::test]
+    async fn call_functions() {
+        let _ = env_logger::try_init();
+
+        let responses = serve(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "load",
+                    "params": { "name": "test", "source": SOURCE } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "call",
+                    "params": { "name": "test.add", "args": [1, 2] } }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "call",
+                    "params": { "name": "test.swap", "args": [{ "x": 1, "y": "a" }] } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "call",
+                    "params": { "name": "test.area", "args": [{ "Rect": [2.0, 3.0] }] } }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "call",
+                    "params": { "name": "test.is_positive", "args": [-1] } }),
+            json!({ "jsonrpc": "2.0", "id": 6, "method": "call",
+                    "params": { "name": "test.shapes", "args": [1.0] } }),
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "type",
+                    "params": { "name": "test.add" } }),
+        ])
+        .await;
+
+        let results: Vec<_> = responses
+            .iter()
+            .map(|response| response["result"].clone())
+            .collect();
+        assert_eq!(results[0]["module"], "test");
+        assert_eq!(
+            results[1..],
+            [
+                json!(3),
+                json!({ "x": "a", "y": 1 }),
+                json!(6.0),
+                json!(false),
+                json!([{ "Circle": 1.0 }, { "Rect": [1.0, 2.0] }]),
+                json!("Int -> Int -> Int"),
+            ][..]
+        );
+    }
+
+    #[tokio::test]
+    async fn report_errors() {
+        let _ = env_logger::try_init();
+
+        let responses = serve(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "load",
+                    "params": { "name": "test", "source": SOURCE } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "call",
+                    "params": { "name": "test.add", "args": ["1", 2] } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "call",
+                    "params": { "name": "test.add", "args": [1, 2, 3] } }),
+            json!({ "jsonrpc": "2.0", "method": "call",
+                    "params": { "name": "test.add", "args": [1, 2] } }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "interrupt", "params": { "id": 3 } }),
+            json!({ "jsonrpc": "2.0", "id": 6, "method": "interrupt" }),
+        ])
+        .await;
+
+        assert_eq!(responses.len(), 6);
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["error"]["code"], SERVER_ERROR);
+        assert_eq!(responses[3]["error"]["code"], SERVER_ERROR);
+        assert_eq!(responses[4]["result"], false);
+        assert_eq!(responses[5]["error"]["code"], INVALID_PARAMS);
+    }
+
+    /// Reads the chunks sent through `receiver`, ending the input once the sender is dropped
+    struct ChannelReader {
+        receiver: std_mpsc::Receiver<Vec<u8>>,
+        buffer: io::Cursor<Vec<u8>>,
+    }
+
+    impl io::Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.buffer.position() == self.buffer.get_ref().len() as u64 {
+                match self.receiver.recv() {
+                    Ok(chunk) => self.buffer = io::Cursor::new(chunk),
+                    Err(_) => return Ok(0),
+                }
+            }
+            self.buffer.read(buf)
+        }
+    }
+
+    /// Sends each line written to it through `sender`
+    struct ChannelWriter {
+        sender: std_mpsc::Sender<Value>,
+        line: Vec<u8>,
+    }
+
+    impl io::Write for ChannelWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.line.extend_from_slice(buf);
+            while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+                let line = self.line.drain(..=end).collect::<Vec<_>>();
+                let _ = self.sender.send(serde_json::from_slice(&line).unwrap());
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupt_long_running_call() {
+        let _ = env_logger::try_init();
+
+        let (request_sender, request_receiver) = std_mpsc::channel::<Vec<u8>>();
+        let (response_sender, responses) = std_mpsc::channel();
+        let send = move |request: Value| {
+            request_sender
+                .send(format!("{}\n", request).into_bytes())
+                .unwrap()
+        };
+
+        let client = std::thread::spawn(move || {
+            let recv = || responses.recv_timeout(Duration::from_secs(60)).unwrap();
+
+            send(json!({ "jsonrpc": "2.0", "id": 1, "method": "load",
+                         "params": { "name": "test", "source": r#"
+let spin x : Int -> Int = if x == 0 then 0 else spin (x + 1)
+let add x y : Int -> Int -> Int = x + y
+{ spin, add }
+"# } }));
+            let loaded: Value = recv();
+            assert_eq!(loaded["result"]["module"], "test");
+
+            send(json!({ "jsonrpc": "2.0", "id": 2, "method": "call",
+                         "params": { "name": "test.spin", "args": [1] } }));
+            // Only the request with the given id may be interrupted
+            send(json!({ "jsonrpc": "2.0", "id": 3, "method": "interrupt",
+                         "params": { "id": 1 } }));
+
+            // The call may not have started yet so keep interrupting until it stops
+            let mut interrupt_id = 100;
+            let spin_response = loop {
+                let interrupt = json!({ "jsonrpc": "2.0", "id": interrupt_id,
+                                        "method": "interrupt", "params": { "id": 2 } });
+                send(interrupt);
+                interrupt_id += 1;
+                match responses.recv_timeout(Duration::from_millis(50)) {
+                    Ok(response) => break response,
+                    Err(std_mpsc::RecvTimeoutError::Timeout) => (),
+                    Err(err) => panic!("{}", err),
+                }
+            };
+            assert_eq!(spin_response["id"], 2);
+            assert_eq!(spin_response["error"]["code"], SERVER_ERROR);
+
+            let wrong_id = recv();
+            let interrupts = (100..interrupt_id).map(|_| recv()).collect::<Vec<Value>>();
+            assert!(
+                interrupts.iter().any(|response| response["result"] == true),
+                "{:?}",
+                interrupts
+            );
+
+            send(json!({ "jsonrpc": "2.0", "id": 4, "method": "call",
+                         "params": { "name": "test.add", "args": [1, 2] } }));
+            let add_response = recv();
+            (wrong_id, add_response)
+        });
+
+        let vm = new_vm().await;
+        run(
+            &vm,
+            &ServeOpt { input: Vec::new() },
+            io::BufReader::new(ChannelReader {
+                receiver: request_receiver,
+                buffer: io::Cursor::new(Vec::new()),
+            }),
+            ChannelWriter {
+                sender: response_sender,
+                line: Vec::new(),
+            },
+        )
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+        let (wrong_id, add_response) = client.join().unwrap();
+        assert_eq!(wrong_id["id"], 3);
+        assert_eq!(wrong_id["result"], false);
+        assert_eq!(add_response["result"], 3);
+    }
+}
@@ -0,0 +1,553 @@
+//! Runner for test modules written with `std.test`
+//!
+//! Each `.glu` file found under the given paths is expected to evaluate to a `std.test.TestCase`.
+//! Every `Test` in those trees runs on its own vm thread and the results are reported in one of
+//! the formats in `TestFormat`.
+use std::{
+    fmt,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use futures::{prelude::*, stream};
+use structopt::StructOpt;
+use walkdir::WalkDir;
+
+use gluon::{
+    base::{
+        filename_to_module,
+        fnv::FnvSet,
+        symbol::Symbol,
+        types::{ArcType, Type},
+    },
+    vm::{
+        self,
+        api::{
+            de::De, generic::A, Getable, Hole, OpaqueValue, OwnedFunction, ValueRef, VmType, IO,
+        },
+        stack::Stacktrace,
+    },
+    Result, RootedThread, Thread, ThreadExt,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TestFormat {
+    Pretty,
+    Tap,
+    Junit,
+}
+
+impl ::std::str::FromStr for TestFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "pretty" => TestFormat::Pretty,
+            "tap" => TestFormat::Tap,
+            "junit" => TestFormat::Junit,
+            _ => return Err("Expected one of 'pretty', 'tap', 'junit'"),
+        })
+    }
+}
+
+#[derive(StructOpt)]
+pub struct TestOpt {
+    #[structopt(
+        name = "PATH",
+        parse(from_os_str),
+        default_value = "tests",
+        help = "Files or directories containing the test modules"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[structopt(
+        long = "filter",
+        help = "Only runs the tests whose name contains the filter"
+    )]
+    filter: Option<String>,
+
+    #[structopt(
+        long = "format",
+        default_value = "pretty",
+        help = "Output format: pretty, tap, junit"
+    )]
+    format: TestFormat,
+
+    #[structopt(long = "jobs", help = "How many tests to run in parallel")]
+    jobs: Option<usize>,
+}
+
+macro_rules! define_test_type {
+    ($name:ident $($args: ident)*) => {
+        impl VmType for $name {
+            type Type = $name;
+            fn make_type(vm: &Thread) -> ArcType {
+                let typ = concat!("std.test.", stringify!($name));
+                Type::app(
+                    vm.get_env().find_type_info(typ).unwrap().into_type(),
+                    vec![$($args::make_type(vm),)* Type::unit()].into_iter().collect(),
+                )
+            }
+        }
+    };
+}
+
+type TestEff = OpaqueValue<RootedThread, TestEffIO>;
+type TestFn = OwnedFunction<fn(()) -> TestEff>;
+
+#[derive(Deserialize)]
+enum TestCase {
+    Test(String, TestFn),
+    Group(String, Vec<TestCase>),
+}
+
+define_test_type! { TestCase Hole }
+
+struct TestEffIO;
+
+define_test_type! { TestEffIO A }
+
+/// A `Test` from a test module, ready to be run
+struct Test {
+    module: String,
+    name: String,
+    location: Location,
+    /// The names of the test's closure and every function defined inside it. Used to find the
+    /// test's own frames in the stacktrace of a panic
+    functions: FnvSet<Symbol>,
+    /// The test function or the error that prevented the test from being loaded
+    function: ::std::result::Result<TestFn, String>,
+}
+
+/// Where a test is defined. If the test panics, the line is the one in the test which was
+/// executing. Otherwise it is the line the test's closure starts at, as assertions which fail
+/// through `std.test` do not carry a source location.
+#[derive(Clone)]
+struct Location {
+    file: PathBuf,
+    line: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+struct TestResult {
+    module: String,
+    name: String,
+    location: Location,
+    duration: Duration,
+    /// The failure message if the test failed
+    failure: Option<String>,
+}
+
+pub async fn run<W>(vm: &Thread, opt: &TestOpt, mut output: W) -> Result<()>
+where
+    W: Write,
+{
+    vm.load_file_async("std/test.glu").await?;
+
+    let mut tests = Vec::new();
+    for file in test_files(&opt.paths) {
+        load_tests(vm, &file, &mut tests).await;
+    }
+    if let Some(filter) = &opt.filter {
+        tests.retain(|test| test.name.contains(&filter[..]));
+    }
+
+    let jobs = opt.jobs.unwrap_or_else(num_cpus::get).max(1);
+    let results: Vec<_> = stream::iter(tests)
+        .map(|test| {
+            let module = test.module.clone();
+            let name = test.name.clone();
+            let location = test.location.clone();
+            tokio::task::spawn_blocking(move || futures::executor::block_on(run_test(test))).map(
+                move |result| {
+                    // A panic only fails the test that caused it, the other tests still run
+                    result.unwrap_or_else(|err| TestResult {
+                        module,
+                        name,
+                        location,
+                        duration: Duration::default(),
+                        failure: Some(format!("The test panicked: {}", err)),
+                    })
+                },
+            )
+        })
+        .buffered(jobs)
+        .collect()
+        .await;
+
+    match opt.format {
+        TestFormat::Pretty => write_pretty(&mut output, &results)?,
+        TestFormat::Tap => write_tap(&mut output, &results)?,
+        TestFormat::Junit => write_junit(&mut output, &results)?,
+    }
+    output.flush()?;
+
+    let failed = results.iter().filter(|r| r.failure.is_some()).count();
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} of {} tests failed", failed, results.len()).into())
+    }
+}
+
+fn test_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<_> = paths
+        .iter()
+        .flat_map(|path| {
+            WalkDir::new(path).into_iter().filter_map(|entry| {
+                entry.ok().and_then(|entry| {
+                    if entry.file_type().is_file()
+                        && entry.path().extension().and_then(|e| e.to_str()) == Some("glu")
+                    {
+                        Some(entry.path().to_owned())
+                    } else {
+                        None
+                    }
+                })
+            })
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Compiles `file` and adds its tests to `tests`. Modules that fail to compile are reported as
+/// a single failing test.
+async fn load_tests(vm: &Thread, file: &Path, tests: &mut Vec<Test>) {
+    let module = filename_to_module(&file.display().to_string());
+    match load_test_case(vm, &module, file).await {
+        Ok(test_case) => add_tests(&module, file, "", test_case, tests),
+        Err(err) => tests.push(Test {
+            module: module.clone(),
+            name: module,
+            location: Location {
+                file: file.to_owned(),
+                line: None,
+            },
+            functions: FnvSet::default(),
+            function: Err(err.to_string()),
+        }),
+    }
+}
+
+async fn load_test_case(vm: &Thread, module: &str, file: &Path) -> Result<TestCase> {
+    let text = std::fs::read_to_string(file)?;
+    let (De(test_case), _) = vm.run_expr_async::<De<TestCase>>(module, &text).await?;
+    Ok(test_case)
+}
+
+fn add_tests(module: &str, file: &Path, prefix: &str, test_case: TestCase, tests: &mut Vec<Test>) {
+    let qualify = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", prefix, name)
+        }
+    };
+    match test_case {
+        TestCase::Test(name, function) => {
+            let (line, functions) = match function.get_variant().as_ref() {
+                ValueRef::Closure(closure) => (
+                    closure
+                        .debug_info()
+                        .source_map
+                        .line(0)
+                        .map(|line| line.to_usize() + 1),
+                    closure.function_names().into_iter().collect(),
+                ),
+                _ => (None, FnvSet::default()),
+            };
+            // Run each test on its own thread so that they can execute in parallel
+            let function = match function.vm().new_thread() {
+                Ok(thread) => Ok(TestFn::from_value(&thread, function.get_variant())),
+                Err(err) => Err(format!("Unable to create a thread for the test: {}", err)),
+            };
+            tests.push(Test {
+                module: module.to_string(),
+                name: qualify(&name),
+                location: Location {
+                    file: file.to_owned(),
+                    line,
+                },
+                functions,
+                function,
+            });
+        }
+        TestCase::Group(name, test_cases) => {
+            let prefix = qualify(&name);
+            for test_case in test_cases {
+                add_tests(module, file, &prefix, test_case, tests);
+            }
+        }
+    }
+}
+
+/// Returns the line of the innermost frame in `stacktrace` which belongs to the test
+fn panic_line(functions: &FnvSet<Symbol>, stacktrace: &Stacktrace) -> Option<usize> {
+    stacktrace
+        .frames
+        .iter()
+        .rev()
+        .filter_map(|frame| frame.as_ref())
+        .find(|frame| functions.contains(&frame.name))
+        .and_then(|frame| frame.line)
+        .map(|line| line.to_usize() + 1)
+}
+
+async fn run_test(test: Test) -> TestResult {
+    let start = Instant::now();
+    let mut location = test.location;
+    let failure = match test.function {
+        Ok(function) => match run_test_fn(function).await {
+            Ok(()) => None,
+            Err(vm::Error::Panic(message, stacktrace)) => {
+                if let Some(line) = stacktrace
+                    .as_ref()
+                    .and_then(|stacktrace| panic_line(&test.functions, stacktrace))
+                {
+                    location.line = Some(line);
+                }
+                Some(message)
+            }
+            Err(err) => Some(err.to_string()),
+        },
+        Err(err) => Some(err),
+    };
+    TestResult {
+        module: test.module,
+        name: test.name,
+        location,
+        duration: start.elapsed(),
+        failure,
+    }
+}
+
+async fn run_test_fn(mut function: TestFn) -> vm::Result<()> {
+    let test = function.call_async(()).await?;
+    let mut run_io: OwnedFunction<fn(TestEff) -> IO<()>> =
+        test.vm().get_global("std.test.run_io")?;
+    match run_io.call_async(test).await? {
+        IO::Value(()) => Ok(()),
+        IO::Exception(err) => Err(vm::Error::Message(err)),
+    }
+}
+
+fn write_pretty(output: &mut impl Write, results: &[TestResult]) -> io::Result<()> {
+    writeln!(output, "running {} tests", results.len())?;
+    for result in results {
+        let status = if result.failure.is_some() {
+            "FAILED"
+        } else {
+            "ok"
+        };
+        writeln!(output, "test {} ... {}", result.name, status)?;
+    }
+
+    let failures: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.failure.as_ref().map(|failure| (result, failure)))
+        .collect();
+    if !failures.is_empty() {
+        writeln!(output, "\nfailures:")?;
+        for (result, failure) in &failures {
+            writeln!(output, "\n---- {} at {} ----", result.name, result.location)?;
+            writeln!(output, "{}", failure.trim())?;
+        }
+    }
+
+    writeln!(
+        output,
+        "\ntest result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        results.len() - failures.len(),
+        failures.len()
+    )
+}
+
+fn write_tap(output: &mut impl Write, results: &[TestResult]) -> io::Result<()> {
+    writeln!(output, "TAP version 13")?;
+    writeln!(output, "1..{}", results.len())?;
+    for (i, result) in results.iter().enumerate() {
+        match &result.failure {
+            None => writeln!(output, "ok {} - {}", i + 1, result.name)?,
+            Some(failure) => {
+                writeln!(output, "not ok {} - {}", i + 1, result.name)?;
+                writeln!(output, "  ---")?;
+                writeln!(output, "  at: {:?}", result.location.to_string())?;
+                writeln!(output, "  message: |")?;
+                for line in failure.trim().lines() {
+                    writeln!(output, "    {}", line)?;
+                }
+                writeln!(output, "  ...")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_junit(output: &mut impl Write, results: &[TestResult]) -> io::Result<()> {
+    let failures = results.iter().filter(|r| r.failure.is_some()).count();
+    let time: Duration = results.iter().map(|r| r.duration).sum();
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        output,
+        r#"<testsuites name="gluon" tests="{}" failures="{}" time="{:.3}">"#,
+        results.len(),
+        failures,
+        time.as_secs_f64()
+    )?;
+
+    let mut start = 0;
+    while start < results.len() {
+        let module = &results[start].module;
+        let end = start
+            + results[start..]
+                .iter()
+                .take_while(|r| r.module == *module)
+                .count();
+        let suite = &results[start..end];
+        writeln!(
+            output,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            xml_escape(module),
+            suite.len(),
+            suite.iter().filter(|r| r.failure.is_some()).count(),
+            suite
+                .iter()
+                .map(|r| r.duration)
+                .sum::<Duration>()
+                .as_secs_f64()
+        )?;
+        for result in suite {
+            write!(
+                output,
+                r#"    <testcase name="{}" classname="{}" file="{}""#,
+                xml_escape(&result.name),
+                xml_escape(module),
+                xml_escape(&result.location.file.display().to_string()),
+            )?;
+            if let Some(line) = result.location.line {
+                write!(output, r#" line="{}""#, line)?;
+            }
+            write!(output, r#" time="{:.3}""#, result.duration.as_secs_f64())?;
+            match &result.failure {
+                None => writeln!(output, "/>")?,
+                Some(failure) => {
+                    writeln!(output, ">")?;
+                    writeln!(
+                        output,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        xml_escape(failure.trim().lines().next().unwrap_or("")),
+                        xml_escape(&format!("{}\nat {}", failure.trim(), result.location))
+                    )?;
+                    writeln!(output, "    </testcase>")?;
+                }
+            }
+        }
+        writeln!(output, "  </testsuite>")?;
+        start = end;
+    }
+    writeln!(output, "</testsuites>")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gluon::import::Import;
+
+    async fn new_vm() -> RootedThread {
+        if std::env::var("GLUON_PATH").is_err() {
+            std::env::set_var("GLUON_PATH", "..");
+        }
+        let vm = gluon::new_vm_async().await;
+        let import = vm.get_macros().get("import");
+        import
+            .as_ref()
+            .and_then(|import| import.downcast_ref::<Import>())
+            .expect("Import macro")
+            .add_path("..");
+        vm
+    }
+
+    async fn run_tests(format: TestFormat, filter: Option<&str>) -> (bool, String) {
+        let vm = new_vm().await;
+        let opt = TestOpt {
+            paths: vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_runner")],
+            filter: filter.map(|filter| filter.to_string()),
+            format,
+            jobs: Some(2),
+        };
+        let mut output = Vec::new();
+        let result = run(&vm, &opt, &mut output).await;
+        (result.is_ok(), String::from_utf8(output).unwrap())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn tap_output() {
+        let _ = env_logger::try_init();
+
+        let (ok, output) = run_tests(TestFormat::Tap, None).await;
+        assert!(!ok);
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(
+            lines[..3],
+            ["TAP version 13", "1..3", "ok 1 - example::passes"]
+        );
+        assert_eq!(lines[3], "not ok 2 - example::fails");
+        assert!(output.contains("example.glu:6"), "{}", output);
+        assert!(output.contains("Assertion failed: 1 != 2"), "{}", output);
+        assert!(output.contains("not ok 3 - example::panics"), "{}", output);
+        // The line of the call which panicked, not the line the test starts at
+        assert!(output.contains("example.glu:10"), "{}", output);
+        assert!(output.contains("reached zero"), "{}", output);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn junit_output() {
+        let _ = env_logger::try_init();
+
+        let (_, output) = run_tests(TestFormat::Junit, None).await;
+        assert!(
+            output.contains(r#"<testsuites name="gluon" tests="3" failures="2""#),
+            "{}",
+            output
+        );
+        assert!(output.contains(r#"<failure message="Assertion failed: 1 != 2">"#));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn filter_tests() {
+        let _ = env_logger::try_init();
+
+        let (ok, output) = run_tests(TestFormat::Pretty, Some("passes")).await;
+        assert!(ok, "{}", output);
+        assert!(output.contains("test example::passes ... ok"), "{}", output);
+        assert!(output.contains("1 passed; 0 failed"), "{}", output);
+    }
+}
@@ -0,0 +1,12 @@
+#!/bin/bash
+#
+# Regenerates `c-api/include/gluon.h` from `c-api/src/lib.rs`. Run it after changing the C API and
+# commit the updated header.
+#
+# Requires cbindgen, `scripts/cargo_install.sh cbindgen 0.13.0`
+
+set -ex
+
+cd "$(dirname "$0")/../c-api"
+
+cbindgen --config cbindgen.toml --crate gluon_c-api --output include/gluon.h
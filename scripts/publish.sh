@@ -13,6 +13,7 @@ declare -a PROJECTS=(
     c-api
     doc
     repl
+    language-server
 )
 
 for PROJECT in "${PROJECTS[@]}"
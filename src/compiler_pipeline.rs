@@ -617,8 +617,19 @@ fn typecheck_expr(
         arena.borrow(),
     );
 
-    tc.typecheck_expr_expected(expr, expected_type)
-        .map_err(|err| InFile::new(compiler.database.state().code_map.clone(), err).into())
+    let result = tc
+        .typecheck_expr_expected(expr, expected_type)
+        .map_err(|err| InFile::new(compiler.database.state().code_map.clone(), err).into());
+
+    let warnings = tc.take_warnings();
+    if warnings.has_errors() {
+        warn!(
+            "{}",
+            InFile::new(compiler.database.state().code_map.clone(), warnings)
+        );
+    }
+
+    result
 }
 
 #[async_trait::async_trait]
@@ -862,7 +873,8 @@ where
                 &source,
                 filename.to_string(),
                 settings.emit_debug_info,
-            );
+            )
+            .checked_arithmetic(settings.checked_arithmetic);
             compiler.compile_expr(core_expr.value.expr())?
         };
         module.function.id = Symbol::from(filename);
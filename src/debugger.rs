@@ -0,0 +1,555 @@
+//! A step debugger for gluon code.
+//!
+//! The debugger is driven by the line hook of the virtual machine. Whenever execution reaches a
+//! breakpoint or finishes a step the hook suspends the executing thread and the `Frontend` is
+//! asked how execution should continue. While suspended the stack of the thread can be inspected
+//! and expressions can be evaluated in the scope of any frame.
+//!
+//! ```no_run
+//! # use gluon::{debugger::{Debugger, Frontend, Resume, Stop}, new_vm_async, Result};
+//! struct PrintLines;
+//!
+//! #[async_trait::async_trait]
+//! impl Frontend for PrintLines {
+//!     async fn stopped(&mut self, _: &Debugger, stop: &Stop) -> Result<Resume> {
+//!         println!("{:?}", stop.frames[0].line);
+//!         Ok(Resume::StepIn)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let debugger = Debugger::new(new_vm_async().await);
+//! debugger.add_breakpoint("example", 2, None);
+//! debugger
+//!     .run_expr(&mut PrintLines, "example", "let x = 1\nx")
+//!     .await
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    future,
+    prelude::*,
+    task::{self, Poll},
+};
+
+use crate::base::{
+    filename_to_module,
+    pos::Line,
+    symbol::Symbol,
+    types::{ArcType, Field, Flags, Type, TypeExt, TypePtr},
+};
+
+use crate::vm::{
+    api::{Getable, Hole, OpaqueValue, OwnedFunction, Pushable, VmType},
+    internal::ValuePrinter,
+    thread::{ActiveThread, DebugInfo, HookFlags, HookFn, RootedValue, Thread, ThreadInternal},
+    Variants,
+};
+
+use crate::{
+    compiler_pipeline::*, query::Compilation, ModuleCompiler, Result, RootedThread, ThreadExt,
+};
+
+/// Name of the record which passes the variables of a frame to evaluated expressions
+const SCOPE_NAME: &str = "__debugger_scope";
+
+/// How execution should continue after the debugger has stopped
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resume {
+    /// Continue until the next breakpoint
+    Continue,
+    /// Stop at the next line, entering any function that gets called
+    StepIn,
+    /// Stop at the next line of the current function or any function below it on the stack
+    StepOver,
+    /// Stop once the current function has returned
+    StepOut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub id: usize,
+    /// The name of the module the breakpoint is in
+    pub module: String,
+    /// The (one based) line of the breakpoint
+    pub line: usize,
+    /// Gluon expression of type `Bool` which must evaluate to `True` for execution to stop. The
+    /// expression may refer to any local or upvariable which is in scope at the breakpoint.
+    pub condition: Option<String>,
+}
+
+impl Breakpoint {
+    fn matches(&self, source_name: &str, line: Line) -> bool {
+        self.line == line.to_usize() + 1 && self.module == source_name.trim_start_matches('@')
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// Execution stopped at the breakpoints with the contained ids
+    Breakpoint(Vec<usize>),
+    /// A step requested by `Resume` finished
+    Step,
+    /// The condition of a breakpoint could not be evaluated
+    ConditionError { breakpoint: usize, message: String },
+}
+
+/// A variable which is available in a stack frame
+#[derive(Clone)]
+pub struct Variable {
+    pub name: String,
+    pub typ: ArcType,
+    pub value: RootedValue<RootedThread>,
+}
+
+impl fmt::Debug for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} : {} = {}", self.name, self.typ, self.show())
+    }
+}
+
+impl Variable {
+    /// Renders the value of the variable as gluon code
+    pub fn show(&self) -> String {
+        show_value(self.value.vm(), &self.typ, self.value.get_variant())
+    }
+
+    /// Returns true if the variable can be referred to from an expression evaluated in the frame
+    fn is_accessible(&self) -> bool {
+        let mut chars = self.name.chars();
+        let valid_name = chars
+            .next()
+            .map_or(false, |c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_');
+        // Type variables of the variable are not in scope of the evaluated expression
+        let closed_type = match *self.typ {
+            Type::Forall(..) => true,
+            _ => !self
+                .typ
+                .flags()
+                .intersects(Flags::HAS_GENERICS | Flags::HAS_VARIABLES | Flags::HAS_SKOLEMS),
+        };
+        valid_name && closed_type && self.name != SCOPE_NAME
+    }
+}
+
+/// A snapshot of a frame on the stack of the debugged thread
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub function_name: Option<String>,
+    pub source_name: String,
+    /// The (one based) line which is executing in this frame
+    pub line: Option<usize>,
+    pub locals: Vec<Variable>,
+    pub upvars: Vec<Variable>,
+}
+
+impl Frame {
+    fn capture(thread: &Thread, info: &DebugInfo, level: usize) -> Option<Frame> {
+        let stack_info = info.stack_info(level)?;
+        let locals = stack_info
+            .locals()
+            .filter_map(|local| {
+                let value = stack_info.local_value(local)?;
+                Some(Variable {
+                    name: local.name.declared_name().to_string(),
+                    typ: local.typ.clone(),
+                    value: thread.root_value(value),
+                })
+            })
+            .collect();
+        let upvars = stack_info
+            .upvars()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, upvar)| {
+                let value = stack_info.upvar_value(i)?;
+                Some(Variable {
+                    name: upvar.name.clone(),
+                    typ: upvar.typ.clone(),
+                    value: thread.root_value(value),
+                })
+            })
+            .collect();
+        Some(Frame {
+            function_name: stack_info.function_name().map(|s| s.to_string()),
+            source_name: stack_info.source_name().to_string(),
+            line: stack_info.line().map(|line| line.to_usize() + 1),
+            locals,
+            upvars,
+        })
+    }
+
+    /// Returns the variables which are visible in this frame. Locals shadow upvariables and
+    /// later locals shadow earlier locals with the same name.
+    pub fn variables(&self) -> Vec<&Variable> {
+        let mut variables: Vec<&Variable> = Vec::new();
+        for variable in self.upvars.iter().chain(&self.locals) {
+            variables.retain(|v| v.name != variable.name);
+            variables.push(variable);
+        }
+        variables
+    }
+}
+
+/// Describes where and why execution stopped
+#[derive(Clone, Debug)]
+pub struct Stop {
+    pub reason: StopReason,
+    /// The frames of the stack where `frames[0]` is the currently executing frame
+    pub frames: Vec<Frame>,
+}
+
+/// The user facing part of the debugger
+#[async_trait::async_trait]
+pub trait Frontend: Send {
+    /// Called each time that execution stops. Execution continues as specified by the returned
+    /// `Resume` or is aborted if an error is returned.
+    async fn stopped(&mut self, debugger: &Debugger, stop: &Stop) -> Result<Resume>;
+}
+
+struct State {
+    breakpoints: Vec<Breakpoint>,
+    next_breakpoint_id: usize,
+    resume: Resume,
+    /// The depth of the stack when execution was resumed
+    depth: usize,
+    stop: Option<Stop>,
+}
+
+impl State {
+    fn on_line(&mut self, thread: &Thread, info: DebugInfo) -> Poll<crate::vm::Result<()>> {
+        let depth = info.stack_info_len();
+        let (source_name, line) = match info.stack_info(0) {
+            Some(stack_info) => match stack_info.line() {
+                Some(line) => (stack_info.source_name().to_string(), line),
+                None => return Poll::Ready(Ok(())),
+            },
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let breakpoints: Vec<_> = self
+            .breakpoints
+            .iter()
+            .filter(|breakpoint| breakpoint.matches(&source_name, line))
+            .map(|breakpoint| breakpoint.id)
+            .collect();
+        let step_finished = match self.resume {
+            Resume::Continue => false,
+            Resume::StepIn => true,
+            Resume::StepOver => depth <= self.depth,
+            Resume::StepOut => depth < self.depth,
+        };
+        if !step_finished && breakpoints.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        let frames = (0..depth)
+            .filter_map(|level| Frame::capture(thread, &info, level))
+            .collect();
+        self.stop = Some(Stop {
+            reason: if step_finished {
+                StopReason::Step
+            } else {
+                StopReason::Breakpoint(breakpoints)
+            },
+            frames,
+        });
+        // Suspends the thread until the debugger polls it again
+        Poll::Pending
+    }
+}
+
+/// Debugs code running on a thread
+pub struct Debugger {
+    thread: RootedThread,
+    state: Arc<Mutex<State>>,
+    /// Whether optimizations were enabled before the debugger turned them off
+    optimize: bool,
+    /// The hook and mask which were set before the debugger replaced them
+    previous_hook: Option<(Option<HookFn>, HookFlags)>,
+}
+
+impl Debugger {
+    /// Creates a debugger for `thread`. Since optimizations remove and reorder code they are
+    /// turned off for everything compiled on `thread` until the debugger is dropped. Any hook set
+    /// on `thread` is replaced until then as well.
+    pub fn new(thread: RootedThread) -> Debugger {
+        let state = Arc::new(Mutex::new(State {
+            breakpoints: Vec::new(),
+            next_breakpoint_id: 1,
+            resume: Resume::Continue,
+            depth: 0,
+            stop: None,
+        }));
+        let previous_hook = {
+            let state = state.clone();
+            let mut context = thread.context();
+            let previous_mask = context.hook_mask();
+            let previous_hook = context.set_hook(Some(Box::new(move |thread, info| {
+                state.lock().unwrap().on_line(thread, info)
+            })));
+            context.set_hook_mask(HookFlags::LINE_FLAG);
+            (previous_hook, previous_mask)
+        };
+        let optimize = {
+            let mut db = thread.get_database_mut();
+            let optimize = db.compiler_settings().optimize;
+            db.set_optimize(false);
+            optimize
+        };
+
+        Debugger {
+            thread,
+            state,
+            optimize,
+            previous_hook: Some(previous_hook),
+        }
+    }
+
+    pub fn thread(&self) -> &RootedThread {
+        &self.thread
+    }
+
+    /// Stops execution at the first line that gets executed
+    pub fn stop_on_entry(&self) {
+        self.state.lock().unwrap().resume = Resume::StepIn;
+    }
+
+    /// Adds a breakpoint at `line` (one based) in `file`. `file` may either be a module name or
+    /// the path of a gluon file. Returns the id of the breakpoint.
+    pub fn add_breakpoint(&self, file: &str, line: usize, condition: Option<String>) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_breakpoint_id;
+        state.next_breakpoint_id += 1;
+        state.breakpoints.push(Breakpoint {
+            id,
+            module: filename_to_module(file),
+            line,
+            condition,
+        });
+        id
+    }
+
+    /// Removes the breakpoint with `id`. Returns `false` if no such breakpoint existed
+    pub fn remove_breakpoint(&self, id: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let len = state.breakpoints.len();
+        state.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        len != state.breakpoints.len()
+    }
+
+    /// Removes all breakpoints in `file`
+    pub fn clear_breakpoints(&self, file: &str) {
+        let module = filename_to_module(file);
+        self.state
+            .lock()
+            .unwrap()
+            .breakpoints
+            .retain(|breakpoint| breakpoint.module != module);
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.state.lock().unwrap().breakpoints.clone()
+    }
+
+    /// Compiles and runs `expr_str` under the debugger
+    pub async fn run_expr(
+        &self,
+        frontend: &mut dyn Frontend,
+        name: &str,
+        expr_str: &str,
+    ) -> Result<(RootedValue<RootedThread>, ArcType)> {
+        let execute = self
+            .thread
+            .run_expr_async::<OpaqueValue<RootedThread, Hole>>(name, expr_str)
+            .map_ok(|(value, typ)| (value.into_inner(), typ));
+        self.drive(frontend, execute).await
+    }
+
+    async fn drive<T>(
+        &self,
+        frontend: &mut dyn Frontend,
+        execute: impl Future<Output = Result<T>> + Send,
+    ) -> Result<T> {
+        futures::pin_mut!(execute);
+        loop {
+            let state = &self.state;
+            let result =
+                future::poll_fn(|cx: &mut task::Context| match execute.as_mut().poll(cx) {
+                    Poll::Ready(result) => Poll::Ready(Some(result)),
+                    // The hook suspended execution, let the frontend decide how to continue
+                    Poll::Pending if state.lock().unwrap().stop.is_some() => Poll::Ready(None),
+                    Poll::Pending => Poll::Pending,
+                })
+                .await;
+            if let Some(result) = result {
+                return result;
+            }
+
+            let mut stop = self.state.lock().unwrap().stop.take().unwrap();
+            if !self.should_stop(&mut stop).await {
+                continue;
+            }
+
+            let resume = frontend.stopped(self, &stop).await?;
+            let mut state = self.state.lock().unwrap();
+            state.resume = resume;
+            state.depth = stop.frames.len();
+        }
+    }
+
+    /// Evaluates the conditions of the breakpoints in `stop`, returning false if execution should
+    /// continue without stopping
+    async fn should_stop(&self, stop: &mut Stop) -> bool {
+        let ids = match &stop.reason {
+            StopReason::Breakpoint(ids) => ids.clone(),
+            _ => return true,
+        };
+        let breakpoints: Vec<_> = self
+            .breakpoints()
+            .into_iter()
+            .filter(|breakpoint| ids.contains(&breakpoint.id))
+            .collect();
+        for breakpoint in breakpoints {
+            let condition = match &breakpoint.condition {
+                Some(condition) => condition,
+                None => return true,
+            };
+            match self.evaluate_condition(&stop.frames[0], condition).await {
+                Ok(true) => return true,
+                Ok(false) => (),
+                Err(err) => {
+                    stop.reason = StopReason::ConditionError {
+                        breakpoint: breakpoint.id,
+                        message: err.to_string(),
+                    };
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    async fn evaluate_condition(&self, frame: &Frame, condition: &str) -> Result<bool> {
+        let (value, _) = self
+            .evaluate_with_type(frame, condition, bool::make_type(&self.thread))
+            .await?;
+        Ok(bool::from_value(value.vm(), value.get_variant()))
+    }
+
+    /// Evaluates `expr` with the variables of `frame` in scope
+    pub async fn evaluate(
+        &self,
+        frame: &Frame,
+        expr: &str,
+    ) -> Result<(RootedValue<RootedThread>, ArcType)> {
+        self.evaluate_with_type(frame, expr, self.thread.global_env().type_cache().hole())
+            .await
+    }
+
+    /// Evaluates `expr` with the variables of `frame` in scope and renders the resulting value
+    pub async fn evaluate_to_string(&self, frame: &Frame, expr: &str) -> Result<String> {
+        let (value, typ) = self.evaluate(frame, expr).await?;
+        Ok(show_value(value.vm(), &typ, value.get_variant()))
+    }
+
+    async fn evaluate_with_type(
+        &self,
+        frame: &Frame,
+        expr: &str,
+        return_type: ArcType,
+    ) -> Result<(RootedValue<RootedThread>, ArcType)> {
+        let variables: Vec<_> = frame
+            .variables()
+            .into_iter()
+            .filter(|variable| variable.is_accessible())
+            .cloned()
+            .collect();
+
+        // The expression is compiled as a function taking the variables of the frame as a record
+        // which is then called with the values captured from the suspended thread
+        let type_cache = self.thread.global_env().type_cache();
+        let scope_type = type_cache.record(
+            Vec::new(),
+            variables
+                .iter()
+                .map(|variable| Field::new(Symbol::from(&variable.name[..]), variable.typ.clone()))
+                .collect(),
+        );
+        let expected = type_cache.function(vec![scope_type], return_type);
+        let names: Vec<_> = variables.iter().map(|v| &v.name[..]).collect();
+        let source = format!(
+            "\\{scope} -> let {{ {names} }} = {scope} in\n{expr}",
+            scope = SCOPE_NAME,
+            names = names.join(", "),
+            expr = expr
+        );
+
+        let thread = self.thread.new_thread()?;
+        let name = "<debugger>";
+        let ExecuteValue { value, typ, .. } = (&source[..])
+            .run_expr(
+                &mut ModuleCompiler::new(&mut thread.get_database()),
+                &*thread,
+                name,
+                &source,
+                Some(&expected),
+            )
+            .await?;
+        let return_type = typ
+            .remove_forall()
+            .as_function()
+            .map(|(_, ret)| ret.clone())
+            .unwrap_or_else(|| typ.clone());
+
+        let mut function: OwnedFunction<fn(Scope) -> OpaqueValue<RootedThread, Hole>> =
+            OwnedFunction::from_value(&thread, value.get_variant());
+        let result = function.call_async(Scope(variables)).await?;
+        Ok((result.into_inner(), return_type))
+    }
+}
+
+impl Drop for Debugger {
+    fn drop(&mut self) {
+        if let Some((hook, mask)) = self.previous_hook.take() {
+            let mut context = self.thread.context();
+            context.set_hook(hook);
+            context.set_hook_mask(mask);
+        }
+        self.thread.get_database_mut().set_optimize(self.optimize);
+    }
+}
+
+/// The variables passed to an evaluated expression
+struct Scope(Vec<Variable>);
+
+impl<'vm> Pushable<'vm> for Scope {
+    fn push(self, context: &mut ActiveThread<'vm>) -> crate::vm::Result<()> {
+        let mut field_names = Vec::with_capacity(self.0.len());
+        let len = self.0.len();
+        for variable in self.0 {
+            field_names.push(context.thread().global_env().intern(&variable.name)?);
+            variable.value.push(context)?;
+        }
+        context.context().push_new_record(len, &field_names)?;
+        Ok(())
+    }
+}
+
+/// Renders `value` of type `typ` as gluon code
+pub fn show_value(thread: &Thread, typ: &ArcType, value: Variants) -> String {
+    let env = thread.get_env();
+    let debug_level = thread.global_env().get_debug_level();
+    ValuePrinter::new(&env, typ, value, &debug_level)
+        .width(80)
+        .max_level(5)
+        .to_string()
+}
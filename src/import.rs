@@ -22,7 +22,7 @@ use itertools::Itertools;
 use crate::base::{
     ast::{self, expr_to_path, Expr, Literal, SpannedExpr},
     filename_to_module,
-    fnv::FnvMap,
+    fnv::{FnvMap, FnvSet},
     pos,
     symbol::Symbol,
     types::ArcType,
@@ -37,6 +37,8 @@ use crate::vm::{
     ExternLoader, ExternModule,
 };
 
+#[cfg(feature = "serialization")]
+use crate::module_cache::ModuleCache;
 use crate::{
     query::{Compilation, CompilerDatabase},
     IoError, ModuleCompiler,
@@ -54,6 +56,10 @@ quick_error! {
                 cycle.iter().chain(Some(module)).format(" -> ")
             )
         }
+        /// The module were denied by the capability policy of the vm
+        Denied(module: String) {
+            display("Importing `{}` was denied by the capability policy of the vm", module)
+        }
         /// Generic message error
         String(message: String) {
             display("{}", message)
@@ -186,6 +192,8 @@ pub(crate) trait ImportApi: Send + Sync {
         module_id: &Symbol,
     ) -> Result<ArcType, (Option<ArcType>, MacroError)>;
     fn snapshot(&self, thread: RootedThread) -> DatabaseSnapshot;
+    #[cfg(feature = "serialization")]
+    fn module_cache(&self) -> Option<Arc<ModuleCache>>;
     fn fork(
         &mut self,
         forker: salsa::ForkState<CompilerDatabase>,
@@ -217,6 +225,10 @@ where
     fn snapshot(&self, thread: RootedThread) -> DatabaseSnapshot {
         Self::snapshot(self, thread)
     }
+    #[cfg(feature = "serialization")]
+    fn module_cache(&self) -> Option<Arc<ModuleCache>> {
+        self.module_cache.read().unwrap().clone()
+    }
     fn fork(
         &mut self,
         forker: salsa::ForkState<CompilerDatabase>,
@@ -233,6 +245,9 @@ pub struct Import<I = DefaultImporter> {
     pub loaders: RwLock<FnvMap<String, ExternLoader>>,
     pub importer: I,
 
+    denied_modules: RwLock<FnvSet<String>>,
+    #[cfg(feature = "serialization")]
+    module_cache: RwLock<Option<Arc<ModuleCache>>>,
     compiler: Mutex<CompilerDatabase>,
 }
 
@@ -242,6 +257,9 @@ impl<I> Import<I> {
         Import {
             paths: RwLock::new(vec![PathBuf::from(".")]),
             loaders: RwLock::default(),
+            denied_modules: RwLock::default(),
+            #[cfg(feature = "serialization")]
+            module_cache: RwLock::default(),
             compiler: CompilerDatabase::new_base(None).into(),
             importer: importer,
         }
@@ -263,6 +281,18 @@ impl<I> Import<I> {
             .insert(String::from(module), loader);
     }
 
+    /// Makes imports of any module in `modules` fail with `Error::Denied`
+    pub fn set_denied_modules(&self, modules: FnvSet<String>) {
+        *self.denied_modules.write().unwrap() = modules;
+    }
+
+    /// Makes modules be loaded from and stored into `cache` instead of always being compiled
+    /// from source
+    #[cfg(feature = "serialization")]
+    pub fn set_module_cache(&self, cache: Option<ModuleCache>) {
+        *self.module_cache.write().unwrap() = cache.map(Arc::new);
+    }
+
     pub fn modules(&self) -> Vec<Cow<'static, str>> {
         STD_LIBS
             .iter()
@@ -377,6 +407,10 @@ impl<I> Import<I> {
     {
         assert!(module_id.is_global());
         let modulename = module_id.name().definition_name();
+        if self.denied_modules.read().unwrap().contains(modulename) {
+            return Err((None, MacroError::new(Error::Denied(modulename.to_string()))));
+        }
+
         // Retrieve the source, first looking in the standard library included in the
         // binary
         let unloaded_module = self.get_unloaded_module(&modulename);
@@ -44,12 +44,18 @@ macro_rules! try_future {
 }
 
 pub mod compiler_pipeline;
+pub mod debugger;
 #[macro_use]
 pub mod import;
 pub mod lift_io;
+#[cfg(feature = "serialization")]
+pub mod module_cache;
+pub mod policy;
+pub mod profiler;
 #[doc(hidden)]
 pub mod query;
 pub mod std_lib;
+pub mod user_macro;
 
 pub use crate::vm::thread::{RootedThread, Thread};
 
@@ -284,6 +290,7 @@ pub struct Settings {
     pub use_standard_lib: bool,
     pub optimize: bool,
     pub run_io: bool,
+    pub checked_arithmetic: bool,
 }
 
 impl Default for Settings {
@@ -295,6 +302,7 @@ impl Default for Settings {
             use_standard_lib: true,
             optimize: true,
             run_io: false,
+            checked_arithmetic: false,
         }
     }
 }
@@ -393,6 +401,15 @@ impl import::DatabaseMut {
         /// (default: false)
         run_io set_run_io: bool
     }
+
+    runtime_option! {
+        /// Sets whether overflow and division by zero in `Int` arithmetic report where they
+        /// happened. The operation fails with an error that can be caught with `io.catch` either
+        /// way, the only difference is that the error is an `Error::Panic` whose message carries
+        /// the source location of the operation.
+        /// (default: false)
+        checked_arithmetic set_checked_arithmetic: bool
+    }
 }
 
 /// Extension trait which provides methods to load and execute gluon code
@@ -564,6 +581,33 @@ pub trait ThreadExt: Send + Sync {
             .await
     }
 
+    /// Deserializes a thread which were serialized with `Thread::serialize_suspended`. Modules
+    /// that were loaded when the thread was serialized are imported first unless they are
+    /// already loaded, so any extern modules they depend on must be added to this thread as well.
+    /// Modules loaded with `load_script` are not importable and must be loaded again before
+    /// calling this.
+    #[cfg(feature = "serialization")]
+    fn deserialize_thread<'de, D>(&self, deserializer: D) -> StdResult<RootedThread, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let vm = self.thread();
+        let import = get_import(vm);
+        vm.deserialize_suspended(deserializer, |name| {
+            if vm.global_env().global_exists(name) {
+                return Ok(());
+            }
+            let module_name = Symbol::from(format!("@{}", name));
+            futures::executor::block_on(import.load_module(
+                &mut ModuleCompiler::new(&mut import.snapshot(vm.root_thread())),
+                vm,
+                &module_name,
+            ))
+            .map(|_| ())
+            .map_err(|(_, err)| err.to_string())
+        })
+    }
+
     /// Parses and typechecks `expr_str` followed by extracting metadata from the created
     /// expression
     async fn extract_metadata(
@@ -758,6 +802,14 @@ pub trait ThreadExt: Send + Sync {
         let expr = skip_implicit_prelude(file_map.span(), &expr.expr());
         Ok(formatter.pretty_expr(&*file_map, expr))
     }
+
+    /// Starts profiling all code that runs on this thread. Profiling ends and the results are
+    /// returned once `Profiler::finish` is called.
+    ///
+    /// Profiling replaces any hook that is set on the thread.
+    fn start_profiling(&self) -> profiler::Profiler {
+        profiler::Profiler::start(self.thread())
+    }
 }
 
 fn skip_implicit_prelude<'a, 'ast>(
@@ -848,6 +900,8 @@ let { ? } = import! std.string
 
 let { ? } = import! std.array
 
+let { ? } = import! std.interpolate
+
 let { error } = import! std.prim
 
 in ()
@@ -856,6 +910,8 @@ in ()
 #[derive(Default)]
 pub struct VmBuilder {
     import_paths: Option<Vec<PathBuf>>,
+    module_cache: Option<PathBuf>,
+    policy: policy::Policy,
 }
 
 impl VmBuilder {
@@ -868,6 +924,17 @@ impl VmBuilder {
         import_paths set_import_paths: Option<Vec<PathBuf>>
     }
 
+    option! {
+        /// Restricts the modules and primitives which scripts may use (default: allow everything)
+        policy set_policy: policy::Policy
+    }
+
+    option! {
+        /// Directory where compiled modules are cached between processes. Requires the
+        /// `serialization` feature (default: None)
+        module_cache set_module_cache: Option<PathBuf>
+    }
+
     pub fn build(self) -> RootedThread {
         futures::executor::block_on(self.build_inner(None))
     }
@@ -891,9 +958,11 @@ impl VmBuilder {
         self,
         spawner: Option<Box<dyn futures::task::Spawn + Send + Sync>>,
     ) -> RootedThread {
+        let (denied_modules, sandbox) = self.policy.into_parts();
         let vm = RootedThread::with_global_state(
             crate::vm::vm::GlobalVmStateBuilder::new()
                 .spawner(spawner)
+                .sandbox(sandbox)
                 .build(),
         );
 
@@ -905,6 +974,10 @@ impl VmBuilder {
                 if let Some(import_paths) = self.import_paths {
                     import.set_paths(import_paths);
                 }
+                import.set_denied_modules(denied_modules);
+
+                #[cfg(feature = "serialization")]
+                import.set_module_cache(self.module_cache.map(module_cache::ModuleCache::new));
 
                 if let Ok(gluon_path) = env::var("GLUON_PATH") {
                     import.add_path(gluon_path);
@@ -913,6 +986,7 @@ impl VmBuilder {
             }
 
             macros.insert(String::from("lift_io"), lift_io::LiftIo);
+            macros.insert(String::from("import_macros"), user_macro::ImportMacros);
         }
 
         add_extern_module_with_deps(
@@ -938,16 +1012,29 @@ impl VmBuilder {
             ("std.int.prim", crate::vm::primitives::load_int),
             ("std.float.prim", crate::vm::primitives::load_float),
             ("std.string.prim", crate::vm::primitives::load_string),
-            ("std.fs.prim", crate::vm::primitives::load_fs),
             ("std.char.prim", crate::vm::primitives::load_char),
             ("std.char.prim", crate::vm::primitives::load_char),
+            ("std.array.prim", crate::vm::primitives::load_array),
+            (
+                "std.interpolate.prim",
+                crate::vm::primitives::load_interpolate,
+            ),
             ("std.thread.prim", crate::vm::channel::load_thread),
             ("std.io.prim", crate::std_lib::io::load),
+            ("std.int32.prim", crate::std_lib::fixed_int::int32::load),
+            ("std.uint64.prim", crate::std_lib::fixed_int::uint64::load),
         ];
         for (name, load_fn) in deps {
             add_extern_module_with_deps(&vm, name, load_fn, vec!["std.types".into()]);
         }
 
+        add_extern_module_with_deps(
+            &vm,
+            "std.fs.prim",
+            crate::vm::primitives::load_fs,
+            vec!["std.types".into(), "std.fs.types".into()],
+        );
+
         add_extern_module_with_deps(
             &vm,
             "std.path.prim",
@@ -955,13 +1042,19 @@ impl VmBuilder {
             vec!["std.path.types".into()],
         );
 
+        add_extern_module_with_deps(
+            &vm,
+            "std.process.prim",
+            crate::std_lib::process::load,
+            vec!["std.process.types".into()],
+        );
+
         let deps: &[(_, fn(&Thread) -> _)] = &[
-            ("std.array.prim", crate::vm::primitives::load_array),
+            ("std.hash_map.prim", crate::vm::hash_trie::load),
             ("std.lazy.prim", crate::vm::lazy::load),
             ("std.reference.prim", crate::vm::reference::load),
             ("std.channel.prim", crate::vm::channel::load_channel),
             ("std.debug.prim", crate::vm::debug::load),
-            ("std.process.prim", crate::std_lib::process::load),
             ("std.env.prim", crate::std_lib::env::load),
         ];
         for (name, load_fn) in deps {
@@ -974,6 +1067,12 @@ impl VmBuilder {
             crate::vm::primitives::load_string_buf,
         );
 
+        add_extern_module(
+            &vm,
+            "std.effect.st.array.prim",
+            crate::vm::primitives::load_array_buf,
+        );
+
         add_extern_module_if!(
             #[cfg(feature = "serialization")],
             available_if = "gluon is compiled with the 'serialization' feature",
@@ -1001,6 +1100,20 @@ impl VmBuilder {
             args(&vm, "std.http.prim", crate::std_lib::http::load)
         );
 
+        add_extern_module_if!(
+            #[cfg(feature = "bignum")],
+            available_if = "gluon is compiled with the 'bignum' feature",
+            dependencies = ["std.types"],
+            args(&vm, "std.bigint.prim", crate::std_lib::bigint::load)
+        );
+
+        add_extern_module_if!(
+            #[cfg(feature = "bignum")],
+            available_if = "gluon is compiled with the 'bignum' feature",
+            dependencies = ["std.types"],
+            args(&vm, "std.decimal.prim", crate::std_lib::decimal::load)
+        );
+
         add_extern_module_if!(
             #[cfg(all(feature = "random", not(target_arch = "wasm32")))],
             available_if = "gluon is compiled with the 'random' feature and is not targeting WASM",
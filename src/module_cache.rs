@@ -0,0 +1,269 @@
+//! An on disk cache of typechecked and compiled modules.
+//!
+//! Each module is stored in its own file, keyed by a hash of the module's source, the compiler
+//! version and the settings which affect the generated code. Since the bytecode and type of a
+//! module depends on the modules it imports, each entry also records a fingerprint of every
+//! imported module which must still match when the entry is loaded in a later process.
+//!
+//! ```no_run
+//! # use gluon::VmBuilder;
+//! let vm = VmBuilder::new()
+//!     .module_cache(Some("target/gluon-cache".into()))
+//!     .build();
+//! ```
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use crate::base::{
+    fnv::{FnvHasher, FnvMap},
+    metadata::Metadata,
+    types::ArcType,
+};
+
+use crate::vm::{
+    api::OpaqueValue,
+    gc::GcPtr,
+    internal::ClosureData,
+    thread::{RootedThread, ThreadInternal},
+    vm::VmEnv,
+};
+
+use crate::{
+    compiler_pipeline::Module,
+    query::{Compilation, CompilerDatabase},
+    serde::ser::SerializeState,
+    Settings,
+};
+
+/// The type, metadata and the (not yet evaluated) closure of a module loaded from the cache
+pub(crate) type CachedModule = (
+    ArcType,
+    Arc<Metadata>,
+    OpaqueValue<RootedThread, GcPtr<ClosureData>>,
+);
+
+/// Stores compiled modules in `dir` so that later processes can skip typechecking and
+/// compiling modules whose source have not changed.
+#[derive(Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    /// The key of each module which has been stored or loaded by this process. Used to detect
+    /// changes in the implementation of imported modules.
+    keys: Mutex<FnvMap<String, u64>>,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> ModuleCache {
+        ModuleCache {
+            dir: dir.into(),
+            keys: Default::default(),
+        }
+    }
+
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    /// Removes every entry from the cache
+    pub fn clear(&self) -> io::Result<()> {
+        self.keys.lock().unwrap().clear();
+        match fs::remove_dir_all(&self.dir) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn key(&self, module: &str, source: &str, settings: &Settings) -> u64 {
+        let mut hasher = FnvHasher::default();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        module.hash(&mut hasher);
+        source.hash(&mut hasher);
+        // `run_io` and `use_standard_lib` does not affect the compiled module
+        settings.implicit_prelude.hash(&mut hasher);
+        settings.emit_debug_info.hash(&mut hasher);
+        settings.full_metadata.hash(&mut hasher);
+        settings.optimize.hash(&mut hasher);
+        settings.checked_arithmetic.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn path(&self, module: &str, key: u64) -> PathBuf {
+        self.dir.join(format!("{}-{:016x}.json", module, key))
+    }
+
+    /// Fingerprints the imported module `global` (`@std.types`) which must already be loaded
+    fn fingerprint(&self, db: &CompilerDatabase, global: &str) -> Option<u64> {
+        let typ = db.get_global(global)?.typ;
+
+        let mut hasher = FnvHasher::default();
+        typ.to_string().hash(&mut hasher);
+        self.keys
+            .lock()
+            .unwrap()
+            .get(global.trim_start_matches('@'))
+            .hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    /// Loads `module` from the cache if it has an entry which is still valid.
+    ///
+    /// Any problem with the entry, such as it being stale or corrupted, is treated as a cache
+    /// miss so that the module is compiled from source instead.
+    pub(crate) async fn load(
+        &self,
+        db: &mut dyn Compilation,
+        module: &str,
+    ) -> Option<CachedModule> {
+        if db.compiler().state().inline_modules.contains_key(module) {
+            return None;
+        }
+        let source = db.module_text(module.to_string()).ok()?;
+        let key = self.key(module, &source, &db.compiler_settings());
+        let path = self.path(module, key);
+
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Unable to read cached module `{}`: {}", path.display(), err);
+                return None;
+            }
+        };
+        let (dependencies, body) = parse_header(&contents)?;
+
+        for (dependency, fingerprint) in &dependencies {
+            if db.import(dependency.clone()).await.is_err()
+                || self.fingerprint(db.compiler(), dependency) != Some(*fingerprint)
+            {
+                debug!(
+                    "Cached module `{}` is stale since `{}` changed",
+                    module, dependency
+                );
+                return None;
+            }
+        }
+
+        let thread = db.thread().root_thread();
+        let result: Result<Module, _> = {
+            use crate::vm::serialization::DeSeed;
+
+            let mut deserializer = serde_json::Deserializer::from_slice(body);
+            DeSeed::new(&thread, &mut thread.current_context()).deserialize(&mut deserializer)
+        };
+        let Module {
+            typ,
+            metadata,
+            module: compiled_module,
+        } = match result {
+            Ok(module) => module,
+            Err(err) => {
+                warn!("Unable to load cached module `{}`: {}", path.display(), err);
+                return None;
+            }
+        };
+
+        let closure = thread
+            .global_env()
+            .new_global_thunk(&thread, compiled_module)
+            .ok()?;
+
+        self.keys.lock().unwrap().insert(module.to_string(), key);
+        info!("Loaded `{}` from the module cache", module);
+
+        Some((typ, metadata, closure))
+    }
+
+    /// Stores `compiled_module` into the cache. All modules it imports must already be loaded.
+    pub(crate) fn store(
+        &self,
+        db: &mut dyn Compilation,
+        name: &str,
+        source: &str,
+        module: &Module,
+    ) -> io::Result<()> {
+        if db.compiler().state().inline_modules.contains_key(name) {
+            return Ok(());
+        }
+        let key = self.key(name, source, &db.compiler_settings());
+
+        let mut contents = Vec::new();
+        for dependency in &module.module.module_globals {
+            let dependency = dependency.definition_name();
+            let fingerprint = match self.fingerprint(db.compiler(), dependency) {
+                Some(fingerprint) => fingerprint,
+                None => return Ok(()),
+            };
+            writeln!(contents, "{:016x} {}", fingerprint, dependency)?;
+        }
+        writeln!(contents)?;
+
+        module
+            .serialize_state(
+                &mut serde_json::Serializer::new(&mut contents),
+                &crate::vm::serialization::SeSeed::new(),
+            )
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        // Write to a temporary file first so that other processes never see a partial entry
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(name, key);
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp_path, &contents)?;
+        fs::rename(&temp_path, &path)?;
+
+        self.keys.lock().unwrap().insert(name.to_string(), key);
+
+        if let Err(err) = self.remove_stale_entries(name, key) {
+            warn!("Unable to remove stale entries of `{}`: {}", name, err);
+        }
+        Ok(())
+    }
+
+    /// Removes the entries of `module` which were stored with a key other than `key`
+    fn remove_stale_entries(&self, module: &str, key: u64) -> io::Result<()> {
+        let current = self.path(module, key);
+        let prefix = format!("{}-", module);
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            // `<module>-<16 hex digits>.json`. Checking the length avoids matching modules whose
+            // name starts with `<module>-`
+            let is_entry = file_name.len() == prefix.len() + 16 + ".json".len()
+                && file_name.starts_with(&prefix[..])
+                && file_name.ends_with(".json")
+                && file_name[prefix.len()..prefix.len() + 16]
+                    .bytes()
+                    .all(|b| b.is_ascii_hexdigit());
+            if is_entry && entry.path() != current {
+                debug!("Removing stale cache entry `{}`", file_name);
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits an entry into the fingerprints of its dependencies and the serialized module
+fn parse_header(contents: &[u8]) -> Option<(Vec<(String, u64)>, &[u8])> {
+    let mut dependencies = Vec::new();
+    let mut reader = contents;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Some((dependencies, reader));
+        }
+        let mut iter = line.splitn(2, ' ');
+        let fingerprint = u64::from_str_radix(iter.next()?, 16).ok()?;
+        dependencies.push((iter.next()?.to_string(), fingerprint));
+    }
+}
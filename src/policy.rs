@@ -0,0 +1,114 @@
+//! Capability policies which restrict what scripts running in a `VmBuilder` created vm may do.
+//!
+//! ```
+//! # use gluon::{policy::Policy, VmBuilder};
+//! let vm = VmBuilder::new()
+//!     .policy(
+//!         Policy::new()
+//!             .deny_module("std.process.prim")
+//!             .deny_primitive("std.env.prim.set_var")
+//!             .fs_root(Some(".".into()))
+//!             .read_only_fs(true),
+//!     )
+//!     .build();
+//! ```
+use std::path::PathBuf;
+
+use crate::{base::fnv::FnvSet, vm::sandbox::Sandbox};
+
+/// The extern modules which give access to the outside world
+const SANDBOXED_MODULES: &[&str] = &[
+    "std.process.prim",
+    "std.env.prim",
+    "std.fs.prim",
+    "std.http.prim",
+];
+
+/// The primitives of otherwise allowed modules which give access to the outside world
+const SANDBOXED_PRIMITIVES: &[&str] = &[
+    "std.io.prim.open_file_with",
+    "std.io.prim.read_file_to_string",
+    "std.io.prim.read_file_to_array",
+    "std.path.prim.metadata",
+    "std.path.prim.symlink_metadata",
+    "std.path.prim.canonicalize",
+    "std.path.prim.read_link",
+    "std.path.prim.read_dir",
+    "std.path.prim.exists",
+    "std.path.prim.is_file",
+    "std.path.prim.is_dir",
+];
+
+/// Decides which modules and primitives a vm may use. The default policy allows everything.
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    denied_modules: FnvSet<String>,
+    sandbox: Sandbox,
+}
+
+impl Policy {
+    pub fn new() -> Policy {
+        Policy::default()
+    }
+
+    /// Denies access to processes, environment variables, the file system and the network. Printing
+    /// to and reading from the console through `std.io` is still allowed. Individual capabilities
+    /// may be added back with `allow_module` and `allow_primitive`, re-allowing a module gives
+    /// access to all of its primitives.
+    pub fn sandboxed() -> Policy {
+        let policy = SANDBOXED_MODULES
+            .iter()
+            .fold(Policy::new(), |policy, module| policy.deny_module(*module));
+        SANDBOXED_PRIMITIVES
+            .iter()
+            .fold(policy, |policy, primitive| {
+                policy.deny_primitive(*primitive)
+            })
+    }
+
+    /// Makes importing `module` (`std.fs.prim`) an error. Any module which imports `module`
+    /// (such as `std.fs`) will fail to import as well.
+    pub fn deny_module(mut self, module: impl Into<String>) -> Self {
+        self.denied_modules.insert(module.into());
+        self
+    }
+
+    /// Allows `module` to be imported again after it were denied
+    pub fn allow_module(mut self, module: &str) -> Self {
+        self.denied_modules.remove(module);
+        self
+    }
+
+    /// Makes calls to the primitive `name` (`std.process.prim.execute`) an error
+    pub fn deny_primitive(mut self, name: impl Into<String>) -> Self {
+        self.sandbox = self.sandbox.deny_primitive(name);
+        self
+    }
+
+    /// Allows calls to the primitive `name` again after it were denied
+    pub fn allow_primitive(mut self, name: &str) -> Self {
+        self.sandbox = self.sandbox.allow_primitive(name);
+        self
+    }
+
+    /// Restricts file system access to paths inside `root`. Relative paths are resolved against
+    /// `root` instead of the current directory.
+    pub fn fs_root(mut self, root: Option<PathBuf>) -> Self {
+        self.sandbox = self.sandbox.fs_root(root);
+        self
+    }
+
+    /// Makes all attempts to write to the file system an error
+    pub fn read_only_fs(mut self, read_only: bool) -> Self {
+        self.sandbox = self.sandbox.read_only_fs(read_only);
+        self
+    }
+
+    pub fn is_module_denied(&self, module: &str) -> bool {
+        self.denied_modules.contains(module)
+    }
+
+    pub(crate) fn into_parts(self) -> (FnvSet<String>, Sandbox) {
+        (self.denied_modules, self.sandbox)
+    }
+}
@@ -0,0 +1,431 @@
+//! An instrumenting profiler for gluon code.
+//!
+//! The profiler uses the call and line hooks of the virtual machine to track which functions are
+//! executing and records the number of calls, the time spent and the memory allocated in each
+//! function.
+//!
+//! ```no_run
+//! # use gluon::{new_vm, ThreadExt};
+//! let vm = new_vm();
+//! let profiler = vm.start_profiling();
+//! vm.run_expr::<i32>("example", "let f x = x + 1\nf 1").unwrap();
+//! let profile = profiler.finish();
+//! profile.write_folded(&mut std::io::stdout()).unwrap();
+//! ```
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::task::Poll;
+use serde_json::json;
+
+use crate::vm::thread::{DebugInfo, HookFlags, HookFn};
+
+use crate::{RootedThread, Thread};
+
+/// Timing and allocation information about a single function
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionProfile {
+    pub function_name: String,
+    pub source_name: String,
+    /// The (one based) line where the function starts
+    pub line: Option<usize>,
+    pub calls: u64,
+    /// Time spent in the function, including the time spent in any functions it called
+    pub inclusive_time: Duration,
+    /// Time spent in the function itself
+    pub exclusive_time: Duration,
+    /// Bytes allocated by the function and the functions it called
+    pub inclusive_allocated: usize,
+    /// Bytes allocated by the function itself
+    pub exclusive_allocated: usize,
+}
+
+impl FunctionProfile {
+    fn label(&self) -> String {
+        match self.line {
+            Some(line) => format!("{} ({}:{})", self.function_name, self.source_name, line),
+            None => format!("{} ({})", self.function_name, self.source_name),
+        }
+    }
+}
+
+/// The time and memory spent in a specific stack of functions
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackProfile {
+    /// Indexes into `Profile::functions`, starting with the outermost function
+    pub frames: Vec<usize>,
+    /// The time spent in the innermost function of `frames`
+    pub time: Duration,
+    /// The bytes allocated by the innermost function of `frames`
+    pub allocated: usize,
+}
+
+/// The result of profiling a thread
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub total_time: Duration,
+    /// All functions that were called, ordered by the time spent in the function itself
+    pub functions: Vec<FunctionProfile>,
+    pub stacks: Vec<StackProfile>,
+}
+
+impl Profile {
+    /// Returns the profile of the first function named `name`
+    pub fn function(&self, name: &str) -> Option<&FunctionProfile> {
+        self.functions.iter().find(|f| f.function_name == name)
+    }
+
+    /// Writes the stacks in the folded format used by flamegraph tools, weighted by the number
+    /// of microseconds spent in each stack
+    pub fn write_folded(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let labels: Vec<_> = self
+            .functions
+            .iter()
+            .map(|function| function.label().replace(';', ":"))
+            .collect();
+        for stack in &self.stacks {
+            let micros = stack.time.as_micros();
+            if micros == 0 {
+                continue;
+            }
+            let mut frames = stack.frames.iter().map(|&i| &labels[i][..]);
+            if let Some(first) = frames.next() {
+                write!(writer, "{}", first)?;
+            }
+            for frame in frames {
+                write!(writer, ";{}", frame)?;
+            }
+            writeln!(writer, " {}", micros)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a JSON report of all functions. Times are given in microseconds.
+    pub fn write_json(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|function| {
+                json!({
+                    "function": function.function_name,
+                    "source": function.source_name,
+                    "line": function.line,
+                    "calls": function.calls,
+                    "inclusive_time_us": micros(function.inclusive_time),
+                    "exclusive_time_us": micros(function.exclusive_time),
+                    "inclusive_allocated_bytes": function.inclusive_allocated,
+                    "exclusive_allocated_bytes": function.exclusive_allocated,
+                })
+            })
+            .collect();
+        let report = json!({
+            "total_time_us": micros(self.total_time),
+            "functions": functions,
+        });
+        serde_json::to_writer_pretty(&mut *writer, &report)?;
+        writeln!(writer)
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros() as u64
+}
+
+/// A node in the tree of all stacks that were observed
+struct Node {
+    function: usize,
+    parent: Option<usize>,
+    children: HashMap<usize, usize>,
+    time: Duration,
+    allocated: usize,
+}
+
+/// A function which is currently on the stack
+struct ActiveFrame {
+    function: usize,
+    node: usize,
+    start: Instant,
+    /// Bytes allocated by this frame and the frames it called so far
+    allocated: usize,
+}
+
+struct Recorder {
+    start: Instant,
+    functions: Vec<FunctionProfile>,
+    /// Maps function names to the functions with that name
+    function_ids: HashMap<String, Vec<usize>>,
+    /// The number of times each function appears on the stack, used to avoid counting the
+    /// inclusive time of recursive calls more than once
+    active: Vec<usize>,
+    nodes: Vec<Node>,
+    roots: HashMap<usize, usize>,
+    stack: Vec<ActiveFrame>,
+    last_time: Instant,
+    last_allocated: usize,
+}
+
+impl Recorder {
+    fn new(allocated: usize) -> Recorder {
+        let now = Instant::now();
+        Recorder {
+            start: now,
+            functions: Vec::new(),
+            function_ids: HashMap::new(),
+            active: Vec::new(),
+            nodes: Vec::new(),
+            roots: HashMap::new(),
+            stack: Vec::new(),
+            last_time: now,
+            last_allocated: allocated,
+        }
+    }
+
+    fn function_id(&mut self, info: &DebugInfo, level: usize) -> usize {
+        let stack_info = info.stack_info(level).unwrap();
+        let function_name = stack_info.function_name().unwrap_or("<unknown>");
+        let source_name = stack_info.source_name();
+
+        let functions = &self.functions;
+        let existing = self.function_ids.get(function_name).and_then(|ids| {
+            ids.iter()
+                .cloned()
+                .find(|&id| functions[id].source_name == source_name)
+        });
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = self.functions.len();
+                self.functions.push(FunctionProfile {
+                    function_name: function_name.to_string(),
+                    source_name: source_name.to_string(),
+                    line: None,
+                    calls: 0,
+                    inclusive_time: Duration::default(),
+                    exclusive_time: Duration::default(),
+                    inclusive_allocated: 0,
+                    exclusive_allocated: 0,
+                });
+                self.active.push(0);
+                self.function_ids
+                    .entry(function_name.to_string())
+                    .or_default()
+                    .push(id);
+                id
+            }
+        };
+
+        // The line of a function which is being called is the line the function starts at
+        if level == 0 && info.state().contains(HookFlags::CALL_FLAG) {
+            if let Some(line) = stack_info.line() {
+                let line = line.to_usize() + 1;
+                let function = &mut self.functions[id];
+                function.line = Some(function.line.map_or(line, |l| l.min(line)));
+            }
+        }
+        id
+    }
+
+    /// Attributes the time and memory used since the last event to the currently executing
+    /// function
+    fn update(&mut self, now: Instant, allocated: usize) {
+        let elapsed = now - self.last_time;
+        let allocated_since = allocated.saturating_sub(self.last_allocated);
+        self.last_time = now;
+        self.last_allocated = allocated;
+
+        if let Some(top) = self.stack.last_mut() {
+            top.allocated += allocated_since;
+
+            let node = &mut self.nodes[top.node];
+            node.time += elapsed;
+            node.allocated += allocated_since;
+
+            let function = &mut self.functions[top.function];
+            function.exclusive_time += elapsed;
+            function.exclusive_allocated += allocated_since;
+        }
+    }
+
+    fn on_event(&mut self, info: &DebugInfo) {
+        let now = Instant::now();
+        self.update(now, info.total_allocated());
+
+        let depth = info.stack_info_len();
+        // On a call the top frame has just been entered so it can not be a frame we have seen
+        let mut keep = if info.state().contains(HookFlags::CALL_FLAG) {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        keep = keep.min(self.stack.len());
+        // Frames may have been replaced without a call event, for instance by returning from one
+        // function and then tail calling another
+        while keep > 0 {
+            let function = self.function_id(info, depth - keep);
+            if function == self.stack[keep - 1].function {
+                break;
+            }
+            keep -= 1;
+        }
+
+        while self.stack.len() > keep {
+            self.pop(now);
+        }
+        for level in (0..depth - self.stack.len()).rev() {
+            let function = self.function_id(info, level);
+            self.push(function, now);
+        }
+    }
+
+    fn push(&mut self, function: usize, now: Instant) {
+        self.functions[function].calls += 1;
+        self.active[function] += 1;
+
+        let nodes_len = self.nodes.len();
+        let children = match self.stack.last() {
+            Some(parent) => &mut self.nodes[parent.node].children,
+            None => &mut self.roots,
+        };
+        let node = *children.entry(function).or_insert(nodes_len);
+        if node == nodes_len {
+            self.nodes.push(Node {
+                function,
+                parent: self.stack.last().map(|parent| parent.node),
+                children: HashMap::new(),
+                time: Duration::default(),
+                allocated: 0,
+            });
+        }
+
+        self.stack.push(ActiveFrame {
+            function,
+            node,
+            start: now,
+            allocated: 0,
+        });
+    }
+
+    fn pop(&mut self, now: Instant) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        self.active[frame.function] -= 1;
+        if self.active[frame.function] == 0 {
+            let function = &mut self.functions[frame.function];
+            function.inclusive_time += now - frame.start;
+            function.inclusive_allocated += frame.allocated;
+        }
+        if let Some(parent) = self.stack.last_mut() {
+            parent.allocated += frame.allocated;
+        }
+    }
+
+    fn finish(&mut self, allocated: usize) -> Profile {
+        let now = Instant::now();
+        self.update(now, allocated);
+        while !self.stack.is_empty() {
+            self.pop(now);
+        }
+
+        // Sort the functions and remap the indexes which refer to them
+        let mut order: Vec<_> = (0..self.functions.len()).collect();
+        order.sort_by(|&l, &r| {
+            self.functions[r]
+                .exclusive_time
+                .cmp(&self.functions[l].exclusive_time)
+        });
+        let mut new_index = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+
+        let stacks = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut frames = vec![new_index[node.function]];
+                let mut parent = node.parent;
+                while let Some(p) = parent {
+                    frames.push(new_index[self.nodes[p].function]);
+                    parent = self.nodes[p].parent;
+                }
+                frames.reverse();
+                StackProfile {
+                    frames,
+                    time: node.time,
+                    allocated: node.allocated,
+                }
+            })
+            .collect();
+
+        Profile {
+            total_time: now - self.start,
+            functions: order
+                .into_iter()
+                .map(|i| self.functions[i].clone())
+                .collect(),
+            stacks,
+        }
+    }
+}
+
+/// Profiles all code executing on a thread. Created with `ThreadExt::start_profiling`.
+pub struct Profiler {
+    thread: RootedThread,
+    recorder: Arc<Mutex<Recorder>>,
+    /// The hook and mask which were set before profiling started, restored once it stops
+    previous_hook: Option<(Option<HookFn>, HookFlags)>,
+}
+
+impl Profiler {
+    /// Starts profiling `thread`. Any hook which were set on the thread is replaced until
+    /// profiling stops.
+    pub fn start(thread: &Thread) -> Profiler {
+        let recorder = Arc::new(Mutex::new(Recorder::new(thread.total_allocated())));
+        let previous_hook = {
+            let recorder = recorder.clone();
+            let mut context = thread.context();
+            let previous_mask = context.hook_mask();
+            let previous_hook = context.set_hook(Some(Box::new(move |_, info| {
+                recorder.lock().unwrap().on_event(&info);
+                Poll::Ready(Ok(()))
+            })));
+            // Returns are only observed when the line changes in the calling function
+            context.set_hook_mask(HookFlags::CALL_FLAG | HookFlags::LINE_FLAG);
+            (previous_hook, previous_mask)
+        };
+        Profiler {
+            thread: thread.root_thread(),
+            recorder,
+            previous_hook: Some(previous_hook),
+        }
+    }
+
+    /// Stops profiling and returns the collected profile
+    pub fn finish(mut self) -> Profile {
+        self.stop();
+        let allocated = self.thread.total_allocated();
+        let mut recorder = self.recorder.lock().unwrap();
+        recorder.finish(allocated)
+    }
+
+    fn stop(&mut self) {
+        if let Some((hook, mask)) = self.previous_hook.take() {
+            let mut context = self.thread.context();
+            context.set_hook(hook);
+            context.set_hook_mask(mask);
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -486,34 +486,50 @@ async fn compiled_module(
     module: String,
     expected_type: Option<ArcType>,
 ) -> StdResult<OpaqueValue<RootedThread, GcPtr<ClosureData>>, Error> {
-    let core_expr = db.core_expr(module.clone(), expected_type).await?;
-    let settings = db.compiler_settings();
+    #[cfg(feature = "serialization")]
+    let is_global_module = expected_type.is_none();
 
-    let mut compiler = ModuleCompiler::new(db.compiler());
+    let name = Name::new(&module);
+    let module_id = Symbol::from(format!("@{}", name));
+    let compiled_module = {
+        let core_expr = db.core_expr(module.clone(), expected_type).await?;
+        let settings = db.compiler_settings();
 
-    let source = compiler
-        .get_filemap(&module)
-        .expect("Filemap does not exist");
+        let mut compiler = ModuleCompiler::new(db.compiler());
 
-    let name = Name::new(&module);
-    let symbols = SymbolModule::new(
-        String::from(AsRef::<str>::as_ref(name.module())),
-        &mut compiler.symbols,
-    );
+        let source = compiler
+            .get_filemap(&module)
+            .expect("Filemap does not exist");
+
+        let symbols = SymbolModule::new(
+            String::from(AsRef::<str>::as_ref(name.module())),
+            &mut compiler.symbols,
+        );
+
+        let env = db.compiler();
+        let mut compiler = vm::compiler::Compiler::new(
+            &*env,
+            env.thread().global_env(),
+            symbols,
+            &source,
+            module.clone(),
+            settings.emit_debug_info,
+        )
+        .checked_arithmetic(settings.checked_arithmetic);
+
+        let mut compiled_module = compiler.compile_expr(core_expr.value.expr())?;
+        compiled_module.function.id = module_id.clone();
+        compiled_module
+    };
+
+    #[cfg(feature = "serialization")]
+    let compiled_module = if is_global_module {
+        store_in_module_cache(db, &module, compiled_module).await
+    } else {
+        compiled_module
+    };
 
     let env = db.compiler();
-    let mut compiler = vm::compiler::Compiler::new(
-        &*env,
-        env.thread().global_env(),
-        symbols,
-        &source,
-        module.clone(),
-        settings.emit_debug_info,
-    );
-
-    let mut compiled_module = compiler.compile_expr(core_expr.value.expr())?;
-    let module_id = Symbol::from(format!("@{}", name));
-    compiled_module.function.id = module_id.clone();
     let closure = env
         .thread()
         .global_env()
@@ -522,6 +538,58 @@ async fn compiled_module(
     Ok(closure)
 }
 
+/// Stores the compiled module in the module cache (if one is used) and gives it back
+#[cfg(feature = "serialization")]
+async fn store_in_module_cache(
+    db: &mut dyn Compilation,
+    module: &str,
+    compiled_module: vm::compiler::CompiledModule,
+) -> vm::compiler::CompiledModule {
+    let cache = match crate::get_import(db.thread()).module_cache() {
+        Some(cache) => cache,
+        None => return compiled_module,
+    };
+    let (typ, metadata) = match db.typechecked_module(module.into(), None).await {
+        Ok(TypecheckValue { typ, metadata, .. }) => (typ, metadata),
+        Err(_) => return compiled_module,
+    };
+    let source = match db.module_text(module.into()) {
+        Ok(source) => source,
+        Err(_) => return compiled_module,
+    };
+
+    let module_value = Module {
+        typ,
+        metadata,
+        module: compiled_module,
+    };
+    if let Err(err) = cache.store(db, module, &source, &module_value) {
+        warn!("Unable to store `{}` in the module cache: {}", module, err);
+    }
+    module_value.module
+}
+
+#[cfg(feature = "serialization")]
+async fn load_from_module_cache(
+    db: &mut dyn Compilation,
+    name: &str,
+) -> Option<crate::module_cache::CachedModule> {
+    let cache = crate::get_import(db.thread()).module_cache()?;
+    cache.load(db, name).await
+}
+
+#[cfg(not(feature = "serialization"))]
+async fn load_from_module_cache(
+    _db: &mut dyn Compilation,
+    _name: &str,
+) -> Option<(
+    ArcType,
+    Arc<Metadata>,
+    OpaqueValue<RootedThread, GcPtr<ClosureData>>,
+)> {
+    None
+}
+
 async fn import(
     db: &mut dyn Compilation,
     modulename: String,
@@ -547,11 +615,17 @@ async fn import(
 }
 
 async fn global_inner(db: &mut dyn Compilation, name: String) -> Result<UnrootedGlobal> {
-    let TypecheckValue { metadata, typ, .. } = db
-        .typechecked_module(name.clone(), None)
-        .map_err(|(_, err)| err)
-        .await?;
-    let closure = db.compiled_module(name.clone(), None).await?;
+    let (typ, metadata, closure) = match load_from_module_cache(db, &name).await {
+        Some(cached) => cached,
+        None => {
+            let TypecheckValue { metadata, typ, .. } = db
+                .typechecked_module(name.clone(), None)
+                .map_err(|(_, err)| err)
+                .await?;
+            let closure = db.compiled_module(name.clone(), None).await?;
+            (typ, metadata, closure)
+        }
+    };
 
     let module_id = closure.function.name.clone();
 
@@ -667,8 +741,14 @@ impl PrimitiveEnv for CompilerDatabase {
 impl MetadataEnv for CompilerDatabase {
     fn get_metadata(&self, id: &SymbolRef) -> Option<Arc<Metadata>> {
         if id.is_global() {
-            self.peek_typechecked_module(id.definition_name())
+            let name = id.definition_name();
+            self.peek_typechecked_module(name)
                 .map(|v| v.metadata.clone())
+                // Modules loaded from the module cache are never typechecked
+                .or_else(|| {
+                    self.peek_global(name.trim_start_matches('@'))
+                        .map(|global| global.metadata)
+                })
         } else {
             None
         }
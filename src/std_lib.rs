@@ -1,4 +1,9 @@
+#[cfg(feature = "bignum")]
+pub mod bigint;
+#[cfg(feature = "bignum")]
+pub mod decimal;
 pub mod env;
+pub mod fixed_int;
 #[cfg(feature = "http")]
 pub mod http;
 pub mod io;
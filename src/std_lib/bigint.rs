@@ -0,0 +1,116 @@
+//! Module containing bindings to the `num-bigint` library.
+
+use crate::real_std::{cmp::Ordering, result::Result as StdResult};
+
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::vm::{self, api::RuntimeResult, thread::Thread, types::VmInt, ExternModule};
+
+/// An arbitrary-precision integer. Can be retrieved from gluon as `&BigInt` or by value with
+/// `UserdataValue<BigInt>`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Userdata, Trace, VmType)]
+#[gluon_userdata(clone)]
+#[gluon(vm_type = "std.bigint.BigInt")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct BigInt(pub num_bigint::BigInt);
+
+fn from_int(i: VmInt) -> BigInt {
+    BigInt(i.into())
+}
+
+fn to_int(x: &BigInt) -> Option<VmInt> {
+    x.0.to_i64()
+}
+
+fn parse(s: &str) -> StdResult<BigInt, ()> {
+    s.parse().map(BigInt).map_err(|_| ())
+}
+
+fn show(x: &BigInt) -> String {
+    x.0.to_string()
+}
+
+fn add(l: &BigInt, r: &BigInt) -> BigInt {
+    BigInt(&l.0 + &r.0)
+}
+
+fn sub(l: &BigInt, r: &BigInt) -> BigInt {
+    BigInt(&l.0 - &r.0)
+}
+
+fn mul(l: &BigInt, r: &BigInt) -> BigInt {
+    BigInt(&l.0 * &r.0)
+}
+
+fn div(l: &BigInt, r: &BigInt) -> RuntimeResult<BigInt, String> {
+    if r.0.is_zero() {
+        RuntimeResult::Panic("attempt to divide by zero".to_string())
+    } else {
+        RuntimeResult::Return(BigInt(&l.0 / &r.0))
+    }
+}
+
+fn rem(l: &BigInt, r: &BigInt) -> RuntimeResult<BigInt, String> {
+    if r.0.is_zero() {
+        RuntimeResult::Panic(
+            "attempt to calculate the remainder with a divisor of zero".to_string(),
+        )
+    } else {
+        RuntimeResult::Return(BigInt(&l.0 % &r.0))
+    }
+}
+
+fn negate(x: &BigInt) -> BigInt {
+    BigInt(-&x.0)
+}
+
+fn abs(x: &BigInt) -> BigInt {
+    BigInt(x.0.abs())
+}
+
+fn pow(x: &BigInt, exponent: VmInt) -> RuntimeResult<BigInt, String> {
+    match exponent.to_usize() {
+        Some(exponent) => RuntimeResult::Return(BigInt(num_traits::pow(x.0.clone(), exponent))),
+        None => RuntimeResult::Panic(format!("Negative exponent {}", exponent)),
+    }
+}
+
+fn eq(l: &BigInt, r: &BigInt) -> bool {
+    l == r
+}
+
+fn compare(l: &BigInt, r: &BigInt) -> Ordering {
+    l.cmp(r)
+}
+
+mod std {
+    pub mod bigint {
+        pub use crate::std_lib::bigint as prim;
+    }
+}
+
+pub fn load(vm: &Thread) -> vm::Result<ExternModule> {
+    vm.register_type::<BigInt>("std.bigint.BigInt", &[])?;
+
+    ExternModule::new(
+        vm,
+        record! {
+            type BigInt => BigInt,
+            from_int => primitive!(1, std::bigint::prim::from_int),
+            to_int => primitive!(1, std::bigint::prim::to_int),
+            parse => primitive!(1, std::bigint::prim::parse),
+            show => primitive!(1, std::bigint::prim::show),
+            add => primitive!(2, std::bigint::prim::add),
+            sub => primitive!(2, std::bigint::prim::sub),
+            mul => primitive!(2, std::bigint::prim::mul),
+            div => primitive!(2, std::bigint::prim::div),
+            rem => primitive!(2, std::bigint::prim::rem),
+            negate => primitive!(1, std::bigint::prim::negate),
+            abs => primitive!(1, std::bigint::prim::abs),
+            pow => primitive!(2, std::bigint::prim::pow),
+            eq => primitive!(2, std::bigint::prim::eq),
+            compare => primitive!(2, std::bigint::prim::compare)
+        },
+    )
+}
@@ -0,0 +1,123 @@
+//! Module containing bindings to the `rust_decimal` library.
+
+use crate::real_std::{cmp::Ordering, convert::TryFrom, result::Result as StdResult};
+
+use crate::vm::{self, api::RuntimeResult, thread::Thread, types::VmInt, ExternModule};
+
+/// A fixed-precision decimal number with up to 28 significant digits. Can be retrieved from gluon
+/// as `&Decimal` or by value with `UserdataValue<Decimal>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Userdata, Trace, VmType)]
+#[gluon_userdata(clone)]
+#[gluon(vm_type = "std.decimal.Decimal")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct Decimal(pub rust_decimal::Decimal);
+
+fn checked(value: Option<rust_decimal::Decimal>, op: &str) -> RuntimeResult<Decimal, String> {
+    match value {
+        Some(value) => RuntimeResult::Return(Decimal(value)),
+        None => RuntimeResult::Panic(format!("attempt to {} with overflow", op)),
+    }
+}
+
+fn from_int(i: VmInt) -> Decimal {
+    Decimal(i.into())
+}
+
+fn parse(s: &str) -> StdResult<Decimal, ()> {
+    s.parse().map(Decimal).map_err(|_| ())
+}
+
+fn show(x: &Decimal) -> String {
+    x.0.to_string()
+}
+
+fn add(l: &Decimal, r: &Decimal) -> RuntimeResult<Decimal, String> {
+    checked(l.0.checked_add(r.0), "add")
+}
+
+fn sub(l: &Decimal, r: &Decimal) -> RuntimeResult<Decimal, String> {
+    checked(l.0.checked_sub(r.0), "subtract")
+}
+
+fn mul(l: &Decimal, r: &Decimal) -> RuntimeResult<Decimal, String> {
+    checked(l.0.checked_mul(r.0), "multiply")
+}
+
+fn div(l: &Decimal, r: &Decimal) -> RuntimeResult<Decimal, String> {
+    if r.0.is_zero() {
+        return RuntimeResult::Panic("attempt to divide by zero".to_string());
+    }
+    checked(l.0.checked_div(r.0), "divide")
+}
+
+fn rem(l: &Decimal, r: &Decimal) -> RuntimeResult<Decimal, String> {
+    if r.0.is_zero() {
+        return RuntimeResult::Panic(
+            "attempt to calculate the remainder with a divisor of zero".to_string(),
+        );
+    }
+    checked(l.0.checked_rem(r.0), "calculate the remainder")
+}
+
+fn negate(x: &Decimal) -> Decimal {
+    Decimal(-x.0)
+}
+
+fn abs(x: &Decimal) -> Decimal {
+    Decimal(x.0.abs())
+}
+
+/// Rounds `x` to `decimal_places` digits after the decimal point using banker's rounding
+fn round(x: &Decimal, decimal_places: VmInt) -> RuntimeResult<Decimal, String> {
+    match u32::try_from(decimal_places) {
+        Ok(decimal_places) => RuntimeResult::Return(Decimal(x.0.round_dp(decimal_places))),
+        Err(_) => RuntimeResult::Panic(format!(
+            "Invalid number of decimal places {}",
+            decimal_places
+        )),
+    }
+}
+
+fn scale(x: &Decimal) -> VmInt {
+    VmInt::from(x.0.scale())
+}
+
+fn eq(l: &Decimal, r: &Decimal) -> bool {
+    l == r
+}
+
+fn compare(l: &Decimal, r: &Decimal) -> Ordering {
+    l.cmp(r)
+}
+
+mod std {
+    pub mod decimal {
+        pub use crate::std_lib::decimal as prim;
+    }
+}
+
+pub fn load(vm: &Thread) -> vm::Result<ExternModule> {
+    vm.register_type::<Decimal>("std.decimal.Decimal", &[])?;
+
+    ExternModule::new(
+        vm,
+        record! {
+            type Decimal => Decimal,
+            from_int => primitive!(1, std::decimal::prim::from_int),
+            parse => primitive!(1, std::decimal::prim::parse),
+            show => primitive!(1, std::decimal::prim::show),
+            add => primitive!(2, std::decimal::prim::add),
+            sub => primitive!(2, std::decimal::prim::sub),
+            mul => primitive!(2, std::decimal::prim::mul),
+            div => primitive!(2, std::decimal::prim::div),
+            rem => primitive!(2, std::decimal::prim::rem),
+            negate => primitive!(1, std::decimal::prim::negate),
+            abs => primitive!(1, std::decimal::prim::abs),
+            round => primitive!(2, std::decimal::prim::round),
+            scale => primitive!(1, std::decimal::prim::scale),
+            eq => primitive!(2, std::decimal::prim::eq),
+            compare => primitive!(2, std::decimal::prim::compare)
+        },
+    )
+}
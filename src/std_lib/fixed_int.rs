@@ -0,0 +1,138 @@
+//! Module containing the fixed-width integer types `Int32` and `UInt64`.
+//!
+//! Unlike `Int` the arithmetic on these types is checked and panics on overflow.
+
+use crate::real_std::{cmp::Ordering, convert::TryFrom, result::Result as StdResult};
+
+use crate::vm::{self, api::RuntimeResult, thread::Thread, types::VmInt, ExternModule};
+
+macro_rules! fixed_int {
+    ($module: ident, $name: ident, $ty: ty) => {
+        fn checked(value: Option<$ty>, op: &str) -> RuntimeResult<$name, String> {
+            match value {
+                Some(value) => RuntimeResult::Return($name(value)),
+                None => RuntimeResult::Panic(format!("attempt to {} with overflow", op)),
+            }
+        }
+
+        fn from_int(i: VmInt) -> Option<$name> {
+            <$ty>::try_from(i).ok().map($name)
+        }
+
+        fn to_int(x: &$name) -> Option<VmInt> {
+            VmInt::try_from(x.0).ok()
+        }
+
+        fn parse(s: &str) -> StdResult<$name, ()> {
+            s.parse().map($name).map_err(|_| ())
+        }
+
+        fn show(x: &$name) -> String {
+            x.0.to_string()
+        }
+
+        fn add(l: &$name, r: &$name) -> RuntimeResult<$name, String> {
+            checked(l.0.checked_add(r.0), "add")
+        }
+
+        fn sub(l: &$name, r: &$name) -> RuntimeResult<$name, String> {
+            checked(l.0.checked_sub(r.0), "subtract")
+        }
+
+        fn mul(l: &$name, r: &$name) -> RuntimeResult<$name, String> {
+            checked(l.0.checked_mul(r.0), "multiply")
+        }
+
+        fn div(l: &$name, r: &$name) -> RuntimeResult<$name, String> {
+            if r.0 == 0 {
+                return RuntimeResult::Panic("attempt to divide by zero".to_string());
+            }
+            checked(l.0.checked_div(r.0), "divide")
+        }
+
+        fn rem(l: &$name, r: &$name) -> RuntimeResult<$name, String> {
+            if r.0 == 0 {
+                return RuntimeResult::Panic(
+                    "attempt to calculate the remainder with a divisor of zero".to_string(),
+                );
+            }
+            checked(l.0.checked_rem(r.0), "calculate the remainder")
+        }
+
+        fn negate(x: &$name) -> RuntimeResult<$name, String> {
+            checked(x.0.checked_neg(), "negate")
+        }
+
+        fn eq(l: &$name, r: &$name) -> bool {
+            l == r
+        }
+
+        fn compare(l: &$name, r: &$name) -> Ordering {
+            l.cmp(r)
+        }
+
+        mod std {
+            pub mod $module {
+                pub use crate::std_lib::fixed_int::$module as prim;
+            }
+        }
+
+        pub fn load(vm: &Thread) -> vm::Result<ExternModule> {
+            vm.register_type::<$name>(
+                concat!("std.", stringify!($module), ".", stringify!($name)),
+                &[],
+            )?;
+
+            ExternModule::new(
+                vm,
+                record! {
+                    type $name => $name,
+                    min_value => $name(<$ty>::min_value()),
+                    max_value => $name(<$ty>::max_value()),
+                    from_int => primitive!(1, std::$module::prim::from_int),
+                    to_int => primitive!(1, std::$module::prim::to_int),
+                    parse => primitive!(1, std::$module::prim::parse),
+                    show => primitive!(1, std::$module::prim::show),
+                    add => primitive!(2, std::$module::prim::add),
+                    sub => primitive!(2, std::$module::prim::sub),
+                    mul => primitive!(2, std::$module::prim::mul),
+                    div => primitive!(2, std::$module::prim::div),
+                    rem => primitive!(2, std::$module::prim::rem),
+                    negate => primitive!(1, std::$module::prim::negate),
+                    eq => primitive!(2, std::$module::prim::eq),
+                    compare => primitive!(2, std::$module::prim::compare)
+                },
+            )
+        }
+    };
+}
+
+/// A signed 32-bit integer. Can be retrieved from gluon as `&Int32` or by value with
+/// `UserdataValue<Int32>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Userdata, Trace, VmType)]
+#[gluon_userdata(clone)]
+#[gluon(vm_type = "std.int32.Int32")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct Int32(pub i32);
+
+/// An unsigned 64-bit integer. Can be retrieved from gluon as `&UInt64` or by value with
+/// `UserdataValue<UInt64>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Userdata, Trace, VmType)]
+#[gluon_userdata(clone)]
+#[gluon(vm_type = "std.uint64.UInt64")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct UInt64(pub u64);
+
+pub mod int32 {
+    use super::*;
+
+    fixed_int!(int32, Int32, i32);
+}
+
+pub mod uint64 {
+    use super::*;
+
+    fixed_int!(uint64, UInt64, u64);
+}
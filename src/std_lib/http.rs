@@ -3,6 +3,7 @@ use crate::real_std::{
     path::PathBuf,
     pin::Pin,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use {
@@ -30,6 +31,7 @@ use crate::vm::{
         IO,
     },
     thread::{ActiveThread, RootedThread, Thread},
+    types::VmInt,
     ExternModule, Variants,
 };
 
@@ -118,6 +120,17 @@ impl fmt::Debug for Body {
     }
 }
 
+impl From<hyper::Body> for Body {
+    fn from(body: hyper::Body) -> Self {
+        Body(Arc::new(Mutex::new(Box::pin(
+            body.map_err(|err| vm::Error::Message(format!("{}", err)))
+                // `PushAsRef` makes the `body` parameter act as a `&[u8]` which means it is
+                // marshalled to `Array Byte` in gluon
+                .map_ok(PushAsRef::<_, [u8]>::new),
+        ))))
+    }
+}
+
 // Since `Body` implements `Userdata` gluon will automatically marshal the gluon representation
 // into `&Body` argument
 fn read_chunk(body: &Body) -> impl Future<Output = IO<Option<PushAsRef<Bytes, [u8]>>>> {
@@ -153,11 +166,17 @@ impl fmt::Debug for ResponseBody {
 }
 
 fn write_response(response: &ResponseBody, bytes: &[u8]) -> impl Future<Output = IO<()>> {
+    write_body(response.0.clone(), bytes)
+}
+
+fn write_body(
+    response: Arc<Mutex<Option<hyper::body::Sender>>>,
+    bytes: &[u8],
+) -> impl Future<Output = IO<()>> {
     use futures::future::poll_fn;
 
     // Turn `bytes´ into a `Bytes` which can be sent to the http body
     let mut unsent_chunk = Some(bytes.to_owned().into());
-    let response = response.0.clone();
     poll_fn(move |cx| {
         info!("Starting response send");
         let mut sender = response.lock().unwrap();
@@ -266,13 +285,7 @@ async fn listen_(
                 method => parts.method.as_str().to_owned(),
                 uri => Uri(parts.uri),
                 // Since `Body` implements `Userdata` it can be directly pushed to gluon
-                body => Body(Arc::new(Mutex::new(Box::pin(
-                    body
-                        .map_err(|err| vm::Error::Message(format!("{}", err)))
-                        // `PushAsRef` makes the `body` parameter act as a `&[u8]` which means it is
-                        // marshalled to `Array Byte` in gluon
-                        .map_ok(PushAsRef::<_, [u8]>::new)
-                ))))
+                body => Body::from(body)
             };
             let (response_sender, response_body) = hyper::Body::channel();
             let response_sender = Arc::new(Mutex::new(Some(response_sender)));
@@ -396,12 +409,186 @@ async fn listen_(
         .await
 }
 
+// The client side. Requests are described by `ClientRequest` which mirrors the gluon record of the
+// same name in `std.http.types` and the responses reuse `Body` so that they can be streamed with
+// `read_chunk`.
+#[derive(Getable, VmType)]
+#[gluon(crate_name = "::vm")]
+struct ClientRequest {
+    method: String,
+    uri: String,
+    headers: Headers,
+    /// Timeout in milliseconds for receiving the response headers, reading the body is not covered
+    timeout: Option<VmInt>,
+}
+
+type ClientResponse = record_type! {
+    status => u16,
+    headers => Headers,
+    body => Body
+};
+
+type StartedRequest = record_type! {
+    body => RequestBody,
+    response => PendingResponse
+};
+
+// A http request body that is being written
+#[derive(Userdata, Trace, VmType)]
+#[gluon(vm_type = "std.http.types.RequestBody")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct RequestBody(Arc<Mutex<Option<hyper::body::Sender>>>);
+
+impl fmt::Debug for RequestBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RequestBody")
+    }
+}
+
+type ResponseResult = Result<hyper::Response<hyper::Body>, String>;
+
+// The response of a request whose body is still being written
+#[derive(Userdata, Trace, VmType)]
+#[gluon(vm_type = "std.http.types.PendingResponse")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct PendingResponse(Arc<Mutex<Option<tokio::task::JoinHandle<ResponseResult>>>>);
+
+impl fmt::Debug for PendingResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PendingResponse")
+    }
+}
+
+type HttpsClient = hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
+
+// The client used for all requests sent from a vm so that connections and the TLS configuration
+// are reused between requests
+#[derive(Userdata, Trace, VmType)]
+#[gluon(vm_type = "std.http.types.Client")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+pub struct Client(HttpsClient);
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Client")
+    }
+}
+
+// hyper needs a tokio runtime to open connections and to drive the timeouts so requests made
+// outside of one fail with an exception instead of panicking
+fn current_runtime() -> Result<tokio::runtime::Handle, String> {
+    tokio::runtime::Handle::try_current()
+        .map_err(|_| "HTTP requests can only be sent from inside a tokio runtime".to_string())
+}
+
+fn build_request(
+    request: ClientRequest,
+    body: hyper::Body,
+) -> Result<(hyper::Request<hyper::Body>, Option<Duration>), String> {
+    let mut builder = hyper::Request::builder()
+        .method(&request.method[..])
+        .uri(&request.uri[..]);
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(request.headers.0);
+    }
+    let http_request = builder.body(body).map_err(|err| err.to_string())?;
+    let timeout = request
+        .timeout
+        .map(|millis| Duration::from_millis(millis.max(0) as u64));
+    Ok((http_request, timeout))
+}
+
+async fn send_request(
+    client: HttpsClient,
+    request: hyper::Request<hyper::Body>,
+    timeout: Option<Duration>,
+) -> ResponseResult {
+    let response = client.request(request);
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, response)
+            .await
+            .map_err(|_| format!("Request timed out after {}ms", timeout.as_millis()))?,
+        None => response.await,
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn client_response(response: hyper::Response<hyper::Body>) -> ClientResponse {
+    let (parts, body) = response.into_parts();
+    record_no_decl! {
+        status => parts.status.as_u16(),
+        headers => Headers(parts.headers),
+        body => Body::from(body)
+    }
+}
+
+fn send(
+    client: &Client,
+    request: ClientRequest,
+    body: &[u8],
+) -> impl Future<Output = IO<ClientResponse>> {
+    let client = client.0.clone();
+    let request = build_request(request, body.to_owned().into());
+    async move {
+        current_runtime()?;
+        let (request, timeout) = request?;
+        send_request(client, request, timeout)
+            .await
+            .map(client_response)
+    }
+    .map(IO::from)
+}
+
+fn start_request(client: &Client, request: ClientRequest) -> IO<StartedRequest> {
+    let runtime = match current_runtime() {
+        Ok(runtime) => runtime,
+        Err(err) => return IO::Exception(err),
+    };
+    let (sender, body) = hyper::Body::channel();
+    let (request, timeout) = match build_request(request, body) {
+        Ok(x) => x,
+        Err(err) => return IO::Exception(err),
+    };
+    // The request must be sent while the body is written so it is driven by a separate task
+    let response = runtime.spawn(send_request(client.0.clone(), request, timeout));
+    IO::Value(record_no_decl! {
+        body => RequestBody(Arc::new(Mutex::new(Some(sender)))),
+        response => PendingResponse(Arc::new(Mutex::new(Some(response))))
+    })
+}
+
+fn write_request(body: &RequestBody, bytes: &[u8]) -> impl Future<Output = IO<()>> {
+    write_body(body.0.clone(), bytes)
+}
+
+fn finish_request(body: &RequestBody) -> IO<()> {
+    // Dropping the sender signals the end of the body
+    *body.0.lock().unwrap() = None;
+    IO::Value(())
+}
+
+fn wait_response(response: &PendingResponse) -> impl Future<Output = IO<ClientResponse>> {
+    let handle = response.0.lock().unwrap().take();
+    async move {
+        let handle = handle.ok_or_else(|| "The response has already been received".to_string())?;
+        let response = handle.await.map_err(|err| err.to_string())??;
+        Ok::<_, String>(client_response(response))
+    }
+    .map(IO::from)
+}
+
 // To let the `http_types` module refer to `Body` and `ResponseBody` we register these types in a
 // separate function which is called before loading `http_types`
 pub fn load_types(vm: &Thread) -> vm::Result<ExternModule> {
     vm.register_type::<Body>("std.http.types.Body", &[])?;
     vm.register_type::<ResponseBody>("std.http.types.ResponseBody", &[])?;
     vm.register_type::<Uri>("std.http.types.Uri", &[])?;
+    vm.register_type::<RequestBody>("std.http.types.RequestBody", &[])?;
+    vm.register_type::<PendingResponse>("std.http.types.PendingResponse", &[])?;
+    vm.register_type::<Client>("std.http.types.Client", &[])?;
 
     ExternModule::new(
         vm,
@@ -410,12 +597,16 @@ pub fn load_types(vm: &Thread) -> vm::Result<ExternModule> {
             type std::http::types::Body => Body,
             type std::http::types::ResponseBody => ResponseBody,
             type std::http::types::Uri => Uri,
+            type std::http::types::RequestBody => RequestBody,
+            type std::http::types::PendingResponse => PendingResponse,
+            type std::http::types::Client => Client,
             type std::http::Method => String,
             type std::http::StatusCode => u16,
             type std::http::Request => Request,
             type std::http::Response => Response,
             type std::http::Headers => Headers,
-            type std::http::HttpState => HttpState
+            type std::http::HttpState => HttpState,
+            type std::http::ClientResponse => ClientResponse
         },
     )
 }
@@ -443,6 +634,12 @@ pub fn load(vm: &Thread) -> vm::Result<ExternModule> {
             listen => primitive!(2, async fn std::http::prim::listen),
             read_chunk => primitive!(1, async fn std::http::prim::read_chunk),
             write_response => primitive!(2, async fn std::http::prim::write_response),
+            client => Client(hyper::Client::builder().build(hyper_tls::HttpsConnector::new())),
+            send => primitive!(3, async fn std::http::prim::send),
+            start_request => primitive!(2, std::http::prim::start_request),
+            write_request => primitive!(2, async fn std::http::prim::write_request),
+            finish_request => primitive!(1, std::http::prim::finish_request),
+            wait_response => primitive!(1, async fn std::http::prim::wait_response),
             port => primitive!(1, "std.http.prim.uri.port", |u: &Uri| (u.0).port().map(|p| p.as_u16())),
             uri => uri_binds!(path host query to_string)
         },
@@ -16,6 +16,7 @@ use crate::vm::{
         Getable, OpaqueValue, OwnedFunction, RuntimeResult, TypedBytecode, WithVM, IO,
     },
     internal::ValuePrinter,
+    sandbox::FsAccess,
     stack::{self, StackFrame},
     thread::{RootedThread, Thread, ThreadInternal},
     types::*,
@@ -72,7 +73,7 @@ impl fmt::Debug for GluonFile {
     }
 }
 
-#[derive(Getable, VmType, Clone, Copy)]
+#[derive(Getable, VmType, Clone, Copy, PartialEq)]
 #[gluon(crate_name = "::vm")]
 enum OpenOptions {
     Read,
@@ -83,8 +84,12 @@ enum OpenOptions {
     CreateNew,
 }
 
-fn open_file_with(path: &str, opts: Vec<OpenOptions>) -> IO<GluonFile> {
+fn open_file_with(
+    WithVM { vm, value: path }: WithVM<&str>,
+    opts: Vec<OpenOptions>,
+) -> IO<GluonFile> {
     let mut open_with = fs::OpenOptions::new();
+    let mut access = FsAccess::Read;
 
     for opt in opts {
         match opt {
@@ -95,25 +100,37 @@ fn open_file_with(path: &str, opts: Vec<OpenOptions>) -> IO<GluonFile> {
             OpenOptions::Create => open_with.create(true),
             OpenOptions::CreateNew => open_with.create_new(true),
         };
+        if opt != OpenOptions::Read {
+            access = FsAccess::Write;
+        }
     }
 
-    open_with
-        .open(path)
+    vm.global_env()
+        .sandbox()
+        .check_path(path.as_ref(), access)
+        .and_then(|path| open_with.open(path))
         .map(|file| GluonFile(Mutex::new(Some(file))))
         .into()
 }
 
-fn read_file_to_array(s: &str) -> IO<Vec<u8>> {
+fn open_sandboxed(vm: &Thread, path: &str) -> io::Result<File> {
+    vm.global_env()
+        .sandbox()
+        .check_path(path.as_ref(), FsAccess::Read)
+        .and_then(File::open)
+}
+
+fn read_file_to_array(WithVM { vm, value: s }: WithVM<&str>) -> IO<Vec<u8>> {
     let mut buffer = Vec::new();
-    match File::open(s).and_then(|mut file| file.read_to_end(&mut buffer)) {
+    match open_sandboxed(vm, s).and_then(|mut file| file.read_to_end(&mut buffer)) {
         Ok(_) => IO::Value(buffer),
         Err(err) => IO::Exception(err.to_string()),
     }
 }
 
-fn read_file_to_string(s: &str) -> IO<String> {
+fn read_file_to_string(WithVM { vm, value: s }: WithVM<&str>) -> IO<String> {
     let mut buffer = String::new();
-    match File::open(s).and_then(|mut file| file.read_to_string(&mut buffer)) {
+    match open_sandboxed(vm, s).and_then(|mut file| file.read_to_string(&mut buffer)) {
         Ok(_) => IO::Value(buffer),
         Err(err) => {
             use crate::real_std::fmt::Write;
@@ -1,4 +1,17 @@
-use crate::real_std::process::Command;
+use crate::real_std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    process::{self, ChildStderr, ChildStdin, ChildStdout, Command},
+    sync::{Arc, Mutex, TryLockError},
+    thread,
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+    prelude::*,
+};
+
 use crate::vm::{api::IO, thread::Thread, ExternModule, Result};
 
 #[derive(Getable, VmType)]
@@ -10,24 +23,350 @@ struct CreateProcess<'a> {
     current_dir: Option<&'a str>,
 }
 
+impl CreateProcess<'_> {
+    fn command(&self) -> Command {
+        let mut command = Command::new(self.command);
+        for arg in &self.args {
+            command.arg(arg);
+        }
+        match self.env {
+            Some(ref env) => {
+                command.env_clear();
+                for (key, value) in env {
+                    command.env(key, value);
+                }
+            }
+            None => (),
+        }
+        if let Some(current_dir) = self.current_dir {
+            command.current_dir(current_dir);
+        }
+        command
+    }
+}
+
+/// What to connect one of the standard streams of a child process to
+#[derive(Getable, VmType, Clone, Copy)]
+#[gluon(crate_name = "::vm")]
+enum Stdio {
+    Inherit,
+    Piped,
+    Null,
+}
+
+impl From<Stdio> for process::Stdio {
+    fn from(stdio: Stdio) -> process::Stdio {
+        match stdio {
+            Stdio::Inherit => process::Stdio::inherit(),
+            Stdio::Piped => process::Stdio::piped(),
+            Stdio::Null => process::Stdio::null(),
+        }
+    }
+}
+
+#[derive(Getable, VmType)]
+#[gluon(crate_name = "::vm")]
+struct StdioOptions {
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+}
+
+#[derive(Pushable, VmType)]
+#[gluon(vm_type = "std.process.types.ExitStatus")]
+#[gluon(crate_name = "::vm")]
+struct ExitStatus {
+    code: Option<i32>,
+    signal: Option<i32>,
+    success: bool,
+}
+
+impl From<process::ExitStatus> for ExitStatus {
+    fn from(status: process::ExitStatus) -> ExitStatus {
+        #[cfg(unix)]
+        let signal = {
+            use crate::real_std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        ExitStatus {
+            code: status.code(),
+            signal,
+            success: status.success(),
+        }
+    }
+}
+
+#[derive(Pushable, VmType)]
+#[gluon(vm_type = "std.process.types.Output")]
+#[gluon(crate_name = "::vm")]
+struct Output {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/// A handle to a running (or exited) child process.
+///
+/// The piped streams are stored separately from the process itself so that reading the output of
+/// the process does not prevent it from being killed or waited on. `wait` holds the lock on the
+/// process until it exits, so while a wait is in progress `kill` signals the process directly
+/// instead.
+#[derive(Userdata, Trace, VmType)]
+#[gluon(vm_type = "std.process.Child")]
+#[gluon(crate_name = "::vm")]
+#[gluon_trace(skip)]
+struct GluonChild {
+    id: u32,
+    /// The handle of the process, used to kill it while `child` is locked by `wait`
+    #[cfg(windows)]
+    handle: usize,
+    child: Arc<Mutex<process::Child>>,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    stdout: Arc<Mutex<Option<BufReader<ChildStdout>>>>,
+    stderr: Arc<Mutex<Option<BufReader<ChildStderr>>>>,
+}
+
+impl fmt::Debug for GluonChild {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Child({})", self.id)
+    }
+}
+
+/// Runs the blocking operation `f` on its own OS thread so that it does not block the executor
+/// of the vm, nor any task on the executor which the operation is waiting for (such as a task
+/// draining the stdout of a process that is being waited on).
+fn blocking<T, F>(f: F) -> impl Future<Output = IO<T>> + Send
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = sender.send(f());
+    });
+    receiver.map(|result| match result {
+        Ok(result) => IO::from(result),
+        Err(_) => IO::Exception("The process operation was cancelled".to_string()),
+    })
+}
+
+fn not_piped(stream: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        format!(
+            "The {} of the process is not piped or has been closed",
+            stream
+        ),
+    )
+}
+
 fn execute(create: CreateProcess) -> IO<Option<i32>> {
-    let mut command = Command::new(create.command);
-    for arg in &create.args {
-        command.arg(arg);
-    }
-    match create.env {
-        Some(env) => {
-            command.env_clear();
-            for (key, value) in &env {
-                command.env(key, value);
+    IO::from(create.command().status().map(|status| status.code()))
+}
+
+fn spawn_with(create: CreateProcess, stdio: StdioOptions) -> IO<GluonChild> {
+    let mut command = create.command();
+    command
+        .stdin(process::Stdio::from(stdio.stdin))
+        .stdout(process::Stdio::from(stdio.stdout))
+        .stderr(process::Stdio::from(stdio.stderr));
+
+    command
+        .spawn()
+        .map(|mut child| GluonChild {
+            id: child.id(),
+            #[cfg(windows)]
+            handle: {
+                use crate::real_std::os::windows::io::AsRawHandle;
+                child.as_raw_handle() as usize
+            },
+            stdin: Arc::new(Mutex::new(child.stdin.take())),
+            stdout: Arc::new(Mutex::new(child.stdout.take().map(BufReader::new))),
+            stderr: Arc::new(Mutex::new(child.stderr.take().map(BufReader::new))),
+            child: Arc::new(Mutex::new(child)),
+        })
+        .into()
+}
+
+fn output(create: CreateProcess) -> impl Future<Output = IO<Output>> {
+    let child = create
+        .command()
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(err) => return Either::Left(future::ready(IO::Exception(err.to_string()))),
+    };
+    Either::Right(blocking(move || {
+        child.wait_with_output().map(|output| Output {
+            status: output.status.into(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }))
+}
+
+fn id(child: &GluonChild) -> u32 {
+    child.id
+}
+
+fn write_stdin(child: &GluonChild, bytes: &[u8]) -> impl Future<Output = IO<()>> {
+    let stdin = child.stdin.clone();
+    let bytes = bytes.to_owned();
+    blocking(move || {
+        let mut stdin = stdin.lock().unwrap();
+        let stdin = stdin.as_mut().ok_or_else(|| not_piped("stdin"))?;
+        stdin.write_all(&bytes)?;
+        stdin.flush()
+    })
+}
+
+fn close_stdin(child: &GluonChild) -> IO<()> {
+    child.stdin.lock().unwrap().take();
+    IO::Value(())
+}
+
+fn read_line<R>(
+    stream: &Arc<Mutex<Option<R>>>,
+    name: &'static str,
+) -> impl Future<Output = IO<Option<String>>>
+where
+    R: BufRead + Send + 'static,
+{
+    let stream = stream.clone();
+    blocking(move || {
+        let mut stream = stream.lock().unwrap();
+        let stream = stream.as_mut().ok_or_else(|| not_piped(name))?;
+        let mut line = String::new();
+        if stream.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
             }
         }
-        None => (),
+        Ok(Some(line))
+    })
+}
+
+fn read_to_end<R>(
+    stream: &Arc<Mutex<Option<R>>>,
+    name: &'static str,
+) -> impl Future<Output = IO<Vec<u8>>>
+where
+    R: Read + Send + 'static,
+{
+    let stream = stream.clone();
+    blocking(move || {
+        let mut stream = stream.lock().unwrap();
+        let stream = stream.as_mut().ok_or_else(|| not_piped(name))?;
+        let mut buffer = Vec::new();
+        stream.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+fn read_to_string<R>(
+    stream: &Arc<Mutex<Option<R>>>,
+    name: &'static str,
+) -> impl Future<Output = IO<String>>
+where
+    R: Read + Send + 'static,
+{
+    let stream = stream.clone();
+    blocking(move || {
+        let mut stream = stream.lock().unwrap();
+        let stream = stream.as_mut().ok_or_else(|| not_piped(name))?;
+        let mut buffer = String::new();
+        stream.read_to_string(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+fn read_stdout_line(child: &GluonChild) -> impl Future<Output = IO<Option<String>>> {
+    read_line(&child.stdout, "stdout")
+}
+
+fn read_stderr_line(child: &GluonChild) -> impl Future<Output = IO<Option<String>>> {
+    read_line(&child.stderr, "stderr")
+}
+
+fn read_stdout_to_end(child: &GluonChild) -> impl Future<Output = IO<Vec<u8>>> {
+    read_to_end(&child.stdout, "stdout")
+}
+
+fn read_stderr_to_end(child: &GluonChild) -> impl Future<Output = IO<Vec<u8>>> {
+    read_to_end(&child.stderr, "stderr")
+}
+
+fn read_stdout_to_string(child: &GluonChild) -> impl Future<Output = IO<String>> {
+    read_to_string(&child.stdout, "stdout")
+}
+
+fn read_stderr_to_string(child: &GluonChild) -> impl Future<Output = IO<String>> {
+    read_to_string(&child.stderr, "stderr")
+}
+
+fn wait(child: &GluonChild) -> impl Future<Output = IO<ExitStatus>> {
+    // Close stdin so that processes which read until the end of their input can exit
+    child.stdin.lock().unwrap().take();
+
+    let child = child.child.clone();
+    blocking(move || child.lock().unwrap().wait().map(ExitStatus::from))
+}
+
+fn try_wait(child: &GluonChild) -> IO<Option<ExitStatus>> {
+    match child.child.try_lock() {
+        Ok(mut child) => IO::from(child.try_wait().map(|status| status.map(ExitStatus::from))),
+        // Another task is blocked in `wait` so the process has not been reaped yet
+        Err(TryLockError::WouldBlock) => IO::Value(None),
+        Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+    }
+}
+
+fn kill(child: &GluonChild) -> IO<()> {
+    match child.child.try_lock() {
+        Ok(mut process) => IO::from(process.kill()),
+        // The process can't have been reaped (freeing its pid or handle) while `wait` holds the
+        // lock so it is safe to signal it directly
+        Err(TryLockError::WouldBlock) => IO::from(kill_waited_on(child)),
+        Err(TryLockError::Poisoned(err)) => panic!("{}", err),
     }
-    if let Some(current_dir) = create.current_dir {
-        command.current_dir(current_dir);
+}
+
+#[cfg(unix)]
+fn kill_waited_on(child: &GluonChild) -> io::Result<()> {
+    if unsafe { libc::kill(child.id as libc::pid_t, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
     }
-    IO::from(command.status().map(|status| status.code()))
+}
+
+#[cfg(windows)]
+fn kill_waited_on(child: &GluonChild) -> io::Result<()> {
+    use winapi::um::processthreadsapi::TerminateProcess;
+
+    if unsafe { TerminateProcess(child.handle as _, 1) } != 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_waited_on(_child: &GluonChild) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "The process can't be killed while it is being waited on",
+    ))
 }
 
 mod std {
@@ -37,10 +376,28 @@ mod std {
 }
 
 pub fn load(vm: &Thread) -> Result<ExternModule> {
+    vm.register_type::<GluonChild>("std.process.Child", &[])?;
+
     ExternModule::new(
         vm,
         record! {
-            execute => primitive!(1, std::process::prim::execute)
+            type Child => GluonChild,
+            type Stdio => Stdio,
+            execute => primitive!(1, std::process::prim::execute),
+            spawn_with => primitive!(2, std::process::prim::spawn_with),
+            output => primitive!(1, async fn std::process::prim::output),
+            id => primitive!(1, std::process::prim::id),
+            write_stdin => primitive!(2, async fn std::process::prim::write_stdin),
+            close_stdin => primitive!(1, std::process::prim::close_stdin),
+            read_stdout_line => primitive!(1, async fn std::process::prim::read_stdout_line),
+            read_stderr_line => primitive!(1, async fn std::process::prim::read_stderr_line),
+            read_stdout_to_end => primitive!(1, async fn std::process::prim::read_stdout_to_end),
+            read_stderr_to_end => primitive!(1, async fn std::process::prim::read_stderr_to_end),
+            read_stdout_to_string => primitive!(1, async fn std::process::prim::read_stdout_to_string),
+            read_stderr_to_string => primitive!(1, async fn std::process::prim::read_stderr_to_string),
+            wait => primitive!(1, async fn std::process::prim::wait),
+            try_wait => primitive!(1, std::process::prim::try_wait),
+            kill => primitive!(1, std::process::prim::kill),
         },
     )
 }
@@ -0,0 +1,647 @@
+//! Support for macros written in gluon.
+//!
+//! A gluon macro is a function of type `std.macro.Macro` exported from a module. The
+//! `import_macros!` macro loads such a module and brings every macro it exports into scope for the
+//! rest of the module being compiled. When one of the macros is invoked its arguments are quoted
+//! into `std.macro.Expr` values, the function is run at compile time and the `Expr` it returns is
+//! spliced back into the AST in place of the macro call.
+use std::{mem, sync::Arc};
+
+use gluon_codegen::Trace;
+
+use ordered_float::NotNan;
+
+use crate::{
+    base::{
+        ast::{
+            self, expr_to_path, Argument, ExprField, PatternField, SpannedExpr, SpannedPattern,
+            TypedIdent, ValueBinding, ValueBindings,
+        },
+        pos::{self, BytePos, Span},
+        symbol::{Symbol, Symbols},
+        types::{ArcType, Type, TypeExt},
+    },
+    check::check_signature,
+    query::{Compilation, CompilerDatabase},
+    vm::{
+        api::{ActiveThread, Getable, OwnedFunction, Pushable, VmType},
+        gc::Trace,
+        macros::{self, Macro, MacroExpander, MacroFuture},
+        thread::{RootedThread, RootedValue, Thread},
+        Variants,
+    },
+};
+
+type MacroFunction = fn(Vec<Expr>) -> Result<Expr, String>;
+
+/// Boxes a recursive field of the types mirroring `std.macro` while marshalling it like `T`
+struct Node<T>(Box<T>);
+
+impl<T> Node<T> {
+    fn new(value: T) -> Self {
+        Node(Box::new(value))
+    }
+}
+
+impl<T> VmType for Node<T>
+where
+    T: VmType,
+{
+    type Type = T::Type;
+
+    fn make_type(vm: &Thread) -> ArcType {
+        T::make_type(vm)
+    }
+}
+
+impl<'vm, T> Pushable<'vm> for Node<T>
+where
+    T: Pushable<'vm>,
+{
+    fn push(self, context: &mut ActiveThread<'vm>) -> crate::vm::Result<()> {
+        (*self.0).push(context)
+    }
+}
+
+impl<'vm, 'value, T> Getable<'vm, 'value> for Node<T>
+where
+    T: Getable<'vm, 'value>,
+{
+    impl_getable_simple!();
+
+    fn from_value(vm: &'vm Thread, value: Variants<'value>) -> Self {
+        Node::new(T::from_value(vm, value))
+    }
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(vm_type = "std.macro.Literal")]
+#[gluon(crate_name = "::vm")]
+enum Literal {
+    Int(i64),
+    Byte(u8),
+    Float(f64),
+    String(String),
+    Char(char),
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(vm_type = "std.macro.Pattern")]
+#[gluon(crate_name = "::vm")]
+enum Pattern {
+    IdentPattern(String),
+    ConstructorPattern(String, Vec<Pattern>),
+    TuplePattern(Vec<Pattern>),
+    RecordPattern(Vec<FieldPattern>),
+    LiteralPattern(Literal),
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(crate_name = "::vm")]
+struct FieldPattern {
+    name: String,
+    value: Option<Pattern>,
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(vm_type = "std.macro.Expr")]
+#[gluon(crate_name = "::vm")]
+enum Expr {
+    Ident(String),
+    Literal(Literal),
+    App(Node<Expr>, Vec<Expr>),
+    Lambda(Vec<String>, Node<Expr>),
+    Infix(Node<Expr>, String, Node<Expr>),
+    Projection(Node<Expr>, String),
+    IfElse(Node<Expr>, Node<Expr>, Node<Expr>),
+    Match(Node<Expr>, Vec<Alternative>),
+    Let(Pattern, Node<Expr>, Node<Expr>),
+    Record(Vec<Field>),
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    Block(Vec<Expr>),
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(crate_name = "::vm")]
+struct Alternative {
+    pattern: Pattern,
+    expr: Expr,
+}
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(crate_name = "::vm")]
+struct Field {
+    name: String,
+    value: Expr,
+}
+
+fn unsupported(kind: &str) -> macros::Error {
+    macros::Error::message(format!(
+        "{} can not be passed to a macro written in gluon",
+        kind
+    ))
+}
+
+fn name(symbol: &Symbol) -> String {
+    symbol.declared_name().to_string()
+}
+
+fn quote_literal(literal: &ast::Literal) -> Literal {
+    match literal {
+        ast::Literal::Int(i) => Literal::Int(*i),
+        ast::Literal::Byte(b) => Literal::Byte(*b),
+        ast::Literal::Float(f) => Literal::Float(f.into_inner()),
+        ast::Literal::String(s) => Literal::String(s.clone()),
+        ast::Literal::Char(c) => Literal::Char(*c),
+    }
+}
+
+fn quote_pattern(pattern: &SpannedPattern<Symbol>) -> Result<Pattern, macros::Error> {
+    Ok(match &pattern.value {
+        ast::Pattern::Ident(id) => Pattern::IdentPattern(name(&id.name)),
+        ast::Pattern::Constructor(id, args) => Pattern::ConstructorPattern(
+            name(&id.name),
+            args.iter()
+                .map(quote_pattern)
+                .collect::<Result<_, macros::Error>>()?,
+        ),
+        ast::Pattern::Tuple { elems, .. } => Pattern::TuplePattern(
+            elems
+                .iter()
+                .map(quote_pattern)
+                .collect::<Result<_, macros::Error>>()?,
+        ),
+        ast::Pattern::Record {
+            fields,
+            implicit_import: None,
+            ..
+        } => Pattern::RecordPattern(
+            fields
+                .iter()
+                .map(|field| match field {
+                    PatternField::Value { name: id, value } => Ok(FieldPattern {
+                        name: name(&id.value),
+                        value: value.as_ref().map(quote_pattern).transpose()?,
+                    }),
+                    PatternField::Type { .. } => Err(unsupported("A type field pattern")),
+                })
+                .collect::<Result<_, macros::Error>>()?,
+        ),
+        ast::Pattern::Literal(literal) => Pattern::LiteralPattern(quote_literal(literal)),
+        ast::Pattern::Record { .. } => return Err(unsupported("An implicit import pattern")),
+        ast::Pattern::As(..) => return Err(unsupported("An as-pattern")),
+        ast::Pattern::Error => return Err(unsupported("An invalid pattern")),
+    })
+}
+
+fn quote_exprs(exprs: &[SpannedExpr<Symbol>]) -> Result<Vec<Expr>, macros::Error> {
+    exprs.iter().map(quote).collect()
+}
+
+/// Converts an expression of the AST into its `std.macro.Expr` representation
+fn quote(expr: &SpannedExpr<Symbol>) -> Result<Expr, macros::Error> {
+    Ok(match &expr.value {
+        ast::Expr::Ident(id) => Expr::Ident(name(&id.name)),
+        ast::Expr::Literal(literal) => Expr::Literal(quote_literal(literal)),
+        ast::Expr::App {
+            func,
+            implicit_args,
+            args,
+        } => {
+            if !implicit_args.is_empty() {
+                return Err(unsupported("An application with implicit arguments"));
+            }
+            Expr::App(Node::new(quote(func)?), quote_exprs(args)?)
+        }
+        ast::Expr::Lambda(lambda) => Expr::Lambda(
+            lambda
+                .args
+                .iter()
+                .map(|arg| name(&arg.name.value.name))
+                .collect(),
+            Node::new(quote(&lambda.body)?),
+        ),
+        ast::Expr::Infix { lhs, op, rhs, .. } => Expr::Infix(
+            Node::new(quote(lhs)?),
+            name(&op.value.name),
+            Node::new(quote(rhs)?),
+        ),
+        ast::Expr::Projection(expr, field, _) => {
+            Expr::Projection(Node::new(quote(expr)?), name(field))
+        }
+        ast::Expr::IfElse(pred, if_true, if_false) => Expr::IfElse(
+            Node::new(quote(pred)?),
+            Node::new(quote(if_true)?),
+            Node::new(quote(if_false)?),
+        ),
+        ast::Expr::Match(expr, alts) => Expr::Match(
+            Node::new(quote(expr)?),
+            alts.iter()
+                .map(|alt| {
+                    Ok(Alternative {
+                        pattern: quote_pattern(&alt.pattern)?,
+                        expr: quote(&alt.expr)?,
+                    })
+                })
+                .collect::<Result<_, macros::Error>>()?,
+        ),
+        ast::Expr::LetBindings(ValueBindings::Plain(bind), body) => {
+            let value = quote(&bind.expr)?;
+            let value = if bind.args.is_empty() {
+                value
+            } else {
+                Expr::Lambda(
+                    bind.args
+                        .iter()
+                        .map(|arg| name(&arg.name.value.name))
+                        .collect(),
+                    Node::new(value),
+                )
+            };
+            Expr::Let(
+                quote_pattern(&bind.name)?,
+                Node::new(value),
+                Node::new(quote(body)?),
+            )
+        }
+        ast::Expr::Record {
+            types,
+            exprs,
+            base: None,
+            ..
+        } if types.is_empty() => Expr::Record(
+            exprs
+                .iter()
+                .map(|field| {
+                    Ok(Field {
+                        name: name(&field.name.value),
+                        value: match &field.value {
+                            Some(value) => quote(value)?,
+                            None => Expr::Ident(name(&field.name.value)),
+                        },
+                    })
+                })
+                .collect::<Result<_, macros::Error>>()?,
+        ),
+        ast::Expr::Tuple { elems, .. } => Expr::Tuple(quote_exprs(elems)?),
+        ast::Expr::Array(array) => Expr::Array(quote_exprs(&array.exprs)?),
+        ast::Expr::Block(exprs) => Expr::Block(quote_exprs(exprs)?),
+        ast::Expr::MacroExpansion { replacement, .. } => quote(replacement)?,
+        ast::Expr::LetBindings(..) => return Err(unsupported("A recursive binding")),
+        ast::Expr::Record { .. } => return Err(unsupported("A record with types or a base")),
+        expr => return Err(unsupported(expr.kind())),
+    })
+}
+
+/// Converts the `Expr` returned from a macro back into the AST.
+///
+/// Identifiers bound inside the generated expression are given fresh symbols so they can not
+/// capture or shadow the bindings at the call site. Any other identifier refers to a binding in
+/// scope where the macro were called.
+struct Splicer<'s, 'ast> {
+    arena: ast::ArenaRef<'s, 'ast, Symbol>,
+    symbols: &'s mut Symbols,
+    span: Span<BytePos>,
+    scope: Vec<(String, Symbol)>,
+}
+
+impl<'s, 'ast> Splicer<'s, 'ast> {
+    fn ident(&mut self, name: &str) -> Symbol {
+        match self.scope.iter().rev().find(|(bound, _)| bound == name) {
+            Some((_, symbol)) => symbol.clone(),
+            None => self.symbols.simple_symbol(name),
+        }
+    }
+
+    fn bind(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol::from(name);
+        self.scope.push((name.to_string(), symbol.clone()));
+        symbol
+    }
+
+    fn literal(&self, literal: Literal) -> Result<ast::Literal, macros::Error> {
+        Ok(match literal {
+            Literal::Int(i) => ast::Literal::Int(i),
+            Literal::Byte(b) => ast::Literal::Byte(b),
+            Literal::Float(f) => {
+                ast::Literal::Float(NotNan::new(f).map_err(|_| {
+                    macros::Error::message("A macro can not return a `NaN` literal")
+                })?)
+            }
+            Literal::String(s) => ast::Literal::String(s),
+            Literal::Char(c) => ast::Literal::Char(c),
+        })
+    }
+
+    fn pattern(&mut self, pattern: Pattern) -> Result<SpannedPattern<'ast, Symbol>, macros::Error> {
+        let pattern = match pattern {
+            Pattern::IdentPattern(name) => ast::Pattern::Ident(TypedIdent::new(self.bind(&name))),
+            Pattern::ConstructorPattern(name, args) => {
+                let id = TypedIdent::new(self.symbols.simple_symbol(name));
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.pattern(arg))
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Pattern::Constructor(id, self.arena.alloc_extend(args))
+            }
+            Pattern::TuplePattern(elems) => {
+                let elems = elems
+                    .into_iter()
+                    .map(|elem| self.pattern(elem))
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Pattern::Tuple {
+                    typ: Type::hole(),
+                    elems: self.arena.alloc_extend(elems),
+                }
+            }
+            Pattern::RecordPattern(fields) => {
+                let fields = fields
+                    .into_iter()
+                    .map(|field| {
+                        let value = match field.value {
+                            Some(value) => Some(self.pattern(value)?),
+                            None => {
+                                let id = TypedIdent::new(self.bind(&field.name));
+                                Some(pos::spanned(self.span, ast::Pattern::Ident(id)))
+                            }
+                        };
+                        Ok(PatternField::Value {
+                            name: pos::spanned(self.span, self.symbols.simple_symbol(field.name)),
+                            value,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Pattern::Record {
+                    typ: Type::hole(),
+                    fields: self.arena.alloc_extend(fields),
+                    implicit_import: None,
+                }
+            }
+            Pattern::LiteralPattern(literal) => ast::Pattern::Literal(self.literal(literal)?),
+        };
+        Ok(pos::spanned(self.span, pattern))
+    }
+
+    fn exprs(
+        &mut self,
+        exprs: Vec<Expr>,
+    ) -> Result<&'ast mut [SpannedExpr<'ast, Symbol>], macros::Error> {
+        let exprs = exprs
+            .into_iter()
+            .map(|expr| self.expr(expr))
+            .collect::<Result<Vec<_>, macros::Error>>()?;
+        Ok(self.arena.alloc_extend(exprs))
+    }
+
+    fn alloc(
+        &mut self,
+        expr: Node<Expr>,
+    ) -> Result<&'ast mut SpannedExpr<'ast, Symbol>, macros::Error> {
+        let expr = self.expr(*expr.0)?;
+        Ok(self.arena.alloc(expr))
+    }
+
+    fn expr(&mut self, expr: Expr) -> Result<SpannedExpr<'ast, Symbol>, macros::Error> {
+        let arena = self.arena;
+        let expr = match expr {
+            Expr::Ident(name) => ast::Expr::Ident(TypedIdent::new(self.ident(&name))),
+            Expr::Literal(literal) => ast::Expr::Literal(self.literal(literal)?),
+            Expr::App(func, args) => {
+                let func = self.expr(*func.0)?;
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Expr::app(arena, func, args)
+            }
+            Expr::Lambda(args, body) => {
+                let scope = self.scope.len();
+                let args = args
+                    .iter()
+                    .map(|arg| {
+                        Argument::explicit(pos::spanned(self.span, TypedIdent::new(self.bind(arg))))
+                    })
+                    .collect::<Vec<_>>();
+                let body = self.alloc(body)?;
+                self.scope.truncate(scope);
+                ast::Expr::Lambda(ast::Lambda {
+                    id: TypedIdent::new(self.symbols.simple_symbol("")),
+                    args: arena.alloc_extend(args),
+                    body,
+                })
+            }
+            Expr::Infix(lhs, op, rhs) => ast::Expr::Infix {
+                lhs: self.alloc(lhs)?,
+                op: pos::spanned(self.span, TypedIdent::new(self.ident(&op))),
+                rhs: self.alloc(rhs)?,
+                implicit_args: &mut [],
+            },
+            Expr::Projection(expr, field) => ast::Expr::Projection(
+                self.alloc(expr)?,
+                self.symbols.simple_symbol(field),
+                Type::hole(),
+            ),
+            Expr::IfElse(pred, if_true, if_false) => ast::Expr::IfElse(
+                self.alloc(pred)?,
+                self.alloc(if_true)?,
+                self.alloc(if_false)?,
+            ),
+            Expr::Match(expr, alts) => {
+                let expr = self.alloc(expr)?;
+                let alts = alts
+                    .into_iter()
+                    .map(|alt| {
+                        let scope = self.scope.len();
+                        let pattern = self.pattern(alt.pattern)?;
+                        let expr = self.expr(alt.expr)?;
+                        self.scope.truncate(scope);
+                        Ok(ast::Alternative { pattern, expr })
+                    })
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Expr::Match(expr, arena.alloc_extend(alts))
+            }
+            Expr::Let(pattern, value, body) => {
+                // The binding is not recursive so the value is spliced before the pattern binds
+                // any names
+                let value = self.expr(*value.0)?;
+                let scope = self.scope.len();
+                let pattern = self.pattern(pattern)?;
+                let body = self.expr(*body.0)?;
+                self.scope.truncate(scope);
+                ast::Expr::let_binding(
+                    arena,
+                    ValueBinding {
+                        metadata: Default::default(),
+                        name: pattern,
+                        typ: None,
+                        resolved_type: Type::hole(),
+                        args: &mut [],
+                        expr: value,
+                    },
+                    body,
+                )
+            }
+            Expr::Record(fields) => {
+                let fields = fields
+                    .into_iter()
+                    .map(|field| {
+                        Ok(ExprField {
+                            metadata: Default::default(),
+                            name: pos::spanned(self.span, self.symbols.simple_symbol(field.name)),
+                            value: Some(self.expr(field.value)?),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, macros::Error>>()?;
+                ast::Expr::Record {
+                    typ: Type::hole(),
+                    types: &mut [],
+                    exprs: arena.alloc_extend(fields),
+                    base: None,
+                }
+            }
+            Expr::Tuple(elems) => ast::Expr::Tuple {
+                typ: Type::hole(),
+                elems: self.exprs(elems)?,
+            },
+            Expr::Array(exprs) => ast::Expr::Array(ast::Array {
+                typ: Type::hole(),
+                exprs: self.exprs(exprs)?,
+            }),
+            Expr::Block(exprs) => ast::Expr::Block(self.exprs(exprs)?),
+        };
+        Ok(pos::spanned(self.span, expr))
+    }
+}
+
+/// A macro defined by a gluon function of type `std.macro.Macro`
+struct UserMacro {
+    name: String,
+    function: RootedValue<RootedThread>,
+}
+
+unsafe impl Trace for UserMacro {
+    impl_trace! { self, _gc, () }
+}
+
+impl Macro for UserMacro {
+    fn expand<'r, 'a: 'r, 'b: 'r, 'ast: 'r>(
+        &self,
+        env: &'b mut MacroExpander<'a>,
+        arena: &'b mut ast::OwnedArena<'ast, Symbol>,
+        args: &'b mut [SpannedExpr<'ast, Symbol>],
+    ) -> MacroFuture<'r, 'ast> {
+        let name = self.name.clone();
+        let function = self.function.clone();
+        Box::pin(async move {
+            let span = args.first().map_or_else(Span::default, |arg| arg.span);
+            let args = quote_exprs(args)?;
+
+            let mut function =
+                OwnedFunction::<MacroFunction>::from_value(env.vm, function.get_variant());
+            let expr = function
+                .call_async(args)
+                .await
+                .map_err(|err| macros::Error::message(err.to_string()))?
+                .map_err(|err| macros::Error::message(format!("`{}!`: {}", name, err)))?;
+
+            // Splice with the symbols of the module so that free identifiers in the expression
+            // refer to the bindings at the call site
+            let mut symbols = mem::replace(&mut env.symbols, Symbols::new());
+            let result = Splicer {
+                arena: arena.borrow(),
+                symbols: &mut symbols,
+                span,
+                scope: Vec::new(),
+            }
+            .expr(expr);
+            let mut expr = match result {
+                Ok(expr) => expr,
+                Err(err) => {
+                    env.symbols = symbols;
+                    return Err(err);
+                }
+            };
+
+            // The returned expression may itself contain macro calls
+            env.run_once(&mut symbols, arena, &mut expr).await;
+            env.symbols = symbols;
+
+            Ok(expr.into())
+        })
+    }
+}
+
+/// `import_macros! module` brings every field of `module` with the type `std.macro.Macro` into
+/// scope as a macro. Expands to the module itself, like `import!`.
+#[derive(Trace)]
+#[gluon(crate_name = "vm")]
+pub(crate) struct ImportMacros;
+
+impl Macro for ImportMacros {
+    fn expand<'r, 'a: 'r, 'b: 'r, 'ast: 'r>(
+        &self,
+        env: &'b mut MacroExpander<'a>,
+        _arena: &'b mut ast::OwnedArena<'ast, Symbol>,
+        args: &'b mut [SpannedExpr<'ast, Symbol>],
+    ) -> MacroFuture<'r, 'ast> {
+        Box::pin(async move {
+            if args.len() != 1 {
+                return Err(macros::Error::message(
+                    "`import_macros!` expects 1 argument",
+                ));
+            }
+
+            let mut module = String::new();
+            expr_to_path(&args[0], &mut module).map_err(macros::Error::message)?;
+            let span = args[0].span;
+
+            let mut db = env
+                .userdata
+                .fork(env.vm.root_thread())
+                .downcast::<salsa::Snapshot<CompilerDatabase>>()
+                .map_err(|_| {
+                    macros::Error::message(
+                        "`import_macros!` requires a `CompilerDatabase` as user data during macro expansion",
+                    )
+                })?;
+
+            // `std.macro` must be loaded before the types mirroring it can be used
+            db.import("std.macro".into())
+                .await
+                .map_err(|err| macros::Error::message(err.to_string()))?;
+            let id = db
+                .import(module.clone())
+                .await
+                .map_err(|err| macros::Error::message(err.to_string()))?;
+            let global = db
+                .global(module)
+                .await
+                .map_err(|err| macros::Error::message(err.to_string()))?;
+            drop(db);
+
+            let macro_type = <MacroFunction as VmType>::make_type(env.vm);
+            let macros = {
+                let vm_env = env.vm.get_env();
+                global
+                    .typ
+                    .remove_forall()
+                    .row_iter()
+                    .filter(|field| check_signature(&vm_env, &macro_type, &field.typ))
+                    .filter_map(|field| {
+                        let name = field.name.declared_name();
+                        global.value.get_field(name).map(|function| UserMacro {
+                            name: name.to_string(),
+                            function,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            };
+            for mac in macros {
+                env.local_macros.insert(mac.name.clone(), Arc::new(mac));
+            }
+
+            Ok(pos::spanned(span, ast::Expr::Ident(id)).into())
+        })
+    }
+}
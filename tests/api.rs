@@ -730,3 +730,25 @@ fn clone_userdata() {
 
     assert_eq!(*result, Test(123));
 }
+
+#[cfg(feature = "bignum")]
+#[test]
+fn marshal_bigint() {
+    use gluon::{std_lib::bigint::BigInt, vm::api::UserdataValue};
+
+    let _ = ::env_logger::try_init();
+
+    let expr = r#"
+        let bigint @ { ? } = import! std.bigint
+        \x -> x * bigint.from_int 2
+    "#;
+
+    let vm = make_vm();
+    let (mut double, _) = vm
+        .run_expr::<OwnedFunction<fn(BigInt) -> UserdataValue<BigInt>>>("<top>", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let x = BigInt(VmInt::max_value().into());
+    let UserdataValue(result) = double.call(x).unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result.0.to_string(), "18446744073709551614");
+}
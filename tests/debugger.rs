@@ -0,0 +1,261 @@
+use gluon::{
+    debugger::{Debugger, Frontend, Resume, Stop, StopReason},
+    new_vm_async,
+    query::Compilation,
+    vm::api::ValueRef,
+    Result, ThreadExt,
+};
+
+const EXPR: &str = r#"let f x =
+    let y = x + 1
+    y
+let a = f 1
+let b = f 2
+a + b
+"#;
+
+/// Records every stop and resumes execution with the next entry of `resumes`
+struct Recorder {
+    resumes: Vec<Resume>,
+    stops: Vec<Stop>,
+    evaluate: Option<&'static str>,
+    evaluated: Vec<String>,
+}
+
+impl Recorder {
+    fn new(resumes: Vec<Resume>) -> Recorder {
+        Recorder {
+            resumes,
+            stops: Vec::new(),
+            evaluate: None,
+            evaluated: Vec::new(),
+        }
+    }
+
+    fn variables(&self) -> Vec<Vec<(String, String)>> {
+        self.stops
+            .iter()
+            .map(|stop| {
+                stop.frames[0]
+                    .variables()
+                    .into_iter()
+                    .filter(|variable| variable.name == "x" || variable.name == "y")
+                    .map(|variable| (variable.name.clone(), variable.show()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl Frontend for Recorder {
+    async fn stopped(&mut self, debugger: &Debugger, stop: &Stop) -> Result<Resume> {
+        if let Some(expr) = self.evaluate {
+            let value = debugger.evaluate_to_string(&stop.frames[0], expr).await?;
+            self.evaluated.push(value);
+        }
+        self.stops.push(stop.clone());
+        Ok(if self.resumes.is_empty() {
+            Resume::Continue
+        } else {
+            self.resumes.remove(0)
+        })
+    }
+}
+
+async fn new_debugger() -> Debugger {
+    Debugger::new(new_vm_async().await)
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|&(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn breakpoint_shows_locals() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    let id = debugger.add_breakpoint("test", 3, None);
+
+    let mut recorder = Recorder::new(vec![]);
+    let (value, _) = debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(value.get_variant().as_ref(), ValueRef::Int(5));
+    assert_eq!(recorder.stops.len(), 2);
+    for stop in &recorder.stops {
+        assert_eq!(stop.reason, StopReason::Breakpoint(vec![id]));
+        assert_eq!(stop.frames[0].line, Some(3));
+        assert_eq!(stop.frames[0].function_name.as_deref(), Some("f"));
+    }
+    assert_eq!(
+        recorder.variables(),
+        vec![
+            pairs(&[("x", "1"), ("y", "2")]),
+            pairs(&[("x", "2"), ("y", "3")])
+        ]
+    );
+}
+
+#[tokio::test]
+async fn conditional_breakpoint() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    debugger.add_breakpoint("test", 3, Some("x == 2".to_string()));
+
+    let mut recorder = Recorder::new(vec![]);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(recorder.variables(), vec![pairs(&[("x", "2"), ("y", "3")])]);
+}
+
+#[tokio::test]
+async fn invalid_condition_stops_with_error() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    let id = debugger.add_breakpoint("test", 3, Some("x".to_string()));
+
+    let mut recorder = Recorder::new(vec![]);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(recorder.stops.len(), 2);
+    match &recorder.stops[0].reason {
+        StopReason::ConditionError { breakpoint, .. } => assert_eq!(*breakpoint, id),
+        reason => panic!("Unexpected stop reason {:?}", reason),
+    }
+}
+
+#[tokio::test]
+async fn step_over_function_call() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    debugger.add_breakpoint("test", 4, None);
+
+    let mut recorder = Recorder::new(vec![Resume::StepOver, Resume::Continue]);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let lines: Vec<_> = recorder
+        .stops
+        .iter()
+        .map(|stop| (stop.reason.clone(), stop.frames[0].line))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (StopReason::Breakpoint(vec![1]), Some(4)),
+            (StopReason::Step, Some(5))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn step_in_and_out_of_function() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    debugger.add_breakpoint("test", 4, None);
+
+    let mut recorder = Recorder::new(vec![Resume::StepIn, Resume::StepOut, Resume::Continue]);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let stops: Vec<_> = recorder
+        .stops
+        .iter()
+        .map(|stop| {
+            (
+                stop.frames[0].function_name.clone(),
+                stop.frames[0].line,
+                stop.frames.len(),
+            )
+        })
+        .collect();
+    assert_eq!(stops.len(), 3);
+    assert_eq!(stops[1].0.as_deref(), Some("f"));
+    assert_eq!(stops[1].1, Some(2));
+    assert!(stops[2].2 < stops[1].2);
+}
+
+#[tokio::test]
+async fn evaluate_in_frame() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    debugger.add_breakpoint("test", 3, None);
+
+    let mut recorder = Recorder::new(vec![]);
+    recorder.evaluate = Some(r#"show (x * y) ++ "!""#);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(recorder.evaluated, [r#""2!""#, r#""6!""#]);
+}
+
+#[tokio::test]
+async fn removed_breakpoint_does_not_stop() {
+    let _ = env_logger::try_init();
+
+    let debugger = new_debugger().await;
+    let id = debugger.add_breakpoint("test", 3, None);
+    assert!(debugger.remove_breakpoint(id));
+    assert!(debugger.breakpoints().is_empty());
+
+    let mut recorder = Recorder::new(vec![]);
+    debugger
+        .run_expr(&mut recorder, "test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    assert!(recorder.stops.is_empty());
+}
+
+#[tokio::test]
+async fn dropping_the_debugger_restores_optimizations() {
+    let _ = env_logger::try_init();
+
+    let vm = new_vm_async().await;
+    let debugger = Debugger::new(vm.clone());
+    assert!(!vm.get_database().compiler_settings().optimize);
+
+    drop(debugger);
+    assert!(vm.get_database().compiler_settings().optimize);
+}
+
+#[tokio::test]
+async fn dropping_the_debugger_removes_its_hook() {
+    let _ = env_logger::try_init();
+
+    let vm = new_vm_async().await;
+    let debugger = Debugger::new(vm.clone());
+    debugger.add_breakpoint("test", 3, None);
+    drop(debugger);
+
+    // The breakpoint would suspend execution forever if the hook of the debugger were left behind
+    let (value, _) = vm
+        .run_expr_async::<i32>("test", EXPR)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 5);
+}
@@ -0,0 +1,129 @@
+use std::fs;
+
+use gluon::{
+    new_vm,
+    vm::api::{OwnedFunction, IO},
+    ThreadExt,
+};
+
+#[test]
+fn create_copy_rename_and_walk() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let { unwrap_ok } = import! std.result
+        let fs = import! std.fs
+        let path = import! std.path
+
+        \root ->
+            let nested = path.join root "a/b"
+            do result = fs.create_dir_all nested
+            let _ = unwrap_ok result
+            do file = io.create_file (path.join nested "file.txt")
+            do _ = io.write_slice_file file [1b, 2b, 3b] 0 3
+            do _ = io.close_file file
+            do copied = fs.copy (path.join nested "file.txt") (path.join root "copy.txt")
+            do _ = fs.rename (path.join root "copy.txt") (path.join root "moved.txt")
+            do walked = fs.walk_dir root
+            io.wrap (unwrap_ok copied, unwrap_ok walked)
+    "#;
+
+    let vm = new_vm();
+    vm.get_database_mut().run_io(true);
+    let (mut test, _) = vm
+        .run_expr::<OwnedFunction<fn(String) -> IO<(u64, Vec<String>)>>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let root = dir.path().to_str().unwrap().to_owned();
+    let (copied, walked) = match test.call(root.clone()) {
+        Ok(IO::Value(value)) => value,
+        Ok(IO::Exception(err)) => panic!("{}", err),
+        Err(err) => panic!("{}", err),
+    };
+    assert_eq!(copied, 3);
+    let expected: Vec<String> = ["a", "a/b", "a/b/file.txt", "moved.txt"]
+        .iter()
+        .map(|path| dir.path().join(path).to_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(walked, expected);
+    assert_eq!(fs::read(dir.path().join("moved.txt")).unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn errors_are_structured() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let fs @ { ErrorKind, ? } = import! std.fs
+        let path = import! std.path
+
+        \root ->
+            do result = fs.remove_file (path.join root "missing.txt")
+            io.wrap (
+                match result with
+                | Ok _ -> "ok"
+                | Err { kind = NotFound } -> "not found"
+                | Err err -> err.message
+            )
+    "#;
+
+    let vm = new_vm();
+    vm.get_database_mut().run_io(true);
+    let (mut test, _) = vm
+        .run_expr::<OwnedFunction<fn(String) -> IO<String>>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let result = test.call(dir.path().to_str().unwrap().to_owned());
+    match result {
+        Ok(IO::Value(value)) => assert_eq!(value, "not found"),
+        Ok(IO::Exception(err)) => panic!("{}", err),
+        Err(err) => panic!("{}", err),
+    }
+}
+
+#[test]
+fn metadata_reports_permissions_and_times() {
+    let _ = ::env_logger::try_init();
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let { unwrap_ok } = import! std.result
+        let fs = import! std.fs
+
+        \path ->
+            do metadata = fs.read_metadata path
+            let metadata = unwrap_ok metadata
+            let permissions = fs.metadata.permissions metadata
+            do _ = fs.set_permissions path { readonly = True, .. permissions }
+            do metadata = fs.read_metadata path
+            let metadata = unwrap_ok metadata
+            io.wrap (
+                (fs.metadata.permissions metadata).readonly,
+                unwrap_ok (fs.metadata.modified metadata) > 0.0
+            )
+    "#;
+
+    let vm = new_vm();
+    vm.get_database_mut().run_io(true);
+    let (mut test, _) = vm
+        .run_expr::<OwnedFunction<fn(String) -> IO<(bool, bool)>>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let result = test.call(file.path().to_str().unwrap().to_owned());
+    match result {
+        Ok(IO::Value(value)) => assert_eq!(value, (true, true)),
+        Ok(IO::Exception(err)) => panic!("{}", err),
+        Err(err) => panic!("{}", err),
+    }
+}
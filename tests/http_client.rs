@@ -0,0 +1,215 @@
+#![cfg(feature = "web")]
+
+use std::{convert::Infallible, net::SocketAddr, time::Duration};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+
+use gluon::{
+    new_vm_async,
+    vm::api::{Getable, VmType, IO},
+    ThreadExt,
+};
+
+async fn handle(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    Ok(match request.uri().path() {
+        "/hello" => Response::new(Body::from("Hello World")),
+        "/echo" => {
+            let header = request.headers().get("x-test").cloned();
+            let method = request.method().to_string();
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            let mut response = Response::new(Body::from(body));
+            if let Some(header) = header {
+                response.headers_mut().insert("x-test", header);
+            }
+            response
+                .headers_mut()
+                .insert("x-method", method.parse().unwrap());
+            response
+        }
+        "/slow" => {
+            tokio::time::delay_for(Duration::from_secs(5)).await;
+            Response::new(Body::empty())
+        }
+        _ => Response::builder().status(404).body(Body::empty()).unwrap(),
+    })
+}
+
+fn start_server() -> SocketAddr {
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(handle))
+    }));
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+async fn run_io<T>(text: &str) -> Result<T, String>
+where
+    T: for<'vm, 'value> Getable<'vm, 'value> + VmType + Send + 'static,
+{
+    let vm = new_vm_async().await;
+    vm.get_database_mut().run_io(true);
+    match vm.run_expr_async::<IO<T>>("test", text).await {
+        Ok((IO::Value(value), _)) => Ok(value),
+        Ok((IO::Exception(err), _)) => Err(err),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn get() {
+    let _ = ::env_logger::try_init();
+
+    let addr = start_server();
+    let text = format!(
+        r#"
+        let {{ wrap, ? }} = import! std.io
+        let client = import! std.http.client
+
+        do response = client.get "http://{}/hello"
+        do body = client.read_body response.body
+        wrap (response.status, body)
+        "#,
+        addr
+    );
+    let (status, body) = run_io::<(u16, Vec<u8>)>(&text)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(status, 200);
+    assert_eq!(body, b"Hello World");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn not_found() {
+    let _ = ::env_logger::try_init();
+
+    let addr = start_server();
+    let text = format!(
+        r#"
+        let {{ wrap, ? }} = import! std.io
+        let client = import! std.http.client
+
+        do response = client.get "http://{}/missing"
+        wrap response.status
+        "#,
+        addr
+    );
+    let status = run_io::<u16>(&text)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(status, 404);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn send_headers_and_body() {
+    let _ = ::env_logger::try_init();
+
+    let addr = start_server();
+    let text = format!(
+        r#"
+        let {{ wrap, ? }} = import! std.io
+        let string = import! std.string
+        let client = import! std.http.client
+
+        let request = {{
+            method = "PUT",
+            headers = [("x-test", string.as_bytes "abc")],
+            .. client.request "http://{}/echo"
+        }}
+        do response = client.send request (string.as_bytes "ping")
+        do body = client.read_body response.body
+        wrap (response.headers, body)
+        "#,
+        addr
+    );
+    let (headers, body) = run_io::<(Vec<(String, Vec<u8>)>, Vec<u8>)>(&text)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(body, b"ping");
+    assert!(
+        headers.contains(&("x-test".to_string(), b"abc".to_vec())),
+        "{:?}",
+        headers
+    );
+    assert!(
+        headers.contains(&("x-method".to_string(), b"PUT".to_vec())),
+        "{:?}",
+        headers
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn stream_request_body() {
+    let _ = ::env_logger::try_init();
+
+    let addr = start_server();
+    let text = format!(
+        r#"
+        let {{ wrap, ? }} = import! std.io
+        let string = import! std.string
+        let client = import! std.http.client
+
+        let write body =
+            seq client.write_body body (string.as_bytes "hello ")
+            client.write_body body (string.as_bytes "world")
+
+        do response = client.send_streaming {{ method = "POST", .. client.request "http://{}/echo" }} write
+        client.read_body response.body
+        "#,
+        addr
+    );
+    let body = run_io::<Vec<u8>>(&text)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(body, b"hello world");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn timeout() {
+    let _ = ::env_logger::try_init();
+
+    let addr = start_server();
+    let text = format!(
+        r#"
+        let {{ wrap, ? }} = import! std.io
+        let client = import! std.http.client
+
+        do response = client.send {{ timeout = Some 100, .. client.request "http://{}/slow" }} []
+        wrap response.status
+        "#,
+        addr
+    );
+    let err = run_io::<u16>(&text)
+        .await
+        .expect_err("Expected the request to time out");
+    assert!(err.contains("timed out"), "{}", err);
+}
+
+#[test]
+fn request_outside_of_a_tokio_runtime_is_an_exception() {
+    let _ = ::env_logger::try_init();
+
+    let get = r#"
+        let { map } = import! std.functor
+        let { ? } = import! std.io
+        let client = import! std.http.client
+
+        map (\response -> response.status) (client.get "http://127.0.0.1:1/")
+        "#;
+    let send_streaming = r#"
+        let { map } = import! std.functor
+        let { wrap, ? } = import! std.io
+        let client = import! std.http.client
+
+        let response = client.send_streaming (client.request "http://127.0.0.1:1/") (\_ -> wrap ())
+        map (\response -> response.status) response
+        "#;
+    for text in &[get, send_streaming] {
+        let err = futures::executor::block_on(run_io::<u16>(text))
+            .expect_err("Expected the request to fail");
+        assert!(err.contains("tokio runtime"), "{}", err);
+    }
+}
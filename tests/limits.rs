@@ -47,3 +47,122 @@ fn stack_overflow() {
         Ok(_) => panic!("Expected an error"),
     }
 }
+
+const COUNT_DOWN: &str = r#"
+let count n : Int -> Int =
+    if n #Int== 0 then 0 else count (n #Int- 1)
+count 100
+"#;
+
+#[test]
+fn out_of_fuel() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut().implicit_prelude(false);
+    vm.set_fuel(Some(50));
+
+    let result = vm.run_expr::<i32>("example", COUNT_DOWN);
+
+    match result {
+        Err(Error::VM(VMError::OutOfFuel { consumed })) => assert!(consumed >= 50),
+        Err(err) => panic!("Unexpected error `{:?}`", err),
+        Ok(_) => panic!("Expected an error"),
+    }
+    assert_eq!(vm.remaining_fuel(), Some(0));
+}
+
+#[test]
+fn fuel_consumption_is_reported() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut().implicit_prelude(false);
+    vm.reset_fuel_consumed();
+    vm.set_fuel(Some(100_000));
+
+    let (value, _) = vm
+        .run_expr::<i32>("example", COUNT_DOWN)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 0);
+
+    let consumed = vm.fuel_consumed();
+    assert!(consumed > 100, "{}", consumed);
+    assert_eq!(vm.remaining_fuel(), Some(100_000 - consumed));
+
+    assert_eq!(vm.reset_fuel_consumed(), consumed);
+    assert_eq!(vm.fuel_consumed(), 0);
+}
+
+#[test]
+fn refuel_suspended_thread() {
+    use futures::{future, prelude::*, task::Poll};
+
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut().implicit_prelude(false);
+    vm.suspend_on_out_of_fuel(true);
+    vm.set_fuel(Some(20));
+
+    let execute = vm.run_expr_async::<i32>("example", COUNT_DOWN);
+    futures::pin_mut!(execute);
+
+    let mut refuels = 0;
+    let result = futures::executor::block_on(future::poll_fn(|cx| loop {
+        match execute.as_mut().poll(cx) {
+            Poll::Ready(result) => return Poll::Ready(result),
+            Poll::Pending if vm.remaining_fuel() == Some(0) => {
+                refuels += 1;
+                vm.add_fuel(20);
+            }
+            Poll::Pending => return Poll::Pending,
+        }
+    }));
+
+    let (value, _) = result.unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 0);
+    assert!(refuels > 1, "{}", refuels);
+}
+
+#[test]
+fn suspending_line_hook_does_not_consume_extra_fuel() {
+    use futures::{future, prelude::*, task::Poll};
+    use gluon::vm::thread::HookFlags;
+
+    let _ = ::env_logger::try_init();
+
+    let run = |suspend: bool| {
+        let vm = make_vm();
+        vm.get_database_mut().implicit_prelude(false);
+        vm.set_fuel(Some(100_000));
+        if suspend {
+            let mut context = vm.context();
+            let mut pending = false;
+            context.set_hook(Some(Box::new(move |_, _| {
+                // Suspend once on every new line
+                pending = !pending;
+                if pending {
+                    Poll::Pending
+                } else {
+                    Poll::Ready(Ok(()))
+                }
+            })));
+            context.set_hook_mask(HookFlags::LINE_FLAG);
+        }
+        vm.reset_fuel_consumed();
+
+        let execute = vm.run_expr_async::<i32>("example", COUNT_DOWN);
+        futures::pin_mut!(execute);
+        let (value, _) = futures::executor::block_on(future::poll_fn(|cx| loop {
+            if let Poll::Ready(result) = execute.as_mut().poll(cx) {
+                return Poll::Ready(result);
+            }
+        }))
+        .unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(value, 0);
+        vm.fuel_consumed()
+    };
+
+    assert_eq!(run(true), run(false));
+}
@@ -0,0 +1,84 @@
+use std::fs;
+
+use gluon::{RootedThread, ThreadExt, VmBuilder};
+
+static MACROS: &str = r#"
+let { Expr, Literal, Pattern, Macro } = import! std.macro
+let array = import! std.array
+
+let twice : Macro = \args ->
+    if array.len args == 1 then
+        let x = array.index args 0
+        Ok (Infix x "+" x)
+    else
+        Err "expected 1 argument"
+
+let add_one : Macro = \args ->
+    let x = array.index args 0
+    Ok (Let (IdentPattern "x") (Literal (Int 1)) (Infix (Ident "x") "+" x))
+
+{ twice, add_one }
+"#;
+
+fn new_vm(dir: &tempfile::TempDir) -> RootedThread {
+    fs::write(dir.path().join("test_macros.glu"), MACROS).unwrap();
+    VmBuilder::new()
+        .import_paths(Some(vec![dir.path().to_owned()]))
+        .build()
+}
+
+#[test]
+fn gluon_macro_is_expanded() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let vm = new_vm(&dir);
+
+    let text = r#"
+        let _ = import_macros! test_macros
+        twice! (1 + 20)
+    "#;
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn gluon_macro_bindings_are_hygienic() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let vm = new_vm(&dir);
+
+    let text = r#"
+        let _ = import_macros! test_macros
+        let x = 10
+        add_one! x
+    "#;
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 11);
+}
+
+#[test]
+fn gluon_macro_errors_are_reported() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let vm = new_vm(&dir);
+
+    let text = r#"
+        let _ = import_macros! test_macros
+        twice! 1 2
+    "#;
+    let err = vm.run_expr::<i32>("test", text).unwrap_err().to_string();
+    assert!(err.contains("expected 1 argument"), "{}", err);
+
+    let text = r#"
+        missing! 1
+    "#;
+    let err = vm.run_expr::<i32>("test", text).unwrap_err().to_string();
+    assert!(err.contains("No macro named `missing!`"), "{}", err);
+}
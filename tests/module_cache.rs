@@ -0,0 +1,108 @@
+#![cfg(feature = "serialization")]
+
+use std::{fs, path::Path};
+
+use gluon::{RootedThread, ThreadExt, VmBuilder};
+
+fn make_vm(cache_dir: &Path, import_dir: &Path) -> RootedThread {
+    VmBuilder::new()
+        .module_cache(Some(cache_dir.to_owned()))
+        .import_paths(Some(vec![import_dir.to_owned()]))
+        .build()
+}
+
+fn cached_modules(cache_dir: &Path, module: &str) -> usize {
+    fs::read_dir(cache_dir)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&format!("{}-", module))
+        })
+        .count()
+}
+
+#[test]
+fn modules_are_reused_across_vms() {
+    let _ = ::env_logger::try_init();
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let import_dir = tempfile::tempdir().unwrap();
+
+    let text = r#"
+        let list = import! std.list
+        list.foldable.foldl (\acc x -> acc + x) 0 (list.of [1, 2, 3])
+    "#;
+
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 6);
+    assert_eq!(cached_modules(cache_dir.path(), "std.list"), 1);
+
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 6);
+    assert_eq!(cached_modules(cache_dir.path(), "std.list"), 1);
+}
+
+#[test]
+fn changed_sources_are_recompiled() {
+    let _ = ::env_logger::try_init();
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let import_dir = tempfile::tempdir().unwrap();
+    let module_path = import_dir.path().join("cache_test.glu");
+    let text = "let m = import! cache_test in m.value";
+
+    fs::write(&module_path, "{ value = 1 }").unwrap();
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 1);
+
+    fs::write(&module_path, "{ value = 2 }").unwrap();
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 2);
+    // The entry for the old source is removed when the new one is stored
+    assert_eq!(cached_modules(cache_dir.path(), "cache_test"), 1);
+}
+
+#[test]
+fn stale_dependencies_invalidate_entries() {
+    let _ = ::env_logger::try_init();
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let import_dir = tempfile::tempdir().unwrap();
+    let dependency_path = import_dir.path().join("cache_dep.glu");
+    fs::write(
+        import_dir.path().join("cache_user.glu"),
+        "let dep = import! cache_dep in { value = dep.value }",
+    )
+    .unwrap();
+    let text = "let m = import! cache_user in m.value";
+
+    fs::write(&dependency_path, "{ value = 1 }").unwrap();
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<i32>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 1);
+
+    fs::write(&dependency_path, r#"{ value = "changed" }"#).unwrap();
+    let vm = make_vm(cache_dir.path(), import_dir.path());
+    let (result, _) = vm
+        .run_expr::<String>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, "changed");
+}
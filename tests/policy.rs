@@ -0,0 +1,168 @@
+use std::{io, path::Path};
+
+use gluon::{
+    policy::Policy,
+    vm::{
+        api::{Hole, OpaqueValue, OwnedFunction, IO},
+        sandbox::{FsAccess, Sandbox},
+    },
+    RootedThread, Thread, ThreadExt, VmBuilder,
+};
+
+fn make_vm(policy: Policy) -> RootedThread {
+    let vm = VmBuilder::new().policy(policy).build();
+    vm.get_database_mut().run_io(true);
+    vm
+}
+
+#[test]
+fn denied_module_names_the_capability() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::new().deny_module("std.process.prim"));
+    let result = vm.run_expr::<OpaqueValue<&Thread, Hole>>("test", "import! std.process");
+
+    match result {
+        Err(err) => assert!(
+            err.to_string().contains(
+                "Importing `std.process.prim` was denied by the capability policy of the vm"
+            ),
+            "{}",
+            err
+        ),
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn allowed_module_can_be_imported() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::sandboxed().allow_module("std.env.prim"));
+    vm.run_expr::<OpaqueValue<&Thread, Hole>>("test", "import! std.env")
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn denied_primitive() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::new().deny_primitive("std.env.prim.set_var"));
+    let text = r#"
+        let env = import! std.env
+        env.set_var "GLUON_POLICY_TEST" "1"
+    "#;
+    let result = vm.run_expr::<IO<()>>("test", text);
+
+    match result {
+        Err(err) => assert!(
+            err.to_string()
+                .contains("Calling the primitive `std.env.prim.set_var` was denied"),
+            "{}",
+            err
+        ),
+        Ok(_) => panic!("Expected an error"),
+    }
+    assert_eq!(std::env::var("GLUON_POLICY_TEST").ok(), None);
+}
+
+#[test]
+fn denying_is_dir_does_not_deny_is_file() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::new().deny_primitive("std.path.prim.is_dir"));
+    let text = r#"
+        let path = import! std.path
+        path.is_file "Cargo.toml"
+    "#;
+    let (is_file, _) = vm
+        .run_expr::<IO<bool>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(is_file, IO::Value(true));
+
+    let text = r#"
+        let path = import! std.path
+        path.is_dir "src"
+    "#;
+    match vm.run_expr::<IO<bool>>("test", text) {
+        Err(err) => assert!(
+            err.to_string()
+                .contains("Calling the primitive `std.path.prim.is_dir` was denied"),
+            "{}",
+            err
+        ),
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn fs_root_restricts_reads() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::new().fs_root(Some("tests".into())));
+    let text = r#"
+        let io = import! std.io
+        io.read_file_to_string
+    "#;
+    let (mut read_file_to_string, _) = vm
+        .run_expr::<OwnedFunction<fn(String) -> IO<String>>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    match read_file_to_string.call("policy.rs".to_string()) {
+        Ok(IO::Value(contents)) => assert!(contents.contains("fs_root_restricts_reads")),
+        Ok(IO::Exception(err)) => panic!("{}", err),
+        Err(err) => panic!("{}", err),
+    }
+
+    match read_file_to_string.call("../Cargo.toml".to_string()) {
+        Ok(IO::Exception(err)) => assert!(err.contains("denied"), "{}", err),
+        Ok(IO::Value(_)) => panic!("Expected an error"),
+        Err(err) => assert!(err.to_string().contains("denied"), "{}", err),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn fs_root_resolves_symlinks_of_paths_that_do_not_exist() {
+    let _ = ::env_logger::try_init();
+
+    let root = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+
+    let sandbox = Sandbox::new().fs_root(Some(root.path().into()));
+
+    let err = sandbox
+        .check_path(Path::new("link/a/b"), FsAccess::Write)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+    let err = sandbox
+        .check_path(Path::new("a/../../b"), FsAccess::Write)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+    let path = sandbox
+        .check_path(Path::new("a/b"), FsAccess::Write)
+        .unwrap();
+    assert_eq!(path, root.path().canonicalize().unwrap().join("a/b"));
+}
+
+#[test]
+fn read_only_fs_denies_writes() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm(Policy::new().read_only_fs(true));
+    let text = r#"
+        let io = import! std.io
+        io.create_file "policy_test_output"
+    "#;
+    let result = vm.run_expr::<IO<OpaqueValue<&Thread, Hole>>>("test", text);
+
+    match result {
+        Ok((IO::Exception(err), _)) => assert!(err.contains("read only"), "{}", err),
+        Ok((IO::Value(_), _)) => panic!("Expected an error"),
+        Err(err) => assert!(err.to_string().contains("read only"), "{}", err),
+    }
+    assert!(!std::path::Path::new("policy_test_output").exists());
+}
@@ -0,0 +1,165 @@
+#![cfg(unix)]
+
+use gluon::{
+    new_vm,
+    vm::api::{Getable, VmType, IO},
+    RootedThread, ThreadExt, VmBuilder,
+};
+
+use tokio::runtime::Runtime;
+
+fn run_io<T>(vm: &RootedThread, text: &str) -> T
+where
+    T: for<'vm, 'value> Getable<'vm, 'value> + VmType + Send + 'static,
+{
+    vm.get_database_mut().run_io(true);
+    let (result, _) = vm
+        .run_expr::<IO<T>>("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    match result {
+        IO::Value(value) => value,
+        IO::Exception(err) => panic!("{}", err),
+    }
+}
+
+#[test]
+fn output_captures_stdout_and_stderr() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let string = import! std.string
+        let { unwrap_ok } = import! std.result
+        let process = import! std.process
+
+        do output = process.output (process.proc "sh" ["-c", "echo out; echo err >&2; exit 3"])
+        io.wrap (
+            unwrap_ok (string.from_utf8 output.stdout),
+            unwrap_ok (string.from_utf8 output.stderr),
+            output.status.code,
+            output.status.success
+        )
+    "#;
+
+    let vm = new_vm();
+    let result = run_io::<(String, String, Option<i32>, bool)>(&vm, text);
+    assert_eq!(
+        result,
+        ("out\n".to_string(), "err\n".to_string(), Some(3), false)
+    );
+}
+
+#[test]
+fn piped_child_streams_lines() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let string = import! std.string
+        let process = import! std.process
+
+        do child = process.spawn_piped (process.proc "cat" [])
+        do _ = process.write_stdin child (string.as_bytes "first\nsecond\r\n")
+        do _ = process.close_stdin child
+        do first = process.read_stdout_line child
+        do second = process.read_stdout_line child
+        do end = process.read_stdout_line child
+        do status = process.wait child
+        io.wrap (first, second, end, status.success)
+    "#;
+
+    let vm = new_vm();
+    let result = run_io::<(Option<String>, Option<String>, Option<String>, bool)>(&vm, text);
+    assert_eq!(
+        result,
+        (
+            Some("first".to_string()),
+            Some("second".to_string()),
+            None,
+            true
+        )
+    );
+}
+
+#[test]
+fn killed_child_reports_signal() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let process = import! std.process
+
+        do child = process.spawn (process.proc "sleep" ["10"])
+        do running = process.try_wait child
+        do _ = process.kill child
+        do status = process.wait child
+        let was_running =
+            match running with
+            | Some _ -> False
+            | None -> True
+        io.wrap (was_running, status.signal, status.code)
+    "#;
+
+    let vm = new_vm();
+    let result = run_io::<(bool, Option<i32>, Option<i32>)>(&vm, text);
+    assert_eq!(result, (true, Some(9), None));
+}
+
+#[test]
+fn wait_does_not_block_the_executor() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let process = import! std.process
+
+        do child = process.spawn (process.proc "sh" ["-c", "exit 7"])
+        do status = process.wait child
+        io.wrap status.code
+    "#;
+
+    let mut runtime = Runtime::new().unwrap();
+    let (result, _) = runtime
+        .block_on(async {
+            let vm = VmBuilder::new().build_async().await;
+            vm.get_database_mut().run_io(true);
+            vm.run_expr_async::<IO<Option<i32>>>("test", text).await
+        })
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, IO::Value(Some(7)));
+}
+
+#[test]
+fn kill_child_while_another_task_waits_on_it() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+        let io @ { ? } = import! std.io
+        let { flat_map } = import! std.monad
+        let thread = import! std.thread
+        let process = import! std.process
+
+        do child = process.spawn (process.proc "sleep" ["10"])
+        do waiter = thread.new_thread ()
+        do waiting = thread.spawn_on waiter (\_ -> process.wait child)
+        // Give the other task time to start waiting
+        do _ = thread.sleep 100
+        do _ = process.kill child
+        do status = waiting
+        io.wrap status.signal
+    "#;
+
+    let mut runtime = Runtime::new().unwrap();
+    let (result, _) = runtime
+        .block_on(async {
+            let vm = VmBuilder::new().build_async().await;
+            vm.get_database_mut().run_io(true);
+            vm.run_expr_async::<IO<Option<i32>>>("test", text).await
+        })
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, IO::Value(Some(9)));
+}
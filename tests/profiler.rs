@@ -0,0 +1,183 @@
+use gluon::{new_vm, RootedThread, ThreadExt};
+
+fn new_vm_without_prelude() -> RootedThread {
+    let thread = new_vm();
+    thread.get_database_mut().set_optimize(false);
+    thread.get_database_mut().implicit_prelude(false);
+    thread
+}
+
+const EXPR: &str = r#"
+let count n : Int -> Int =
+    if n #Int== 0 then 0 else count (n #Int- 1)
+
+let make_array x : Int -> Array Int = [x, x, x]
+
+let g x =
+    let _ = make_array x
+    count x
+
+g 10
+"#;
+
+#[test]
+fn counts_calls() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let profiler = thread.start_profiling();
+    let (result, _) = thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let profile = profiler.finish();
+
+    assert_eq!(result, 0);
+    assert_eq!(profile.function("g").map(|f| f.calls), Some(1));
+    assert_eq!(profile.function("make_array").map(|f| f.calls), Some(1));
+    assert_eq!(profile.function("count").map(|f| f.calls), Some(11));
+}
+
+#[test]
+fn inclusive_time_contains_exclusive_time() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let profiler = thread.start_profiling();
+    thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let profile = profiler.finish();
+
+    for function in &profile.functions {
+        assert!(
+            function.exclusive_time <= function.inclusive_time,
+            "{:?}",
+            function
+        );
+        assert!(
+            function.exclusive_allocated <= function.inclusive_allocated,
+            "{:?}",
+            function
+        );
+        assert!(
+            function.inclusive_time <= profile.total_time,
+            "{:?}",
+            function
+        );
+    }
+
+    let g = profile.function("g").unwrap();
+    let count = profile.function("count").unwrap();
+    assert!(count.inclusive_time <= g.inclusive_time);
+    assert!(profile.function("make_array").unwrap().inclusive_allocated > 0);
+}
+
+#[test]
+fn stacks_refer_to_callers() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let profiler = thread.start_profiling();
+    thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let profile = profiler.finish();
+
+    let names = |stack: &gluon::profiler::StackProfile| {
+        stack
+            .frames
+            .iter()
+            .map(|&i| profile.functions[i].function_name.clone())
+            .collect::<Vec<_>>()
+    };
+    let stacks: Vec<_> = profile.stacks.iter().map(names).collect();
+    assert!(
+        stacks
+            .iter()
+            .any(|stack| stack.ends_with(&["g".to_string(), "make_array".to_string()])),
+        "{:?}",
+        stacks
+    );
+    assert!(
+        stacks
+            .iter()
+            .any(|stack| stack.ends_with(&["g".to_string(), "count".to_string()])),
+        "{:?}",
+        stacks
+    );
+}
+
+#[test]
+fn writes_folded_and_json_output() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let profiler = thread.start_profiling();
+    thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let profile = profiler.finish();
+
+    let mut folded = Vec::new();
+    profile.write_folded(&mut folded).unwrap();
+    for line in String::from_utf8(folded).unwrap().lines() {
+        let (_, count) = line.split_at(line.rfind(' ').unwrap());
+        count.trim().parse::<u128>().unwrap();
+    }
+
+    let mut json = Vec::new();
+    profile.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let functions = json["functions"].as_array().unwrap();
+    assert_eq!(functions.len(), profile.functions.len());
+    assert!(functions
+        .iter()
+        .any(|f| f["function"] == "count" && f["calls"] == 11));
+}
+
+#[test]
+fn finishing_removes_the_hook() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let profiler = thread.start_profiling();
+    let profile = profiler.finish();
+    assert!(profile.functions.is_empty());
+
+    thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn finishing_restores_the_previous_hook() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use gluon::vm::thread::{HookFlags, ThreadInternal};
+
+    let _ = env_logger::try_init();
+
+    let thread = new_vm_without_prelude();
+    let calls = Arc::new(AtomicUsize::new(0));
+    {
+        let calls = calls.clone();
+        let mut context = thread.context();
+        context.set_hook(Some(Box::new(move |_, _| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            futures::task::Poll::Ready(Ok(()))
+        })));
+        context.set_hook_mask(HookFlags::CALL_FLAG);
+    }
+
+    let profiler = thread.start_profiling();
+    profiler.finish();
+    assert_eq!(thread.context().hook_mask(), HookFlags::CALL_FLAG);
+
+    thread
+        .run_expr::<i32>("test", EXPR)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(calls.load(Ordering::SeqCst) > 0);
+}
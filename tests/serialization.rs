@@ -1,8 +1,14 @@
 #![cfg(feature = "serialization")]
 extern crate serde_state as serde;
+#[macro_use]
+extern crate gluon_vm;
+#[macro_use]
+extern crate gluon_codegen;
 
 use std::{fs::File, io::Read};
 
+use futures::future;
+
 use crate::serde::ser::SerializeState;
 
 use gluon::{
@@ -229,6 +235,147 @@ fn roundtrip_thread() {
     roundtrip(&thread, &Into::<Result<_, _>>::into(value).unwrap());
 }
 
+#[test]
+fn suspend_and_resume_thread_in_new_vm() {
+    use std::{sync::Mutex, task::Poll};
+
+    use gluon::{
+        import::add_extern_module,
+        vm::{thread::ThreadInternal, ExternModule},
+    };
+
+    let _ = env_logger::try_init();
+
+    static LOG: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    fn log(x: i32) -> i32 {
+        LOG.lock().unwrap().push(x);
+        x
+    }
+
+    fn new_vm_with_log() -> RootedThread {
+        let vm = new_vm();
+        add_extern_module(&vm, "test_log", |thread| {
+            ExternModule::new(
+                thread,
+                record! {
+                    log => primitive!(1, "test_log.log", log),
+                },
+            )
+        });
+        vm
+    }
+
+    fn resume(thread: &Thread) -> Poll<()> {
+        futures::executor::block_on(future::lazy(|cx| {
+            thread
+                .resume(cx)
+                .map(|result| result.map(|_| ()).unwrap_or_else(|err| panic!("{}", err)))
+        }))
+    }
+
+    let expr = r#"
+        let { spawn, yield } = import! std.thread
+        let { log } = import! test_log
+        spawn (\_ ->
+            let x = log 1
+            let _ = yield ()
+            let _ = log (x + 1)
+            ())
+    "#;
+    let vm = new_vm_with_log();
+    let (thread, _) = vm
+        .run_expr::<RootedThread>("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(resume(&thread), Poll::Pending);
+    assert_eq!(*LOG.lock().unwrap(), [1]);
+
+    let mut buffer = Vec::new();
+    thread
+        .serialize_suspended(&mut serde_json::Serializer::new(&mut buffer))
+        .unwrap_or_else(|err| panic!("{}", err));
+    drop(thread);
+    drop(vm);
+
+    let vm = new_vm_with_log();
+    let thread = vm
+        .deserialize_thread(&mut serde_json::Deserializer::from_slice(&buffer))
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // `yield` was interrupted by the serialization so it is called again
+    assert_eq!(resume(&thread), Poll::Pending);
+    assert_eq!(resume(&thread), Poll::Ready(()));
+    assert_eq!(*LOG.lock().unwrap(), [1, 2]);
+}
+
+#[test]
+fn userdata_in_globals_are_linked_by_name() {
+    use gluon::{
+        import::add_extern_module,
+        vm::{api::ValueRef, ExternModule},
+    };
+
+    let _ = env_logger::try_init();
+
+    #[derive(Debug, Userdata, Trace)]
+    struct Counter(i32);
+    impl gluon::vm::api::VmType for Counter {
+        type Type = Counter;
+    }
+
+    fn new_vm_with_counter() -> RootedThread {
+        let vm = new_vm();
+        vm.register_type::<Counter>("Counter", &[]).unwrap();
+        add_extern_module(&vm, "test_counter", |thread| {
+            ExternModule::new(
+                thread,
+                record! {
+                    counter => Counter(5),
+                },
+            )
+        });
+        vm
+    }
+
+    let thread = new_vm_with_counter();
+    let (value, _) = thread
+        .run_expr::<OpaqueValue<&Thread, Hole>>(
+            "test",
+            "let { counter } = import! test_counter in counter",
+        )
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // Userdata can only be serialized if the seed knows the global it is stored in
+    value
+        .get_variant()
+        .serialize_state(
+            &mut serde_json::Serializer::new(&mut Vec::new()),
+            &SeSeed::new(),
+        )
+        .unwrap_err();
+
+    let mut buffer = Vec::new();
+    value
+        .get_variant()
+        .serialize_state(
+            &mut serde_json::Serializer::new(&mut buffer),
+            &SeSeed::with_globals(&thread),
+        )
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let thread = new_vm_with_counter();
+    thread
+        .run_expr::<OpaqueValue<&Thread, Hole>>("test", "import! test_counter")
+        .unwrap_or_else(|err| panic!("{}", err));
+    let value: RootedValue<RootedThread> = DeSeed::new(&thread, &mut thread.current_context())
+        .deserialize(&mut serde_json::Deserializer::from_slice(&buffer))
+        .unwrap_or_else(|err| panic!("{}", err));
+    match value.get_variant().as_ref() {
+        ValueRef::Userdata(data) => assert_eq!(data.downcast_ref::<Counter>().unwrap().0, 5),
+        _ => panic!("Expected userdata, got {:?}", value),
+    }
+}
+
 #[test]
 fn issue_805_no_deadlock_in_deserialize() {
     fn serialize_value(value: gluon::vm::Variants) -> Box<[u8]> {
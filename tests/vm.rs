@@ -522,6 +522,71 @@ cmp 5 6
 String::from("5 != 6")
 }
 
+test_expr! { prelude string_interpolation,
+r#"
+let x = 1
+let y = 2.5
+"x = ${x}, { y } = ${ { y }.y }, \${escaped}"
+"#,
+String::from("x = 1, { y } = 2.5, ${escaped}")
+}
+
+test_expr! { prelude string_interpolation_inserts_strings_without_quotes,
+r#"
+let name = "bob"
+"hi ${name}, ${Some name}"
+"#,
+String::from("hi bob, Some (\"bob\")")
+}
+
+test_expr! { prelude string_interpolation_ignores_shadowed_show,
+r#"
+let show x = "shadowed"
+let (++) x y = "shadowed"
+let x = 1
+"x = ${x}, ${"y"}"
+"#,
+String::from("x = 1, y")
+}
+
+test_expr! { string_interpolation_without_prelude,
+r#"
+let { ? } = import! std.int
+let { ? } = import! std.interpolate
+let x = 1
+"x = ${x}"
+"#,
+String::from("x = 1")
+}
+
+test_expr! { prelude string_interpolation_uses_display_instances,
+r#"
+let { Display } = import! std.interpolate
+type Point = { x : Int, y : Int }
+let display_point : Display Point = { display = \p -> "(${p.x}, ${p.y})" }
+let p : Point = { x = 1, y = 2 }
+"p = ${p}"
+"#,
+String::from("p = (1, 2)")
+}
+
+test_expr! { prelude reference_keeps_young_values_alive_across_collections,
+r#"
+let { ref, (<-), load } = import! std.reference
+let r = ref { x = 0 }
+let loop n =
+    if n #Int== 0 then
+        ()
+    else
+        // Allocates enough to run several collections while `r` refers to young values
+        r <- { x = (load r).x #Int+ 1 }
+        loop (n #Int- 1)
+loop 100000
+(load r).x
+"#,
+100000i32
+}
+
 #[test]
 fn rename_types_after_binding() {
     let _ = ::env_logger::try_init();
@@ -699,6 +764,62 @@ int.max_value * 2
     }
 }
 
+#[test]
+fn constant_arithmetic_overflow_is_a_runtime_error() {
+    let _ = ::env_logger::try_init();
+    let vm = make_vm();
+    for text in &["9223372036854775807 + 1", "1 / 0"] {
+        // Constant folding must leave the failing operation to the vm instead of panicking
+        let result = vm.run_expr::<i32>("<top>", text);
+        match result {
+            Err(Error::VM(vm::Error::Message(ref err))) if err.contains("overflow") => (),
+            Err(err) => panic!("Unexpected error `{}`", err),
+            Ok(_) => panic!("Expected an error"),
+        }
+    }
+}
+
+#[test]
+fn checked_arithmetic_reports_source_location() {
+    let _ = ::env_logger::try_init();
+    let text = r#"
+let f x y : Int -> Int -> Int = x / y
+f 1 0
+"#;
+    let vm = make_vm();
+    vm.get_database_mut().set_checked_arithmetic(true);
+    let result = vm.run_expr::<i32>("<top>", text);
+    match result {
+        Err(Error::VM(vm::Error::Panic(ref err, Some(_))))
+            if err.starts_with("attempt to divide by zero at ") => {}
+        Err(err) => panic!("Unexpected error `{}`", err),
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn checked_arithmetic_overflow_can_be_caught() {
+    let _ = ::env_logger::try_init();
+    let text = r#"
+let io @ { ? } = import! std.io
+let int = import! std.int
+let action =
+    do _ = io.println ""
+    io.applicative.wrap (show (int.max_value + 1))
+io.catch action io.applicative.wrap
+"#;
+    let vm = make_vm();
+    vm.get_database_mut().run_io(true);
+    vm.get_database_mut().set_checked_arithmetic(true);
+    let (result, _) = vm
+        .run_expr::<IO<String>>("<top>", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    match result {
+        IO::Value(ref err) if err.contains("with overflow at ") => (),
+        result => panic!("Unexpected result `{:?}`", result),
+    }
+}
+
 #[test]
 fn partially_applied_constructor_is_lambda() {
     let _ = ::env_logger::try_init();
@@ -930,6 +1051,45 @@ fn deep_clone_partial_application() {
     );
 }
 
+#[test]
+fn heap_snapshot_groups_records_by_fields() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    let (value, _) = vm
+        .run_expr::<OpaqueValue<RootedThread, Hole>>("test", r#" { x = 1, y = "a" } "#)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let snapshot = vm.heap_snapshot();
+    let record = snapshot
+        .types
+        .iter()
+        .find(|typ| typ.name == "{ x, y }")
+        .expect("Record type");
+    assert_eq!(record.count, 1);
+    assert_eq!(record.retention_path, Some(vec!["{ x, y }".to_string()]));
+    assert!(snapshot.bytes <= vm.allocated_memory());
+    drop(value);
+}
+
+#[test]
+fn heap_snapshot_reports_running_child_as_busy() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    let child = vm.new_thread().unwrap();
+
+    // Holding the context of the child simulates it executing while the snapshot is taken
+    let context = child.context();
+    let snapshot = vm.heap_snapshot();
+    drop(context);
+
+    assert!(snapshot.threads.iter().any(|thread| thread.busy));
+
+    let snapshot = vm.heap_snapshot();
+    assert!(snapshot.threads.iter().all(|thread| !thread.busy));
+}
+
 test_expr! { prelude issue_601,
 r"
 let { wrap } = import! std.applicative
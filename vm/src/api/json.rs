@@ -2,12 +2,16 @@ extern crate serde_json;
 
 use std::{borrow::Borrow, fmt, result::Result as StdResult};
 
-use crate::base::types::ArcType;
+use crate::base::{
+    resolve,
+    types::{ctor_args, ArcType, BuiltinType, NullInterner, Type, TypeExt},
+};
 
 use crate::{
-    api::{Getable, OpaqueValue, ValueRef, VmInt, VmType},
+    api::{Collect, Getable, OpaqueValue, Pushable, ValueRef, VmInt, VmType},
     thread::{ActiveThread, RootedThread, Thread, ThreadInternal},
-    ExternModule, Result, Variants,
+    types::VmTag,
+    Error, ExternModule, Result, Variants,
 };
 
 use crate::serde::de::{self, DeserializeState, MapAccess, SeqAccess, Visitor};
@@ -143,6 +147,187 @@ impl<'vm, 'value> crate::api::Getable<'vm, 'value> for serde_json::Value {
     }
 }
 
+/// Marshals a `serde_json::Value` into a gluon value of type `typ`.
+///
+/// Unlike the `Pushable` implementation of `serde_json::Value`, which produces a `std.json.Value`,
+/// this uses `typ` to decide how the value is represented. Records are read from objects and
+/// variants use the same externally tagged representation that `serde` uses for Rust enums
+/// (`"None"`, `{ "Some": 1 }`, `{ "Pair": [1, 2] }`). `()` is represented as `null`.
+pub struct Typed<'a> {
+    typ: &'a ArcType,
+    value: &'a serde_json::Value,
+}
+
+impl<'a> Typed<'a> {
+    pub fn new(typ: &'a ArcType, value: &'a serde_json::Value) -> Self {
+        Typed { typ, value }
+    }
+}
+
+impl<'vm, 'a> Pushable<'vm> for Typed<'a> {
+    fn push(self, context: &mut ActiveThread<'vm>) -> Result<()> {
+        use serde_json::Value as Json;
+
+        let thread = context.thread();
+        let typ = resolve::remove_aliases(&thread.get_env(), &mut NullInterner, self.typ.clone());
+        let error = || {
+            Error::Message(format!(
+                "Unable to marshal `{}` as `{}`",
+                self.value, self.typ
+            ))
+        };
+        match (&*typ, self.value) {
+            (Type::Builtin(BuiltinType::Int), Json::Number(n)) => {
+                n.as_i64().ok_or_else(error)?.push(context)
+            }
+            (Type::Builtin(BuiltinType::Byte), Json::Number(n)) => {
+                let b = n.as_u64().filter(|&b| b <= u64::from(u8::max_value()));
+                (b.ok_or_else(error)? as u8).push(context)
+            }
+            (Type::Builtin(BuiltinType::Float), Json::Number(n)) => {
+                n.as_f64().ok_or_else(error)?.push(context)
+            }
+            (Type::Builtin(BuiltinType::String), Json::String(s)) => s.as_str().push(context),
+            (Type::Builtin(BuiltinType::Char), Json::String(s)) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c.push(context),
+                    _ => Err(error()),
+                }
+            }
+            (Type::App(f, args), Json::Array(elems))
+                if **f == Type::Builtin(BuiltinType::Array) =>
+            {
+                Collect::new(elems.iter().map(|elem| Typed::new(&args[0], elem))).push(context)
+            }
+            (Type::Record(_), Json::Null) if typ.row_iter().next().is_none() => ().push(context),
+            (Type::Record(_), Json::Object(fields)) => {
+                let mut field_names = Vec::new();
+                for field in typ.row_iter() {
+                    let value = fields.get(field.name.as_ref()).ok_or_else(|| {
+                        format!("Missing field `{}` in `{}`", field.name, self.value)
+                    })?;
+                    Typed::new(&field.typ, value).push(context)?;
+                    field_names.push(thread.global_env().intern(field.name.as_ref())?);
+                }
+                context
+                    .context()
+                    .push_new_record(field_names.len(), &field_names)?;
+                Ok(())
+            }
+            (Type::Variant(_), Json::Bool(b)) if is_bool(&typ) => b.push(context),
+            (Type::Variant(_), _) => {
+                let (name, args) = match self.value {
+                    Json::String(name) => (name, None),
+                    Json::Object(map) if map.len() == 1 => {
+                        let (name, args) = map.iter().next().unwrap();
+                        (name, Some(args))
+                    }
+                    _ => return Err(error()),
+                };
+                let (tag, variant) = typ
+                    .row_iter()
+                    .enumerate()
+                    .find(|(_, variant)| variant.name.as_ref() == name)
+                    .ok_or_else(error)?;
+                let arg_types: Vec<_> = ctor_args(&variant.typ).collect();
+                let len = match (&arg_types[..], args) {
+                    ([], None) => 0,
+                    ([typ], Some(arg)) => {
+                        Typed::new(typ, arg).push(context)?;
+                        1
+                    }
+                    (_, Some(Json::Array(args))) if args.len() == arg_types.len() => {
+                        for (typ, arg) in arg_types.iter().zip(args) {
+                            Typed::new(typ, arg).push(context)?;
+                        }
+                        args.len()
+                    }
+                    _ => return Err(error()),
+                };
+                context.context().push_new_data(tag as VmTag, len)?;
+                Ok(())
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Marshals `value`, which must be of type `typ`, into the representation described by
+/// [`Typed`](struct.Typed.html).
+pub fn to_typed_json(thread: &Thread, value: Variants, typ: &ArcType) -> Result<serde_json::Value> {
+    use serde_json::Value as Json;
+
+    let typ = resolve::remove_aliases(&thread.get_env(), &mut NullInterner, typ.clone());
+    let error = || {
+        Error::Message(format!(
+            "Unable to marshal a value of type `{}` to JSON",
+            typ
+        ))
+    };
+    Ok(match (&*typ, value.as_ref()) {
+        (Type::Builtin(BuiltinType::Int), ValueRef::Int(i)) => i.into(),
+        (Type::Builtin(BuiltinType::Byte), ValueRef::Byte(b)) => b.into(),
+        (Type::Builtin(BuiltinType::Float), ValueRef::Float(f)) => {
+            Json::Number(serde_json::Number::from_f64(f).ok_or_else(error)?)
+        }
+        (Type::Builtin(BuiltinType::String), ValueRef::String(s)) => s.into(),
+        (Type::Builtin(BuiltinType::Char), ValueRef::Int(i)) => ::std::char::from_u32(i as u32)
+            .ok_or_else(error)?
+            .to_string()
+            .into(),
+        (Type::App(_, args), ValueRef::Array(array)) => Json::Array(
+            array
+                .as_ref()
+                .iter()
+                .map(|elem| to_typed_json(thread, elem, &args[0]))
+                .collect::<Result<_>>()?,
+        ),
+        (Type::Record(_), _) if typ.row_iter().next().is_none() => Json::Null,
+        (Type::Record(_), ValueRef::Data(data)) => Json::Object(
+            typ.row_iter()
+                .map(|field| {
+                    let value = data
+                        .lookup_field(thread, field.name.as_ref())
+                        .ok_or_else(error)?;
+                    Ok((
+                        field.name.to_string(),
+                        to_typed_json(thread, value, &field.typ)?,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        (Type::Variant(_), ValueRef::Data(data)) if is_bool(&typ) => Json::Bool(data.tag() != 0),
+        (Type::Variant(_), ValueRef::Data(data)) => {
+            let variant = typ.row_iter().nth(data.tag() as usize).ok_or_else(error)?;
+            let mut args = ctor_args(&variant.typ)
+                .zip(data.iter())
+                .map(|(typ, arg)| to_typed_json(thread, arg, typ))
+                .collect::<Result<Vec<_>>>()?;
+            let name = variant.name.to_string();
+            match args.len() {
+                0 => Json::String(name),
+                1 => Json::Object(Some((name, args.pop().unwrap())).into_iter().collect()),
+                _ => Json::Object(Some((name, Json::Array(args))).into_iter().collect()),
+            }
+        }
+        _ => return Err(error()),
+    })
+}
+
+fn is_bool(typ: &ArcType) -> bool {
+    let mut variants = typ.row_iter();
+    match (variants.next(), variants.next(), variants.next()) {
+        (Some(f), Some(t), None) => {
+            f.name.as_ref() == "False"
+                && t.name.as_ref() == "True"
+                && ctor_args(&f.typ).next().is_none()
+                && ctor_args(&t.typ).next().is_none()
+        }
+        _ => false,
+    }
+}
+
 #[derive(Pushable, Getable, SerializeState)]
 #[serde(serialize_state = "Thread")]
 #[gluon(gluon_vm)]
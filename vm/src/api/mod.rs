@@ -24,7 +24,10 @@ use crate::{
     stack::Lock,
     thread::{RootedThread, ThreadInternal, VmRoot, VmRootInternal},
     types::{VmIndex, VmInt, VmTag},
-    value::{ArrayDef, ArrayRepr, ClosureData, DataStruct, Value, ValueArray, ValueRepr},
+    value::{
+        ArrayDef, ArrayRepr, BytecodeFunction, ClosureData, DataStruct, Value, ValueArray,
+        ValueRepr,
+    },
     vm::{self, RootedValue, Status, Thread},
     Error, Result, Variants,
 };
@@ -158,6 +161,19 @@ impl<'a> Closure<'a> {
     pub fn debug_info(&self) -> &crate::compiler::DebugInfo {
         &self.0.function.debug_info
     }
+    /// Returns the names of the closure's function and of every function defined inside it, as
+    /// they appear in the frames of a `Stacktrace`
+    pub fn function_names(&self) -> Vec<Symbol> {
+        fn add_names(function: &BytecodeFunction, names: &mut Vec<Symbol>) {
+            names.push(function.name.clone());
+            for inner in &function.inner_functions {
+                add_names(inner, names);
+            }
+        }
+        let mut names = Vec::new();
+        add_names(&self.0.function, &mut names);
+        names
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -1336,14 +1352,15 @@ where
     match value.as_ref() {
         ValueRef::Data(data) => {
             if data.tag() == 1 {
-                let key = K2::from_value(vm, data.get_variant(0).expect("key"));
-                let value = V2::from_value(vm, data.get_variant(1).expect("value"));
+                // `Bin size key value left right`
+                let key = K2::from_value(vm, data.get_variant(1).expect("key"));
+                let value = V2::from_value(vm, data.get_variant(2).expect("value"));
                 map.extend(Some((key, value)));
 
-                let left = data.get_variant(2).expect("left");
+                let left = data.get_variant(3).expect("left");
                 from_gluon_map(map, vm, left);
 
-                let right = data.get_variant(3).expect("right");
+                let right = data.get_variant(4).expect("right");
                 from_gluon_map(map, vm, right);
             }
         }
@@ -63,6 +63,7 @@ unsafe impl<T> Trace for Sender<T> {
 
 impl<T> Sender<T> {
     fn send(&self, value: &Value) {
+        self.thread.write_barrier(value);
         // SAFETY Rooted when stored in `queue`
         unsafe {
             self.queue.lock().unwrap().push_back(value.clone_unrooted());
@@ -452,6 +452,7 @@ pub struct Compiler<'a> {
     source: &'a ::codespan::FileMap,
     source_name: String,
     emit_debug_info: bool,
+    checked_arithmetic: bool,
     empty_symbol: Symbol,
     hole: ArcType,
 }
@@ -498,10 +499,18 @@ impl<'a> Compiler<'a> {
             source: source,
             source_name: source_name,
             emit_debug_info: emit_debug_info,
+            checked_arithmetic: false,
             hole: Type::hole(),
         }
     }
 
+    /// Sets whether `Int` arithmetic should panic with the source location on overflow and
+    /// division by zero (default: false)
+    pub fn checked_arithmetic(mut self, checked_arithmetic: bool) -> Self {
+        self.checked_arithmetic = checked_arithmetic;
+        self
+    }
+
     fn intern(&mut self, s: &str) -> Result<InternedStr> {
         self.vm.intern(s)
     }
@@ -985,6 +994,10 @@ impl<'a> Compiler<'a> {
             function.function.instructions[end - 2] = Jump(end as VmIndex);
         } else {
             let instr = match self.symbols.string(op) {
+                "#Int+" if self.checked_arithmetic => CheckedAddInt,
+                "#Int-" if self.checked_arithmetic => CheckedSubtractInt,
+                "#Int*" if self.checked_arithmetic => CheckedMultiplyInt,
+                "#Int/" if self.checked_arithmetic => CheckedDivideInt,
                 "#Int+" => AddInt,
                 "#Int-" => SubtractInt,
                 "#Int*" => MultiplyInt,
@@ -28,6 +28,7 @@ use crate::{
         Allocator, Alternative, ArenaExt, CExpr, Closure, ClosureRef, CoreClosure, CoreExpr, Expr,
         LetBinding, Literal, Named, Pattern,
     },
+    types::VmInt,
     Error, Result,
 };
 
@@ -1387,11 +1388,19 @@ impl<'a, 'e> Compiler<'a, 'e> {
         let r = self.peek_reduced_expr(r);
         match (l.as_ref(), r.as_ref()) {
             (&Expr::Const(Literal::Int(l), ..), &Expr::Const(Literal::Int(r), ..)) => {
-                let f = binop!();
+                // Overflow and division by zero are left for the VM to report at runtime
+                let f: fn(VmInt, VmInt) -> Option<VmInt> =
+                    match id.name.as_ref().chars().last().unwrap() {
+                        '+' => VmInt::checked_add,
+                        '-' => VmInt::checked_sub,
+                        '*' => VmInt::checked_mul,
+                        '/' => VmInt::checked_div,
+                        _ => return None,
+                    };
                 Some(
                     self.allocator
                         .arena
-                        .alloc(Expr::Const(Literal::Int(f(l, r)), expr.span())),
+                        .alloc(Expr::Const(Literal::Int(f(l, r)?), expr.span())),
                 )
             }
             (&Expr::Const(Literal::Float(l), ..), &Expr::Const(Literal::Float(r), ..)) => {
@@ -1650,6 +1659,26 @@ pub(crate) mod tests {
         assert_eq_expr!(expr, "3");
     }
 
+    #[test]
+    fn dont_fold_division_by_zero() {
+        let _ = ::env_logger::try_init();
+
+        let expr = r#"
+            (#Int/) 1 0
+        "#;
+        assert_eq_expr!(expr, "(#Int/) 1 0");
+    }
+
+    #[test]
+    fn dont_fold_overflow() {
+        let _ = ::env_logger::try_init();
+
+        let expr = r#"
+            (#Int+) 9223372036854775807 1
+        "#;
+        assert_eq_expr!(expr, "(#Int+) 9223372036854775807 1");
+    }
+
     #[test]
     fn fold_function_call_basic() {
         let _ = ::env_logger::try_init();
@@ -0,0 +1,409 @@
+use crate::base::{
+    ast::{
+        self, Alternative, Argument, Array, Expr, ExprField, Lambda, Pattern, SpannedExpr,
+        TypeBinding, TypedIdent, ValueBinding,
+    },
+    pos::{self, BytePos, Span},
+    symbol::{Symbol, Symbols},
+    types::{ctor_args, remove_forall, row_iter, KindedIdent, Type, TypeContext},
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate<'ast>(
+    mut arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let span = bind.name.span;
+    let type_name = bind.alias.value.name.declared_name();
+
+    let arbitrary_fn = TypedIdent::new(symbols.simple_symbol("arbitrary_"));
+    let shrink_fn = TypedIdent::new(symbols.simple_symbol("shrink_"));
+    let x = Symbol::from("x");
+    let size = Symbol::from("size");
+    let gen = Symbol::from("gen");
+
+    let names = Names {
+        arbitrary: symbols.simple_symbol("arbitrary"),
+        shrink: symbols.simple_symbol("shrink"),
+        smaller: symbols.simple_symbol("smaller"),
+        map: symbols.simple_symbol("map"),
+        append: symbols.simple_symbol("<>"),
+        cons: symbols.simple_symbol("Cons"),
+        nil: symbols.simple_symbol("Nil"),
+        arbitrary_fn: arbitrary_fn.name.clone(),
+        shrink_fn: shrink_fn.name.clone(),
+    };
+
+    let (gen_expr, alts) = match **remove_forall(bind.alias.value.unresolved_type()) {
+        Type::Variant(ref variants) => {
+            let mut base = Vec::new();
+            let mut recursive = Vec::new();
+            let mut alts = Vec::new();
+            for variant in row_iter(variants) {
+                let fields: Vec<_> = ctor_args(&variant.typ)
+                    .enumerate()
+                    .map(|(i, field)| {
+                        (
+                            is_self_type(&bind.alias.value.name, field),
+                            TypedIdent::new(Symbol::from(format!("arg{}", i))),
+                        )
+                    })
+                    .collect();
+
+                let pack = |args: Vec<SpannedExpr<'ast, Symbol>>| {
+                    arena.app(span, variant.name.clone(), args)
+                };
+
+                let variant_gen = generate_gen(arena, symbols, span, &names, &fields, &pack);
+                if fields.iter().any(|(self_type, _)| *self_type) {
+                    recursive.push(variant_gen);
+                } else {
+                    base.push(variant_gen);
+                }
+
+                let pattern =
+                    pos::spanned(
+                        span,
+                        Pattern::Constructor(
+                            TypedIdent::new(variant.name.clone()),
+                            arena.alloc_extend(fields.iter().map(|(_, field)| {
+                                pos::spanned(span, Pattern::Ident(field.clone()))
+                            })),
+                        ),
+                    );
+                alts.push(Alternative {
+                    pattern,
+                    expr: generate_shrink(arena, symbols, span, &names, &fields, &pack),
+                });
+            }
+
+            let variants_fn = symbols.simple_symbol("variants");
+            let gen_expr = arena.app(
+                span,
+                variants_fn,
+                vec![array(arena, span, base), array(arena, span, recursive)],
+            );
+            (gen_expr, alts)
+        }
+        Type::Record(ref row) => {
+            if let Some(field) =
+                row_iter(row).find(|field| is_self_type(&bind.alias.value.name, &field.typ))
+            {
+                return Err(Error::message(format!(
+                    "Unable to derive Arbitrary for `{}` since the field `{}` is recursive",
+                    type_name,
+                    field.name.declared_name()
+                )));
+            }
+
+            let fields: Vec<_> = row_iter(row)
+                .map(|field| {
+                    (
+                        false,
+                        TypedIdent::new(Symbol::from(field.name.declared_name())),
+                    )
+                })
+                .collect();
+
+            let pack = |args: Vec<SpannedExpr<'ast, Symbol>>| {
+                pos::spanned(
+                    span,
+                    Expr::Record {
+                        typ: Type::hole(),
+                        types: &mut [],
+                        exprs: arena.alloc_extend(row_iter(row).zip(args).map(|(field, value)| {
+                            ExprField {
+                                metadata: Default::default(),
+                                name: pos::spanned(span, field.name.clone()),
+                                value: Some(value),
+                            }
+                        })),
+                        base: None,
+                    },
+                )
+            };
+
+            let gen_expr = generate_gen(arena, symbols, span, &names, &fields, &pack);
+
+            let pattern = arena.generate_record_pattern(
+                span,
+                row,
+                fields.iter().map(|(_, field)| field.clone()),
+            );
+            let alt = Alternative {
+                pattern,
+                expr: generate_shrink(arena, symbols, span, &names, &fields, &pack),
+            };
+            (gen_expr, vec![alt])
+        }
+        _ => return Err(Error::message("Unable to derive Arbitrary for this type")),
+    };
+
+    let mut self_type = {
+        let mut arena = arena;
+        move || bind.alias.value.self_type(&mut arena)
+    };
+
+    // The generator is wrapped in a lambda so that recursive variants can refer to it
+    let arbitrary_expr =
+        pos::spanned(
+            span,
+            Expr::Lambda(Lambda {
+                id: TypedIdent::new(symbols.simple_symbol("arbitrary_lambda")),
+                args: arena.alloc_extend([size.clone(), gen.clone()].iter().map(|arg| {
+                    Argument::explicit(pos::spanned(span, TypedIdent::new(arg.clone())))
+                })),
+                body: arena.alloc(pos::spanned(
+                    span,
+                    Expr::App {
+                        func: arena.alloc(arena.paren(span, gen_expr)),
+                        implicit_args: &mut [],
+                        args: arena.alloc_extend(vec![ident(span, size), ident(span, gen)]),
+                    },
+                )),
+            }),
+        );
+
+    let arbitrary_record_expr = Expr::rec_let_bindings(
+        arena,
+        vec![
+            ValueBinding {
+                name: pos::spanned(span, Pattern::Ident(arbitrary_fn.clone())),
+                args: &mut [],
+                expr: arbitrary_expr,
+                metadata: Default::default(),
+                typ: Some(TypeContext::app(
+                    &mut arena.clone(),
+                    arena.ident(KindedIdent::new(symbols.simple_symbol("Gen"))),
+                    arena.clone().alloc_extend(Some(self_type())),
+                )),
+                resolved_type: Type::hole(),
+            },
+            ValueBinding {
+                name: pos::spanned(span, Pattern::Ident(shrink_fn.clone())),
+                args: arena.alloc_extend(Some(Argument::explicit(pos::spanned(
+                    span,
+                    TypedIdent::new(x.clone()),
+                )))),
+                expr: pos::spanned(
+                    span,
+                    Expr::Match(arena.alloc(ident(span, x)), arena.alloc_extend(alts)),
+                ),
+                metadata: Default::default(),
+                typ: Some(arena.clone().function(vec![self_type()], arena.hole())),
+                resolved_type: Type::hole(),
+            },
+        ],
+        pos::spanned(
+            span,
+            Expr::Record {
+                typ: Type::hole(),
+                types: &mut [],
+                exprs: arena.alloc_extend(vec![
+                    ExprField {
+                        metadata: Default::default(),
+                        name: pos::spanned(span, names.arbitrary.clone()),
+                        value: Some(ident(span, arbitrary_fn.name.clone())),
+                    },
+                    ExprField {
+                        metadata: Default::default(),
+                        name: pos::spanned(span, names.shrink.clone()),
+                        value: Some(ident(span, shrink_fn.name.clone())),
+                    },
+                ]),
+                base: None,
+            },
+        ),
+    );
+
+    let imports = vec![
+        arena.generate_import_(
+            span,
+            symbols,
+            &["Gen"],
+            &["arbitrary", "shrink", "variants", "smaller"],
+            true,
+            "std.test.quickcheck",
+        ),
+        arena.generate_import_(span, symbols, &["List"], &[], true, "std.list"),
+        arena.generate_import(span, symbols, &[], &["map"], "std.functor"),
+        arena.generate_import(span, symbols, &[], &["<*>", "wrap"], "std.applicative"),
+        arena.generate_import(span, symbols, &[], &["<>"], "std.semigroup"),
+    ];
+
+    let expr = imports
+        .into_iter()
+        .rev()
+        .fold(pos::spanned(span, arbitrary_record_expr), |expr, bind| {
+            pos::spanned(span, Expr::let_binding(arena, bind, expr))
+        });
+
+    Ok(ValueBinding {
+        name: pos::spanned(
+            span,
+            Pattern::Ident(TypedIdent::new(
+                symbols.simple_symbol(format!("arbitrary_{}", type_name)),
+            )),
+        ),
+        args: &mut [],
+        expr,
+        metadata: Default::default(),
+        typ: Some(binding_type(arena, symbols, "Arbitrary", self_type(), bind)),
+        resolved_type: Type::hole(),
+    })
+}
+
+struct Names {
+    arbitrary: Symbol,
+    shrink: Symbol,
+    smaller: Symbol,
+    map: Symbol,
+    append: Symbol,
+    cons: Symbol,
+    nil: Symbol,
+    arbitrary_fn: Symbol,
+    shrink_fn: Symbol,
+}
+
+fn array<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    span: Span<BytePos>,
+    exprs: Vec<SpannedExpr<'ast, Symbol>>,
+) -> SpannedExpr<'ast, Symbol> {
+    pos::spanned(
+        span,
+        Expr::Array(Array {
+            typ: Type::hole(),
+            exprs: arena.alloc_extend(exprs),
+        }),
+    )
+}
+
+/// Generates `map (\arg0 arg1 -> pack) arbitrary <*> arbitrary`, using `smaller arbitrary_` for
+/// fields of the type itself
+fn generate_gen<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    span: Span<BytePos>,
+    names: &Names,
+    fields: &[(bool, TypedIdent<Symbol>)],
+    pack: &dyn Fn(Vec<SpannedExpr<'ast, Symbol>>) -> SpannedExpr<'ast, Symbol>,
+) -> SpannedExpr<'ast, Symbol> {
+    let packed = pack(
+        fields
+            .iter()
+            .map(|(_, field)| ident(span, field.name.clone()))
+            .collect(),
+    );
+    if fields.is_empty() {
+        return arena.app(
+            span,
+            symbols.simple_symbol("wrap"),
+            vec![arena.paren(span, packed)],
+        );
+    }
+
+    let field_symbols: Vec<_> = fields.iter().map(|(_, field)| field.clone()).collect();
+    arena.sequence_actions(symbols, span, &field_symbols, packed, &mut |name| {
+        let self_type = fields
+            .iter()
+            .any(|(self_type, field)| *self_type && field.name == *name);
+        if self_type {
+            arena.paren(
+                span,
+                arena.app(
+                    span,
+                    names.smaller.clone(),
+                    vec![ident(span, names.arbitrary_fn.clone())],
+                ),
+            )
+        } else {
+            ident(span, names.arbitrary.clone())
+        }
+    })
+}
+
+/// Generates the shrunk values of a constructor by shrinking one field at a time. Fields of the
+/// type itself are also tried directly since they are always smaller than the whole value.
+fn generate_shrink<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    span: Span<BytePos>,
+    names: &Names,
+    fields: &[(bool, TypedIdent<Symbol>)],
+    pack: &dyn Fn(Vec<SpannedExpr<'ast, Symbol>>) -> SpannedExpr<'ast, Symbol>,
+) -> SpannedExpr<'ast, Symbol> {
+    let shrunk_value = Symbol::from("shrunk");
+
+    let subterms = fields
+        .iter()
+        .filter(|(self_type, _)| *self_type)
+        .map(|(_, field)| {
+            arena.app(
+                span,
+                names.cons.clone(),
+                vec![
+                    ident(span, field.name.clone()),
+                    ident(span, names.nil.clone()),
+                ],
+            )
+        });
+
+    let shrink_fields = fields.iter().enumerate().map(|(i, (self_type, field))| {
+        let rebuild = pos::spanned(
+            span,
+            Expr::Lambda(Lambda {
+                id: TypedIdent::new(symbols.simple_symbol("shrink_field")),
+                args: arena.alloc_extend(Some(Argument::explicit(pos::spanned(
+                    span,
+                    TypedIdent::new(shrunk_value.clone()),
+                )))),
+                body: arena.alloc(pack(
+                    fields
+                        .iter()
+                        .enumerate()
+                        .map(|(j, (_, other))| {
+                            if i == j {
+                                ident(span, shrunk_value.clone())
+                            } else {
+                                ident(span, other.name.clone())
+                            }
+                        })
+                        .collect(),
+                )),
+            }),
+        );
+        let shrink = if *self_type {
+            names.shrink_fn.clone()
+        } else {
+            names.shrink.clone()
+        };
+        arena.app(
+            span,
+            names.map.clone(),
+            vec![
+                arena.paren(span, rebuild),
+                arena.paren(
+                    span,
+                    arena.app(span, shrink, vec![ident(span, field.name.clone())]),
+                ),
+            ],
+        )
+    });
+
+    let mut candidates: Vec<_> = subterms.collect();
+    candidates.extend(shrink_fields);
+    candidates
+        .into_iter()
+        .fold(None, |acc, candidate| {
+            Some(match acc {
+                Some(prev) => arena.infix(span, prev, names.append.clone(), candidate),
+                None => candidate,
+            })
+        })
+        .unwrap_or_else(|| ident(span, names.nil.clone()))
+}
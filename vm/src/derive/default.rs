@@ -0,0 +1,86 @@
+use crate::base::{
+    ast::{self, Expr, ExprField, Pattern, TypeBinding, TypedIdent, ValueBinding},
+    pos,
+    symbol::{Symbol, Symbols},
+    types::{remove_forall, row_iter, Type},
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let span = bind.name.span;
+    let type_name = bind.alias.value.name.declared_name();
+
+    let row = match **remove_forall(bind.alias.value.unresolved_type()) {
+        Type::Record(ref row) => row,
+        Type::Variant(_) => {
+            return Err(Error::message(format!(
+                "Unable to derive Default for `{}` since there is no way to pick a default \
+                 constructor",
+                type_name
+            )));
+        }
+        _ => return Err(Error::message("Unable to derive default for this type")),
+    };
+
+    if let Some(field) =
+        row_iter(row).find(|field| is_self_type(&bind.alias.value.name, &field.typ))
+    {
+        return Err(Error::message(format!(
+            "Unable to derive Default for `{}` since the field `{}` is recursive",
+            type_name,
+            field.name.declared_name()
+        )));
+    }
+
+    let default_record_expr = pos::spanned(
+        span,
+        Expr::Record {
+            typ: Type::hole(),
+            types: &mut [],
+            exprs: arena.alloc_extend(row_iter(row).map(|field| ExprField {
+                metadata: Default::default(),
+                name: pos::spanned(span, field.name.clone()),
+                value: Some(ident(span, symbols.simple_symbol("default"))),
+            })),
+            base: None,
+        },
+    );
+
+    let default_import = arena.generate_import(span, symbols, &[], &["default"], "std.default");
+    let expr = pos::spanned(
+        span,
+        Expr::Record {
+            typ: Type::hole(),
+            types: &mut [],
+            exprs: arena.alloc_extend(vec![ExprField {
+                metadata: Default::default(),
+                name: pos::spanned(span, symbols.simple_symbol("default")),
+                value: Some(default_record_expr),
+            }]),
+            base: None,
+        },
+    );
+
+    let self_type = bind.alias.value.self_type(&mut arena.clone());
+
+    Ok(ValueBinding {
+        name: pos::spanned(
+            span,
+            Pattern::Ident(TypedIdent::new(
+                symbols.simple_symbol(format!("default_{}", type_name)),
+            )),
+        ),
+        args: &mut [],
+        expr: pos::spanned(span, Expr::let_binding(arena, default_import, expr)),
+        metadata: Default::default(),
+        typ: Some(binding_type(arena, symbols, "Default", self_type, bind)),
+        resolved_type: Type::hole(),
+    })
+}
@@ -61,12 +61,18 @@ pub fn generate<'ast>(
 
     let comparison_expr = match **remove_forall(bind.alias.value.unresolved_type()) {
         Type::Variant(ref variants) => {
-            let catch_all_alternative = Alternative {
-                pattern: pos::spanned(
-                    span,
-                    Pattern::Ident(TypedIdent::new(symbols.simple_symbol("_"))),
-                ),
-                expr: ident(span, symbols.simple_symbol("False")),
+            // With a single constructor the alternatives generated below already match every
+            // pair of values so the catch all alternative would be unreachable
+            let catch_all_alternative = if row_iter(variants).len() > 1 {
+                Some(Alternative {
+                    pattern: pos::spanned(
+                        span,
+                        Pattern::Ident(TypedIdent::new(symbols.simple_symbol("_"))),
+                    ),
+                    expr: ident(span, symbols.simple_symbol("False")),
+                })
+            } else {
+                None
             };
 
             let alts: Vec<_> = row_iter(variants)
@@ -115,7 +121,7 @@ pub fn generate<'ast>(
                         expr,
                     }
                 })
-                .chain(Some(catch_all_alternative))
+                .chain(catch_all_alternative)
                 .collect();
             Expr::Match(matcher, arena.alloc_extend(alts))
         }
@@ -0,0 +1,776 @@
+//! Derives `Functor`, `Foldable` and `Traversable` over the last type parameter of a type
+use crate::base::{
+    ast::{
+        self, Alternative, Argument, AstType, Expr, ExprField, Lambda, Pattern, SpannedExpr,
+        SpannedPattern, TypeBinding, TypedIdent, ValueBinding,
+    },
+    kind::Kind,
+    pos::{self, BytePos, Span},
+    symbol::{Symbol, Symbols},
+    types::{
+        ctor_args, remove_forall, row_iter, walk_type, ArgType, Generic, KindedIdent, Type,
+        TypeContext,
+    },
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate_functor<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let mut derive = Derive::new(arena, symbols, bind, "Functor", "map_")?;
+    let span = derive.span;
+
+    let f = Symbol::from("f");
+    let x = Symbol::from("x");
+
+    let alts = derive
+        .shapes()?
+        .into_iter()
+        .map(|shape| {
+            let exprs = shape
+                .fields
+                .iter()
+                .map(|(typ, arg)| {
+                    let mapped = derive.map_field(symbols, &f, typ, ident(span, arg.clone()))?;
+                    Ok(mapped.unwrap_or_else(|| ident(span, arg.clone())))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(Alternative {
+                expr: derive.rebuild(&shape, exprs),
+                pattern: shape.pattern,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // (a -> b) -> T a -> T b
+    let b = derive.fresh_generic(symbols, "b");
+    let b_type = || arena.clone().generic(b.clone());
+    let typ = arena.clone().function(
+        vec![
+            arena.clone().function(Some(derive.param_type()), b_type()),
+            derive.self_type_with(derive.param_type()),
+        ],
+        derive.self_type_with(b_type()),
+    );
+
+    let map_binding = derive.recursive_binding(None, &[f], &x, alts, typ);
+    let map_import = arena.generate_import(span, symbols, &[], &["map"], "std.functor");
+    let export_expr = derive.record(symbols, vec![("map", ident(span, derive.recurse.clone()))]);
+
+    Ok(derive.instance(symbols, vec![map_import], vec![map_binding], export_expr))
+}
+
+pub fn generate_foldable<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let mut derive = Derive::new(arena, symbols, bind, "Foldable", "foldr_")?;
+    let span = derive.span;
+
+    let f = Symbol::from("f");
+    let z = Symbol::from("z");
+    let x = Symbol::from("x");
+    let b = derive.fresh_generic(symbols, "b");
+    let b_type = || arena.clone().generic(b.clone());
+
+    let mut fold_bindings = Vec::new();
+    let mut exports = Vec::new();
+    for &(name, left) in &[("foldr", false), ("foldl", true)] {
+        derive.recurse = symbols.simple_symbol(format!("{}_", name));
+
+        let alts = derive
+            .shapes()?
+            .into_iter()
+            .map(|shape| {
+                let mut fields: Vec<_> = shape.fields.iter().collect();
+                // `foldr` starts from the last field so that the first field is folded last
+                if !left {
+                    fields.reverse();
+                }
+                let expr =
+                    fields
+                        .into_iter()
+                        .try_fold(ident(span, z.clone()), |acc, (typ, arg)| {
+                            let value = ident(span, arg.clone());
+                            derive.fold_field(symbols, left, &f, typ, value, acc)
+                        })?;
+                Ok(Alternative {
+                    expr,
+                    pattern: shape.pattern,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // (a -> b -> b) -> b -> T a -> b
+        let folder = arena
+            .clone()
+            .function(in_order(left, derive.param_type(), b_type()), b_type());
+        let typ = arena.clone().function(
+            vec![folder, b_type(), derive.self_type_with(derive.param_type())],
+            b_type(),
+        );
+
+        fold_bindings.push(derive.recursive_binding(None, &[f.clone(), z.clone()], &x, alts, typ));
+        exports.push((name, ident(span, derive.recurse.clone())));
+    }
+
+    let foldable_import =
+        arena.generate_import(span, symbols, &[], &["foldr", "foldl"], "std.foldable");
+    let export_expr = derive.record(symbols, exports);
+
+    Ok(derive.instance(symbols, vec![foldable_import], fold_bindings, export_expr))
+}
+
+pub fn generate_traversable<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let mut derive = Derive::new(arena, symbols, bind, "Traversable", "traverse_")?;
+    let span = derive.span;
+
+    let f = Symbol::from("f");
+    let x = Symbol::from("x");
+    let app = Symbol::from("app");
+
+    let alts = derive
+        .shapes()?
+        .into_iter()
+        .map(|shape| {
+            let actions = shape
+                .fields
+                .iter()
+                .map(|(typ, arg)| derive.traverse_field(symbols, &f, typ, ident(span, arg.clone())))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            // wrap (\y_1 y_2 -> C y_1 y_2) <*> action_1 <*> action_2
+            let ys: Vec<_> = actions.iter().map(|_| derive.fresh("y")).collect();
+            let rebuilt =
+                derive.rebuild(&shape, ys.iter().map(|y| ident(span, y.clone())).collect());
+            let constructor = if ys.is_empty() {
+                rebuilt
+            } else {
+                arena.paren(span, derive.lambda(symbols, ys, rebuilt))
+            };
+            let expr = actions.into_iter().fold(
+                arena.app(span, symbols.simple_symbol("wrap"), Some(constructor)),
+                |acc, action| arena.infix(span, acc, symbols.simple_symbol("<*>"), action),
+            );
+            Ok(Alternative {
+                expr,
+                pattern: shape.pattern,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // [Applicative m] -> (a -> m b) -> T a -> m (T b)
+    let b = derive.fresh_generic(symbols, "b");
+    let m = derive.fresh_generic(symbols, "m");
+    let m_of = |typ| {
+        TypeContext::app(
+            &mut arena.clone(),
+            arena.clone().generic(m.clone()),
+            arena.clone().alloc_extend(Some(typ)),
+        )
+    };
+    let applicative = TypeContext::app(
+        &mut arena.clone(),
+        arena
+            .clone()
+            .ident(KindedIdent::new(symbols.simple_symbol("Applicative"))),
+        arena
+            .clone()
+            .alloc_extend(Some(arena.clone().generic(m.clone()))),
+    );
+    let typ = arena.clone().function_implicit(
+        Some(applicative),
+        arena.clone().function(
+            vec![
+                arena.clone().function(
+                    Some(derive.param_type()),
+                    m_of(arena.clone().generic(b.clone())),
+                ),
+                derive.self_type_with(derive.param_type()),
+            ],
+            m_of(derive.self_type_with(arena.clone().generic(b.clone()))),
+        ),
+    );
+
+    let traverse_binding = derive.recursive_binding(Some(&app), &[f], &x, alts, typ);
+
+    let applicative_import = arena.generate_import(
+        span,
+        symbols,
+        &["Applicative"],
+        &["wrap", "<*>"],
+        "std.applicative",
+    );
+    let traversable_import =
+        arena.generate_import(span, symbols, &[], &["traverse"], "std.traversable");
+
+    // traverse = \app -> traverse_ ?app
+    let traverse_expr = derive.lambda(
+        symbols,
+        vec![app.clone()],
+        pos::spanned(
+            span,
+            Expr::App {
+                func: arena.alloc(ident(span, derive.recurse.clone())),
+                implicit_args: arena.alloc_extend(Some(ident(span, app.clone()))),
+                args: &mut [],
+            },
+        ),
+    );
+    // The `Functor` and `Foldable` instances are found through implicit resolution so they do
+    // not need to be derived
+    let functor_instance = symbols.simple_symbol("functor_instance");
+    let foldable_instance = symbols.simple_symbol("foldable_instance");
+    let functor_binding =
+        implicit_instance(arena, symbols, span, functor_instance.clone(), "Functor");
+    let foldable_binding =
+        implicit_instance(arena, symbols, span, foldable_instance.clone(), "Foldable");
+    let export_expr = derive.record(
+        symbols,
+        vec![
+            ("functor", ident(span, functor_instance)),
+            ("foldable", ident(span, foldable_instance)),
+            ("traverse", traverse_expr),
+        ],
+    );
+
+    Ok(derive.instance(
+        symbols,
+        vec![
+            applicative_import,
+            traversable_import,
+            functor_binding,
+            foldable_binding,
+        ],
+        vec![traverse_binding],
+        export_expr,
+    ))
+}
+
+/// Orders the arguments of the function passed to `foldl` or `foldr`
+fn in_order<T>(left: bool, value: T, acc: T) -> Vec<T> {
+    if left {
+        vec![acc, value]
+    } else {
+        vec![value, acc]
+    }
+}
+
+/// A constructor or record together with the pattern which binds its fields
+struct Shape<'a, 'ast> {
+    pattern: SpannedPattern<'ast, Symbol>,
+    /// The constructor, or `None` if the shape is a record
+    constructor: Option<Symbol>,
+    field_names: Vec<Symbol>,
+    fields: Vec<(&'a AstType<'ast, Symbol>, Symbol)>,
+}
+
+struct Derive<'a, 'b, 'ast> {
+    arena: ast::ArenaRef<'b, 'ast, Symbol>,
+    span: Span<BytePos>,
+    bind: &'a TypeBinding<'ast, Symbol>,
+    derive_name: &'static str,
+    /// The type parameter which is mapped over
+    param: &'a Generic<Symbol>,
+    /// The recursive function which does the traversal
+    recurse: Symbol,
+    fresh_index: usize,
+}
+
+impl<'a, 'b, 'ast> Derive<'a, 'b, 'ast> {
+    fn new(
+        arena: ast::ArenaRef<'b, 'ast, Symbol>,
+        symbols: &mut Symbols,
+        bind: &'a TypeBinding<'ast, Symbol>,
+        derive_name: &'static str,
+        recurse: &str,
+    ) -> Result<Self, Error> {
+        let param = bind.alias.value.params().last().ok_or_else(|| {
+            Error::message(format!(
+                "Unable to derive {} for `{}` since it does not have any type parameters",
+                derive_name,
+                bind.alias.value.name.declared_name()
+            ))
+        })?;
+        Ok(Derive {
+            arena,
+            span: bind.name.span,
+            bind,
+            derive_name,
+            param,
+            recurse: symbols.simple_symbol(recurse),
+            fresh_index: 0,
+        })
+    }
+
+    fn unsupported(&self) -> Error {
+        Error::message(format!(
+            "Unable to derive {} for `{}` since `{}` is not only used as the last argument of \
+             a type or in the return type of a function",
+            self.derive_name,
+            self.bind.alias.value.name.declared_name(),
+            self.param.id.declared_name(),
+        ))
+    }
+
+    fn fresh(&mut self, name: &str) -> Symbol {
+        self.fresh_index += 1;
+        Symbol::from(format!("{}_{}", name, self.fresh_index))
+    }
+
+    /// Creates a type variable which does not shadow any of the type's parameters
+    fn fresh_generic(&self, symbols: &mut Symbols, name: &str) -> Generic<Symbol> {
+        let mut name = name.to_string();
+        while self
+            .bind
+            .alias
+            .value
+            .params()
+            .iter()
+            .any(|param| param.id.declared_name() == name)
+        {
+            name.push('_');
+        }
+        Generic::new(symbols.simple_symbol(name), Kind::hole())
+    }
+
+    fn occurs(&self, typ: &AstType<'ast, Symbol>) -> bool {
+        let mut occurs = false;
+        walk_type(typ, |typ: &AstType<'ast, Symbol>| match **typ {
+            Type::Generic(ref gen) if gen.id.name_eq(&self.param.id) => occurs = true,
+            _ => (),
+        });
+        occurs
+    }
+
+    /// Returns true if `typ` is the type being derived, applied to the same last parameter
+    fn is_recursive(&self, typ: &AstType<'ast, Symbol>) -> bool {
+        match **typ {
+            Type::App(_, ref args) if is_self_type(&self.bind.alias.value.name, typ) => {
+                match args.last().map(|arg| &**arg) {
+                    Some(Type::Generic(gen)) => gen.id.name_eq(&self.param.id),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Splits `F b c a` into `F b c` and `a`, requiring that the parameter only occurs in `a`
+    fn split_app<'t>(
+        &self,
+        typ: &'t AstType<'ast, Symbol>,
+    ) -> Result<&'t AstType<'ast, Symbol>, Error> {
+        match **typ {
+            Type::App(ref func, ref args) => match args.split_last() {
+                Some((last, init))
+                    if !self.occurs(func) && !init.iter().any(|arg| self.occurs(arg)) =>
+                {
+                    Ok(last)
+                }
+                _ => Err(self.unsupported()),
+            },
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn param_type(&self) -> AstType<'ast, Symbol> {
+        self.arena.clone().generic(self.param.clone())
+    }
+
+    /// The type being derived, with its last parameter replaced by `last`
+    fn self_type_with(&self, last: AstType<'ast, Symbol>) -> AstType<'ast, Symbol> {
+        let mut arena = self.arena.clone();
+        let params = self.bind.alias.value.params();
+        let args = params[..params.len() - 1]
+            .iter()
+            .map(|param| arena.generic(param.clone()))
+            .chain(Some(last))
+            .collect::<Vec<_>>();
+        let f = arena.ident(KindedIdent::new(self.bind.alias.value.name.clone()));
+        TypeContext::app(&mut arena, f, self.arena.alloc_extend(args))
+    }
+
+    fn shapes(&self) -> Result<Vec<Shape<'a, 'ast>>, Error> {
+        let span = self.span;
+        let arena = self.arena;
+        Ok(
+            match **remove_forall(self.bind.alias.value.unresolved_type()) {
+                Type::Variant(ref variants) => row_iter(variants)
+                    .map(|variant| {
+                        let fields: Vec<_> = ctor_args(&variant.typ)
+                            .enumerate()
+                            .map(|(i, typ)| (typ, Symbol::from(format!("arg_{}", i))))
+                            .collect();
+                        Shape {
+                            pattern: pos::spanned(
+                                span,
+                                Pattern::Constructor(
+                                    TypedIdent::new(variant.name.clone()),
+                                    arena.alloc_extend(fields.iter().map(|(_, arg)| {
+                                        pos::spanned(
+                                            span,
+                                            Pattern::Ident(TypedIdent::new(arg.clone())),
+                                        )
+                                    })),
+                                ),
+                            ),
+                            constructor: Some(variant.name.clone()),
+                            field_names: Vec::new(),
+                            fields,
+                        }
+                    })
+                    .collect(),
+                Type::Record(ref row) => {
+                    let fields: Vec<_> = row_iter(row)
+                        .enumerate()
+                        .map(|(i, field)| (&field.typ, Symbol::from(format!("arg_{}", i))))
+                        .collect();
+                    vec![Shape {
+                        pattern: arena.generate_record_pattern(
+                            span,
+                            row,
+                            fields.iter().map(|(_, arg)| TypedIdent::new(arg.clone())),
+                        ),
+                        constructor: None,
+                        field_names: row_iter(row).map(|field| field.name.clone()).collect(),
+                        fields,
+                    }]
+                }
+                _ => {
+                    return Err(Error::message(format!(
+                        "Unable to derive {} for this type",
+                        self.derive_name
+                    )))
+                }
+            },
+        )
+    }
+
+    /// Constructs a value with the same shape as `shape` from `exprs`
+    fn rebuild(
+        &self,
+        shape: &Shape<'a, 'ast>,
+        exprs: Vec<SpannedExpr<'ast, Symbol>>,
+    ) -> SpannedExpr<'ast, Symbol> {
+        let span = self.span;
+        match shape.constructor {
+            Some(ref constructor) => self.arena.app(span, constructor.clone(), exprs),
+            None => pos::spanned(
+                span,
+                Expr::Record {
+                    typ: Type::hole(),
+                    types: &mut [],
+                    exprs: self
+                        .arena
+                        .alloc_extend(shape.field_names.iter().zip(exprs).map(|(name, value)| {
+                            ExprField {
+                                metadata: Default::default(),
+                                name: pos::spanned(span, name.clone()),
+                                value: Some(value),
+                            }
+                        })),
+                    base: None,
+                },
+            ),
+        }
+    }
+
+    fn lambda(
+        &self,
+        symbols: &mut Symbols,
+        args: Vec<Symbol>,
+        body: SpannedExpr<'ast, Symbol>,
+    ) -> SpannedExpr<'ast, Symbol> {
+        let span = self.span;
+        pos::spanned(
+            span,
+            Expr::Lambda(Lambda {
+                args: self.arena.alloc_extend(
+                    args.into_iter()
+                        .map(|arg| Argument::explicit(pos::spanned(span, TypedIdent::new(arg)))),
+                ),
+                body: self.arena.alloc(body),
+                id: TypedIdent::new(symbols.simple_symbol("lambda")),
+            }),
+        )
+    }
+
+    fn call(
+        &self,
+        func: SpannedExpr<'ast, Symbol>,
+        args: Vec<SpannedExpr<'ast, Symbol>>,
+    ) -> SpannedExpr<'ast, Symbol> {
+        pos::spanned(
+            self.span,
+            Expr::App {
+                func: self.arena.alloc(func),
+                implicit_args: &mut [],
+                args: self.arena.alloc_extend(args),
+            },
+        )
+    }
+
+    /// Returns an expression which applies `f` to every `a` in `value`, or `None` if `typ` does
+    /// not contain `a`
+    fn map_field(
+        &mut self,
+        symbols: &mut Symbols,
+        f: &Symbol,
+        typ: &AstType<'ast, Symbol>,
+        value: SpannedExpr<'ast, Symbol>,
+    ) -> Result<Option<SpannedExpr<'ast, Symbol>>, Error> {
+        if !self.occurs(typ) {
+            return Ok(None);
+        }
+        let span = self.span;
+        Ok(Some(match **typ {
+            Type::Generic(_) => self.arena.app(span, f.clone(), Some(value)),
+            _ if self.is_recursive(typ) => self.arena.app(
+                span,
+                self.recurse.clone(),
+                vec![ident(span, f.clone()), value],
+            ),
+            // \y -> f_mapped (value y)
+            Type::Function(ArgType::Explicit, ref arg, ref ret) if !self.occurs(arg) => {
+                let y = self.fresh("y");
+                let result = self.call(value, vec![ident(span, y.clone())]);
+                let mapped = self
+                    .map_field(symbols, f, ret, result)?
+                    .expect("Parameter occurs in the return type");
+                self.lambda(symbols, vec![y], mapped)
+            }
+            // map (\y -> f_mapped y) value
+            Type::App(..) => {
+                let last = self.split_app(typ)?;
+                let y = self.fresh("y");
+                let mapped = self
+                    .map_field(symbols, f, last, ident(span, y.clone()))?
+                    .expect("Parameter occurs in the last argument");
+                let mapper = self.lambda(symbols, vec![y], mapped);
+                self.arena.app(
+                    span,
+                    symbols.simple_symbol("map"),
+                    vec![self.arena.paren(span, mapper), value],
+                )
+            }
+            _ => return Err(self.unsupported()),
+        }))
+    }
+
+    /// Returns an expression which folds every `a` in `value` into `acc`, from the left if
+    /// `left` is true
+    fn fold_field(
+        &mut self,
+        symbols: &mut Symbols,
+        left: bool,
+        f: &Symbol,
+        typ: &AstType<'ast, Symbol>,
+        value: SpannedExpr<'ast, Symbol>,
+        acc: SpannedExpr<'ast, Symbol>,
+    ) -> Result<SpannedExpr<'ast, Symbol>, Error> {
+        if !self.occurs(typ) {
+            return Ok(acc);
+        }
+        let span = self.span;
+        Ok(match **typ {
+            Type::Generic(_) => self.arena.app(span, f.clone(), in_order(left, value, acc)),
+            _ if self.is_recursive(typ) => self.arena.app(
+                span,
+                self.recurse.clone(),
+                vec![ident(span, f.clone()), acc, value],
+            ),
+            // foldr (\y acc -> f_folded y acc) acc value
+            Type::App(..) => {
+                let last = self.split_app(typ)?;
+                let y = self.fresh("y");
+                let inner_acc = self.fresh("acc");
+                let folded = self.fold_field(
+                    symbols,
+                    left,
+                    f,
+                    last,
+                    ident(span, y.clone()),
+                    ident(span, inner_acc.clone()),
+                )?;
+                let folder = self.lambda(symbols, in_order(left, y, inner_acc), folded);
+                self.arena.app(
+                    span,
+                    symbols.simple_symbol(if left { "foldl" } else { "foldr" }),
+                    vec![self.arena.paren(span, folder), acc, value],
+                )
+            }
+            _ => return Err(self.unsupported()),
+        })
+    }
+
+    /// Returns an action which applies `f` to every `a` in `value`
+    fn traverse_field(
+        &mut self,
+        symbols: &mut Symbols,
+        f: &Symbol,
+        typ: &AstType<'ast, Symbol>,
+        value: SpannedExpr<'ast, Symbol>,
+    ) -> Result<SpannedExpr<'ast, Symbol>, Error> {
+        let span = self.span;
+        if !self.occurs(typ) {
+            return Ok(self
+                .arena
+                .app(span, symbols.simple_symbol("wrap"), Some(value)));
+        }
+        Ok(match **typ {
+            Type::Generic(_) => self.arena.app(span, f.clone(), Some(value)),
+            _ if self.is_recursive(typ) => self.arena.app(
+                span,
+                self.recurse.clone(),
+                vec![ident(span, f.clone()), value],
+            ),
+            // traverse (\y -> f_traversed y) value
+            Type::App(..) => {
+                let last = self.split_app(typ)?;
+                let y = self.fresh("y");
+                let traversed = self.traverse_field(symbols, f, last, ident(span, y.clone()))?;
+                let traverser = self.lambda(symbols, vec![y], traversed);
+                self.arena.app(
+                    span,
+                    symbols.simple_symbol("traverse"),
+                    vec![self.arena.paren(span, traverser), value],
+                )
+            }
+            _ => return Err(self.unsupported()),
+        })
+    }
+
+    /// `rec let recurse ?implicit args x : typ = match x with alts`
+    fn recursive_binding(
+        &self,
+        implicit: Option<&Symbol>,
+        args: &[Symbol],
+        x: &Symbol,
+        alts: Vec<Alternative<'ast, Symbol>>,
+        typ: AstType<'ast, Symbol>,
+    ) -> ValueBinding<'ast, Symbol> {
+        let span = self.span;
+        let arg = |arg: &Symbol| pos::spanned(span, TypedIdent::new(arg.clone()));
+        ValueBinding {
+            name: pos::spanned(span, Pattern::Ident(TypedIdent::new(self.recurse.clone()))),
+            args: self.arena.alloc_extend(
+                implicit
+                    .map(|implicit| Argument::implicit(arg(implicit)))
+                    .into_iter()
+                    .chain(
+                        args.iter()
+                            .chain(Some(x))
+                            .map(|x| Argument::explicit(arg(x))),
+                    ),
+            ),
+            expr: pos::spanned(
+                span,
+                Expr::Match(
+                    self.arena.alloc(ident(span, x.clone())),
+                    self.arena.alloc_extend(alts),
+                ),
+            ),
+            metadata: Default::default(),
+            typ: Some(typ),
+            resolved_type: Type::hole(),
+        }
+    }
+
+    fn record(
+        &self,
+        symbols: &mut Symbols,
+        fields: Vec<(&str, SpannedExpr<'ast, Symbol>)>,
+    ) -> SpannedExpr<'ast, Symbol> {
+        let span = self.span;
+        pos::spanned(
+            span,
+            Expr::Record {
+                typ: Type::hole(),
+                types: &mut [],
+                exprs: self
+                    .arena
+                    .alloc_extend(fields.into_iter().map(|(name, value)| ExprField {
+                        metadata: Default::default(),
+                        name: pos::spanned(span, symbols.simple_symbol(name)),
+                        value: Some(value),
+                    })),
+                base: None,
+            },
+        )
+    }
+
+    /// `let imports in rec let bindings in export_expr`, bound to the instance
+    fn instance(
+        &self,
+        symbols: &mut Symbols,
+        imports: Vec<ValueBinding<'ast, Symbol>>,
+        bindings: Vec<ValueBinding<'ast, Symbol>>,
+        export_expr: SpannedExpr<'ast, Symbol>,
+    ) -> ValueBinding<'ast, Symbol> {
+        let span = self.span;
+        let body = pos::spanned(
+            span,
+            Expr::rec_let_bindings(self.arena, bindings, export_expr),
+        );
+        let expr = imports.into_iter().rev().fold(body, |expr, import| {
+            pos::spanned(span, Expr::let_binding(self.arena, import, expr))
+        });
+        self.instance_binding(symbols, expr)
+    }
+
+    /// Binds `expr` to `functor_T : Functor (T b c)` for a type `T b c a`
+    fn instance_binding(
+        &self,
+        symbols: &mut Symbols,
+        expr: SpannedExpr<'ast, Symbol>,
+    ) -> ValueBinding<'ast, Symbol> {
+        let span = self.span;
+        let mut arena = self.arena.clone();
+        let alias = &self.bind.alias.value;
+
+        let params = alias.params();
+        let args = params[..params.len() - 1]
+            .iter()
+            .map(|param| arena.generic(param.clone()))
+            .collect::<Vec<_>>();
+        let f = arena.ident(KindedIdent::new(alias.name.clone()));
+        let self_type = if args.is_empty() {
+            f
+        } else {
+            TypeContext::app(&mut arena, f, self.arena.alloc_extend(args))
+        };
+        let derive_type = arena.ident(KindedIdent::new(symbols.simple_symbol(self.derive_name)));
+        let typ = TypeContext::app(
+            &mut arena,
+            derive_type,
+            self.arena.alloc_extend(Some(self_type)),
+        );
+
+        ValueBinding {
+            name: pos::spanned(
+                span,
+                Pattern::Ident(TypedIdent::new(symbols.simple_symbol(format!(
+                    "{}_{}",
+                    self.derive_name.to_lowercase(),
+                    alias.name.declared_name()
+                )))),
+            ),
+            args: &mut [],
+            expr,
+            metadata: Default::default(),
+            typ: Some(typ),
+            resolved_type: Type::hole(),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::base::{
+    ast::{
+        self, Alternative, Argument, Expr, ExprField, Literal, Pattern, SpannedExpr, TypeBinding,
+        TypedIdent, ValueBinding,
+    },
+    pos,
+    symbol::{Symbol, Symbols},
+    types::{ctor_args, remove_forall, row_iter, Type, TypeContext},
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate<'ast>(
+    mut arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let span = bind.name.span;
+
+    let hash = TypedIdent::new(symbols.simple_symbol("hash_"));
+    let x = Symbol::from("x");
+    let hasher = Symbol::from("hasher");
+
+    // Feeds each field into the hasher, starting with `start`
+    let generate_hash_chain = |symbols: &mut Symbols,
+                               start: SpannedExpr<'ast, Symbol>,
+                               fields: Vec<(bool, Symbol)>|
+     -> SpannedExpr<'ast, Symbol> {
+        fields.into_iter().fold(start, |acc, (self_type, field)| {
+            let hash_symbol = if self_type {
+                hash.name.clone()
+            } else {
+                symbols.simple_symbol("hash_into")
+            };
+            arena.app(
+                span,
+                hash_symbol,
+                vec![ident(span, field), arena.paren(span, acc)],
+            )
+        })
+    };
+
+    let alts = match **remove_forall(bind.alias.value.unresolved_type()) {
+        Type::Variant(ref variants) => row_iter(variants)
+            .enumerate()
+            .map(|(tag, variant)| {
+                let fields: Vec<_> = ctor_args(&variant.typ)
+                    .map(|field| {
+                        (
+                            is_self_type(&bind.alias.value.name, field),
+                            Symbol::from("arg"),
+                        )
+                    })
+                    .collect();
+
+                let pattern = pos::spanned(
+                    span,
+                    Pattern::Constructor(
+                        TypedIdent::new(variant.name.clone()),
+                        arena.alloc_extend(fields.iter().map(|(_, field)| {
+                            pos::spanned(span, Pattern::Ident(TypedIdent::new(field.clone())))
+                        })),
+                    ),
+                );
+
+                // Write the index of the constructor first so that `A 1` and `B 1` hash differently
+                let write_tag = arena.app(
+                    span,
+                    symbols.simple_symbol("write_int"),
+                    vec![
+                        pos::spanned(span, Expr::Literal(Literal::Int(tag as i64))),
+                        ident(span, hasher.clone()),
+                    ],
+                );
+
+                Alternative {
+                    pattern,
+                    expr: generate_hash_chain(symbols, write_tag, fields),
+                }
+            })
+            .collect(),
+        Type::Record(ref row) => {
+            let fields: Vec<_> = row_iter(row)
+                .map(|field| {
+                    (
+                        is_self_type(&bind.alias.value.name, &field.typ),
+                        Symbol::from(field.name.declared_name()),
+                    )
+                })
+                .collect();
+
+            let pattern = arena.generate_record_pattern(
+                span,
+                row,
+                fields
+                    .iter()
+                    .map(|(_, field)| TypedIdent::new(field.clone())),
+            );
+            let expr = generate_hash_chain(symbols, ident(span, hasher.clone()), fields);
+            vec![Alternative { pattern, expr }]
+        }
+        _ => return Err(Error::message("Unable to derive hash for this type")),
+    };
+
+    let mut self_type = {
+        let mut arena = arena;
+        move || bind.alias.value.self_type(&mut arena)
+    };
+
+    let hash_record_expr =
+        Expr::rec_let_bindings(
+            arena,
+            vec![ValueBinding {
+                name: pos::spanned(span, Pattern::Ident(hash.clone())),
+                args: arena.alloc_extend([x.clone(), hasher].iter().map(|arg| {
+                    Argument::explicit(pos::spanned(span, TypedIdent::new(arg.clone())))
+                })),
+                expr: pos::spanned(
+                    span,
+                    Expr::Match(arena.alloc(ident(span, x)), arena.alloc_extend(alts)),
+                ),
+                metadata: Default::default(),
+                typ: Some(
+                    arena
+                        .clone()
+                        .function(vec![self_type(), arena.hole()], arena.hole()),
+                ),
+                resolved_type: Type::hole(),
+            }],
+            pos::spanned(
+                span,
+                Expr::Record {
+                    typ: Type::hole(),
+                    types: &mut [],
+                    exprs: arena.alloc_extend(vec![ExprField {
+                        metadata: Default::default(),
+                        name: pos::spanned(span, symbols.simple_symbol("hash")),
+                        value: Some(ident(span, hash.name.clone())),
+                    }]),
+                    base: None,
+                },
+            ),
+        );
+
+    let hash_import =
+        arena.generate_import(span, symbols, &[], &["write_int", "hash_into"], "std.hash");
+
+    Ok(ValueBinding {
+        name: pos::spanned(
+            span,
+            Pattern::Ident(TypedIdent::new(symbols.simple_symbol(format!(
+                "hash_{}",
+                bind.alias.value.name.declared_name()
+            )))),
+        ),
+        args: &mut [],
+        expr: pos::spanned(
+            span,
+            Expr::let_binding(arena, hash_import, pos::spanned(span, hash_record_expr)),
+        ),
+        metadata: Default::default(),
+        typ: Some(binding_type(arena, symbols, "Hash", self_type(), bind)),
+        resolved_type: Type::hole(),
+    })
+}
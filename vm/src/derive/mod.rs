@@ -11,8 +11,13 @@ use crate::base::{
 
 use crate::macros::Error;
 
+mod arbitrary;
+mod default;
 mod deserialize;
 mod eq;
+mod functor;
+mod hash;
+mod ord;
 mod serialize;
 mod show;
 
@@ -30,8 +35,15 @@ pub fn generate<'ast>(
                 Ok(match arg {
                     "Eq" => eq::generate(arena, symbols, bind),
                     "Show" => show::generate(arena, symbols, bind),
+                    "Ord" => ord::generate(arena, symbols, bind),
+                    "Hash" => hash::generate(arena, symbols, bind),
+                    "Default" => default::generate(arena, symbols, bind),
+                    "Functor" => functor::generate_functor(arena, symbols, bind),
+                    "Foldable" => functor::generate_foldable(arena, symbols, bind),
+                    "Traversable" => functor::generate_traversable(arena, symbols, bind),
                     "Deserialize" => deserialize::generate(arena, symbols, bind),
                     "Serialize" => serialize::generate(arena, symbols, bind),
+                    "Arbitrary" => arbitrary::generate(arena, symbols, bind),
                     _ => {
                         return Err(Error::message(format!(
                             "`{}` is not a type that can be derived",
@@ -283,6 +295,45 @@ fn is_self_type(self_: &Symbol, typ: &AstType<Symbol>) -> bool {
     }
 }
 
+/// Generates `let name ?instance : [Class a] -> Class a = instance`. Referring to `name` lets
+/// implicit resolution find the `Class` instance of a type, regardless of whether that instance
+/// was derived or written by hand.
+fn implicit_instance<'ast>(
+    arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    span: Span<BytePos>,
+    name: Symbol,
+    class: &str,
+) -> ValueBinding<'ast, Symbol> {
+    let instance = Symbol::from("instance");
+    let a = Symbol::from("a");
+    let class_symbol = symbols.simple_symbol(class);
+    let class_type = || {
+        TypeContext::app(
+            &mut arena.clone(),
+            arena.clone().ident(KindedIdent::new(class_symbol.clone())),
+            arena
+                .clone()
+                .alloc_extend(Some(arena.clone().generic(a.clone()))),
+        )
+    };
+    ValueBinding {
+        name: pos::spanned(span, Pattern::Ident(TypedIdent::new(name))),
+        args: arena.alloc_extend(Some(Argument::implicit(pos::spanned(
+            span,
+            TypedIdent::new(instance.clone()),
+        )))),
+        expr: ident(span, instance),
+        metadata: Default::default(),
+        typ: Some(
+            arena
+                .clone()
+                .function_implicit(Some(class_type()), class_type()),
+        ),
+        resolved_type: Type::hole(),
+    }
+}
+
 fn binding_type<'ast>(
     arena: ast::ArenaRef<'_, 'ast, Symbol>,
     symbols: &mut Symbols,
@@ -0,0 +1,279 @@
+use crate::base::{
+    ast::{
+        self, Alternative, Argument, Expr, ExprField, Pattern, SpannedExpr, SpannedPattern,
+        TypeBinding, TypedIdent, ValueBinding,
+    },
+    pos,
+    symbol::{Symbol, Symbols},
+    types::{ctor_args, remove_forall, row_iter, Type, TypeContext},
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate<'ast>(
+    mut arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let span = bind.name.span;
+
+    let compare = TypedIdent::new(symbols.simple_symbol("compare_"));
+    let l = Symbol::from("l");
+    let r = Symbol::from("r");
+
+    let matcher = arena.alloc(pos::spanned(
+        span,
+        Expr::Tuple {
+            typ: Type::hole(),
+            elems: arena.alloc_extend(vec![ident(span, l.clone()), ident(span, r.clone())]),
+        },
+    ));
+
+    let tuple_pattern = |l_pattern, r_pattern| {
+        pos::spanned(
+            span,
+            Pattern::Tuple {
+                typ: Type::hole(),
+                elems: arena.alloc_extend(vec![l_pattern, r_pattern]),
+            },
+        )
+    };
+
+    // Compares the fields in order, returning the first ordering which is not `EQ`
+    let generate_compare_chain =
+        |symbols: &mut Symbols, fields: Vec<(bool, Symbol, Symbol)>| -> SpannedExpr<'ast, Symbol> {
+            fields
+                .into_iter()
+                .rev()
+                .fold(None, |rest, (self_type, l, r)| {
+                    let compare_symbol = if self_type {
+                        compare.name.clone()
+                    } else {
+                        symbols.simple_symbol("compare")
+                    };
+                    let comparison =
+                        arena.app(span, compare_symbol, vec![ident(span, l), ident(span, r)]);
+
+                    Some(match rest {
+                        Some(rest) => {
+                            let ordering = Symbol::from("ordering");
+                            pos::spanned(
+                                span,
+                                Expr::Match(
+                                    arena.alloc(comparison),
+                                    arena.alloc_extend(vec![
+                                        Alternative {
+                                            pattern: pos::spanned(
+                                                span,
+                                                Pattern::Constructor(
+                                                    TypedIdent::new(symbols.simple_symbol("EQ")),
+                                                    &mut [],
+                                                ),
+                                            ),
+                                            expr: rest,
+                                        },
+                                        Alternative {
+                                            pattern: pos::spanned(
+                                                span,
+                                                Pattern::Ident(TypedIdent::new(ordering.clone())),
+                                            ),
+                                            expr: ident(span, ordering),
+                                        },
+                                    ]),
+                                ),
+                            )
+                        }
+                        None => comparison,
+                    })
+                })
+                .unwrap_or_else(|| ident(span, symbols.simple_symbol("EQ")))
+        };
+
+    let comparison_expr = match **remove_forall(bind.alias.value.unresolved_type()) {
+        Type::Variant(ref variants) => {
+            let ctor_pattern = |name: &Symbol, args: Vec<SpannedPattern<'ast, Symbol>>| {
+                pos::spanned(
+                    span,
+                    Pattern::Constructor(TypedIdent::new(name.clone()), arena.alloc_extend(args)),
+                )
+            };
+            let wildcard = |symbols: &mut Symbols| -> SpannedPattern<'ast, Symbol> {
+                pos::spanned(
+                    span,
+                    Pattern::Ident(TypedIdent::new(symbols.simple_symbol("_"))),
+                )
+            };
+
+            let mut alts: Vec<_> = row_iter(variants)
+                .map(|variant| {
+                    let fields: Vec<_> = ctor_args(&variant.typ)
+                        .map(|field| {
+                            (
+                                is_self_type(&bind.alias.value.name, field),
+                                Symbol::from("arg_l"),
+                                Symbol::from("arg_r"),
+                            )
+                        })
+                        .collect();
+
+                    let pattern_args =
+                        |symbols: Vec<&Symbol>| -> Vec<SpannedPattern<'ast, Symbol>> {
+                            symbols
+                                .into_iter()
+                                .map(|symbol| {
+                                    pos::spanned(
+                                        span,
+                                        Pattern::Ident(TypedIdent::new(symbol.clone())),
+                                    )
+                                })
+                                .collect()
+                        };
+                    let pattern = tuple_pattern(
+                        ctor_pattern(
+                            &variant.name,
+                            pattern_args(fields.iter().map(|f| &f.1).collect()),
+                        ),
+                        ctor_pattern(
+                            &variant.name,
+                            pattern_args(fields.iter().map(|f| &f.2).collect()),
+                        ),
+                    );
+
+                    Alternative {
+                        pattern,
+                        expr: generate_compare_chain(symbols, fields),
+                    }
+                })
+                .collect();
+
+            // Constructors which are declared first are less than those which are declared later
+            let variant_count = row_iter(variants).len();
+            for variant in row_iter(variants).take(variant_count.saturating_sub(1)) {
+                let wildcards = |symbols: &mut Symbols| -> Vec<SpannedPattern<'ast, Symbol>> {
+                    ctor_args(&variant.typ).map(|_| wildcard(symbols)).collect()
+                };
+                let lesser = tuple_pattern(
+                    ctor_pattern(&variant.name, wildcards(symbols)),
+                    wildcard(symbols),
+                );
+                let greater = tuple_pattern(
+                    wildcard(symbols),
+                    ctor_pattern(&variant.name, wildcards(symbols)),
+                );
+                alts.push(Alternative {
+                    pattern: lesser,
+                    expr: ident(span, symbols.simple_symbol("LT")),
+                });
+                alts.push(Alternative {
+                    pattern: greater,
+                    expr: ident(span, symbols.simple_symbol("GT")),
+                });
+            }
+
+            Expr::Match(matcher, arena.alloc_extend(alts))
+        }
+        Type::Record(ref row) => {
+            let fields: Vec<_> = row_iter(row)
+                .map(|field| {
+                    (
+                        is_self_type(&bind.alias.value.name, &field.typ),
+                        Symbol::from(format!("{}_l", field.name.declared_name())),
+                        Symbol::from(format!("{}_r", field.name.declared_name())),
+                    )
+                })
+                .collect();
+
+            let pattern = tuple_pattern(
+                arena.generate_record_pattern(
+                    span,
+                    row,
+                    fields.iter().map(|f| TypedIdent::new(f.1.clone())),
+                ),
+                arena.generate_record_pattern(
+                    span,
+                    row,
+                    fields.iter().map(|f| TypedIdent::new(f.2.clone())),
+                ),
+            );
+            let expr = generate_compare_chain(symbols, fields);
+            Expr::Match(
+                matcher,
+                arena.alloc_extend(vec![Alternative { pattern, expr }]),
+            )
+        }
+        _ => return Err(Error::message("Unable to derive ord for this type")),
+    };
+
+    let mut self_type = {
+        let mut arena = arena;
+        move || bind.alias.value.self_type(&mut arena)
+    };
+
+    let type_name = bind.alias.value.name.declared_name();
+    // The `Eq` instance is found through implicit resolution so it does not need to be derived
+    let eq_instance = symbols.simple_symbol("eq_instance");
+    let ord_record_expr =
+        Expr::rec_let_bindings(
+            arena,
+            vec![ValueBinding {
+                name: pos::spanned(span, Pattern::Ident(compare.clone())),
+                args: arena.alloc_extend([l, r].iter().map(|arg| {
+                    Argument::explicit(pos::spanned(span, TypedIdent::new(arg.clone())))
+                })),
+                expr: pos::spanned(span, comparison_expr),
+                metadata: Default::default(),
+                typ: Some(
+                    arena
+                        .clone()
+                        .function(vec![self_type(), self_type()], arena.hole()),
+                ),
+                resolved_type: Type::hole(),
+            }],
+            pos::spanned(
+                span,
+                Expr::Record {
+                    typ: Type::hole(),
+                    types: &mut [],
+                    exprs: arena.alloc_extend(vec![
+                        ExprField {
+                            metadata: Default::default(),
+                            name: pos::spanned(span, symbols.simple_symbol("eq")),
+                            value: Some(ident(span, eq_instance.clone())),
+                        },
+                        ExprField {
+                            metadata: Default::default(),
+                            name: pos::spanned(span, symbols.simple_symbol("compare")),
+                            value: Some(ident(span, compare.name.clone())),
+                        },
+                    ]),
+                    base: None,
+                },
+            ),
+        );
+
+    let ordering_import = arena.generate_import(span, symbols, &["Ordering"], &[], "std.types");
+    let compare_import = arena.generate_import(span, symbols, &[], &["compare"], "std.cmp");
+    let eq_binding = implicit_instance(arena, symbols, span, eq_instance, "Eq");
+    let expr = vec![ordering_import, compare_import, eq_binding]
+        .into_iter()
+        .rev()
+        .fold(pos::spanned(span, ord_record_expr), |expr, binding| {
+            pos::spanned(span, Expr::let_binding(arena, binding, expr))
+        });
+
+    Ok(ValueBinding {
+        name: pos::spanned(
+            span,
+            Pattern::Ident(TypedIdent::new(
+                symbols.simple_symbol(format!("ord_{}", type_name)),
+            )),
+        ),
+        args: &mut [],
+        expr,
+        metadata: Default::default(),
+        typ: Some(binding_type(arena, symbols, "Ord", self_type(), bind)),
+        resolved_type: Type::hole(),
+    })
+}
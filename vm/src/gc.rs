@@ -1,10 +1,11 @@
 use std::{
     any::{Any, TypeId},
     cell::Cell,
-    cmp::Ordering,
+    cmp::{self, Ordering},
     collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     fmt,
     hash::{Hash, Hasher},
+    iter,
     marker::PhantomData,
     mem,
     ops::{Deref, DerefMut},
@@ -19,6 +20,7 @@ use crate::{
 };
 
 pub mod mutex;
+pub mod snapshot;
 
 #[doc(hidden)]
 #[macro_export]
@@ -172,7 +174,7 @@ impl<'s> WriteOnly<'s, str> {
     }
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde_derive", derive(Deserialize, Serialize))]
 pub struct Generation(i32);
 
@@ -205,7 +207,26 @@ impl Generation {
     }
 }
 
-/// A mark and sweep garbage collector.
+/// Size of the chunks which the nursery bump allocates young values in
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Values larger than this are allocated individually instead of in a chunk (they still start out
+/// as young values)
+const MAX_CHUNK_VALUE_SIZE: usize = CHUNK_SIZE / 8;
+
+/// How many bytes of young values that may be allocated before a minor collection is run
+const DEFAULT_NURSERY_SIZE: usize = 8 * CHUNK_SIZE;
+
+/// A generational mark and sweep garbage collector.
+///
+/// New values are allocated in the nursery where most values are bump allocated into large chunks.
+/// When the nursery fills up a minor collection is run which only traverses the young values,
+/// dropping the unreachable ones and promoting the rest to the old generation. Values are never
+/// moved, a chunk which contains promoted values is instead kept alive until all of them have been
+/// freed by a full collection.
+///
+/// Minor collections rely on old values never referring to young values. Any code which mutates a
+/// value after it has been allocated must therefore call `write_barrier` or `tenure`.
 #[derive(Debug)]
 #[cfg_attr(feature = "serde_derive", derive(DeserializeState, SerializeState))]
 #[cfg_attr(
@@ -220,12 +241,26 @@ impl Generation {
     serde(serialize_state = "crate::serialization::SeSeed")
 )]
 pub struct Gc {
-    /// Linked list of all objects allocted by this garbage collector.
+    /// Linked list of the old objects which were too large to be allocated in a chunk.
     #[cfg_attr(feature = "serde_derive", serde(skip))]
     values: Option<AllocPtr>,
+    /// Young values which have not yet survived a collection
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    nursery: Nursery,
+    /// Determines how `mark` treats values during the current traversal
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    mark_mode: MarkMode,
+    /// Records how values are reached while a snapshot is taken
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    retention: Option<Box<snapshot::Retention>>,
     /// How many bytes which is currently allocated
     allocated_memory: usize,
-    /// How many bytes this garbage collector can allocate before a collection is run
+    /// How many bytes which have been allocated in total, including values which have since been
+    /// collected
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    total_allocated: usize,
+    /// How many bytes of old values this garbage collector can hold before a full collection is
+    /// run
     collect_limit: usize,
     /// The maximum number of bytes this garbage collector may contain
     memory_limit: usize,
@@ -259,8 +294,9 @@ pub struct Gc {
 
 impl Drop for Gc {
     fn drop(&mut self) {
-        if let Some(values) = self.values.take() {
-            mem::forget(values);
+        if self.values.is_some() || !self.nursery.is_empty() {
+            mem::forget(self.values.take());
+            self.nursery.leak();
             if std::thread::panicking() {
                 eprintln!("Gc values were not dropped explicitly. Leaking the allocatons!");
             } else {
@@ -330,16 +366,48 @@ where
 #[derive(Debug)]
 struct TypeInfo {
     drop: unsafe fn(*mut ()),
+    type_name: &'static str,
     generation: Generation,
     tag: Option<InternedStr>,
     fields: FnvMap<InternedStr, VmIndex>,
     fields_key: Arc<[InternedStr]>,
 }
 
+/// Which generation an allocated value belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Age {
+    /// The value has been allocated since the last collection
+    Young,
+    /// The value has survived a collection (or been promoted by a write barrier)
+    Old,
+    /// The value has been dropped but its memory is still part of a nursery chunk
+    Freed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MarkMode {
+    /// Marks every value of this generation
+    Full,
+    /// Only marks young values, old values can only refer to other old values so there is no need
+    /// to traverse them
+    Young,
+    /// Promotes young values to the old generation instead of marking them
+    Tenure,
+    /// Records how each value is reached without marking it
+    Snapshot,
+}
+
+impl Default for MarkMode {
+    fn default() -> MarkMode {
+        MarkMode::Full
+    }
+}
+
 #[derive(Debug)]
 struct GcHeader {
     next: Option<AllocPtr>,
     marked: Cell<bool>,
+    age: Cell<Age>,
     value_size: usize,
     type_info: *const TypeInfo,
 }
@@ -356,15 +424,7 @@ impl AllocPtr {
             unsafe {
                 let alloc_size = GcHeader::value_offset() + value_size;
                 let ptr = allocate(alloc_size) as *mut GcHeader;
-                ptr::write(
-                    ptr,
-                    GcHeader {
-                        next: None,
-                        type_info: type_info,
-                        value_size: value_size,
-                        marked: Cell::new(false),
-                    },
-                );
+                ptr::write(ptr, GcHeader::new(type_info, value_size));
                 AllocPtr { ptr }
             }
         }
@@ -373,7 +433,7 @@ impl AllocPtr {
     }
 
     fn size(&self) -> usize {
-        GcHeader::value_offset() + self.value_size
+        self.deref().size()
     }
 }
 
@@ -414,6 +474,16 @@ impl DerefMut for AllocPtr {
 }
 
 impl GcHeader {
+    fn new(type_info: *const TypeInfo, value_size: usize) -> GcHeader {
+        GcHeader {
+            next: None,
+            type_info: type_info,
+            value_size: value_size,
+            marked: Cell::new(false),
+            age: Cell::new(Age::Young),
+        }
+    }
+
     fn value(&mut self) -> *mut () {
         unsafe {
             let ptr: *mut GcHeader = self;
@@ -430,6 +500,212 @@ impl GcHeader {
     fn generation(&self) -> Generation {
         unsafe { (*self.type_info).generation }
     }
+
+    fn size(&self) -> usize {
+        GcHeader::value_offset() + self.value_size
+    }
+
+    /// The number of bytes this value occupies in a chunk
+    fn chunk_size(&self) -> usize {
+        let align = mem::align_of::<f64>();
+        (self.size() + align - 1) / align * align
+    }
+
+    /// Returns true if the value was not reached by the last traversal and can be freed. Old
+    /// values are only freed by a full collection.
+    fn is_garbage(&self, full: bool) -> bool {
+        !self.marked.get() && (full || self.age.get() == Age::Young)
+    }
+
+    /// Clears the mark of a surviving value and moves it to the old generation
+    fn promote(&self) {
+        self.marked.set(false);
+        self.age.set(Age::Old);
+    }
+
+    unsafe fn drop_value(&mut self) {
+        ((*self.type_info).drop)(self.value());
+    }
+}
+
+/// A block of memory which young values are bump allocated in
+#[derive(Debug)]
+struct Chunk {
+    ptr: *mut u8,
+    /// How many bytes at the start of the chunk that are in use
+    used: usize,
+    /// The number of values in the chunk which have not been freed
+    live: usize,
+}
+
+unsafe impl Send for Chunk {}
+
+impl Chunk {
+    fn new() -> Chunk {
+        Chunk {
+            ptr: unsafe { allocate(CHUNK_SIZE) },
+            used: 0,
+            live: 0,
+        }
+    }
+
+    /// Allocates `size` bytes for a value, returning `None` if the chunk is full
+    fn alloc(&mut self, size: usize) -> Option<*mut GcHeader> {
+        if self.used + size > CHUNK_SIZE {
+            return None;
+        }
+        let ptr = unsafe { self.ptr.add(self.used) as *mut GcHeader };
+        self.used += size;
+        self.live += 1;
+        Some(ptr)
+    }
+
+    /// Iterates over the headers of all values in the chunk, including freed values
+    fn headers(&self) -> impl Iterator<Item = *mut GcHeader> + '_ {
+        let mut offset = 0;
+        iter::from_fn(move || {
+            if offset < self.used {
+                unsafe {
+                    let header = self.ptr.add(offset) as *mut GcHeader;
+                    offset += (*header).chunk_size();
+                    Some(header)
+                }
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Drops all unreachable values and promotes the rest. Returns the number of freed bytes.
+    unsafe fn sweep(&mut self, full: bool) -> usize {
+        let mut freed = 0;
+        let mut live = 0;
+        for header in self.headers() {
+            let header = &mut *header;
+            match header.age.get() {
+                Age::Freed => (),
+                _ if header.is_garbage(full) => {
+                    debug!("FREE: {:?}", header);
+                    header.drop_value();
+                    header.age.set(Age::Freed);
+                    freed += header.size();
+                }
+                _ => {
+                    header.promote();
+                    live += 1;
+                }
+            }
+        }
+        self.live = live;
+        freed
+    }
+
+    /// Drops all values which have not already been freed
+    unsafe fn clear(&mut self) {
+        for header in self.headers() {
+            let header = &mut *header;
+            if header.age.get() != Age::Freed {
+                header.drop_value();
+                header.age.set(Age::Freed);
+            }
+        }
+        self.used = 0;
+        self.live = 0;
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe { deallocate(self.ptr, CHUNK_SIZE) }
+    }
+}
+
+/// The young generation of a `Gc`
+#[derive(Debug)]
+struct Nursery {
+    /// Chunks which young values are allocated in, new values are bump allocated in the last one
+    chunks: Vec<Chunk>,
+    /// Chunks which contain values that survived a minor collection, no new values are allocated
+    /// in these
+    tenured: Vec<Chunk>,
+    /// Empty chunks which can be reused
+    free: Vec<Chunk>,
+    /// Linked list of young values which were too large to be allocated in a chunk
+    large_values: Option<AllocPtr>,
+    /// How many bytes of young values which are currently allocated
+    allocated_memory: usize,
+    /// How many bytes of young values can be allocated before a minor collection is run
+    limit: usize,
+}
+
+impl Default for Nursery {
+    fn default() -> Nursery {
+        Nursery {
+            chunks: Vec::new(),
+            tenured: Vec::new(),
+            free: Vec::new(),
+            large_values: None,
+            allocated_memory: 0,
+            limit: DEFAULT_NURSERY_SIZE,
+        }
+    }
+}
+
+impl Nursery {
+    fn alloc(&mut self, header: GcHeader) -> *mut GcHeader {
+        let size = header.chunk_size();
+        let ptr = match self.chunks.last_mut().and_then(|chunk| chunk.alloc(size)) {
+            Some(ptr) => ptr,
+            None => {
+                let mut chunk = self.free.pop().unwrap_or_else(Chunk::new);
+                let ptr = chunk
+                    .alloc(size)
+                    .expect("Value should fit in an empty chunk");
+                self.chunks.push(chunk);
+                ptr
+            }
+        };
+        unsafe { ptr::write(ptr, header) };
+        ptr
+    }
+
+    fn is_empty(&self) -> bool {
+        self.large_values.is_none() && self.chunks.is_empty() && self.tenured.is_empty()
+    }
+
+    /// Sweeps the chunks in `chunks`, moving the ones which still contain values to `tenured`.
+    /// Returns the number of freed bytes.
+    unsafe fn sweep_chunks(&mut self, chunks: Vec<Chunk>, full: bool) -> usize {
+        let mut freed = 0;
+        for mut chunk in chunks {
+            freed += chunk.sweep(full);
+            if chunk.live == 0 {
+                chunk.used = 0;
+                // Keep enough empty chunks around to fill the nursery again
+                if self.free.len() * CHUNK_SIZE < self.limit {
+                    self.free.push(chunk);
+                }
+            } else {
+                self.tenured.push(chunk);
+            }
+        }
+        freed
+    }
+
+    unsafe fn clear(&mut self) {
+        self.large_values = None;
+        for mut chunk in self.chunks.drain(..).chain(self.tenured.drain(..)) {
+            chunk.clear();
+        }
+        self.allocated_memory = 0;
+    }
+
+    /// Forgets all values without dropping them
+    fn leak(&mut self) {
+        mem::forget(self.large_values.take());
+        mem::forget(mem::replace(&mut self.chunks, Vec::new()));
+        mem::forget(mem::replace(&mut self.tenured, Vec::new()));
+    }
 }
 
 pub struct OwnedPtr<T: ?Sized>(NonNull<T>);
@@ -1042,17 +1318,37 @@ where
     fn trace(&self, gc: &mut Gc) {
         if !gc.mark(self) {
             // Continue traversing if this ptr was not already marked
-            (**self).trace(gc);
+            if gc.mark_mode == MarkMode::Snapshot {
+                gc.trace_retained(self);
+            } else {
+                (**self).trace(gc);
+            }
         }
     }
 }
 
+fn list_headers(values: &Option<AllocPtr>) -> impl Iterator<Item = &GcHeader> {
+    iter::successors(values.as_deref(), |header| header.next.as_deref())
+}
+
+/// Caps the nursery so that a minor collection always runs before a small memory limit is hit
+fn nursery_limit(memory_limit: usize) -> usize {
+    cmp::min(DEFAULT_NURSERY_SIZE, memory_limit / 2)
+}
+
 impl Gc {
     /// Constructs a new garbage collector
     pub fn new(generation: Generation, memory_limit: usize) -> Gc {
         Gc {
             values: None,
+            nursery: Nursery {
+                limit: nursery_limit(memory_limit),
+                ..Nursery::default()
+            },
+            mark_mode: MarkMode::Full,
+            retention: None,
             allocated_memory: 0,
+            total_allocated: 0,
             collect_limit: 100,
             memory_limit: memory_limit,
             type_infos: FnvMap::default(),
@@ -1066,8 +1362,15 @@ impl Gc {
         self.allocated_memory
     }
 
+    /// Returns the number of bytes which have been allocated since the collector was created.
+    /// Unlike `allocated_memory` this never decreases when values are collected.
+    pub fn total_allocated(&self) -> usize {
+        self.total_allocated
+    }
+
     pub fn set_memory_limit(&mut self, memory_limit: usize) {
         self.memory_limit = memory_limit;
+        self.nursery.limit = nursery_limit(memory_limit);
     }
 
     pub fn generation(&self) -> Generation {
@@ -1150,6 +1453,7 @@ impl Gc {
         tag: Option<&InternedStr>,
         fields: Option<&[InternedStr]>,
         type_id: TypeId,
+        type_name: &'static str,
         drop: unsafe fn(*mut ()),
     ) -> *const TypeInfo {
         match fields {
@@ -1171,6 +1475,7 @@ impl Gc {
                         .entry(owned_fields.clone())
                         .or_insert(Box::new(TypeInfo {
                             drop,
+                            type_name,
                             generation: self.generation,
                             tag: unsafe { tag.map(|tag| tag.clone_unrooted()) },
                             fields: unsafe {
@@ -1189,6 +1494,7 @@ impl Gc {
                     Entry::Occupied(entry) => &**entry.get(),
                     Entry::Vacant(entry) => &**entry.insert(Box::new(TypeInfo {
                         drop,
+                        type_name,
                         generation: self.generation,
                         tag: Some(unsafe { tag.clone_unrooted() }),
                         fields: FnvMap::default(),
@@ -1199,6 +1505,7 @@ impl Gc {
                     Entry::Occupied(entry) => &**entry.get(),
                     Entry::Vacant(entry) => &**entry.insert(Box::new(TypeInfo {
                         drop,
+                        type_name,
                         generation: self.generation,
                         tag: None,
                         fields: FnvMap::default(),
@@ -1222,19 +1529,38 @@ impl Gc {
             def.tag(),
             def.fields(),
             TypeId::of::<D::Value>(),
+            std::any::type_name::<D::Value>(),
             drop::<D::Value>,
         );
 
-        let mut ptr = AllocPtr::new::<D::Value>(type_info, size);
-        ptr.next = self.values.take();
-        self.allocated_memory += ptr.size();
+        let mut large_value = None;
+        let header = if size <= MAX_CHUNK_VALUE_SIZE {
+            debug_assert!(mem::align_of::<D::Value>() <= mem::align_of::<f64>());
+            let header = GcHeader::new(type_info, size);
+            // Marked as freed until the value has been initialized
+            header.age.set(Age::Freed);
+            self.nursery.alloc(header)
+        } else {
+            let ptr = AllocPtr::new::<D::Value>(type_info, size);
+            let header = ptr.ptr;
+            large_value = Some(ptr);
+            header
+        };
         unsafe {
-            let p: *mut D::Value = D::Value::make_ptr(&def, ptr.value());
+            let p: *mut D::Value = D::Value::make_ptr(&def, (*header).value());
             let ret: *const D::Value = &*def.initialize(WriteOnly::new(p));
             // Check that the returned pointer is the same as the one we sent as an extra precaution
             // that the pointer was initialized
             assert!(ret == p);
-            self.values = Some(ptr);
+            (*header).age.set(Age::Young);
+            if let Some(mut ptr) = large_value {
+                ptr.next = self.nursery.large_values.take();
+                self.nursery.large_values = Some(ptr);
+            }
+            let alloc_size = (*header).size();
+            self.allocated_memory += alloc_size;
+            self.total_allocated = self.total_allocated.saturating_add(alloc_size);
+            self.nursery.allocated_memory += alloc_size;
             let mut ptr = OwnedPtr(NonNull::new_unchecked(p));
             D::Value::unroot(&mut ptr);
             OwnedGcRef::with_root(ptr, self)
@@ -1245,9 +1571,13 @@ impl Gc {
     where
         R: Trace + CollectScope,
     {
-        if self.allocated_memory >= self.collect_limit {
+        let old_memory = self.allocated_memory - self.nursery.allocated_memory;
+        if old_memory >= self.collect_limit {
             self.collect(roots);
             true
+        } else if self.nursery.allocated_memory >= self.nursery.limit {
+            self.collect_young(roots);
+            true
         } else {
             false
         }
@@ -1267,12 +1597,73 @@ impl Gc {
         })
     }
 
+    /// Does a minor collection which only traverses and frees young values, promoting the
+    /// surviving values to the old generation. This function is unsafe since roots need to cover
+    /// all reachable object.
+    pub unsafe fn collect_young<R>(&mut self, roots: R)
+    where
+        R: Trace + CollectScope,
+    {
+        info!("Start minor collect {:?}", self.generation);
+        self.mark_mode = MarkMode::Young;
+        roots.scope(self, |self_| {
+            roots.trace(self_);
+            self_.mark_mode = MarkMode::Full;
+            self_.sweep_nursery(false);
+        })
+    }
+
+    /// Must be called after the value behind `ptr` has been mutated to refer to other values. If
+    /// `ptr` has already been promoted then any young values it refers to are promoted as well.
+    pub fn write_barrier<T: ?Sized + Trace>(&mut self, ptr: &GcPtr<T>) {
+        let header = ptr.header();
+        if header.generation() == self.generation() && header.age.get() == Age::Old {
+            self.tenure(&**ptr);
+        }
+    }
+
+    /// Promotes every young value reachable from `value` to the old generation. Used as a write
+    /// barrier when `value` is about to be stored in a location which may be reachable from old
+    /// values, such as the inside of a `Reference`.
+    pub fn tenure<T: ?Sized + Trace>(&mut self, value: &T) {
+        self.mark_mode = MarkMode::Tenure;
+        value.trace(self);
+        self.mark_mode = MarkMode::Full;
+    }
+
     /// Marks the GcPtr
     /// Returns true if the pointer was already marked
     pub fn mark<T: ?Sized>(&mut self, value: &GcPtr<T>) -> bool {
         let header = value.header();
+        let generation = header.generation();
         // We only need to mark and trace values from this garbage collectors generation
-        if header.generation().is_parent_of(self.generation()) || header.marked.get() {
+        if generation.is_parent_of(self.generation()) {
+            return true;
+        }
+        let own_value = generation == self.generation();
+        match self.mark_mode {
+            MarkMode::Full => (),
+            // Old values can only refer to other old values so there is no need to traverse them
+            MarkMode::Young => {
+                if own_value && header.age.get() == Age::Old {
+                    return true;
+                }
+            }
+            MarkMode::Tenure => {
+                if !own_value || header.age.get() == Age::Old {
+                    return true;
+                }
+                header.age.set(Age::Old);
+                return false;
+            }
+            MarkMode::Snapshot => {
+                return match self.retention {
+                    Some(ref mut retention) if own_value => retention.visit(header),
+                    _ => true,
+                };
+            }
+        }
+        if header.marked.get() {
             true
         } else {
             header.marked.set(true);
@@ -1326,6 +1717,40 @@ impl Gc {
         }
         info!("GC: Freed {} / Traversed {}", free_count, count);
         self.values = first;
+
+        self.sweep_nursery(true);
+    }
+
+    /// Frees the unmarked young values and promotes the rest. If `full` is set the old values
+    /// which live in chunks are swept as well.
+    unsafe fn sweep_nursery(&mut self, full: bool) {
+        let mut freed = 0;
+
+        let mut large_values = self.nursery.large_values.take();
+        while let Some(mut ptr) = large_values {
+            large_values = ptr.next.take();
+            if ptr.is_garbage(full) {
+                freed += ptr.size();
+                debug!("FREE: {:?}", ptr);
+                drop(ptr);
+            } else {
+                ptr.promote();
+                ptr.next = self.values.take();
+                self.values = Some(ptr);
+            }
+        }
+
+        // The tenured chunks must be swept before the young chunks are moved into them
+        if full {
+            let tenured = mem::replace(&mut self.nursery.tenured, Vec::new());
+            freed += self.nursery.sweep_chunks(tenured, true);
+        }
+        let young = mem::replace(&mut self.nursery.chunks, Vec::new());
+        freed += self.nursery.sweep_chunks(young, full);
+
+        info!("GC: Freed {} bytes from the nursery", freed);
+        self.allocated_memory -= freed;
+        self.nursery.allocated_memory = 0;
     }
 
     // Drop all values.
@@ -1333,6 +1758,22 @@ impl Gc {
     // SAFETY: No `GcPtr` allocated from this Gc must be reachable after calling this
     pub unsafe fn clear(&mut self) {
         self.values = None;
+        self.nursery.clear();
+    }
+
+    /// Iterates over the headers of all values allocated by this garbage collector
+    fn headers(&self) -> impl Iterator<Item = &GcHeader> + '_ {
+        let chunk_headers = self
+            .nursery
+            .chunks
+            .iter()
+            .chain(&self.nursery.tenured)
+            .flat_map(|chunk| chunk.headers())
+            .map(|header| unsafe { &*header })
+            .filter(|header| header.age.get() != Age::Freed);
+        list_headers(&self.values)
+            .chain(list_headers(&self.nursery.large_values))
+            .chain(chunk_headers)
     }
 
     fn free(&mut self, header: Option<AllocPtr>) {
@@ -1374,21 +1815,14 @@ mod tests {
     }
 
     fn object_count(gc: &Gc) -> usize {
-        let mut header: &GcHeader = match gc.values {
-            Some(ref x) => &**x,
-            None => return 0,
-        };
-        let mut count = 1;
-        loop {
-            match header.next {
-                Some(ref ptr) => {
-                    count += 1;
-                    header = &**ptr;
-                }
-                None => break,
-            }
+        gc.object_count()
+    }
+
+    fn age(value: &Value) -> Age {
+        match *value {
+            Data(ref data) => data.fields.header().age.get(),
+            _ => ice!(),
         }
-        count
     }
 
     #[derive(Trace)]
@@ -1451,7 +1885,8 @@ mod tests {
         let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
         let ptr = unsafe { gc.alloc(Def { elems: &[Int(1)] }).unwrap().unrooted() };
         let header: *const _ = ptr.header();
-        let other: &mut GcHeader = gc.values.as_mut().unwrap();
+        let other: &mut GcHeader =
+            unsafe { &mut *gc.nursery.chunks[0].headers().next().unwrap() };
         assert_eq!(&*ptr as *const _ as *const (), other.value());
         assert_eq!(header, other as *const _);
 
@@ -1523,6 +1958,118 @@ mod tests {
         }
         assert_eq!(true, dropped.get());
 
+        unsafe { gc.clear() }
+    }
+    #[test]
+    fn minor_collection_promotes_reachable_values() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        stack.push(new_data(gc.alloc(Def { elems: &[Int(1)] }).unwrap()));
+        gc.alloc(Def { elems: &[Int(2)] }).unwrap();
+        assert_eq!(object_count(&gc), 2);
+        assert_eq!(age(&stack[0]), Age::Young);
+
+        unsafe {
+            gc.collect_young(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 1);
+        assert_eq!(age(&stack[0]), Age::Old);
+
+        // Old values are only freed by a full collection
+        stack.pop();
+        unsafe {
+            gc.collect_young(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 1);
+        unsafe {
+            gc.collect(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 0);
+
+        unsafe { gc.clear() }
+    }
+
+    struct Large([u8; MAX_CHUNK_VALUE_SIZE + 1]);
+
+    unsafe impl Trace for Large {
+        impl_trace! { self, _gc, () }
+    }
+
+    #[test]
+    fn large_values_start_out_young() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        gc.alloc(Move(Large([0; MAX_CHUNK_VALUE_SIZE + 1]))).unwrap();
+        assert_eq!(list_headers(&gc.nursery.large_values).count(), 1);
+        assert_eq!(object_count(&gc), 1);
+
+        unsafe {
+            gc.collect_young(());
+        }
+        assert_eq!(object_count(&gc), 0);
+
+        unsafe { gc.clear() }
+    }
+
+    #[test]
+    fn write_barrier_promotes_values_stored_in_old_values() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        stack.push(new_data(gc.alloc(Def { elems: &[] }).unwrap()));
+        unsafe {
+            gc.collect_young(&mut *stack);
+        }
+
+        let young = new_data(gc.alloc(Def { elems: &[Int(1)] }).unwrap());
+        assert_eq!(age(&young), Age::Young);
+        match stack[0] {
+            Data(ref data) => unsafe {
+                let mut fields = data.fields.clone_unrooted();
+                fields.as_mut().push(young.clone_unrooted());
+                gc.write_barrier(&fields);
+            },
+            _ => ice!(),
+        }
+        assert_eq!(age(&young), Age::Old);
+
+        unsafe {
+            gc.collect_young(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 2);
+
+        unsafe { gc.clear() }
+    }
+    #[test]
+    fn snapshot_groups_values_by_type() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        let inner = new_data(gc.alloc(Def { elems: &[Int(1)] }).unwrap());
+        stack.push(new_data(gc.alloc(Def { elems: &[inner] }).unwrap()));
+        gc.alloc(Move(Large([0; MAX_CHUNK_VALUE_SIZE + 1]))).unwrap();
+
+        let snapshot = gc.snapshot(&mut *stack);
+        let vec_name = std::any::type_name::<Vec<Value>>();
+        let large_size = GcHeader::value_offset() + mem::size_of::<Large>();
+        let vec_size = GcHeader::value_offset() + mem::size_of::<Vec<Value>>();
+        assert_eq!(snapshot.count, 3);
+        assert_eq!(snapshot.bytes, large_size + 2 * vec_size);
+        assert_eq!(
+            snapshot.types,
+            vec![
+                snapshot::TypeSnapshot {
+                    name: std::any::type_name::<Large>().into(),
+                    count: 1,
+                    bytes: large_size,
+                    retention_path: None,
+                },
+                snapshot::TypeSnapshot {
+                    name: vec_name.into(),
+                    count: 2,
+                    bytes: 2 * vec_size,
+                    retention_path: Some(vec![vec_name.into()]),
+                },
+            ]
+        );
+
         unsafe { gc.clear() }
     }
 }
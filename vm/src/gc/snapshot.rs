@@ -0,0 +1,184 @@
+//! Summaries of the values allocated by a garbage collector.
+use std::{cmp::Reverse, collections::hash_map::Entry};
+
+use crate::base::fnv::FnvMap;
+
+use super::{Gc, GcHeader, GcPtr, MarkMode, Trace, TypeInfo};
+
+/// A summary of the values allocated by a garbage collector
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct HeapSnapshot {
+    /// The number of allocated values
+    pub count: usize,
+    /// The number of bytes used by the allocated values
+    pub bytes: usize,
+    /// The allocated values grouped by their type, the types using the most memory comes first
+    pub types: Vec<TypeSnapshot>,
+    /// Totals for each thread which has been spawned from the thread that the snapshot was taken
+    /// of
+    pub threads: Vec<ThreadHeap>,
+}
+
+/// The values of a single type in a `HeapSnapshot`
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct TypeSnapshot {
+    /// The name of the type. Records are named after their fields and variants after their tag.
+    pub name: String,
+    pub count: usize,
+    pub bytes: usize,
+    /// The types of the values which keep one of the values of this type alive, starting with a
+    /// value referred to directly from a root and ending with this type. `None` if no value of
+    /// this type is reachable, in which case they are freed by the next collection.
+    pub retention_path: Option<Vec<String>>,
+}
+
+/// The memory used by a thread and the threads spawned from it
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct ThreadHeap {
+    pub count: usize,
+    pub bytes: usize,
+    /// `true` if the thread was executing while the snapshot was taken, in which case `count` and
+    /// `bytes` are not known and set to zero
+    pub busy: bool,
+    pub threads: Vec<ThreadHeap>,
+}
+
+/// Records which value first reached each value while traversing from the roots
+#[derive(Debug, Default)]
+pub(super) struct Retention {
+    /// Maps each reached value to the value which referred to it (`None` for values that are
+    /// referred to directly from a root)
+    parents: FnvMap<*const GcHeader, Option<*const GcHeader>>,
+    /// The reached values in the order they were reached
+    order: Vec<*const GcHeader>,
+    /// The values which are currently being traversed
+    stack: Vec<*const GcHeader>,
+}
+
+unsafe impl Send for Retention {}
+
+impl Retention {
+    /// Records that `header` was reached. Returns true if it had already been reached.
+    pub(super) fn visit(&mut self, header: &GcHeader) -> bool {
+        match self.parents.entry(header) {
+            Entry::Occupied(_) => true,
+            Entry::Vacant(entry) => {
+                entry.insert(self.stack.last().cloned());
+                self.order.push(header);
+                false
+            }
+        }
+    }
+
+    fn path(
+        &self,
+        header: *const GcHeader,
+        names: &FnvMap<*const TypeInfo, String>,
+    ) -> Vec<String> {
+        let mut path = Vec::new();
+        let mut current = Some(header);
+        while let Some(header) = current {
+            let info = unsafe { (*header).type_info };
+            path.push(names.get(&info).cloned().unwrap_or_default());
+            current = self.parents.get(&header).cloned().unwrap_or(None);
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn type_name(info: &TypeInfo) -> String {
+    match info.tag {
+        Some(ref tag) => tag.to_string(),
+        None if !info.fields_key.is_empty() => format!(
+            "{{ {} }}",
+            info.fields_key
+                .iter()
+                .map(|field| &field[..])
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => info.type_name.to_string(),
+    }
+}
+
+impl Gc {
+    /// Creates a snapshot of all values allocated by this garbage collector. `roots` are
+    /// traversed to find the retention path of each type but no value is freed.
+    pub fn snapshot<R>(&mut self, roots: R) -> HeapSnapshot
+    where
+        R: Trace,
+    {
+        self.retention = Some(Box::new(Retention::default()));
+        self.mark_mode = MarkMode::Snapshot;
+        roots.trace(self);
+        self.mark_mode = MarkMode::Full;
+        let retention = self.retention.take().expect("Retention");
+
+        let mut snapshot = HeapSnapshot::default();
+        let mut infos = FnvMap::<*const TypeInfo, (usize, usize)>::default();
+        for header in self.headers() {
+            snapshot.count += 1;
+            snapshot.bytes += header.size();
+
+            let info = infos.entry(header.type_info).or_insert((0, 0));
+            info.0 += 1;
+            info.1 += header.size();
+        }
+
+        // Several `TypeInfo`s may share a name (such as a record and a variant with the same
+        // fields) so merge them
+        let names: FnvMap<_, _> = infos
+            .keys()
+            .map(|&info| (info, type_name(unsafe { &*info })))
+            .collect();
+        let mut types = FnvMap::<&str, TypeSnapshot>::default();
+        for (info, &(count, bytes)) in &infos {
+            let name = &names[info];
+            let typ = types.entry(name).or_insert_with(|| TypeSnapshot {
+                name: name.clone(),
+                ..TypeSnapshot::default()
+            });
+            typ.count += count;
+            typ.bytes += bytes;
+        }
+
+        for &header in &retention.order {
+            let name = match names.get(&unsafe { &*header }.type_info) {
+                Some(name) => name,
+                None => continue,
+            };
+            if let Some(typ) = types.get_mut(&name[..]) {
+                if typ.retention_path.is_none() {
+                    typ.retention_path = Some(retention.path(header, &names));
+                }
+            }
+        }
+
+        snapshot.types = types.into_iter().map(|(_, typ)| typ).collect();
+        snapshot
+            .types
+            .sort_by(|l, r| (Reverse(l.bytes), &l.name).cmp(&(Reverse(r.bytes), &r.name)));
+        snapshot
+    }
+
+    /// Traces the value behind `ptr`, recording that it is the value which reaches any values that
+    /// have not been reached before
+    pub(super) fn trace_retained<T: ?Sized + Trace>(&mut self, ptr: &GcPtr<T>) {
+        if let Some(retention) = &mut self.retention {
+            retention.stack.push(ptr.header());
+        }
+        (**ptr).trace(self);
+        if let Some(retention) = &mut self.retention {
+            retention.stack.pop();
+        }
+    }
+
+    /// Returns the number of values allocated by this garbage collector
+    pub fn object_count(&self) -> usize {
+        self.headers().count()
+    }
+}
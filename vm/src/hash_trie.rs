@@ -0,0 +1,284 @@
+//! A persistent hash array mapped trie which maps hashes to gluon values. Used as the backing store
+//! of `std.hash_map` which resolves hash collisions and equality in gluon.
+use crate::real_std::{any::Any, fmt, marker::PhantomData, sync::Arc};
+
+use crate::{
+    api::{generic::A, Generic, Unrooted, Userdata},
+    gc::{CloneUnrooted, GcRef, Move, Trace},
+    types::VmInt,
+    value::{Cloner, Value},
+    vm::Thread,
+    ExternModule, Result,
+};
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+#[derive(Debug, Trace)]
+#[gluon(gluon_vm)]
+enum Node {
+    Leaf(u64, Value),
+    /// Only the children whose bit is set in the bitmap are stored
+    Branch(u32, Vec<Arc<Node>>),
+}
+
+fn index(hash: u64, shift: u32) -> u32 {
+    debug_assert!(
+        shift < 64,
+        "Distinct hashes must differ in one of the first 64 bits"
+    );
+    ((hash >> shift) & MASK) as u32
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        match self {
+            Node::Leaf(..) => true,
+            Node::Branch(..) => false,
+        }
+    }
+
+    fn get(&self, hash: u64, shift: u32) -> Option<&Value> {
+        match self {
+            Node::Leaf(leaf_hash, value) => {
+                if *leaf_hash == hash {
+                    Some(value)
+                } else {
+                    None
+                }
+            }
+            Node::Branch(bitmap, children) => {
+                let bit = 1 << index(hash, shift);
+                if bitmap & bit == 0 {
+                    None
+                } else {
+                    let pos = (bitmap & (bit - 1)).count_ones() as usize;
+                    children[pos].get(hash, shift + BITS)
+                }
+            }
+        }
+    }
+
+    /// Returns the node with `value` inserted and whether the hash was not already present
+    fn insert(node: &Arc<Node>, hash: u64, value: Value, shift: u32) -> (Arc<Node>, bool) {
+        match &**node {
+            Node::Leaf(leaf_hash, _) if *leaf_hash == hash => {
+                (Arc::new(Node::Leaf(hash, value)), false)
+            }
+            Node::Leaf(leaf_hash, _) => {
+                // Push the existing leaf down one level and insert the new value next to it
+                let branch = Arc::new(Node::Branch(
+                    1 << index(*leaf_hash, shift),
+                    vec![node.clone()],
+                ));
+                Node::insert(&branch, hash, value, shift)
+            }
+            Node::Branch(bitmap, children) => {
+                let bit = 1 << index(hash, shift);
+                let pos = (bitmap & (bit - 1)).count_ones() as usize;
+                let mut children = children.clone();
+                let added = if bitmap & bit == 0 {
+                    children.insert(pos, Arc::new(Node::Leaf(hash, value)));
+                    true
+                } else {
+                    let (child, added) = Node::insert(&children[pos], hash, value, shift + BITS);
+                    children[pos] = child;
+                    added
+                };
+                (Arc::new(Node::Branch(bitmap | bit, children)), added)
+            }
+        }
+    }
+
+    /// Returns `None` if `hash` does not exist in the node, otherwise returns the node with the
+    /// hash removed (or `Some(None)` if the node became empty)
+    fn remove(node: &Arc<Node>, hash: u64, shift: u32) -> Option<Option<Arc<Node>>> {
+        match &**node {
+            Node::Leaf(leaf_hash, _) => {
+                if *leaf_hash == hash {
+                    Some(None)
+                } else {
+                    None
+                }
+            }
+            Node::Branch(bitmap, children) => {
+                let bit = 1 << index(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let pos = (bitmap & (bit - 1)).count_ones() as usize;
+                let mut children = children.clone();
+                let bitmap = match Node::remove(&children[pos], hash, shift + BITS)? {
+                    Some(child) => {
+                        children[pos] = child;
+                        *bitmap
+                    }
+                    None => {
+                        children.remove(pos);
+                        bitmap & !bit
+                    }
+                };
+                Some(match &children[..] {
+                    [] => None,
+                    // A single leaf can be stored at any level so move it up
+                    [child] if child.is_leaf() => Some(child.clone()),
+                    _ => Some(Arc::new(Node::Branch(bitmap, children))),
+                })
+            }
+        }
+    }
+
+    fn for_each<'a>(&'a self, f: &mut impl FnMut(&'a Value)) {
+        match self {
+            Node::Leaf(_, value) => f(value),
+            Node::Branch(_, children) => {
+                for child in children {
+                    child.for_each(f);
+                }
+            }
+        }
+    }
+
+    fn deep_clone(&self, deep_cloner: &mut Cloner) -> Result<Node> {
+        Ok(match self {
+            // SAFETY During the `alloc` call the unrooted values are scanned through the `DataDef`
+            Node::Leaf(hash, value) => unsafe {
+                Node::Leaf(*hash, deep_cloner.deep_clone(value)?.unrooted())
+            },
+            Node::Branch(bitmap, children) => Node::Branch(
+                *bitmap,
+                children
+                    .iter()
+                    .map(|child| child.deep_clone(deep_cloner).map(Arc::new))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+/// A persistent map from `Int` hashes to values of type `T`. Updates share all untouched nodes
+/// with the map they were created from.
+#[derive(VmType)]
+#[gluon(gluon_vm)]
+#[gluon(vm_type = "std.hash_map.HashTrie")]
+pub struct HashTrie<T> {
+    root: Option<Arc<Node>>,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Userdata for HashTrie<T>
+where
+    T: Any + Send + Sync,
+{
+    fn deep_clone<'gc>(
+        &self,
+        deep_cloner: &'gc mut Cloner,
+    ) -> Result<GcRef<'gc, Box<dyn Userdata>>> {
+        let root = match &self.root {
+            Some(root) => Some(Arc::new(root.deep_clone(deep_cloner)?)),
+            None => None,
+        };
+        let data: Box<dyn Userdata> = Box::new(HashTrie {
+            root,
+            len: self.len,
+            _marker: PhantomData::<A>,
+        });
+        deep_cloner.gc().alloc(Move(data))
+    }
+}
+
+impl<T> fmt::Debug for HashTrie<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = f.debug_list();
+        if let Some(root) = &self.root {
+            root.for_each(&mut |value| {
+                list.entry(value);
+            });
+        }
+        list.finish()
+    }
+}
+
+unsafe impl<T> Trace for HashTrie<T> {
+    impl_trace_fields! { self, gc; root }
+}
+
+impl<T> HashTrie<T> {
+    fn with_root(root: Option<Arc<Node>>, len: usize) -> Self {
+        HashTrie {
+            root,
+            len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn empty(_: ()) -> HashTrie<A> {
+    HashTrie::with_root(None, 0)
+}
+
+fn get(hash: VmInt, trie: &HashTrie<A>) -> Option<Unrooted<A>> {
+    let value = trie.root.as_ref()?.get(hash as u64, 0)?;
+    // SAFETY The returned, unrooted value gets pushed immediately to the stack
+    unsafe { Some(Unrooted::from(value.clone_unrooted())) }
+}
+
+fn insert(hash: VmInt, value: Generic<A>, trie: &HashTrie<A>) -> HashTrie<A> {
+    // SAFETY The value is rooted by the returned trie once it is pushed to the stack
+    let value = unsafe { value.get_value().clone_unrooted() };
+    let hash = hash as u64;
+    match &trie.root {
+        Some(root) => {
+            let (root, added) = Node::insert(root, hash, value, 0);
+            HashTrie::with_root(Some(root), trie.len + added as usize)
+        }
+        None => HashTrie::with_root(Some(Arc::new(Node::Leaf(hash, value))), 1),
+    }
+}
+
+fn remove(hash: VmInt, trie: &HashTrie<A>) -> HashTrie<A> {
+    let removed = trie
+        .root
+        .as_ref()
+        .and_then(|root| Node::remove(root, hash as u64, 0));
+    match removed {
+        Some(root) => HashTrie::with_root(root, trie.len - 1),
+        None => HashTrie::with_root(trie.root.clone(), trie.len),
+    }
+}
+
+fn len(trie: &HashTrie<A>) -> VmInt {
+    trie.len as VmInt
+}
+
+fn values(trie: &HashTrie<A>) -> Vec<Unrooted<A>> {
+    let mut values = Vec::with_capacity(trie.len);
+    if let Some(root) = &trie.root {
+        // SAFETY The returned, unrooted values gets pushed immediately to the stack
+        root.for_each(&mut |value| unsafe { values.push(Unrooted::from(value.clone_unrooted())) });
+    }
+    values
+}
+
+mod std {
+    pub mod hash_map {
+        pub use crate::hash_trie as prim;
+    }
+}
+
+pub fn load(vm: &Thread) -> Result<ExternModule> {
+    vm.register_type::<HashTrie<A>>("std.hash_map.HashTrie", &["a"])?;
+    ExternModule::new(
+        vm,
+        record! {
+            type HashTrie a => HashTrie<A>,
+            empty => primitive!(1, std::hash_map::prim::empty),
+            get => primitive!(2, std::hash_map::prim::get),
+            insert => primitive!(3, std::hash_map::prim::insert),
+            remove => primitive!(2, std::hash_map::prim::remove),
+            len => primitive!(1, std::hash_map::prim::len),
+            values => primitive!(1, std::hash_map::prim::values),
+        },
+    )
+}
@@ -121,6 +121,7 @@ fn force(
                                 Ok(value) => value,
                                 Err(err) => return RuntimeResult::Panic(err.to_string().into()),
                             };
+                            lazy.thread.write_barrier(value.get_value());
                             let mut lazy_lock = lazy.value.lock().unwrap();
                             match *lazy_lock {
                                 Lazy_::Blackhole(_, ref mut x) => {
@@ -54,10 +54,12 @@ pub mod compiler;
 pub mod core;
 pub mod debug;
 pub mod dynamic;
+pub mod hash_trie;
 pub mod lazy;
 pub mod macros;
 pub mod primitives;
 pub mod reference;
+pub mod sandbox;
 pub mod stack;
 pub mod thread;
 pub mod types;
@@ -186,6 +188,12 @@ quick_error! {
         Interrupted {
             display("Thread was interrupted")
         }
+        OutOfFuel { consumed: u64 } {
+            display("Thread ran out of fuel after executing {} instructions", consumed)
+        }
+        PrimitiveDenied(primitive: String) {
+            display("Calling the primitive `{}` was denied by the sandbox", primitive)
+        }
         Panic(err: String, stacktrace: Option<Stacktrace>) {
             display("{}", Panic { err, stacktrace })
         }
@@ -10,7 +10,7 @@ use std::{
 use {
     codespan_reporting::Diagnostic,
     downcast_rs::{impl_downcast, Downcast},
-    futures::{prelude::*, task::Spawn},
+    futures::{future, prelude::*, task::Spawn},
 };
 
 use gluon_codegen::Trace;
@@ -396,6 +396,11 @@ pub struct MacroExpander<'a> {
     pub errors: Errors,
     pub userdata: &'a (dyn MacroUserdata + 'a),
     pub spawn: Option<&'a (dyn Spawn + Send + Sync + 'a)>,
+    /// Macros which are only in scope for the module being expanded, such as macros written in
+    /// gluon. Looked up when a macro is not found in the `MacroEnv`.
+    pub local_macros: FnvMap<String, Arc<dyn Macro>>,
+    /// The symbols of the module being expanded. Only available while `Macro::expand` is running.
+    pub symbols: Symbols,
     macros: &'a MacroEnv,
 }
 
@@ -411,6 +416,8 @@ impl<'a> MacroExpander<'a> {
             macros: vm.get_macros(),
             userdata,
             spawn,
+            local_macros: FnvMap::default(),
+            symbols: Symbols::new(),
             errors: Errors::new(),
         }
     }
@@ -422,6 +429,8 @@ impl<'a> MacroExpander<'a> {
             macros: self.macros,
             userdata: self.userdata,
             spawn: self.spawn,
+            local_macros: self.local_macros.clone(),
+            symbols: Symbols::new(),
             errors: Errors::new(),
         }
     }
@@ -457,14 +466,19 @@ impl<'a> MacroExpander<'a> {
         };
         visitor.visit_expr(expr);
         let MacroVisitor { exprs, .. } = visitor;
-        self.expand(arena, exprs).await
+        self.expand(symbols, arena, exprs).await
     }
 
     async fn expand<'ast>(
         &mut self,
+        symbols: &mut Symbols,
         arena: &mut ast::OwnedArena<'ast, Symbol>,
         mut exprs: Vec<(&'_ mut SpannedExpr<'ast, Symbol>, Arc<dyn Macro>)>,
     ) {
+        // Lend the symbols of the module to the macros so that identifiers they generate can refer
+        // to bindings in the module
+        mem::swap(&mut self.symbols, symbols);
+
         let mut futures = Vec::with_capacity(exprs.len());
         for (expr, mac) in exprs.drain(..) {
             let result = match &mut expr.value {
@@ -480,6 +494,8 @@ impl<'a> MacroExpander<'a> {
             }
         }
 
+        mem::swap(&mut self.symbols, symbols);
+
         let mut stream = futures
             .into_iter()
             .collect::<futures::stream::FuturesUnordered<_>>();
@@ -498,6 +514,30 @@ impl<'a> MacroExpander<'a> {
     }
 }
 
+/// Expands to the macro in `MacroExpander::local_macros` with the same name
+struct LocalMacro(String);
+
+unsafe impl Trace for LocalMacro {
+    impl_trace! { self, _gc, { } }
+}
+
+impl Macro for LocalMacro {
+    fn expand<'r, 'a: 'r, 'b: 'r, 'ast: 'r>(
+        &self,
+        env: &'b mut MacroExpander<'a>,
+        arena: &'b mut ast::OwnedArena<'ast, Symbol>,
+        args: &'b mut [SpannedExpr<'ast, Symbol>],
+    ) -> MacroFuture<'r, 'ast> {
+        match env.local_macros.get(&self.0).cloned() {
+            Some(mac) => mac.expand(env, arena, args),
+            None => Box::pin(future::err(Error::message(format!(
+                "No macro named `{}!` is in scope",
+                self.0
+            )))),
+        }
+    }
+}
+
 fn replace_expr<'ast>(
     arena: &ast::OwnedArena<'ast, Symbol>,
     expr: &mut SpannedExpr<'ast, Symbol>,
@@ -540,10 +580,13 @@ impl<'a, 'b, 'c, 'e, 'ast> MutVisitor<'e, 'ast> for MacroVisitor<'a, 'b, 'c, '_,
                     }
 
                     let name = id.name.as_ref();
-                    match self.expander.macros.get(&name[..name.len() - 1]) {
+                    let name = &name[..name.len() - 1];
+                    match self.expander.macros.get(name) {
                         // FIXME Avoid cloning args
                         Some(m) => Some(m.clone()),
-                        None => None,
+                        // The macro may be brought into scope by a macro which is expanded before
+                        // this one so defer the lookup until then
+                        None => Some(Arc::new(LocalMacro(name.to_string())) as Arc<dyn Macro>),
                     }
                 }
                 _ => None,
@@ -1,28 +1,33 @@
 //! Module containing functions for interacting with gluon's primitive types.
 use crate::real_std::{
+    cmp::Ordering,
     ffi::OsStr,
     fs, io,
     marker::PhantomData,
-    path::{self, Path},
+    path::{self, Path, PathBuf},
     result::Result as StdResult,
     str::FromStr,
     string::String as StdString,
     sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use futures::Future;
+
 use crate::base::types::ArcType;
 
 use crate::{
     api::{
         generic::{self, A, S},
-        primitive, Array, Getable, Opaque, OpaqueRef, Pushable, Pushed, RuntimeResult, ValueRef,
-        VmType, WithVM, IO,
+        primitive, Array, Generic, Getable, Opaque, OpaqueRef, OpaqueValue, OwnedFunction,
+        Pushable, Pushed, RuntimeResult, Unrooted, ValueRef, VmType, WithVM, IO,
     },
     gc::{DataDef, Trace, WriteOnly},
+    sandbox::FsAccess,
     stack::{ExternState, StackFrame},
     types::VmInt,
-    value::{GcStr, Repr, ValueArray},
-    vm::{Status, Thread},
+    value::{GcStr, Repr, Value, ValueArray},
+    vm::{RootedThread, Status, Thread},
     Error, ExternModule, Result, Variants,
 };
 
@@ -161,6 +166,219 @@ pub mod array {
         };
         RuntimeResult::Return(Getable::from_value(lhs.vm_(), Variants::from(value)))
     }
+
+    pub(crate) fn reverse<'vm>(
+        array: Array<'vm, generic::A>,
+    ) -> RuntimeResult<Array<'vm, generic::A>, Error> {
+        #[derive(Trace)]
+        #[gluon(gluon_vm)]
+        struct Reverse<'a> {
+            array: &'a ValueArray,
+        }
+
+        unsafe impl<'a> DataDef for Reverse<'a> {
+            type Value = ValueArray;
+
+            fn size(&self) -> usize {
+                ValueArray::size_of(self.array.repr(), self.array.len())
+            }
+
+            fn initialize<'w>(self, mut result: WriteOnly<'w, ValueArray>) -> &'w mut ValueArray {
+                unsafe {
+                    let result = &mut *result.as_mut_ptr();
+                    let array = self.array;
+                    result.set_repr(array.repr());
+                    result.initialize((0..array.len()).rev().map(|i| array.get(i).unwrap()));
+                    result
+                }
+            }
+        }
+
+        let mut context = array.vm().context();
+        let result = context.alloc(Reverse {
+            array: &array.get_array(),
+        });
+
+        let value = match result {
+            Ok(value) => value,
+            Err(err) => return RuntimeResult::Panic(err),
+        };
+
+        RuntimeResult::Return(Getable::from_value(array.vm_(), Variants::from(value)))
+    }
+
+    pub(crate) fn concat<'vm>(
+        arrays: Array<'vm, Array<'vm, generic::A>>,
+    ) -> RuntimeResult<Array<'vm, generic::A>, Error> {
+        #[derive(Trace)]
+        #[gluon(gluon_vm)]
+        struct Concat<'a> {
+            arrays: &'a [&'a ValueArray],
+        }
+        impl<'a> Concat<'a> {
+            fn repr(&self) -> Repr {
+                // Empty arrays may not have the correct representation set so take it from the
+                // first non-empty array
+                self.arrays
+                    .iter()
+                    .find(|array| !array.is_empty())
+                    .map_or(Repr::Unknown, |array| array.repr())
+            }
+        }
+
+        unsafe impl<'a> DataDef for Concat<'a> {
+            type Value = ValueArray;
+
+            fn size(&self) -> usize {
+                let len = self.arrays.iter().map(|array| array.len()).sum();
+                ValueArray::size_of(self.repr(), len)
+            }
+
+            fn initialize<'w>(self, mut result: WriteOnly<'w, ValueArray>) -> &'w mut ValueArray {
+                unsafe {
+                    let result = &mut *result.as_mut_ptr();
+                    result.set_repr(self.repr());
+                    result.initialize(self.arrays.iter().flat_map(|array| array.iter()));
+                    result
+                }
+            }
+        }
+
+        let outer = arrays.get_array();
+        let inner: Vec<_> = outer
+            .iter()
+            .map(|value| match value.as_ref() {
+                ValueRef::Array(array) => array,
+                _ => ice!("Value is not an array"),
+            })
+            .collect();
+        let inner_refs: Vec<&ValueArray> = inner.iter().map(|array| &**array).collect();
+
+        let mut context = arrays.vm().context();
+        let result = context.alloc(Concat {
+            arrays: &inner_refs,
+        });
+
+        let value = match result {
+            Ok(value) => value,
+            Err(err) => return RuntimeResult::Panic(err),
+        };
+
+        RuntimeResult::Return(Getable::from_value(arrays.vm_(), Variants::from(value)))
+    }
+
+    type Element = OpaqueValue<RootedThread, generic::A>;
+
+    pub(crate) fn map(
+        mut f: OwnedFunction<fn(Element) -> OpaqueValue<RootedThread, generic::B>>,
+        array: Vec<Element>,
+    ) -> impl Future<Output = RuntimeResult<Vec<OpaqueValue<RootedThread, generic::B>>, Error>>
+    {
+        async move {
+            let mut result = Vec::with_capacity(array.len());
+            for x in array {
+                match f.call_async(x).await {
+                    Ok(y) => result.push(y),
+                    Err(err) => return RuntimeResult::Panic(err),
+                }
+            }
+            RuntimeResult::Return(result)
+        }
+    }
+
+    pub(crate) fn filter(
+        mut predicate: OwnedFunction<fn(Element) -> bool>,
+        array: Vec<Element>,
+    ) -> impl Future<Output = RuntimeResult<Vec<Element>, Error>> {
+        async move {
+            let mut result = Vec::new();
+            for x in array {
+                match predicate.call_async(x.clone()).await {
+                    Ok(true) => result.push(x),
+                    Ok(false) => (),
+                    Err(err) => return RuntimeResult::Panic(err),
+                }
+            }
+            RuntimeResult::Return(result)
+        }
+    }
+
+    type Compare = OwnedFunction<fn(Element, Element) -> Ordering>;
+
+    async fn merge(
+        compare: &mut Compare,
+        left: Vec<Element>,
+        right: Vec<Element>,
+    ) -> Result<Vec<Element>> {
+        let mut result = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        loop {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => compare.call_async(l.clone(), r.clone()).await?,
+                _ => break,
+            };
+            // Take from `left` unless it is strictly greater to keep the sort stable
+            result.extend(if ordering == Ordering::Greater {
+                right.next()
+            } else {
+                left.next()
+            });
+        }
+        result.extend(left);
+        result.extend(right);
+        Ok(result)
+    }
+
+    /// Stable merge sort. The comparison function is a gluon function which may yield so the
+    /// standard library's sort can't be used.
+    pub(crate) fn sort_by(
+        mut compare: Compare,
+        array: Vec<Element>,
+    ) -> impl Future<Output = RuntimeResult<Vec<Element>, Error>> {
+        async move {
+            let mut runs: Vec<Vec<Element>> = array.into_iter().map(|x| vec![x]).collect();
+            while runs.len() > 1 {
+                let mut merged = Vec::with_capacity((runs.len() + 1) / 2);
+                let mut iter = runs.into_iter();
+                while let Some(left) = iter.next() {
+                    match iter.next() {
+                        Some(right) => match merge(&mut compare, left, right).await {
+                            Ok(run) => merged.push(run),
+                            Err(err) => return RuntimeResult::Panic(err),
+                        },
+                        None => merged.push(left),
+                    }
+                }
+                runs = merged;
+            }
+            RuntimeResult::Return(runs.pop().unwrap_or_default())
+        }
+    }
+
+    /// Searches a sorted array using `compare` which returns the ordering of an element relative to
+    /// the target. Returns `Ok` with the index of a matching element or `Err` with the index the
+    /// target could be inserted at.
+    pub(crate) fn binary_search_by(
+        mut compare: OwnedFunction<fn(Element) -> Ordering>,
+        array: OpaqueValue<RootedThread, [Element]>,
+    ) -> impl Future<Output = RuntimeResult<StdResult<usize, usize>, Error>> {
+        async move {
+            let mut low = 0;
+            let mut high = array.len();
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let x = array.get2(mid as VmInt).unwrap();
+                match compare.call_async(x).await {
+                    Ok(Ordering::Less) => low = mid + 1,
+                    Ok(Ordering::Greater) => high = mid,
+                    Ok(Ordering::Equal) => return RuntimeResult::Return(Ok(mid)),
+                    Err(err) => return RuntimeResult::Panic(err),
+                }
+            }
+            RuntimeResult::Return(Err(low))
+        }
+    }
 }
 
 mod string {
@@ -282,6 +500,13 @@ mod string {
     }
 }
 
+#[doc(hidden)]
+pub mod interpolate {
+    pub fn concat(parts: Vec<String>) -> String {
+        parts.concat()
+    }
+}
+
 fn parse<T>(s: &str) -> StdResult<T, ()>
 where
     T: FromStr,
@@ -338,6 +563,9 @@ mod std {
     pub mod array {
         pub use crate::primitives::array as prim;
     }
+    pub mod interpolate {
+        pub use crate::primitives::interpolate as prim;
+    }
     pub mod byte {
         pub type prim = u8;
     }
@@ -355,6 +583,9 @@ mod std {
             pub mod string {
                 pub use crate::primitives::st_string as prim;
             }
+            pub mod array {
+                pub use crate::primitives::st_array as prim;
+            }
         }
     }
 }
@@ -537,7 +768,13 @@ pub fn load_array(vm: &Thread) -> Result<ExternModule> {
             len => primitive!(1, std::array::prim::len),
             index => primitive!(2, std::array::prim::index),
             append => primitive!(2, std::array::prim::append),
-            slice => primitive!(3, std::array::prim::slice)
+            slice => primitive!(3, std::array::prim::slice),
+            reverse => primitive!(1, std::array::prim::reverse),
+            concat => primitive!(1, std::array::prim::concat),
+            map => primitive!(2, async fn std::array::prim::map),
+            filter => primitive!(2, async fn std::array::prim::filter),
+            sort_by => primitive!(2, async fn std::array::prim::sort_by),
+            binary_search_by => primitive!(2, async fn std::array::prim::binary_search_by)
         },
     )
 }
@@ -575,6 +812,15 @@ pub fn load_string(vm: &Thread) -> Result<ExternModule> {
     )
 }
 
+pub fn load_interpolate(vm: &Thread) -> Result<ExternModule> {
+    ExternModule::new(
+        vm,
+        record! {
+            concat => primitive!(1, std::interpolate::prim::concat)
+        },
+    )
+}
+
 impl<'a> VmType for path::Component<'a> {
     type Type = Component<'static>;
     fn make_type(vm: &Thread) -> ArcType {
@@ -611,6 +857,340 @@ unsafe impl Trace for DirEntry {
     impl_trace! { self, _gc, { } }
 }
 
+fn sandboxed_path(thread: &Thread, path: &Path) -> io::Result<PathBuf> {
+    thread
+        .global_env()
+        .sandbox()
+        .check_path(path, FsAccess::Read)
+}
+
+fn read_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<Vec<DirEntry>> {
+    IO::from(sandboxed_path(vm, path).and_then(|path| {
+        fs::read_dir(path).and_then(|iter| {
+            iter.map(|result| result.map(DirEntry))
+                .collect::<io::Result<Vec<_>>>()
+        })
+    }))
+}
+
+fn path_metadata(WithVM { vm, value: path }: WithVM<&Path>) -> IO<Metadata> {
+    IO::from(sandboxed_path(vm, path).and_then(|path| path.metadata().map(Metadata)))
+}
+
+fn path_symlink_metadata(WithVM { vm, value: path }: WithVM<&Path>) -> IO<Metadata> {
+    IO::from(sandboxed_path(vm, path).and_then(|path| path.symlink_metadata().map(Metadata)))
+}
+
+fn path_canonicalize(WithVM { vm, value: path }: WithVM<&Path>) -> IO<PathBuf> {
+    IO::from(sandboxed_path(vm, path).and_then(|path| path.canonicalize()))
+}
+
+fn path_read_link(WithVM { vm, value: path }: WithVM<&Path>) -> IO<PathBuf> {
+    IO::from(sandboxed_path(vm, path).and_then(|path| path.read_link()))
+}
+
+fn path_read_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<Vec<PathBuf>> {
+    IO::from(
+        sandboxed_path(vm, path)
+            .and_then(|path| {
+                path.read_dir()?
+                    .map(|result| Ok(result?.path()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(|err| Error::Message(err.to_string())),
+    )
+}
+
+// Paths outside of the sandbox are reported as missing
+fn path_exists(WithVM { vm, value: path }: WithVM<&Path>) -> IO<bool> {
+    IO::Value(sandboxed_path(vm, path).map_or(false, |path| path.exists()))
+}
+
+fn path_is_file(WithVM { vm, value: path }: WithVM<&Path>) -> IO<bool> {
+    IO::Value(sandboxed_path(vm, path).map_or(false, |path| path.is_file()))
+}
+
+fn path_is_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<bool> {
+    IO::Value(sandboxed_path(vm, path).map_or(false, |path| path.is_dir()))
+}
+
+/// The kind of a failed file system operation, mirroring `std::io::ErrorKind`
+#[derive(Pushable, VmType)]
+#[gluon(vm_type = "std.fs.types.ErrorKind")]
+#[gluon(gluon_vm)]
+pub enum FsErrorKind {
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    InvalidInput,
+    InvalidData,
+    Interrupted,
+    UnexpectedEof,
+    Other,
+}
+
+impl From<io::ErrorKind> for FsErrorKind {
+    fn from(kind: io::ErrorKind) -> FsErrorKind {
+        match kind {
+            io::ErrorKind::NotFound => FsErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => FsErrorKind::PermissionDenied,
+            io::ErrorKind::AlreadyExists => FsErrorKind::AlreadyExists,
+            io::ErrorKind::InvalidInput => FsErrorKind::InvalidInput,
+            io::ErrorKind::InvalidData => FsErrorKind::InvalidData,
+            io::ErrorKind::Interrupted => FsErrorKind::Interrupted,
+            io::ErrorKind::UnexpectedEof => FsErrorKind::UnexpectedEof,
+            _ => FsErrorKind::Other,
+        }
+    }
+}
+
+#[derive(Pushable, VmType)]
+#[gluon(vm_type = "std.fs.types.Error")]
+#[gluon(gluon_vm)]
+pub struct FsError {
+    kind: FsErrorKind,
+    path: Option<PathBuf>,
+    message: StdString,
+}
+
+impl FsError {
+    fn new(path: Option<&Path>, err: io::Error) -> FsError {
+        FsError {
+            kind: err.kind().into(),
+            path: path.map(|path| path.to_owned()),
+            message: err.to_string(),
+        }
+    }
+}
+
+type FsResult<T> = StdResult<T, FsError>;
+
+#[derive(Getable, Pushable, VmType)]
+#[gluon(vm_type = "std.fs.types.Permissions")]
+#[gluon(gluon_vm)]
+pub struct Permissions {
+    readonly: bool,
+    mode: Option<u32>,
+}
+
+impl From<fs::Permissions> for Permissions {
+    fn from(permissions: fs::Permissions) -> Permissions {
+        #[cfg(unix)]
+        let mode = {
+            use crate::real_std::os::unix::fs::PermissionsExt;
+            Some(permissions.mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Permissions {
+            readonly: permissions.readonly(),
+            mode,
+        }
+    }
+}
+
+/// Runs `f` on `path` after checking it against the sandbox, reporting failures as an `Error`
+/// value instead of an exception
+fn with_fs_path<T>(
+    vm: &Thread,
+    path: &Path,
+    access: FsAccess,
+    f: impl FnOnce(&Path) -> io::Result<T>,
+) -> IO<FsResult<T>> {
+    IO::Value(
+        vm.global_env()
+            .sandbox()
+            .check_path(path, access)
+            .and_then(|resolved| f(&resolved))
+            .map_err(|err| FsError::new(Some(path), err)),
+    )
+}
+
+/// Like `with_fs_path` but for operations which read from (or move) `from` and write to `to`
+fn with_fs_paths<T>(
+    vm: &Thread,
+    from: &Path,
+    from_access: FsAccess,
+    to: &Path,
+    f: impl FnOnce(&Path, &Path) -> io::Result<T>,
+) -> IO<FsResult<T>> {
+    let sandbox = vm.global_env().sandbox();
+    IO::Value(
+        sandbox
+            .check_path(from, from_access)
+            .map_err(|err| FsError::new(Some(from), err))
+            .and_then(|resolved_from| {
+                let resolved_to = sandbox
+                    .check_path(to, FsAccess::Write)
+                    .map_err(|err| FsError::new(Some(to), err))?;
+                f(&resolved_from, &resolved_to).map_err(|err| FsError::new(Some(from), err))
+            }),
+    )
+}
+
+fn fs_create_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, fs::create_dir)
+}
+
+fn fs_create_dir_all(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, fs::create_dir_all)
+}
+
+fn fs_remove_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, fs::remove_dir)
+}
+
+fn fs_remove_dir_all(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, fs::remove_dir_all)
+}
+
+fn fs_remove_file(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, fs::remove_file)
+}
+
+fn fs_rename(WithVM { vm, value: from }: WithVM<&Path>, to: &Path) -> IO<FsResult<()>> {
+    with_fs_paths(vm, from, FsAccess::Write, to, fs::rename)
+}
+
+fn fs_copy(WithVM { vm, value: from }: WithVM<&Path>, to: &Path) -> IO<FsResult<u64>> {
+    with_fs_paths(vm, from, FsAccess::Read, to, fs::copy)
+}
+
+fn fs_hard_link(WithVM { vm, value: from }: WithVM<&Path>, to: &Path) -> IO<FsResult<()>> {
+    with_fs_paths(vm, from, FsAccess::Read, to, fs::hard_link)
+}
+
+/// Creates a symbolic link at `link` pointing to `original`. `original` is stored as is, relative
+/// paths are resolved relative to the directory of `link`
+fn fs_symlink(
+    WithVM {
+        vm,
+        value: original,
+    }: WithVM<&Path>,
+    link: &Path,
+) -> IO<FsResult<()>> {
+    with_fs_path(vm, link, FsAccess::Write, |link| {
+        #[cfg(unix)]
+        {
+            crate::real_std::os::unix::fs::symlink(original, link)
+        }
+        #[cfg(windows)]
+        {
+            use crate::real_std::os::windows::fs::{symlink_dir, symlink_file};
+            let target = link
+                .parent()
+                .map_or(original.to_owned(), |dir| dir.join(original));
+            if target.is_dir() {
+                symlink_dir(original, link)
+            } else {
+                symlink_file(original, link)
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = (original, link);
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Symbolic links are not supported on this platform",
+            ))
+        }
+    })
+}
+
+fn fs_read_link(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<PathBuf>> {
+    with_fs_path(vm, path, FsAccess::Read, fs::read_link)
+}
+
+fn fs_canonicalize(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<PathBuf>> {
+    with_fs_path(vm, path, FsAccess::Read, fs::canonicalize)
+}
+
+fn fs_metadata(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<Metadata>> {
+    with_fs_path(vm, path, FsAccess::Read, |path| {
+        path.metadata().map(Metadata)
+    })
+}
+
+fn fs_symlink_metadata(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<Metadata>> {
+    with_fs_path(vm, path, FsAccess::Read, |path| {
+        path.symlink_metadata().map(Metadata)
+    })
+}
+
+fn fs_set_permissions(
+    WithVM { vm, value: path }: WithVM<&Path>,
+    permissions: Permissions,
+) -> IO<FsResult<()>> {
+    with_fs_path(vm, path, FsAccess::Write, |path| {
+        let mut new_permissions = path.metadata()?.permissions();
+        new_permissions.set_readonly(permissions.readonly);
+        #[cfg(unix)]
+        {
+            use crate::real_std::os::unix::fs::PermissionsExt;
+            if let Some(mode) = permissions.mode {
+                new_permissions.set_mode(mode);
+            }
+        }
+        fs::set_permissions(path, new_permissions)
+    })
+}
+
+// Paths outside of the sandbox are reported as missing
+fn fs_exists(WithVM { vm, value: path }: WithVM<&Path>) -> IO<bool> {
+    IO::Value(sandboxed_path(vm, path).map_or(false, |path| path.exists()))
+}
+
+/// Lists every file and directory below `path`. Symbolic links are listed but not followed and
+/// the result is sorted so it does not depend on the platform.
+fn fs_walk_dir(WithVM { vm, value: path }: WithVM<&Path>) -> IO<FsResult<Vec<PathBuf>>> {
+    IO::Value(
+        sandboxed_path(vm, path)
+            .map_err(|err| FsError::new(Some(path), err))
+            .and_then(|root| {
+                let mut found = Vec::new();
+                let mut stack = vec![root.clone()];
+                while let Some(dir) = stack.pop() {
+                    let mut entries = fs::read_dir(&dir)
+                        .and_then(|iter| {
+                            iter.map(|entry| entry.map(|entry| entry.path()))
+                                .collect::<io::Result<Vec<_>>>()
+                        })
+                        .map_err(|err| FsError::new(Some(&dir), err))?;
+
+                    for entry in &entries {
+                        let is_dir = entry
+                            .symlink_metadata()
+                            .map_err(|err| FsError::new(Some(entry), err))?
+                            .is_dir();
+                        if is_dir {
+                            stack.push(entry.clone());
+                        }
+                    }
+                    found.extend(entries);
+                }
+                found.sort();
+                // Report the paths relative to what was passed in, not where the sandbox placed them
+                Ok(found
+                    .into_iter()
+                    .map(|found| match found.strip_prefix(&root) {
+                        Ok(relative) => path.join(relative),
+                        Err(_) => found,
+                    })
+                    .collect())
+            }),
+    )
+}
+
+/// Converts a timestamp into seconds since the unix epoch
+fn fs_time(time: io::Result<SystemTime>) -> FsResult<f64> {
+    time.map(|time| match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    })
+    .map_err(|err| FsError::new(None, err))
+}
+
 pub fn load_fs(vm: &Thread) -> Result<ExternModule> {
     vm.register_type::<Metadata>("std.fs.Metadata", &[])?;
     vm.register_type::<DirEntry>("std.fs.DirEntry", &[])?;
@@ -620,10 +1200,27 @@ pub fn load_fs(vm: &Thread) -> Result<ExternModule> {
         record! {
             type Metadata => Metadata,
             type DirEntry => DirEntry,
-
-            read_dir => primitive!(1, "std.fs.prim.read_dir", |p: &Path| {
-                IO::from(fs::read_dir(p).and_then(|iter| iter.map(|result| result.map(DirEntry)).collect::<io::Result<Vec<_>>>()))
-            }),
+            type Error => FsError,
+            type ErrorKind => FsErrorKind,
+            type Permissions => Permissions,
+
+            read_dir => primitive!(1, "std.fs.prim.read_dir", read_dir),
+            create_dir => primitive!(1, "std.fs.prim.create_dir", fs_create_dir),
+            create_dir_all => primitive!(1, "std.fs.prim.create_dir_all", fs_create_dir_all),
+            remove_dir => primitive!(1, "std.fs.prim.remove_dir", fs_remove_dir),
+            remove_dir_all => primitive!(1, "std.fs.prim.remove_dir_all", fs_remove_dir_all),
+            remove_file => primitive!(1, "std.fs.prim.remove_file", fs_remove_file),
+            rename => primitive!(2, "std.fs.prim.rename", fs_rename),
+            copy => primitive!(2, "std.fs.prim.copy", fs_copy),
+            hard_link => primitive!(2, "std.fs.prim.hard_link", fs_hard_link),
+            symlink => primitive!(2, "std.fs.prim.symlink", fs_symlink),
+            read_link => primitive!(1, "std.fs.prim.read_link", fs_read_link),
+            canonicalize => primitive!(1, "std.fs.prim.canonicalize", fs_canonicalize),
+            read_metadata => primitive!(1, "std.fs.prim.read_metadata", fs_metadata),
+            read_symlink_metadata => primitive!(1, "std.fs.prim.read_symlink_metadata", fs_symlink_metadata),
+            set_permissions => primitive!(2, "std.fs.prim.set_permissions", fs_set_permissions),
+            exists => primitive!(1, "std.fs.prim.exists", fs_exists),
+            walk_dir => primitive!(1, "std.fs.prim.walk_dir", fs_walk_dir),
 
             dir_entry => record! {
                 path => primitive!(1, "std.fs.prim.dir_entry.path", |m: &DirEntry| m.0.path()),
@@ -635,6 +1232,11 @@ pub fn load_fs(vm: &Thread) -> Result<ExternModule> {
                 is_dir => primitive!(1, "std.fs.prim.metadata.is_dir", |m: &Metadata| m.0.is_dir()),
                 is_file => primitive!(1, "std.fs.prim.metadata.is_file", |m: &Metadata| m.0.is_file()),
                 len => primitive!(1, "std.fs.prim.metadata.len", |m: &Metadata| m.0.len()),
+                is_symlink => primitive!(1, "std.fs.prim.metadata.is_symlink", |m: &Metadata| m.0.file_type().is_symlink()),
+                permissions => primitive!(1, "std.fs.prim.metadata.permissions", |m: &Metadata| Permissions::from(m.0.permissions())),
+                modified => primitive!(1, "std.fs.prim.metadata.modified", |m: &Metadata| fs_time(m.0.modified())),
+                accessed => primitive!(1, "std.fs.prim.metadata.accessed", |m: &Metadata| fs_time(m.0.accessed())),
+                created => primitive!(1, "std.fs.prim.metadata.created", |m: &Metadata| fs_time(m.0.created())),
             },
         },
     )
@@ -669,22 +1271,14 @@ pub fn load_path(vm: &Thread) -> Result<ExternModule> {
                     })
                     .collect::<Vec<_>>()
             }),
-            metadata => primitive!(1, "std.path.prim.metadata", |p: &Path| IO::from(p.metadata().map(Metadata))),
-            symlink_metadata => primitive!(1, "std.path.prim.symlink_metadata", |p: &Path| IO::from(p.symlink_metadata().map(Metadata))),
-            canonicalize => primitive!(1, "std.path.prim.canonicalize", |p: &Path| IO::from(p.canonicalize())),
-            read_link => primitive!(1, "std.path.prim.read_link", |p: &Path| IO::from(p.read_link())),
-            read_dir => primitive!(
-                1,
-                "std.path.prim.read_dir",
-                |p: &Path| IO::from(
-                        p.read_dir()
-                            .and_then(|iter| iter.map(|result| Ok(result?.path())).collect::<StdResult<Vec<_>, _>>())
-                            .map_err(|err| Error::Message(err.to_string()))
-                    )
-            ),
-            exists => primitive!(1, "std.path.prim.exists", |p: &Path| IO::Value(p.exists())),
-            is_file => primitive!(1, "std.path.prim.is_file", |p: &Path| IO::Value(p.is_file())),
-            is_dir => primitive!(1, "std.path.prim.is_file", |p: &Path| IO::Value(p.is_dir())),
+            metadata => primitive!(1, "std.path.prim.metadata", path_metadata),
+            symlink_metadata => primitive!(1, "std.path.prim.symlink_metadata", path_symlink_metadata),
+            canonicalize => primitive!(1, "std.path.prim.canonicalize", path_canonicalize),
+            read_link => primitive!(1, "std.path.prim.read_link", path_read_link),
+            read_dir => primitive!(1, "std.path.prim.read_dir", path_read_dir),
+            exists => primitive!(1, "std.path.prim.exists", path_exists),
+            is_file => primitive!(1, "std.path.prim.is_file", path_is_file),
+            is_dir => primitive!(1, "std.path.prim.is_dir", path_is_dir),
         },
     )
 }
@@ -756,6 +1350,92 @@ pub fn load_string_buf(vm: &Thread) -> Result<ExternModule> {
     )
 }
 
+pub mod st_array {
+    use super::*;
+
+    use crate::gc::CloneUnrooted;
+
+    pub(crate) fn len(buf: &ArrayBuf<S, A>) -> usize {
+        buf.0.lock().unwrap().len()
+    }
+
+    pub(crate) fn push(buf: &ArrayBuf<S, A>, value: WithVM<Generic<A>>) {
+        let WithVM { vm, value } = value;
+        // The buffer may already have been promoted to the old generation
+        vm.write_barrier(value.get_value());
+        // SAFETY Rooted when stored in the buffer
+        unsafe {
+            buf.0
+                .lock()
+                .unwrap()
+                .push(value.get_value().clone_unrooted())
+        }
+    }
+
+    pub(crate) fn pop(buf: &ArrayBuf<S, A>) -> Option<Unrooted<A>> {
+        // The returned, unrooted value gets pushed immediately to the stack
+        buf.0.lock().unwrap().pop().map(Unrooted::from)
+    }
+
+    pub(crate) fn get(buf: &ArrayBuf<S, A>, index: usize) -> RuntimeResult<Unrooted<A>, String> {
+        match buf.0.lock().unwrap().get(index) {
+            // SAFETY The returned, unrooted value gets pushed immediately to the stack
+            Some(value) => unsafe { RuntimeResult::Return(Unrooted::from(value.clone_unrooted())) },
+            None => RuntimeResult::Panic(format!("Index {} is out of range", index)),
+        }
+    }
+
+    pub(crate) fn set(
+        buf: &ArrayBuf<S, A>,
+        index: usize,
+        value: WithVM<Generic<A>>,
+    ) -> RuntimeResult<(), String> {
+        let WithVM { vm, value } = value;
+        match buf.0.lock().unwrap().get_mut(index) {
+            Some(slot) => {
+                vm.write_barrier(value.get_value());
+                // SAFETY Rooted when stored in the buffer
+                *slot = unsafe { value.get_value().clone_unrooted() };
+                RuntimeResult::Return(())
+            }
+            None => RuntimeResult::Panic(format!("Index {} is out of range", index)),
+        }
+    }
+
+    pub(crate) fn freeze(buf: &ArrayBuf<S, A>) -> Vec<Unrooted<A>> {
+        // SAFETY The returned, unrooted values gets pushed immediately to the stack
+        buf.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|value| unsafe { Unrooted::from(value.clone_unrooted()) })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, VmType, Userdata, Trace)]
+#[gluon(vm_type = "std.effect.st.array.ArrayBuf")]
+#[gluon(gluon_vm)]
+pub(crate) struct ArrayBuf<S, T>(Mutex<Vec<Value>>, PhantomData<(S, T)>);
+
+pub fn load_array_buf(vm: &Thread) -> Result<ExternModule> {
+    vm.register_type::<ArrayBuf<S, A>>("std.effect.st.array.ArrayBuf", &["s", "a"])?;
+
+    ExternModule::new(
+        vm,
+        record! {
+            type ArrayBuf s a => ArrayBuf<S, A>,
+            len => primitive!(1, std::effect::st::array::prim::len),
+            new => primitive!(1, "std.effect.st.array.new", |()| ArrayBuf(Default::default(), PhantomData::<(S, A)>)),
+            push => primitive!(2, std::effect::st::array::prim::push),
+            pop => primitive!(1, std::effect::st::array::prim::pop),
+            get => primitive!(2, std::effect::st::array::prim::get),
+            set => primitive!(3, std::effect::st::array::prim::set),
+            freeze => primitive!(1, std::effect::st::array::prim::freeze)
+        },
+    )
+}
+
 #[allow(non_camel_case_types, deprecated)]
 pub fn load<'vm>(vm: &'vm Thread) -> Result<ExternModule> {
     vm.define_global(
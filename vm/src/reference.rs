@@ -54,6 +54,7 @@ fn set(r: &Reference<A>, a: Generic<A>) -> RuntimeResult<(), String> {
     match r.thread.deep_clone_value(&r.thread, a.get_value()) {
         // SAFETY Rooted when stored in the reference
         Ok(a) => unsafe {
+            r.thread.write_barrier(a.get_value());
             *r.value.lock().unwrap() = a.get_value().clone_unrooted();
             RuntimeResult::Return(())
         },
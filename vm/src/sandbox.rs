@@ -0,0 +1,120 @@
+//! Restrictions on the primitives that code running in a virtual machine may use.
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::base::fnv::FnvSet;
+
+/// How a primitive accesses the file system
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FsAccess {
+    Read,
+    Write,
+}
+
+/// Restricts which primitives may be called and which parts of the file system they may access.
+/// The default sandbox does not restrict anything.
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    denied_primitives: FnvSet<String>,
+    fs_root: Option<PathBuf>,
+    read_only_fs: bool,
+}
+
+impl Sandbox {
+    pub fn new() -> Sandbox {
+        Sandbox::default()
+    }
+
+    /// Denies calls to the primitive with the full name `name` (`std.env.prim.set_var`)
+    pub fn deny_primitive(mut self, name: impl Into<String>) -> Self {
+        self.denied_primitives.insert(name.into());
+        self
+    }
+
+    /// Allows calls to the primitive `name` again after it were denied
+    pub fn allow_primitive(mut self, name: &str) -> Self {
+        self.denied_primitives.remove(name);
+        self
+    }
+
+    /// Restricts the primitives that access the file system to paths inside `root`. Relative
+    /// paths are resolved against `root`.
+    pub fn fs_root(mut self, root: Option<PathBuf>) -> Self {
+        self.fs_root = root.map(|root| root.canonicalize().unwrap_or(root));
+        self
+    }
+
+    /// Denies all primitives from writing to the file system
+    pub fn read_only_fs(mut self, read_only: bool) -> Self {
+        self.read_only_fs = read_only;
+        self
+    }
+
+    pub fn is_primitive_denied(&self, name: &str) -> bool {
+        !self.denied_primitives.is_empty() && self.denied_primitives.contains(name)
+    }
+
+    /// Checks that `path` may be accessed, returning the path that should be used to access it
+    pub fn check_path(&self, path: &Path, access: FsAccess) -> io::Result<PathBuf> {
+        if access == FsAccess::Write && self.read_only_fs {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "Writing to `{}` was denied since the file system is read only",
+                    path.display()
+                ),
+            ));
+        }
+
+        let root = match &self.fs_root {
+            Some(root) => root,
+            None => return Ok(path.to_owned()),
+        };
+
+        let denied = || {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "Access to `{}` was denied since it is outside of `{}`",
+                    path.display(),
+                    root.display()
+                ),
+            )
+        };
+
+        // Resolve symlinks in the longest prefix of the path that exists so they can't be used to
+        // escape the root. The components which do not exist yet can't be symlinks but any `..`
+        // among them would be resolved relative to a directory that may be a symlink so they are
+        // rejected
+        let full_path = root.join(path);
+        let mut existing = full_path.as_path();
+        let mut missing = Vec::new();
+        let mut resolved = loop {
+            match existing.canonicalize() {
+                Ok(canonical) => break canonical,
+                Err(_) => match (existing.components().next_back(), existing.parent()) {
+                    (Some(component), Some(parent)) => {
+                        missing.push(component);
+                        existing = parent;
+                    }
+                    _ => return Err(denied()),
+                },
+            }
+        };
+        for component in missing.into_iter().rev() {
+            match component {
+                Component::Normal(name) => resolved.push(name),
+                Component::CurDir => (),
+                _ => return Err(denied()),
+            }
+        }
+
+        if resolved.starts_with(root) {
+            Ok(resolved)
+        } else {
+            Err(denied())
+        }
+    }
+}
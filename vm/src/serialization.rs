@@ -1,23 +1,25 @@
-use std::{borrow::Cow, cell::RefCell, marker::PhantomData, rc::Rc, sync::Arc};
+use std::{borrow::Cow, cell::RefCell, fmt, marker::PhantomData, rc::Rc, sync::Arc};
 
 use itertools::Itertools;
 
 use crate::serde::{
-    de::{Deserialize, DeserializeSeed, DeserializeState, Error},
-    ser::{Seeded, Serialize, SerializeSeq, SerializeState, Serializer},
+    de::{Deserialize, DeserializeSeed, DeserializeState, Error, SeqAccess, Visitor},
+    ser::{Seeded, Serialize, SerializeSeq, SerializeState, SerializeTuple, Serializer},
     Deserializer,
 };
 
 use crate::base::{
+    fnv::FnvMap,
     serialization::{NodeMap, NodeToId, SharedSeed},
     symbol::{Symbol, Symbols},
     types::ArcType,
 };
 
 use crate::{
+    api::{Userdata, ValueRef},
     array::Array,
     gc::{CloneUnrooted, DataDef, GcPtr, GcRef, OwnedGcRef, WriteOnly},
-    stack::State,
+    stack::{ExternCallState, Stack, State},
     thread::{ActiveThread, ExecuteContext, RootedThread, RootedValue, Thread, ThreadInternal},
     types::VmIndex,
     value::{
@@ -63,6 +65,8 @@ impl AsMut<NodeMap> for DeSeed<'_> {
 
 pub struct SeSeed {
     node_to_id: crate::base::serialization::SeSeed,
+    /// The global names of the userdata values which can be serialized
+    userdata: FnvMap<*const (), String>,
 }
 
 impl AsRef<NodeToId> for SeSeed {
@@ -75,8 +79,50 @@ impl SeSeed {
     pub fn new() -> SeSeed {
         SeSeed {
             node_to_id: Default::default(),
+            userdata: Default::default(),
         }
     }
+
+    /// Creates a seed which serializes any userdata that is stored in the globals of `thread`
+    /// (directly or in a field of a record) as the name of that global. The name is looked up
+    /// again when deserializing so the userdata must be registered under the same name in the
+    /// thread that deserializes it.
+    pub fn with_globals(thread: &Thread) -> SeSeed {
+        fn add_userdata(
+            thread: &Thread,
+            userdata: &mut FnvMap<*const (), String>,
+            name: &str,
+            value: Variants,
+        ) {
+            match value.as_ref() {
+                ValueRef::Userdata(data) => {
+                    userdata
+                        .entry(data as *const dyn Userdata as *const ())
+                        .or_insert_with(|| name.to_string());
+                }
+                ValueRef::Data(data) => {
+                    for field in data.field_names() {
+                        if let Some(value) = data.lookup_field(thread, field) {
+                            add_userdata(thread, userdata, &format!("{}.{}", name, field), value);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut seed = SeSeed::new();
+        let globals = thread.global_env().get_globals();
+        for (name, global) in &globals.globals {
+            add_userdata(
+                thread,
+                &mut seed.userdata,
+                name,
+                Variants::new(&global.value),
+            );
+        }
+        seed
+    }
 }
 
 fn gc_seed<S, T>(seed: &mut S) -> SharedSeed<GcPtr<T>, S> {
@@ -795,6 +841,28 @@ where
     }
 }
 
+/// Wraps any operators in `id` with parens so that it is acceptable for `get_global`
+fn escape_global_id(id: &str) -> Cow<str> {
+    let mut escaped_id = Cow::Borrowed("");
+    let iter = id
+        .split(|c: char| c == '.')
+        .map(|s| {
+            if s.chars()
+                .next()
+                .map_or(false, crate::base::ast::is_operator_char)
+            {
+                Cow::Owned(format!("({})", s))
+            } else {
+                Cow::Borrowed(s)
+            }
+        })
+        .intersperse(Cow::Borrowed("."));
+    for s in iter {
+        escaped_id += s;
+    }
+    escaped_id
+}
+
 impl<'de, 'gc> DeserializeState<'de, DeSeed<'gc>> for ExternFunction {
     fn deserialize_state<D>(seed: &mut DeSeed<'gc>, deserializer: D) -> Result<Self, D::Error>
     where
@@ -808,28 +876,9 @@ impl<'de, 'gc> DeserializeState<'de, DeSeed<'gc>> for ExternFunction {
         }
 
         let partial = ExternFunction_::deserialize(deserializer)?;
-        // Wrap any operators with parens so that they are acceptable for `get_global`
-        let mut escaped_id = Cow::Borrowed("");
-        let iter = partial
-            .id
-            .split(|c: char| c == '.')
-            .map(|s| {
-                if s.chars()
-                    .next()
-                    .map_or(false, crate::base::ast::is_operator_char)
-                {
-                    Cow::Owned(format!("({})", s))
-                } else {
-                    Cow::Borrowed(s)
-                }
-            })
-            .intersperse(Cow::Borrowed("."));
-        for s in iter {
-            escaped_id += s;
-        }
         let function = seed
             .thread
-            .get_global::<OpaqueValue<RootedThread, Hole>>(&escaped_id)
+            .get_global::<OpaqueValue<RootedThread, Hole>>(&escape_global_id(&partial.id))
             .map_err(|err| D::Error::custom(err))?;
         match function.get_value().get_repr() {
             ValueRepr::Function(function) if partial.args == function.args => Ok(ExternFunction {
@@ -854,12 +903,52 @@ where
     }
 }
 
-pub fn serialize_userdata<S, T>(_: &T, _: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    use crate::serde::ser::Error;
-    Err(S::Error::custom("Userdata cannot be serialized"))
+pub mod userdata {
+    use super::*;
+
+    pub fn serialize<S>(
+        userdata: &GcPtr<Box<dyn Userdata>>,
+        serializer: S,
+        seed: &SeSeed,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use crate::serde::ser::Error;
+        match seed
+            .userdata
+            .get(&(&***userdata as *const dyn Userdata as *const ()))
+        {
+            Some(name) => name.serialize(serializer),
+            None => Err(S::Error::custom(
+                "Userdata cannot be serialized unless it is stored in a global",
+            )),
+        }
+    }
+
+    pub fn deserialize<'de, 'gc, D>(
+        seed: &mut DeSeed<'gc>,
+        deserializer: D,
+    ) -> Result<GcPtr<Box<dyn Userdata>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use crate::api::{Hole, OpaqueValue};
+
+        let name = String::deserialize(deserializer)?;
+        let value = seed
+            .thread
+            .get_global::<OpaqueValue<RootedThread, Hole>>(&escape_global_id(&name))
+            .map_err(|err| D::Error::custom(err))?;
+        match value.get_value().get_repr() {
+            // SAFETY The userdata is kept alive by the global
+            ValueRepr::Userdata(data) => unsafe { Ok(data.clone_unrooted()) },
+            _ => Err(D::Error::custom(format_args!(
+                "Expected `{}` to be userdata",
+                name
+            ))),
+        }
+    }
 }
 
 impl<'a> crate::serde::ser::SerializeState<crate::serialization::SeSeed> for Variants<'a> {
@@ -886,6 +975,125 @@ impl<'de, 'gc> DeserializeState<'de, DeSeed<'gc>> for RootedValue<RootedThread>
     }
 }
 
+impl Thread {
+    /// Serializes the stack of this thread so that it can be resumed later, possibly in another
+    /// process, by `deserialize_suspended`. The thread must not be running, usually it has been
+    /// suspended by `std.thread.yield`.
+    ///
+    /// The bytecode of any closures on the stack is serialized along with the values.
+    /// Extern functions and userdata which are stored in globals are serialized as their names
+    /// and linked again when deserializing. The names of all the globals are also stored so that
+    /// any modules the thread may depend on can be loaded again before it is deserialized.
+    ///
+    /// If the thread is suspended in an extern function, that function is called again with the
+    /// same arguments when the deserialized thread is resumed.
+    pub fn serialize_suspended<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use crate::serde::ser::Error;
+
+        let seed = SeSeed::with_globals(self);
+        let mut globals: Vec<String> = self
+            .global_env()
+            .get_globals()
+            .globals
+            .keys()
+            .cloned()
+            .collect();
+        globals.sort();
+
+        let mut context = self.current_context();
+        let stack = context.stack();
+        let frames = stack.get_frames();
+        for (i, frame) in frames.iter().enumerate() {
+            match &frame.state {
+                State::Extern(ext) if ext.call_state == ExternCallState::InPoll => {
+                    return Err(S::Error::custom(
+                        "The thread is running and cannot be serialized",
+                    ));
+                }
+                State::Extern(ext)
+                    if i + 1 != frames.len() && ext.call_state != ExternCallState::Start =>
+                {
+                    return Err(S::Error::custom(format_args!(
+                        "The thread cannot be serialized while `{}` is calling into gluon",
+                        ext.function.id
+                    )));
+                }
+                _ => (),
+            }
+        }
+
+        let mut serializer = serializer.serialize_tuple(2)?;
+        serializer.serialize_element(&globals)?;
+        serializer.serialize_element(&Seeded::new(&seed, &*stack))?;
+        serializer.end()
+    }
+
+    /// Deserializes a thread which were serialized with `serialize_suspended` as a new child
+    /// thread of `self`. `load_global` is called with the name of each global that existed when
+    /// the thread was serialized, before any values are deserialized, and should load that global
+    /// if it does not exist already.
+    pub fn deserialize_suspended<'de, D, F>(
+        &self,
+        deserializer: D,
+        load_global: F,
+    ) -> Result<RootedThread, D::Error>
+    where
+        D: Deserializer<'de>,
+        F: FnMut(&str) -> Result<(), String>,
+    {
+        struct SuspendedVisitor<'a, F> {
+            thread: &'a Thread,
+            load_global: F,
+        }
+
+        impl<'de, F> Visitor<'de> for SuspendedVisitor<'_, F>
+        where
+            F: FnMut(&str) -> Result<(), String>,
+        {
+            type Value = ();
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a suspended thread")
+            }
+
+            fn visit_seq<V>(mut self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let globals: Vec<String> = seq
+                    .next_element()?
+                    .ok_or_else(|| V::Error::invalid_length(0, &self))?;
+                for global in &globals {
+                    (self.load_global)(global).map_err(V::Error::custom)?;
+                }
+
+                let mut context = self.thread.current_context();
+                let stack: Option<Stack> = {
+                    let mut seed = DeSeed::new(self.thread, &mut context);
+                    seq.next_element_seed(crate::serde::de::Seed::new(&mut seed))?
+                };
+                let mut stack = stack.ok_or_else(|| V::Error::invalid_length(1, &self))?;
+                stack.restart_extern_call();
+                *context.stack() = stack;
+                Ok(())
+            }
+        }
+
+        let thread = self.new_thread().map_err(D::Error::custom)?;
+        deserializer.deserialize_tuple(
+            2,
+            SuspendedVisitor {
+                thread: &thread,
+                load_global,
+            },
+        )?;
+        Ok(thread)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,6 +524,20 @@ impl Stack {
         &mut self.frames
     }
 
+    /// Makes an extern function which was in the middle of being called when the stack was
+    /// serialized get called again from the start, as its future could not be serialized
+    #[cfg(feature = "serde")]
+    pub(crate) fn restart_extern_call(&mut self) {
+        if let Some(Frame {
+            state: State::Extern(ext),
+            ..
+        }) = self.frames.last_mut()
+        {
+            ext.call_state = ExternCallState::Start;
+            ext.locked = None;
+        }
+    }
+
     pub fn current_frame<S>(&mut self) -> StackFrame<S>
     where
         S: StackState,
@@ -37,10 +37,14 @@ use crate::base::{
 use crate::{
     api::{Getable, Pushable, ValueRef, VmType},
     compiler::UpvarInfo,
-    gc::{self, CloneUnrooted, DataDef, Gc, GcPtr, GcRef, Generation, Move},
+    gc::{
+        self,
+        snapshot::{HeapSnapshot, ThreadHeap},
+        CloneUnrooted, DataDef, Gc, GcPtr, GcRef, Generation, Move,
+    },
     interner::InternedStr,
     macros::MacroEnv,
-    source_map::LocalIter,
+    source_map::{Local, LocalIter},
     stack::{
         ClosureState, ExternCallState, ExternState, Frame, Lock, Stack, StackFrame, StackState,
         State,
@@ -920,6 +924,12 @@ impl Thread {
         self.collect_with_context(&mut context);
     }
 
+    /// Must be called before `value` is stored in a location that the garbage collector may have
+    /// promoted to the old generation, such as the inside of a `Reference`.
+    pub(crate) fn write_barrier(&self, value: &Value) {
+        self.owned_context().gc.tenure(value);
+    }
+
     fn collect_with_context(&self, context: &mut OwnedContext) {
         debug_assert!(ptr::eq::<Thread>(self, context.thread));
         self.with_roots(context, |gc, roots| unsafe {
@@ -945,10 +955,95 @@ impl Thread {
         self.owned_context().gc.allocated_memory()
     }
 
+    /// Returns the number of bytes which this thread has allocated in total, including memory
+    /// which has since been collected
+    pub fn total_allocated(&self) -> usize {
+        self.owned_context().gc.total_allocated()
+    }
+
+    /// Returns a snapshot of the values allocated by this thread, along with the totals of every
+    /// thread spawned from it.
+    pub fn heap_snapshot(&self) -> HeapSnapshot {
+        let mut snapshot = {
+            let mut context = self.owned_context();
+            self.with_roots(&mut context, |gc, roots| gc.snapshot(roots))
+        };
+        snapshot.threads = self.child_heaps();
+        snapshot
+    }
+
+    fn child_heaps(&self) -> Vec<ThreadHeap> {
+        self.child_threads
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(_, thread)| {
+                // Waiting on a thread which is running (or which is the caller) could block
+                // indefinitely so it is reported as busy instead
+                let (count, bytes, busy) = match thread.context.try_lock() {
+                    Ok(context) => (
+                        context.gc.object_count(),
+                        context.gc.allocated_memory(),
+                        false,
+                    ),
+                    Err(sync::TryLockError::WouldBlock) => (0, 0, true),
+                    Err(sync::TryLockError::Poisoned(err)) => panic!("{}", err),
+                };
+                ThreadHeap {
+                    count,
+                    bytes,
+                    busy,
+                    threads: thread.child_heaps(),
+                }
+            })
+            .collect()
+    }
+
     pub fn set_memory_limit(&self, memory_limit: usize) {
         self.owned_context().gc.set_memory_limit(memory_limit)
     }
 
+    /// Limits the number of instructions that this thread may execute to `fuel`. Once the fuel
+    /// runs out execution fails with `Error::OutOfFuel` (or is suspended, see
+    /// `suspend_on_out_of_fuel`). Passing `None` removes the limit.
+    ///
+    /// Only instructions executed by this thread are counted, threads spawned from this thread
+    /// have their own limits.
+    pub fn set_fuel(&self, fuel: Option<u64>) {
+        let mut context = self.owned_context();
+        context.fuel.remaining = fuel;
+        context.fuel.wake();
+    }
+
+    /// Adds `fuel` to the remaining fuel, waking the thread if it were suspended due to running
+    /// out of fuel. Does nothing if the fuel is unlimited.
+    pub fn add_fuel(&self, fuel: u64) {
+        self.owned_context().fuel.add(fuel)
+    }
+
+    /// Returns the fuel that remains or `None` if the fuel is unlimited
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.owned_context().fuel.remaining
+    }
+
+    /// Returns the number of instructions this thread has executed since the last call to
+    /// `reset_fuel_consumed`. Counted regardless of whether the fuel is limited.
+    pub fn fuel_consumed(&self) -> u64 {
+        self.owned_context().fuel.consumed
+    }
+
+    /// Resets the consumed fuel to zero, returning the previous value
+    pub fn reset_fuel_consumed(&self) -> u64 {
+        mem::replace(&mut self.owned_context().fuel.consumed, 0)
+    }
+
+    /// If `suspend` is true, running out of fuel suspends execution instead of returning an
+    /// error. Execution resumes once more fuel is added with `add_fuel` or `set_fuel`, which
+    /// lets an async host refuel the thread without aborting it.
+    pub fn suspend_on_out_of_fuel(&self, suspend: bool) {
+        self.owned_context().fuel.suspend = suspend;
+    }
+
     pub fn interrupt(&self) {
         self.interrupt.store(true, atomic::Ordering::Relaxed)
     }
@@ -1335,6 +1430,7 @@ pub type HookFn = Box<dyn FnMut(&Thread, DebugInfo) -> Poll<Result<()>> + Send +
 pub struct DebugInfo<'a> {
     stack: &'a Stack,
     state: HookFlags,
+    total_allocated: usize,
 }
 
 impl fmt::Debug for DebugInfo<'_> {
@@ -1357,6 +1453,12 @@ impl<'a> DebugInfo<'a> {
         self.state
     }
 
+    /// Returns the number of bytes allocated by the thread in total, including memory which has
+    /// since been collected
+    pub fn total_allocated(&self) -> usize {
+        self.total_allocated
+    }
+
     /// Returns a struct which can be queried about information about the stack
     /// at a specific level where `0` is the currently executing frame.
     pub fn stack_info(&self, level: usize) -> Option<StackInfo> {
@@ -1468,6 +1570,27 @@ impl<'a> StackInfo<'a> {
             _ => &[],
         }
     }
+
+    /// Returns the value currently stored in `local`, where `local` is one of the locals returned
+    /// by `locals`
+    pub fn local_value(&self, local: &Local) -> Option<Variants> {
+        let index = self.frame().offset + local.index;
+        self.info
+            .stack
+            .get_values()
+            .get(index as usize)
+            .map(Variants::new)
+    }
+
+    /// Returns the value of the upvar at `index`. The upvar is described by `upvars()[index]`
+    pub fn upvar_value(&self, index: usize) -> Option<Variants> {
+        match self.frame().state {
+            State::Closure(ClosureState { ref closure, .. }) => {
+                closure.upvars.get(index).map(Variants::new)
+            }
+            _ => None,
+        }
+    }
 }
 
 bitflags::bitflags! {
@@ -1488,6 +1611,41 @@ struct Hook {
     previous_instruction_index: usize,
 }
 
+/// Limits the number of instructions that a thread may execute
+#[derive(Default)]
+struct Fuel {
+    /// The number of instructions left to execute or `None` if execution is unlimited
+    remaining: Option<u64>,
+    /// The number of instructions executed since the counter was last reset
+    consumed: u64,
+    /// If set, execution is suspended instead of failing when the fuel runs out
+    suspend: bool,
+    /// Woken when fuel is added to a suspended thread
+    waker: Option<task::Waker>,
+}
+
+impl Fuel {
+    fn add(&mut self, fuel: u64) {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_add(fuel);
+        }
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn register(&mut self, waker: &task::Waker) {
+        match &self.waker {
+            Some(current) if current.will_wake(waker) => (),
+            _ => self.waker = Some(waker.clone()),
+        }
+    }
+}
+
 type PollFnInner<'a> = Box<
     dyn for<'vm> FnMut(
             &mut task::Context<'_>,
@@ -1521,6 +1679,8 @@ pub struct Context {
     pub(crate) gc: Gc,
     #[cfg_attr(feature = "serde_derive", serde(skip))]
     hook: Hook,
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    fuel: Fuel,
     max_stack_size: VmIndex,
 
     /// Stack of polling functions used for extern functions returning futures
@@ -1538,6 +1698,7 @@ impl Context {
                 flags: HookFlags::empty(),
                 previous_instruction_index: usize::max_value(),
             },
+            fuel: Fuel::default(),
             max_stack_size: VmIndex::max_value(),
             poll_fns: Vec::new(),
         }
@@ -1613,6 +1774,10 @@ impl Context {
         self.hook.flags = flags;
     }
 
+    pub fn hook_mask(&self) -> HookFlags {
+        self.hook.flags
+    }
+
     pub fn set_max_stack_size(&mut self, limit: VmIndex) {
         self.max_stack_size = limit;
     }
@@ -1680,6 +1845,7 @@ impl<'b> OwnedContext<'b> {
         DebugInfo {
             stack: &self.stack,
             state: HookFlags::empty(),
+            total_allocated: self.gc.total_allocated(),
         }
     }
 
@@ -1784,6 +1950,7 @@ impl<'b> OwnedContext<'b> {
                             let info = DebugInfo {
                                 stack: &context.stack.stack(),
                                 state: HookFlags::CALL_FLAG,
+                                total_allocated: context.gc.total_allocated(),
                             };
                             ready!(hook(thread, info))?
                         }
@@ -1860,7 +2027,7 @@ impl<'b> OwnedContext<'b> {
                         );
 
                         let closure_context = context.from_state();
-                        match ready!(closure_context.execute_())? {
+                        match ready!(closure_context.execute_(cx))? {
                             Some(new_context) => context = new_context,
                             None => return Ok(None).into(),
                         }
@@ -1887,9 +2054,14 @@ impl<'b> OwnedContext<'b> {
         let mut status = Status::Ok;
         match call_state {
             ExternCallState::Start => {
+                let thread = self.thread;
+                let name = function.id.as_pretty_str();
+                if thread.global_env().sandbox().is_primitive_denied(name) {
+                    return Err(Error::PrimitiveDenied(name.to_string())).into();
+                }
+
                 // Make sure that the stack is not borrowed during the external function call
                 // Necessary since we do not know what will happen during the function call
-                let thread = self.thread;
                 drop(self);
                 status = (function.function)(thread);
 
@@ -2012,6 +2184,7 @@ impl<'b> OwnedContext<'b> {
             gc: &mut context.gc,
             stack: StackFrame::current(&mut context.stack),
             hook: &mut context.hook,
+            fuel: &mut context.fuel,
             max_stack_size: context.max_stack_size,
             poll_fns: &context.poll_fns,
         }
@@ -2047,6 +2220,7 @@ pub struct ExecuteContext<'b, 'gc, S: StackState = ClosureState> {
     pub stack: StackFrame<'b, S>,
     pub gc: &'gc mut Gc,
     hook: &'b mut Hook,
+    fuel: &'b mut Fuel,
     max_stack_size: VmIndex,
     poll_fns: &'b [PollFn],
 }
@@ -2116,7 +2290,10 @@ where
 }
 
 impl<'b, 'gc> ExecuteContext<'b, 'gc> {
-    fn execute_(mut self) -> Poll<Result<Option<ExecuteContext<'b, 'gc, State>>>> {
+    fn execute_(
+        mut self,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Result<Option<ExecuteContext<'b, 'gc, State>>>> {
         let state = &self.stack.frame().state;
         let function = unsafe { state.closure.function.clone_unrooted() };
         {
@@ -2139,10 +2316,30 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
 
             debug_instruction(&self.stack, instruction_index, instr);
 
+            // The hook may suspend execution, in which case the instruction is executed again
+            // once resumed so the fuel is only charged after the hook is done
             if self.hook.flags.contains(HookFlags::LINE_FLAG) {
                 ready!(self.run_hook(&function, instruction_index))?;
             }
 
+            if let Some(remaining) = &mut self.fuel.remaining {
+                if *remaining == 0 {
+                    // Store the current instruction so execution can resume from it
+                    self.stack.frame_mut().state.instruction_index = instruction_index;
+                    if self.fuel.suspend {
+                        // Running out of fuel suspends execution until fuel is added
+                        self.fuel.register(cx.waker());
+                        return Poll::Pending;
+                    }
+                    return Err(Error::OutOfFuel {
+                        consumed: self.fuel.consumed,
+                    })
+                    .into();
+                }
+                *remaining -= 1;
+            }
+            self.fuel.consumed += 1;
+
             match instr {
                 Push(i) => {
                     let v = match self.stack.get(i as usize) {
@@ -2316,6 +2513,8 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                                 {
                                     *var = value.clone_unrooted();
                                 }
+                                // A collection may have promoted the record before it was closed
+                                self.gc.write_barrier(&data);
                                 self.stack.pop_many(data.fields.len() as VmIndex);
                             }
                         }
@@ -2474,6 +2673,8 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                                 {
                                     *var = value.clone_unrooted();
                                 }
+                                // A collection may have promoted the closure before it was closed
+                                self.gc.write_barrier(&closure);
                             }
                             let pop = closure.upvars.len() as VmIndex + 1;
                             self.stack.pop_many(pop); //Remove the closure
@@ -2491,6 +2692,18 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                 DivideInt => binop_int(self.thread, &mut self.stack, VmInt::checked_div)?,
                 IntLT => binop_bool(self.thread, &mut self.stack, |l: VmInt, r| l < r)?,
                 IntEQ => binop_bool(self.thread, &mut self.stack, |l: VmInt, r| l == r)?,
+                CheckedAddInt => {
+                    self.checked_binop_int(&function, instruction_index, "+", VmInt::checked_add)?
+                }
+                CheckedSubtractInt => {
+                    self.checked_binop_int(&function, instruction_index, "-", VmInt::checked_sub)?
+                }
+                CheckedMultiplyInt => {
+                    self.checked_binop_int(&function, instruction_index, "*", VmInt::checked_mul)?
+                }
+                CheckedDivideInt => {
+                    self.checked_binop_int(&function, instruction_index, "/", VmInt::checked_div)?
+                }
 
                 AddByte => binop_byte(self.thread, &mut self.stack, u8::checked_add)?,
                 SubtractByte => binop_byte(self.thread, &mut self.stack, u8::checked_sub)?,
@@ -2553,6 +2766,45 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
         }
     }
 
+    /// Executes an integer operation, turning overflow and division by zero into a panic which
+    /// points at the source location of the instruction.
+    fn checked_binop_int(
+        &mut self,
+        function: &BytecodeFunction,
+        instruction_index: usize,
+        op: &str,
+        f: fn(VmInt, VmInt) -> Option<VmInt>,
+    ) -> Result<()> {
+        let result = binop(self.thread, &mut self.stack, |l: VmInt, r: VmInt| {
+            f(l, r).map(ValueRepr::Int).ok_or_else(|| {
+                let message = if op == "/" && r == 0 {
+                    "attempt to divide by zero".to_string()
+                } else {
+                    format!("attempt to compute `{} {} {}` with overflow", l, op, r)
+                };
+                Error::Panic(message, None)
+            })
+        });
+        result.map_err(|err| match err {
+            Error::Panic(message, _) => {
+                // Store the index so that the stacktrace points at the failing instruction
+                self.stack.frame_mut().state.instruction_index = instruction_index;
+                let debug_info = &function.debug_info;
+                let message = match debug_info.source_map.line(instruction_index) {
+                    Some(line) => format!(
+                        "{} at {}:Line {}",
+                        message,
+                        debug_info.source_name,
+                        line.number()
+                    ),
+                    None => format!("{} in {}", message, debug_info.source_name),
+                };
+                Error::Panic(message, Some(self.stack.stack().stacktrace(0)))
+            }
+            err => err,
+        })
+    }
+
     fn run_hook(&mut self, function: &BytecodeFunction, index: usize) -> Poll<Result<()>> {
         if let Some(ref mut hook) = self.hook.function {
             let current_line = function.debug_info.source_map.line(index);
@@ -2566,6 +2818,7 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                 let info = DebugInfo {
                     stack: &self.stack.stack(),
                     state: HookFlags::LINE_FLAG,
+                    total_allocated: self.gc.total_allocated(),
                 };
                 ready!(hook(self.thread, info))?
             }
@@ -2584,6 +2837,7 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc, State> {
             stack: self.stack.from_state(),
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2600,6 +2854,7 @@ where
             stack: self.stack.to_state(),
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2616,6 +2871,7 @@ where
             stack,
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2639,6 +2895,7 @@ where
                     stack,
                     gc: self.gc,
                     hook: self.hook,
+                    fuel: self.fuel,
                     max_stack_size: self.max_stack_size,
                     poll_fns: self.poll_fns,
                 })
@@ -2648,6 +2905,7 @@ where
                 stack: StackFrame::current(stack),
                 gc: self.gc,
                 hook: self.hook,
+                fuel: self.fuel,
                 max_stack_size: self.max_stack_size,
                 poll_fns: self.poll_fns,
             }),
@@ -2932,6 +3190,7 @@ impl<'vm> ActiveThread<'vm> {
             gc: &mut context.gc,
             stack: StackFrame::current(&mut context.stack),
             hook: &mut context.hook,
+            fuel: &mut context.fuel,
             max_stack_size: context.max_stack_size,
             poll_fns: &context.poll_fns,
         }
@@ -165,6 +165,15 @@ pub enum Instruction {
     DivideInt,
     IntLT,
     IntEQ,
+    /// Like `AddInt` but reports overflow as a panic which includes the source location
+    CheckedAddInt,
+    /// Like `SubtractInt` but reports overflow as a panic which includes the source location
+    CheckedSubtractInt,
+    /// Like `MultiplyInt` but reports overflow as a panic which includes the source location
+    CheckedMultiplyInt,
+    /// Like `DivideInt` but reports overflow and division by zero as a panic which includes the
+    /// source location
+    CheckedDivideInt,
 
     AddByte,
     SubtractByte,
@@ -210,7 +219,8 @@ impl Instruction {
             NewClosure { .. } => 1,
             CloseClosure(_) => -1,
             PushUpVar(_) => 1,
-            AddInt | SubtractInt | MultiplyInt | DivideInt | IntLT | IntEQ | AddFloat | AddByte
+            AddInt | SubtractInt | MultiplyInt | DivideInt | IntLT | IntEQ | CheckedAddInt
+            | CheckedSubtractInt | CheckedMultiplyInt | CheckedDivideInt | AddFloat | AddByte
             | SubtractByte | MultiplyByte | DivideByte | ByteLT | ByteEQ | SubtractFloat
             | MultiplyFloat | DivideFloat | FloatLT | FloatEQ => -1,
             Return => 0,
@@ -430,12 +430,10 @@ pub(crate) enum ValueRepr {
         #[cfg_attr(feature = "serde_derive", serde(serialize_state))]
         GcPtr<PartialApplicationData>,
     ),
-    // TODO Implement serializing of userdata
-    #[cfg_attr(feature = "serde_derive", serde(skip_deserializing))]
     Userdata(
         #[cfg_attr(
             feature = "serde_derive",
-            serde(serialize_with = "crate::serialization::serialize_userdata")
+            serde(state_with = "crate::serialization::userdata")
         )]
         GcPtr<Box<dyn Userdata>>,
     ),
@@ -30,6 +30,7 @@ use crate::{
     interner::{InternedStr, Interner},
     lazy::Lazy,
     macros::MacroEnv,
+    sandbox::Sandbox,
     thread::ThreadInternal,
     types::*,
     value::{BytecodeFunction, ClosureData, ClosureDataDef, Value},
@@ -245,6 +246,9 @@ pub struct GlobalVmState {
 
     #[cfg_attr(feature = "serde_derive", serde(skip))]
     spawner: Option<Box<dyn futures::task::Spawn + Send + Sync>>,
+
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    sandbox: Sandbox,
 }
 
 unsafe impl Trace for GlobalVmState {
@@ -539,6 +543,7 @@ impl<'a> VmEnvInstance<'a> {
 #[derive(Default)]
 pub struct GlobalVmStateBuilder {
     spawner: Option<Box<dyn futures::task::Spawn + Send + Sync>>,
+    sandbox: Sandbox,
 }
 
 impl GlobalVmStateBuilder {
@@ -551,6 +556,12 @@ impl GlobalVmStateBuilder {
         self
     }
 
+    /// Restricts the primitives which may be used by the virtual machine
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
     pub fn build(self) -> GlobalVmState {
         let mut vm = GlobalVmState {
             env: Default::default(),
@@ -564,6 +575,7 @@ impl GlobalVmStateBuilder {
             debug_level: RwLock::new(DebugLevel::default()),
             thread_reference_count: Default::default(),
             spawner: self.spawner,
+            sandbox: self.sandbox,
         };
         vm.add_types().unwrap();
         vm
@@ -791,4 +803,8 @@ impl GlobalVmState {
     pub fn spawner(&self) -> Option<&(dyn futures::task::Spawn + Send + Sync)> {
         self.spawner.as_ref().map(|s| &**s)
     }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
 }